@@ -0,0 +1,54 @@
+use crate::*;
+
+/// A visitor that threads an accumulator through the values it visits: `visit` receives the
+/// accumulator as it was after the previous value and returns it updated, e.g. the environment of
+/// a symbolic execution after executing a statement. This keeps the state that flows through the
+/// traversal out of the visitor, so that branches can be visited with copies of it.
+///
+/// To recurse, use `drive_inner_acc(x, acc, self)`, which threads `acc` through the contents of
+/// `x` in order and returns it.
+pub trait VisitAcc<'a, A, T: ?Sized>: Visitor {
+    fn visit(&mut self, acc: A, x: &'a T) -> ControlFlow<Self::Break, A>;
+}
+
+/// Adapts a `VisitAcc` visitor to `Visit` by holding the accumulator between calls.
+pub struct AccDriver<'w, A, V: ?Sized> {
+    /// Always `Some`, except while a value is being visited.
+    acc: Option<A>,
+    v: &'w mut V,
+}
+
+impl<'w, A, V: ?Sized> AccDriver<'w, A, V> {
+    pub fn new(acc: A, v: &'w mut V) -> Self {
+        AccDriver { acc: Some(acc), v }
+    }
+
+    /// The accumulator after the values visited so far.
+    pub fn into_acc(self) -> A {
+        self.acc.unwrap()
+    }
+}
+
+impl<A, V: Visitor + ?Sized> Visitor for AccDriver<'_, A, V> {
+    type Break = V::Break;
+}
+
+impl<'a, A, T: ?Sized, V: VisitAcc<'a, A, T> + ?Sized> Visit<'a, T> for AccDriver<'_, A, V> {
+    fn visit(&mut self, x: &'a T) -> ControlFlow<Self::Break> {
+        let acc = self.acc.take().unwrap();
+        self.acc = Some(self.v.visit(acc, x)?);
+        Continue(())
+    }
+}
+
+/// Call `v.visit(acc, _)` on the contents of `x` in order, passing the accumulator returned by
+/// each to the next, and return the last one.
+pub fn drive_inner_acc<'a, A, T, V>(x: &'a T, acc: A, v: &mut V) -> ControlFlow<V::Break, A>
+where
+    T: ?Sized + for<'w> Drive<'a, AccDriver<'w, A, V>>,
+    V: Visitor,
+{
+    let mut driver = AccDriver::new(acc, v);
+    x.drive_inner(&mut driver)?;
+    Continue(driver.into_acc())
+}
@@ -1,3 +1,10 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+
 use crate::*;
 
 impl<'s, T: ?Sized, V> Drive<'s, V> for Box<T>
@@ -24,6 +31,14 @@ where
         v.visit(&**self, &**other)
     }
 }
+impl<'s, T: ?Sized, V> DriveZipMut<'s, V> for Box<T>
+where
+    V: VisitZipMut<'s, T>,
+{
+    fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        v.visit(&mut **self, &**other)
+    }
+}
 
 impl<'s, T: ?Sized, V> Drive<'s, V> for &T
 where
@@ -65,6 +80,81 @@ where
         v.visit(&**self, &**other)
     }
 }
+impl<'s, T: ?Sized, V> DriveZipMut<'s, V> for &mut T
+where
+    V: VisitZipMut<'s, T>,
+{
+    fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        v.visit(&mut **self, &**other)
+    }
+}
+
+// Pinned pointers are followed. They can only be visited mutably if the pointee is `Unpin`.
+impl<'s, P: Deref, V> Drive<'s, V> for Pin<P>
+where
+    V: Visit<'s, P::Target>,
+{
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        v.visit(&**self)
+    }
+}
+impl<'s, P: DerefMut<Target: Unpin>, V> DriveMut<'s, V> for Pin<P>
+where
+    V: VisitMut<'s, P::Target>,
+{
+    fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+        v.visit(self.as_mut().get_mut())
+    }
+}
+impl<'s, P: Deref, V> DriveTwo<'s, V> for Pin<P>
+where
+    V: VisitTwo<'s, P::Target>,
+{
+    fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        v.visit(&**self, &**other)
+    }
+}
+impl<'s, P: DerefMut<Target: Unpin>, V> DriveZipMut<'s, V> for Pin<P>
+where
+    V: VisitZipMut<'s, P::Target>,
+{
+    fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        v.visit(self.as_mut().get_mut(), &**other)
+    }
+}
+
+impl<'s, T: ?Sized, V> Drive<'s, V> for ManuallyDrop<T>
+where
+    V: Visit<'s, T>,
+{
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        v.visit(&**self)
+    }
+}
+impl<'s, T: ?Sized, V> DriveMut<'s, V> for ManuallyDrop<T>
+where
+    V: VisitMut<'s, T>,
+{
+    fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+        v.visit(&mut **self)
+    }
+}
+impl<'s, T: ?Sized, V> DriveTwo<'s, V> for ManuallyDrop<T>
+where
+    V: VisitTwo<'s, T>,
+{
+    fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        v.visit(&**self, &**other)
+    }
+}
+impl<'s, T: ?Sized, V> DriveZipMut<'s, V> for ManuallyDrop<T>
+where
+    V: VisitZipMut<'s, T>,
+{
+    fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        v.visit(&mut **self, &**other)
+    }
+}
 
 impl<'s, A, B, V: Visit<'s, A> + Visit<'s, B>> Drive<'s, V> for (A, B) {
     fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
@@ -91,6 +181,15 @@ impl<'s, A, B, V: VisitTwo<'s, A> + VisitTwo<'s, B>> DriveTwo<'s, V> for (A, B)
         Continue(())
     }
 }
+impl<'s, A, B, V: VisitZipMut<'s, A> + VisitZipMut<'s, B>> DriveZipMut<'s, V> for (A, B) {
+    fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        let (sa, sb) = self;
+        let (oa, ob) = other;
+        v.visit(sa, oa)?;
+        v.visit(sb, ob)?;
+        Continue(())
+    }
+}
 
 impl<'s, A, B, C, V: Visit<'s, A> + Visit<'s, B> + Visit<'s, C>> Drive<'s, V> for (A, B, C) {
     fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
@@ -124,6 +223,18 @@ impl<'s, A, B, C, V: VisitTwo<'s, A> + VisitTwo<'s, B> + VisitTwo<'s, C>> DriveT
         Continue(())
     }
 }
+impl<'s, A, B, C, V: VisitZipMut<'s, A> + VisitZipMut<'s, B> + VisitZipMut<'s, C>>
+    DriveZipMut<'s, V> for (A, B, C)
+{
+    fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        let (sa, sb, sc) = self;
+        let (oa, ob, oc) = other;
+        v.visit(sa, oa)?;
+        v.visit(sb, ob)?;
+        v.visit(sc, oc)?;
+        Continue(())
+    }
+}
 
 impl<'s, A, B, V: Visit<'s, A> + Visit<'s, B>> Drive<'s, V> for Result<A, B> {
     fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
@@ -152,12 +263,141 @@ impl<'s, A, B, V: VisitTwo<'s, A> + VisitTwo<'s, B>> DriveTwo<'s, V> for Result<
         }
     }
 }
+impl<'s, A, B, V: VisitZipMut<'s, A> + VisitZipMut<'s, B>> DriveZipMut<'s, V> for Result<A, B> {
+    fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        match (self, other) {
+            (Ok(x), Ok(y)) => v.visit(x, y),
+            (Err(x), Err(y)) => v.visit(x, y),
+            _ => Break(Default::default()),
+        }
+    }
+}
+
+impl<T, V: VisitFold<T>> Fold<V> for Box<T> {
+    fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self> {
+        Continue(Box::new(v.visit(*self)?))
+    }
+}
+impl<T, V: VisitFold<T>> Fold<V> for Vec<T> {
+    fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self> {
+        fold_iter(self, v)
+    }
+}
+impl<T, V: VisitFold<T>> Fold<V> for Option<T> {
+    fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self> {
+        Continue(match self {
+            Some(x) => Some(v.visit(x)?),
+            None => None,
+        })
+    }
+}
+impl<T, const N: usize, V: VisitFold<T>> Fold<V> for [T; N] {
+    fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self> {
+        let folded: Vec<T> = fold_iter(self, v)?;
+        // The lengths match since `fold_iter` either folds all items or breaks.
+        Continue(folded.try_into().ok().unwrap())
+    }
+}
+impl<A, B, V: VisitFold<A> + VisitFold<B>> Fold<V> for (A, B) {
+    fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self> {
+        let (x, y) = self;
+        Continue((v.visit(x)?, v.visit(y)?))
+    }
+}
+impl<A, B, C, V: VisitFold<A> + VisitFold<B> + VisitFold<C>> Fold<V> for (A, B, C) {
+    fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self> {
+        let (x, y, z) = self;
+        Continue((v.visit(x)?, v.visit(y)?, v.visit(z)?))
+    }
+}
+impl<A, B, V: VisitFold<A> + VisitFold<B>> Fold<V> for Result<A, B> {
+    fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self> {
+        Continue(match self {
+            Ok(x) => Ok(v.visit(x)?),
+            Err(x) => Err(v.visit(x)?),
+        })
+    }
+}
+
+// Implement `DriveAny[Mut]` for a type by going through its `Drive[Mut]` impl.
+// If given, `heap_size(this) expr` computes `own_heap_size`.
+macro_rules! any_impl {
+    (<$($param:ident),*> $ty:ty $(, heap_size($this:ident) $heap_size:expr)?) => {
+        impl<$($param: DriveAny),*> DriveAny for $ty {
+            fn drive_any_inner<'a>(&'a self, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+                self.drive_inner(&mut AnyDriver::new(v))
+            }
+            $(
+                fn own_heap_size(&self) -> usize {
+                    let $this = self;
+                    $heap_size
+                }
+            )?
+        }
+        impl<$($param: DriveAnyMut),*> DriveAnyMut for $ty {
+            fn drive_any_inner_mut(&mut self, v: &mut dyn AnyVisitorMut) -> ControlFlow<()> {
+                self.drive_inner_mut(&mut AnyDriverMut::new(v))
+            }
+        }
+        impl<$($param: DriveDiff + ::std::fmt::Debug),*> DriveDiff for $ty {
+            fn drive_diff_inner<'a>(&'a self, other: &'a Self, v: &mut DiffVisitor) -> ControlFlow<()> {
+                self.drive_two_inner(other, v)
+            }
+        }
+        impl<$($param),*> NodeKind for $ty {
+            fn kind(&self) -> &'static str {
+                short_type_name::<Self>()
+            }
+        }
+    };
+}
+any_impl!(<T> Box<T>, heap_size(_this) size_of::<T>());
+any_impl!(<A, B> (A, B));
+any_impl!(<A, B, C> (A, B, C));
+any_impl!(<A, B> Result<A, B>);
+any_impl!(<T> ManuallyDrop<T>);
+
+// Make an impl for a newtype wrapper by driving into the wrapped value.
+macro_rules! wrapper_impl {
+    ($($wrapper:ident)::+) => {
+        impl<'s, T, V: Visit<'s, T>> Drive<'s, V> for $($wrapper)::+<T> {
+            fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+                v.visit(&self.0)
+            }
+        }
+        impl<'s, T, V: VisitMut<'s, T>> DriveMut<'s, V> for $($wrapper)::+<T> {
+            fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+                v.visit(&mut self.0)
+            }
+        }
+        impl<'s, T, V: VisitTwo<'s, T>> DriveTwo<'s, V> for $($wrapper)::+<T> {
+            fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+                v.visit(&self.0, &other.0)
+            }
+        }
+        impl<'s, T, V: VisitZipMut<'s, T>> DriveZipMut<'s, V> for $($wrapper)::+<T> {
+            fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+                v.visit(&mut self.0, &other.0)
+            }
+        }
+        impl<T, V: VisitFold<T>> Fold<V> for $($wrapper)::+<T> {
+            fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self> {
+                Continue($($wrapper)::+(v.visit(self.0)?))
+            }
+        }
+        any_impl!(<T> $($wrapper)::+<T>);
+    };
+}
+wrapper_impl!(std::num::Wrapping);
+wrapper_impl!(std::num::Saturating);
+wrapper_impl!(std::cmp::Reverse);
 
 // Make an impl for an iterable type.
 macro_rules! iter_impl {
         (<$($param_or_const:ident $($const_ident:ident : $const_ty:ty)?),*> $ty:ty,
             $iter:ident($iter_ty:ty),
             $iter_mut:ident($iter_mut_ty:ty)
+            $(, heap_size($this:ident) $heap_size:expr)?
         ) => {
             impl<'s, $($param_or_const $($const_ident : $const_ty)?,)* V> Drive<'s, V> for $ty
             where
@@ -192,19 +432,182 @@ macro_rules! iter_impl {
                     crate::drive_iter_two(self, other, v)
                 }
             }
+            impl<'s, $($param_or_const $($const_ident : $const_ty)?,)* V> DriveZipMut<'s, V> for $ty
+            where
+                V: Visitor,
+                V: VisitZipMut<'s, $iter_mut_ty>,
+            {
+                fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+                    crate::drive_iter_zip_mut(self.$iter_mut(), other.$iter(), v)
+                }
+            }
+            impl<$($param_or_const $($const_ident : $const_ty)?,)*> DriveAny for $ty
+            where
+                $iter_ty: DriveAny,
+            {
+                fn drive_any_inner<'a>(&'a self, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+                    self.drive_inner(&mut AnyDriver::new(v))
+                }
+                $(
+                    fn own_heap_size(&self) -> usize {
+                        let $this = self;
+                        $heap_size
+                    }
+                )?
+            }
+            impl<$($param_or_const $($const_ident : $const_ty)?,)*> DriveAnyMut for $ty
+            where
+                $iter_mut_ty: DriveAnyMut,
+            {
+                fn drive_any_inner_mut(&mut self, v: &mut dyn AnyVisitorMut) -> ControlFlow<()> {
+                    self.drive_inner_mut(&mut AnyDriverMut::new(v))
+                }
+            }
+            impl<$($param_or_const $($const_ident : $const_ty)?,)*> DriveDiff for $ty
+            where
+                $iter_ty: DriveDiff + Debug,
+            {
+                fn drive_diff_inner<'a>(&'a self, other: &'a Self, v: &mut DiffVisitor) -> ControlFlow<()> {
+                    self.drive_two_inner(other, v)
+                }
+            }
+            impl<$($param_or_const $($const_ident : $const_ty)?,)*> NodeKind for $ty {
+                fn kind(&self) -> &'static str {
+                    short_type_name::<Self>()
+                }
+            }
         };
     }
-iter_impl!(<T> Vec<T>, iter(T), iter_mut(T));
+iter_impl!(<T> Vec<T>, iter(T), iter_mut(T), heap_size(this) this.capacity() * size_of::<T>());
 iter_impl!(<T> Option<T>, iter(T), iter_mut(T));
 iter_impl!(<T, const N: usize> [T; N], iter(T), iter_mut(T));
 
+// Unsized types, reachable through `Box`, `&`, etc. These aren't `DriveAny` since they can't be
+// made into a `&dyn DriveAny`. Together with the pointer impls, this makes `Box<[T]>`, `Rc<[T]>`,
+// `Arc<str>` etc. drivable, as long as the visitor drives through `[T]`/`str`.
+impl<'s, T, V: Visit<'s, T>> Drive<'s, V> for [T] {
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter(self, v)
+    }
+}
+impl<'s, T, V: VisitMut<'s, T>> DriveMut<'s, V> for [T] {
+    fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_mut(self, v)
+    }
+}
+impl<'s, T, V: VisitTwo<'s, T>> DriveTwo<'s, V> for [T] {
+    fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_two(self, other, v)
+    }
+}
+impl<'s, T, V: VisitZipMut<'s, T>> DriveZipMut<'s, V> for [T] {
+    fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_zip_mut(self, other, v)
+    }
+}
+
+/// Maps visit each key then its value. Keys can't be modified in place, so only the values are
+/// visited mutably; likewise sets are only visited immutably. These are generic over the hasher, so
+/// they also cover aliases like `FxHashMap`.
+impl<'s, K, T, S, V> Drive<'s, V> for HashMap<K, T, S>
+where
+    V: Visit<'s, K> + Visit<'s, T>,
+{
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_pairs(self, v)
+    }
+}
+impl<'s, K, T, S, V: VisitMut<'s, T>> DriveMut<'s, V> for HashMap<K, T, S> {
+    fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_mut(self.values_mut(), v)
+    }
+}
+impl<'s, T, S, V: Visit<'s, T>> Drive<'s, V> for HashSet<T, S> {
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter(self, v)
+    }
+}
+impl<K: DriveAny, T: DriveAny, S: 'static> DriveAny for HashMap<K, T, S> {
+    fn drive_any_inner<'a>(&'a self, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+        self.drive_inner(&mut AnyDriver::new(v))
+    }
+    fn own_heap_size(&self) -> usize {
+        self.capacity() * size_of::<(K, T)>()
+    }
+}
+impl<K: 'static, T: DriveAnyMut, S: 'static> DriveAnyMut for HashMap<K, T, S> {
+    fn drive_any_inner_mut(&mut self, v: &mut dyn AnyVisitorMut) -> ControlFlow<()> {
+        self.drive_inner_mut(&mut AnyDriverMut::new(v))
+    }
+}
+impl<T: DriveAny, S: 'static> DriveAny for HashSet<T, S> {
+    fn drive_any_inner<'a>(&'a self, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+        self.drive_inner(&mut AnyDriver::new(v))
+    }
+    fn own_heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>()
+    }
+}
+/// The elements of a set can't be modified in place, so there is nothing to visit mutably.
+impl<T: 'static, S: 'static> DriveAnyMut for HashSet<T, S> {
+    fn drive_any_inner_mut(&mut self, _: &mut dyn AnyVisitorMut) -> ControlFlow<()> {
+        Continue(())
+    }
+}
+impl<K, T, S> NodeKind for HashMap<K, T, S> {
+    fn kind(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
+}
+impl<T, S> NodeKind for HashSet<T, S> {
+    fn kind(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
+}
+/// Unlike in place, keys can be changed by value: maps and sets are rebuilt from the folded
+/// entries, so entries whose keys fold to the same value are merged.
+impl<K, T, S, V> Fold<V> for HashMap<K, T, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+    V: VisitFold<K> + VisitFold<T>,
+{
+    fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self> {
+        let mut folded = HashMap::with_capacity_and_hasher(self.len(), S::default());
+        for (k, x) in self {
+            folded.insert(v.visit(k)?, v.visit(x)?);
+        }
+        Continue(folded)
+    }
+}
+impl<T, S, V> Fold<V> for HashSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher + Default,
+    V: VisitFold<T>,
+{
+    fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self> {
+        fold_iter(self, v)
+    }
+}
+
+#[cfg(feature = "nonempty")]
+iter_impl!(<T> nonempty::NonEmpty<T>, iter(T), iter_mut(T),
+    heap_size(this) this.tail.capacity() * size_of::<T>());
+#[cfg(feature = "vec1")]
+iter_impl!(<T> vec1::Vec1<T>, iter(T), iter_mut(T),
+    heap_size(this) this.capacity() * size_of::<T>());
+#[cfg(feature = "smallvec")]
+iter_impl!(<T, const N: usize> smallvec::SmallVec<[T; N]>, iter(T), iter_mut(T),
+    heap_size(this) if this.spilled() { this.capacity() * size_of::<T>() } else { 0 });
+#[cfg(feature = "thin_vec")]
+iter_impl!(<T> thin_vec::ThinVec<T>, iter(T), iter_mut(T),
+    heap_size(this) this.capacity() * size_of::<T>());
+
 // Make an impl for a type without contents to visit.
+// If given, `heap_size(this) expr` computes `own_heap_size`.
 macro_rules! leaf_impl {
-    ($ty:ty, $($rest:tt)*) => {
-        leaf_impl!($ty);
-        leaf_impl!($($rest)*);
-    };
-    ($ty:ty) => {
+    (@drive $ty:ty) => {
         impl<'s, V: Visitor> Drive<'s, V> for $ty {
             fn drive_inner(&'s self, _: &mut V) -> ControlFlow<V::Break> {
                 Continue(())
@@ -224,10 +627,825 @@ macro_rules! leaf_impl {
                 }
             }
         }
+        impl<'s, V: Visitor<Break: Default>> DriveZipMut<'s, V> for $ty {
+            fn drive_zip_mut_inner(&'s mut self, _: &'s Self, _: &mut V) -> ControlFlow<V::Break> {
+                Continue(())
+            }
+        }
+    };
+    (@impl $ty:ty $(, $own_heap_size:item)?) => {
+        leaf_impl!(@drive $ty);
+        impl<V: Visitor> Fold<V> for $ty {
+            fn fold_inner(self, _: &mut V) -> ControlFlow<V::Break, Self> {
+                Continue(self)
+            }
+        }
+        impl DriveAny for $ty {
+            fn drive_any_inner<'a>(&'a self, _: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+                Continue(())
+            }
+            $($own_heap_size)?
+        }
+        impl DriveAnyMut for $ty {
+            fn drive_any_inner_mut(&mut self, _: &mut dyn AnyVisitorMut) -> ControlFlow<()> {
+                Continue(())
+            }
+        }
+        impl DriveDiff for $ty {
+            fn drive_diff_inner<'a>(&'a self, other: &'a Self, v: &mut DiffVisitor) -> ControlFlow<()> {
+                self.drive_two_inner(other, v)
+            }
+        }
+        impl NodeKind for $ty {
+            fn kind(&self) -> &'static str {
+                short_type_name::<Self>()
+            }
+        }
+    };
+    ($ty:ty, heap_size($this:ident) $heap_size:expr) => {
+        leaf_impl!(@impl $ty, fn own_heap_size(&self) -> usize {
+            let $this = self;
+            $heap_size
+        });
+    };
+    ($ty:ty, $($rest:tt)*) => {
+        leaf_impl!($ty);
+        leaf_impl!($($rest)*);
+    };
+    ($ty:ty) => {
+        leaf_impl!(@impl $ty);
     };
 }
 leaf_impl!(bool, char, u8, u16, u32, u64, u128, usize);
 leaf_impl!(i8, i16, i32, i64, i128, isize);
-leaf_impl!((), String);
+leaf_impl!(f32, f64);
+leaf_impl!(
+    std::num::NonZeroU8,
+    std::num::NonZeroU16,
+    std::num::NonZeroU32,
+    std::num::NonZeroU64,
+    std::num::NonZeroU128,
+    std::num::NonZeroUsize
+);
+leaf_impl!(
+    std::num::NonZeroI8,
+    std::num::NonZeroI16,
+    std::num::NonZeroI32,
+    std::num::NonZeroI64,
+    std::num::NonZeroI128,
+    std::num::NonZeroIsize
+);
+leaf_impl!((), Infallible);
+leaf_impl!(String, heap_size(this) this.capacity());
+leaf_impl!(std::ffi::CString, heap_size(this) this.as_bytes_with_nul().len());
+leaf_impl!(std::ffi::OsString, heap_size(this) this.capacity());
+leaf_impl!(std::path::PathBuf, heap_size(this) this.capacity());
+leaf_impl!(
+    std::time::Duration,
+    std::time::Instant,
+    std::time::SystemTime
+);
+leaf_impl!(std::cmp::Ordering);
+leaf_impl!(Discriminant);
+
+// Unsized types without contents, reachable through `Box`, `&`, etc. These aren't `DriveAny` since
+// they can't be made into a `&dyn DriveAny`.
+macro_rules! unsized_leaf_impl {
+    ($($ty:ty),*) => {
+        $(leaf_impl!(@drive $ty);)*
+    };
+}
+unsized_leaf_impl!(str, std::ffi::CStr, std::ffi::OsStr, std::path::Path);
+
 #[cfg(feature = "extra_impls")]
 leaf_impl!(ustr::Ustr);
+#[cfg(feature = "bytes")]
+leaf_impl!(bytes::Bytes, bytes::BytesMut);
+#[cfg(feature = "camino")]
+leaf_impl!(camino::Utf8PathBuf);
+#[cfg(feature = "camino")]
+unsized_leaf_impl!(camino::Utf8Path);
+#[cfg(feature = "url")]
+leaf_impl!(url::Url);
+#[cfg(feature = "smol_str")]
+leaf_impl!(smol_str::SmolStr);
+#[cfg(feature = "compact_str")]
+leaf_impl!(compact_str::CompactString);
+#[cfg(feature = "ecow")]
+leaf_impl!(ecow::EcoString);
+
+/// The bits of a `BitVec` are visited as `bool`s. Bits are packed so we can't hand out references
+/// to them; instead we visit references to constants, or to a temporary that is written back.
+#[cfg(feature = "bitvec")]
+mod bitvec_impls {
+    use crate::*;
+    use bitvec::{order::BitOrder, store::BitStore, vec::BitVec};
+
+    fn bool_ref(b: bool) -> &'static bool {
+        if b {
+            &true
+        } else {
+            &false
+        }
+    }
+
+    impl<'s, T: BitStore, O: BitOrder, V: Visit<'s, bool>> Drive<'s, V> for BitVec<T, O> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            for b in self.iter().by_vals() {
+                v.visit(bool_ref(b))?;
+            }
+            Continue(())
+        }
+    }
+    impl<'s, T: BitStore, O: BitOrder, V> DriveMut<'s, V> for BitVec<T, O>
+    where
+        V: for<'b> VisitMut<'b, bool>,
+    {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            for mut bit in self.iter_mut() {
+                let mut b = *bit;
+                let result = v.visit(&mut b);
+                bit.set(b);
+                result?;
+            }
+            Continue(())
+        }
+    }
+    impl<'s, T: BitStore, O: BitOrder, V: VisitTwo<'s, bool>> DriveTwo<'s, V> for BitVec<T, O> {
+        fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+            if self.len() != other.len() {
+                return Break(Default::default());
+            }
+            for (x, y) in self.iter().by_vals().zip(other.iter().by_vals()) {
+                v.visit(bool_ref(x), bool_ref(y))?;
+            }
+            Continue(())
+        }
+    }
+    impl<T: BitStore, O: BitOrder> NodeKind for BitVec<T, O> {
+        fn kind(&self) -> &'static str {
+            "BitVec"
+        }
+    }
+    impl<T: BitStore + 'static, O: BitOrder + 'static> DriveAny for BitVec<T, O> {
+        fn drive_any_inner<'a>(&'a self, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+            for b in self.iter().by_vals() {
+                drive_any(bool_ref(b), v)?;
+            }
+            Continue(())
+        }
+    }
+    impl<T: BitStore + 'static, O: BitOrder + 'static> DriveAnyMut for BitVec<T, O> {
+        fn drive_any_inner_mut(&mut self, v: &mut dyn AnyVisitorMut) -> ControlFlow<()> {
+            for mut bit in self.iter_mut() {
+                let mut b = *bit;
+                let result = drive_any_mut(&mut b, v);
+                bit.set(b);
+                result?;
+            }
+            Continue(())
+        }
+    }
+    impl<T: BitStore + 'static, O: BitOrder + 'static> DriveDiff for BitVec<T, O> {
+        fn drive_diff_inner<'a>(&'a self, other: &'a Self, v: &mut DiffVisitor) -> ControlFlow<()> {
+            self.drive_two_inner(other, v)
+        }
+    }
+}
+
+/// Arrays are visited element by element, in logical order.
+#[cfg(feature = "ndarray")]
+mod ndarray_impls {
+    use crate::*;
+    use ndarray::{Array, Dimension};
+    use std::fmt::Debug;
+
+    impl<'s, A, D: Dimension, V: Visit<'s, A>> Drive<'s, V> for Array<A, D> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter(self, v)
+        }
+    }
+    impl<'s, A, D: Dimension, V: VisitMut<'s, A>> DriveMut<'s, V> for Array<A, D> {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_mut(self, v)
+        }
+    }
+    impl<'s, A, D: Dimension, V: VisitTwo<'s, A>> DriveTwo<'s, V> for Array<A, D> {
+        fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+            if self.shape() != other.shape() {
+                return Break(Default::default());
+            }
+            drive_iter_two(self, other, v)
+        }
+    }
+    impl<A, D: Dimension> NodeKind for Array<A, D> {
+        fn kind(&self) -> &'static str {
+            "Array"
+        }
+    }
+    impl<A: DriveAny, D: Dimension + 'static> DriveAny for Array<A, D> {
+        fn drive_any_inner<'a>(&'a self, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+            self.drive_inner(&mut AnyDriver::new(v))
+        }
+    }
+    impl<A: DriveAnyMut, D: Dimension + 'static> DriveAnyMut for Array<A, D> {
+        fn drive_any_inner_mut(&mut self, v: &mut dyn AnyVisitorMut) -> ControlFlow<()> {
+            self.drive_inner_mut(&mut AnyDriverMut::new(v))
+        }
+    }
+    impl<A: DriveDiff + Debug, D: Dimension + 'static> DriveDiff for Array<A, D> {
+        fn drive_diff_inner<'a>(&'a self, other: &'a Self, v: &mut DiffVisitor) -> ControlFlow<()> {
+            self.drive_two_inner(other, v)
+        }
+    }
+}
+
+/// Arena-allocated boxes and vectors behave like their `std` counterparts. Since they borrow the
+/// arena, they can't implement `DriveAny[Mut]`.
+#[cfg(feature = "bumpalo")]
+mod bumpalo_impls {
+    use crate::*;
+    use bumpalo::{boxed::Box, collections::Vec};
+
+    impl<'s, T: ?Sized, V: Visit<'s, T>> Drive<'s, V> for Box<'_, T> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            v.visit(&**self)
+        }
+    }
+    impl<'s, T: ?Sized, V: VisitMut<'s, T>> DriveMut<'s, V> for Box<'_, T> {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            v.visit(&mut **self)
+        }
+    }
+    impl<'s, T: ?Sized, V: VisitTwo<'s, T>> DriveTwo<'s, V> for Box<'_, T> {
+        fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+            v.visit(&**self, &**other)
+        }
+    }
+
+    impl<'s, T, V: Visit<'s, T>> Drive<'s, V> for Vec<'_, T> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter(self, v)
+        }
+    }
+    impl<'s, T, V: VisitMut<'s, T>> DriveMut<'s, V> for Vec<'_, T> {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_mut(self, v)
+        }
+    }
+    impl<'s, T, V: VisitTwo<'s, T>> DriveTwo<'s, V> for Vec<'_, T> {
+        fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_two(self, other, v)
+        }
+    }
+}
+
+/// Shared pointers are followed. Visiting mutably uses `make_mut`, which clones the contents if the
+/// pointer isn't unique; see the doc of the `DriveMut` impl.
+macro_rules! arc_impl {
+    ($($arc:ident)::+, $kind:literal) => {
+        impl<'s, T: ?Sized, V: Visit<'s, T>> Drive<'s, V> for $($arc)::+<T> {
+            fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+                v.visit(&**self)
+            }
+        }
+        /// **This clones shared contents.** The contents are visited through `make_mut`, so if
+        /// other pointers share them, they are deep-cloned first and this pointer no longer shares
+        /// them afterwards: mutating a DAG with shared subtrees unshares every subtree it visits,
+        /// even if the visitor doesn't change anything.
+        ///
+        /// This also requires `T: Clone + Sized`, so e.g. `Rc<[T]>` or `Arc<str>` aren't
+        /// `DriveMut`; `skip` such fields or visit them with a custom impl.
+        impl<'s, T: Clone, V: VisitMut<'s, T>> DriveMut<'s, V> for $($arc)::+<T> {
+            fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+                v.visit($($arc)::+::make_mut(self))
+            }
+        }
+        impl<'s, T: ?Sized, V: VisitTwo<'s, T>> DriveTwo<'s, V> for $($arc)::+<T> {
+            fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+                v.visit(&**self, &**other)
+            }
+        }
+        impl<T: DriveAny> DriveAny for $($arc)::+<T> {
+            fn drive_any_inner<'a>(&'a self, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+                self.drive_inner(&mut AnyDriver::new(v))
+            }
+        }
+        impl<T: DriveAnyMut + Clone> DriveAnyMut for $($arc)::+<T> {
+            fn drive_any_inner_mut(&mut self, v: &mut dyn AnyVisitorMut) -> ControlFlow<()> {
+                self.drive_inner_mut(&mut AnyDriverMut::new(v))
+            }
+        }
+        impl<T: DriveDiff + Debug> DriveDiff for $($arc)::+<T> {
+            fn drive_diff_inner<'a>(&'a self, other: &'a Self, v: &mut DiffVisitor) -> ControlFlow<()> {
+                self.drive_two_inner(other, v)
+            }
+        }
+        impl<T: ?Sized> NodeKind for $($arc)::+<T> {
+            fn kind(&self) -> &'static str {
+                $kind
+            }
+        }
+    };
+}
+arc_impl!(std::rc::Rc, "Rc");
+arc_impl!(std::sync::Arc, "Arc");
+#[cfg(feature = "servo_arc")]
+arc_impl!(servo_arc::Arc, "Arc");
+#[cfg(feature = "triomphe")]
+arc_impl!(triomphe::Arc, "Arc");
+
+/// Like shared pointers, an `EcoVec` is cloned on write if it isn't unique.
+#[cfg(feature = "ecow")]
+mod ecow_impls {
+    use crate::*;
+    use ecow::EcoVec;
+
+    impl<'s, T, V: Visit<'s, T>> Drive<'s, V> for EcoVec<T> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter(self.as_slice(), v)
+        }
+    }
+    /// **This clones shared contents.** The elements are visited through `make_mut`, so if the
+    /// vector is shared, it is cloned first and no longer shared afterwards, even if the visitor
+    /// doesn't change anything.
+    impl<'s, T: Clone, V: VisitMut<'s, T>> DriveMut<'s, V> for EcoVec<T> {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_mut(self.make_mut(), v)
+        }
+    }
+    impl<'s, T, V: VisitTwo<'s, T>> DriveTwo<'s, V> for EcoVec<T> {
+        fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_two(self.as_slice(), other.as_slice(), v)
+        }
+    }
+}
+
+#[cfg(feature = "either")]
+mod either_impls {
+    use crate::*;
+    use either::Either::{self, Left, Right};
+
+    impl<'s, L, R, V: Visit<'s, L> + Visit<'s, R>> Drive<'s, V> for Either<L, R> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            match self {
+                Left(x) => v.visit(x)?,
+                Right(x) => v.visit(x)?,
+            }
+            Continue(())
+        }
+    }
+    impl<'s, L, R, V: VisitMut<'s, L> + VisitMut<'s, R>> DriveMut<'s, V> for Either<L, R> {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            match self {
+                Left(x) => v.visit(x)?,
+                Right(x) => v.visit(x)?,
+            }
+            Continue(())
+        }
+    }
+    impl<'s, L, R, V: VisitTwo<'s, L> + VisitTwo<'s, R>> DriveTwo<'s, V> for Either<L, R> {
+        fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+            match (self, other) {
+                (Left(x), Left(y)) => v.visit(x, y),
+                (Right(x), Right(y)) => v.visit(x, y),
+                _ => Break(Default::default()),
+            }
+        }
+    }
+    impl<'s, L, R, V: VisitZipMut<'s, L> + VisitZipMut<'s, R>> DriveZipMut<'s, V> for Either<L, R> {
+        fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+            match (self, other) {
+                (Left(x), Left(y)) => v.visit(x, y),
+                (Right(x), Right(y)) => v.visit(x, y),
+                _ => Break(Default::default()),
+            }
+        }
+    }
+    any_impl!(<L, R> Either<L, R>);
+
+    /// A visitor chosen at runtime visits with whichever visitor it holds.
+    impl<L: Visitor, R: Visitor<Break = L::Break>> Visitor for Either<L, R> {
+        type Break = L::Break;
+    }
+    impl<'a, T, L, R> Visit<'a, T> for Either<L, R>
+    where
+        T: ?Sized,
+        L: Visit<'a, T>,
+        R: Visit<'a, T> + Visitor<Break = L::Break>,
+    {
+        fn visit(&mut self, x: &'a T) -> ControlFlow<Self::Break> {
+            match self {
+                Left(v) => v.visit(x),
+                Right(v) => v.visit(x),
+            }
+        }
+    }
+    impl<'a, T, L, R> VisitMut<'a, T> for Either<L, R>
+    where
+        T: ?Sized,
+        L: VisitMut<'a, T>,
+        R: VisitMut<'a, T> + Visitor<Break = L::Break>,
+    {
+        fn visit(&mut self, x: &'a mut T) -> ControlFlow<Self::Break> {
+            match self {
+                Left(v) => v.visit(x),
+                Right(v) => v.visit(x),
+            }
+        }
+    }
+    impl<'a, L: AnyVisitor<'a>, R: AnyVisitor<'a>> AnyVisitor<'a> for Either<L, R> {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            either::for_both!(self, v => v.enter(x))
+        }
+        fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+            either::for_both!(self, v => v.exit(x))
+        }
+        fn enter_binder(&mut self) {
+            either::for_both!(self, v => v.enter_binder())
+        }
+        fn exit_binder(&mut self) {
+            either::for_both!(self, v => v.exit_binder())
+        }
+    }
+    impl<L: AnyVisitorMut, R: AnyVisitorMut> AnyVisitorMut for Either<L, R> {
+        fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+            either::for_both!(self, v => v.enter(x))
+        }
+        fn exit(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<()> {
+            either::for_both!(self, v => v.exit(x))
+        }
+        fn enter_binder(&mut self) {
+            either::for_both!(self, v => v.enter_binder())
+        }
+        fn exit_binder(&mut self) {
+            either::for_both!(self, v => v.exit_binder())
+        }
+    }
+}
+
+/// Persistent collections are only visited immutably. Maps visit each key then its value.
+#[cfg(feature = "im")]
+mod im_impls {
+    use crate::*;
+    use im::{HashMap, OrdMap, Vector};
+    use std::hash::{BuildHasher, Hash};
+
+    impl<'s, T: Clone, V: Visit<'s, T>> Drive<'s, V> for Vector<T> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter(self, v)
+        }
+    }
+    impl<'s, K, T, S, V> Drive<'s, V> for HashMap<K, T, S>
+    where
+        K: Hash + Eq,
+        S: BuildHasher,
+        V: Visit<'s, K> + Visit<'s, T>,
+    {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_pairs(self.iter(), v)
+        }
+    }
+    impl<'s, K: Ord, T, V: Visit<'s, K> + Visit<'s, T>> Drive<'s, V> for OrdMap<K, T> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_pairs(self.iter(), v)
+        }
+    }
+}
+
+/// Persistent collections are only visited immutably. Maps visit each key then its value.
+#[cfg(feature = "rpds")]
+mod rpds_impls {
+    use crate::*;
+    use archery::SharedPointerKind;
+    use rpds::{HashTrieMap, RedBlackTreeMap, Vector};
+    use std::hash::{BuildHasher, Hash};
+
+    impl<'s, T, P: SharedPointerKind, V: Visit<'s, T>> Drive<'s, V> for Vector<T, P> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter(self, v)
+        }
+    }
+    impl<'s, K, T, P, H, V> Drive<'s, V> for HashTrieMap<K, T, P, H>
+    where
+        K: Eq + Hash,
+        P: SharedPointerKind,
+        H: BuildHasher + Clone,
+        V: Visit<'s, K> + Visit<'s, T>,
+    {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_pairs(self.iter(), v)
+        }
+    }
+    impl<'s, K, T, P, V> Drive<'s, V> for RedBlackTreeMap<K, T, P>
+    where
+        K: Ord,
+        P: SharedPointerKind,
+        V: Visit<'s, K> + Visit<'s, T>,
+    {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_pairs(self.iter(), v)
+        }
+    }
+}
+
+/// The entries are visited while holding a read lock on their shard (a write lock when visiting
+/// mutably), so the visitor must not access the map itself. The references don't outlive the
+/// locks, so the visitor must accept any lifetime. Each key is visited before its value; when
+/// visiting mutably, only the values are visited since keys can't be modified in place.
+#[cfg(feature = "dashmap")]
+mod dashmap_impls {
+    use crate::*;
+    use dashmap::DashMap;
+    use std::hash::{BuildHasher, Hash};
+
+    impl<'s, K, T, S, V> Drive<'s, V> for DashMap<K, T, S>
+    where
+        K: Eq + Hash,
+        S: BuildHasher + Clone,
+        V: for<'a> Visit<'a, K> + for<'a> Visit<'a, T>,
+    {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            for entry in self.iter() {
+                v.visit(entry.key())?;
+                v.visit(entry.value())?;
+            }
+            Continue(())
+        }
+    }
+    impl<'s, K, T, S, V> DriveMut<'s, V> for DashMap<K, T, S>
+    where
+        K: Eq + Hash,
+        S: BuildHasher + Clone,
+        V: for<'a> VisitMut<'a, T>,
+    {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            for mut entry in self.iter_mut() {
+                v.visit(entry.value_mut())?;
+            }
+            Continue(())
+        }
+    }
+}
+
+/// An arena visits all its values, in allocation order. Ids are leaves.
+#[cfg(feature = "id_arena")]
+mod id_arena_impls {
+    use crate::*;
+    use id_arena::{Arena, ArenaBehavior, Id};
+
+    impl<'s, T, A: ArenaBehavior, V: Visit<'s, T>> Drive<'s, V> for Arena<T, A> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter(self.iter().map(|(_, x)| x), v)
+        }
+    }
+    impl<'s, T, A: ArenaBehavior, V: VisitMut<'s, T>> DriveMut<'s, V> for Arena<T, A> {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_mut(self.iter_mut().map(|(_, x)| x), v)
+        }
+    }
+    impl<'s, T, A: ArenaBehavior, V: VisitTwo<'s, T>> DriveTwo<'s, V> for Arena<T, A> {
+        fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_two(self.iter().map(|(_, x)| x), other.iter().map(|(_, x)| x), v)
+        }
+    }
+
+    impl<'s, T, V: Visitor> Drive<'s, V> for Id<T> {
+        fn drive_inner(&'s self, _: &mut V) -> ControlFlow<V::Break> {
+            Continue(())
+        }
+    }
+    impl<'s, T, V: Visitor> DriveMut<'s, V> for Id<T> {
+        fn drive_inner_mut(&'s mut self, _: &mut V) -> ControlFlow<V::Break> {
+            Continue(())
+        }
+    }
+    impl<'s, T, V: Visitor<Break: Default>> DriveTwo<'s, V> for Id<T> {
+        fn drive_two_inner(&'s self, other: &'s Self, _: &mut V) -> ControlFlow<V::Break> {
+            if self == other {
+                Continue(())
+            } else {
+                Break(Default::default())
+            }
+        }
+    }
+}
+
+/// Like for `id_arena`, an arena visits all its values, in allocation order, and indices are leaves.
+#[cfg(feature = "la_arena")]
+mod la_arena_impls {
+    use crate::*;
+    use la_arena::{Arena, Idx};
+
+    impl<'s, T, V: Visit<'s, T>> Drive<'s, V> for Arena<T> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter(self.values(), v)
+        }
+    }
+    impl<'s, T, V: VisitMut<'s, T>> DriveMut<'s, V> for Arena<T> {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_mut(self.values_mut(), v)
+        }
+    }
+    impl<'s, T, V: VisitTwo<'s, T>> DriveTwo<'s, V> for Arena<T> {
+        fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_two(self.values(), other.values(), v)
+        }
+    }
+
+    impl<'s, T, V: Visitor> Drive<'s, V> for Idx<T> {
+        fn drive_inner(&'s self, _: &mut V) -> ControlFlow<V::Break> {
+            Continue(())
+        }
+    }
+    impl<'s, T, V: Visitor> DriveMut<'s, V> for Idx<T> {
+        fn drive_inner_mut(&'s mut self, _: &mut V) -> ControlFlow<V::Break> {
+            Continue(())
+        }
+    }
+    impl<'s, T, V: Visitor<Break: Default>> DriveTwo<'s, V> for Idx<T> {
+        fn drive_two_inner(&'s self, other: &'s Self, _: &mut V) -> ControlFlow<V::Break> {
+            if self == other {
+                Continue(())
+            } else {
+                Break(Default::default())
+            }
+        }
+    }
+}
+
+/// Shared access locks around the visit of the contents, which therefore can't be borrowed beyond
+/// it. Mutable access doesn't need to lock.
+#[cfg(feature = "parking_lot")]
+mod parking_lot_impls {
+    use crate::*;
+    use parking_lot::{Mutex, RwLock};
+
+    impl<'s, T: ?Sized, V: for<'a> Visit<'a, T>> Drive<'s, V> for Mutex<T> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            v.visit(&*self.lock())
+        }
+    }
+    impl<'s, T: ?Sized, V: VisitMut<'s, T>> DriveMut<'s, V> for Mutex<T> {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            v.visit(self.get_mut())
+        }
+    }
+
+    impl<'s, T: ?Sized, V: for<'a> Visit<'a, T>> Drive<'s, V> for RwLock<T> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            v.visit(&*self.read())
+        }
+    }
+    impl<'s, T: ?Sized, V: VisitMut<'s, T>> DriveMut<'s, V> for RwLock<T> {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            v.visit(self.get_mut())
+        }
+    }
+}
+
+/// A graph visits all its node weights, then all its edge weights, in index order. The two can't
+/// be borrowed mutably for the same lifetime, hence the higher-ranked bounds on `DriveMut`.
+#[cfg(feature = "petgraph")]
+mod petgraph_impls {
+    use crate::*;
+    use petgraph::graph::{Graph, IndexType};
+    use petgraph::stable_graph::StableGraph;
+    use petgraph::EdgeType;
+
+    impl<'s, N, E, Ty, Ix, V> Drive<'s, V> for Graph<N, E, Ty, Ix>
+    where
+        Ty: EdgeType,
+        Ix: IndexType,
+        V: Visit<'s, N> + Visit<'s, E>,
+    {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter(self.node_weights(), v)?;
+            drive_iter(self.edge_weights(), v)
+        }
+    }
+    impl<'s, N, E, Ty, Ix, V> DriveMut<'s, V> for Graph<N, E, Ty, Ix>
+    where
+        Ty: EdgeType,
+        Ix: IndexType,
+        V: for<'a> VisitMut<'a, N> + for<'a> VisitMut<'a, E>,
+    {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_mut(self.node_weights_mut(), v)?;
+            drive_iter_mut(self.edge_weights_mut(), v)
+        }
+    }
+
+    impl<'s, N, E, Ty, Ix, V> Drive<'s, V> for StableGraph<N, E, Ty, Ix>
+    where
+        Ty: EdgeType,
+        Ix: IndexType,
+        V: Visit<'s, N> + Visit<'s, E>,
+    {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter(self.node_weights(), v)?;
+            drive_iter(self.edge_weights(), v)
+        }
+    }
+    impl<'s, N, E, Ty, Ix, V> DriveMut<'s, V> for StableGraph<N, E, Ty, Ix>
+    where
+        Ty: EdgeType,
+        Ix: IndexType,
+        V: for<'a> VisitMut<'a, N> + for<'a> VisitMut<'a, E>,
+    {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_mut(self.node_weights_mut(), v)?;
+            drive_iter_mut(self.edge_weights_mut(), v)
+        }
+    }
+}
+
+/// A JSON value visits the elements of an array and the values of an object. Scalars have no
+/// contents; a visitor that wants to inspect them matches on the `Value` it is given.
+#[cfg(feature = "serde_json")]
+mod serde_json_impls {
+    use crate::*;
+    use serde_json::Value;
+
+    impl<'s, V: Visit<'s, Value>> Drive<'s, V> for Value {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            match self {
+                Value::Array(values) => drive_iter(values, v),
+                Value::Object(map) => drive_iter(map.values(), v),
+                Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => Continue(()),
+            }
+        }
+    }
+    impl<'s, V: VisitMut<'s, Value>> DriveMut<'s, V> for Value {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            match self {
+                Value::Array(values) => drive_iter_mut(values, v),
+                Value::Object(map) => drive_iter_mut(map.values_mut(), v),
+                Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => Continue(()),
+            }
+        }
+    }
+}
+
+/// Like JSON values, a TOML value visits the elements of an array and the values of a table.
+#[cfg(feature = "toml")]
+mod toml_impls {
+    use crate::*;
+    use toml::Value;
+
+    impl<'s, V: Visit<'s, Value>> Drive<'s, V> for Value {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            match self {
+                Value::Array(values) => drive_iter(values, v),
+                Value::Table(table) => drive_iter(table.values(), v),
+                Value::String(_)
+                | Value::Integer(_)
+                | Value::Float(_)
+                | Value::Boolean(_)
+                | Value::Datetime(_) => Continue(()),
+            }
+        }
+    }
+    impl<'s, V: VisitMut<'s, Value>> DriveMut<'s, V> for Value {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            match self {
+                Value::Array(values) => drive_iter_mut(values, v),
+                Value::Table(table) => drive_iter_mut(table.iter_mut().map(|(_, x)| x), v),
+                Value::String(_)
+                | Value::Integer(_)
+                | Value::Float(_)
+                | Value::Boolean(_)
+                | Value::Datetime(_) => Continue(()),
+            }
+        }
+    }
+}
+
+/// A YAML value visits the elements of a sequence, the values of a mapping and the value inside a
+/// tag. Mapping keys are values too, but they aren't visited since they can't be modified in place.
+#[cfg(feature = "serde_yaml")]
+mod serde_yaml_impls {
+    use crate::*;
+    use serde_yaml::Value;
+
+    impl<'s, V: Visit<'s, Value>> Drive<'s, V> for Value {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            match self {
+                Value::Sequence(values) => drive_iter(values, v),
+                Value::Mapping(map) => drive_iter(map.values(), v),
+                Value::Tagged(tagged) => v.visit(&tagged.value),
+                Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => Continue(()),
+            }
+        }
+    }
+    impl<'s, V: VisitMut<'s, Value>> DriveMut<'s, V> for Value {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            match self {
+                Value::Sequence(values) => drive_iter_mut(values, v),
+                Value::Mapping(map) => drive_iter_mut(map.values_mut(), v),
+                Value::Tagged(tagged) => v.visit(&mut tagged.value),
+                Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => Continue(()),
+            }
+        }
+    }
+}
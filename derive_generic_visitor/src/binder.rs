@@ -0,0 +1,31 @@
+use crate::*;
+
+/// A visitor that keeps track of the binders it is under, e.g. to interpret de Bruijn indices.
+/// The `Drive[Mut]` impls derived with `#[drive(binder)]` on a field call `enter_binder` before
+/// visiting the field and `exit_binder` after, even if the visit breaks, and require
+/// `V: VisitBinder` for any visitor `V`; `#[drive(binder)]` on a type puts all its fields under a
+/// binder. By default these only maintain `binder_depth`.
+pub trait VisitBinder: Visitor {
+    /// The number of binders entered and not yet exited.
+    fn binder_depth(&mut self) -> &mut usize;
+
+    fn enter_binder(&mut self) {
+        *self.binder_depth() += 1;
+    }
+
+    fn exit_binder(&mut self) {
+        *self.binder_depth() -= 1;
+    }
+}
+
+impl<V: VisitBinder + ?Sized> VisitBinder for &mut V {
+    fn binder_depth(&mut self) -> &mut usize {
+        (**self).binder_depth()
+    }
+    fn enter_binder(&mut self) {
+        (**self).enter_binder()
+    }
+    fn exit_binder(&mut self) {
+        (**self).exit_binder()
+    }
+}
@@ -0,0 +1,98 @@
+use crate::*;
+
+/// Wraps a type-erased visitor and stops the traversal once it has entered a given number of
+/// nodes, e.g. to bound the work done by an analysis on adversarial inputs. Afterwards,
+/// `is_truncated` tells whether the value was only partially visited.
+///
+/// The node that exceeds the budget isn't passed on to the inner visitor: the traversal breaks
+/// instead. Like for any break, the nodes being visited at that point don't get their `exit`.
+pub struct Budgeted<V> {
+    inner: V,
+    budget: usize,
+    entered: usize,
+    truncated: bool,
+}
+
+impl<V> Budgeted<V> {
+    /// Let `inner` visit at most `budget` nodes.
+    pub fn new(inner: V, budget: usize) -> Self {
+        Budgeted {
+            inner,
+            budget,
+            entered: 0,
+            truncated: false,
+        }
+    }
+
+    /// The wrapped visitor.
+    pub fn inner(&mut self) -> &mut V {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+
+    /// Whether the traversal was stopped because the budget ran out.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// The number of nodes entered so far.
+    pub fn entered(&self) -> usize {
+        self.entered
+    }
+
+    /// The number of nodes that can still be entered.
+    pub fn remaining(&self) -> usize {
+        self.budget - self.entered
+    }
+
+    /// Counts a node about to be entered. Breaks if it doesn't fit in the budget.
+    fn spend(&mut self) -> ControlFlow<()> {
+        if self.entered == self.budget {
+            self.truncated = true;
+            return Break(());
+        }
+        self.entered += 1;
+        Continue(())
+    }
+}
+
+impl<'a, V: AnyVisitor<'a>> AnyVisitor<'a> for Budgeted<V> {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        self.spend()?;
+        self.inner.enter(x)
+    }
+
+    fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+        self.inner.exit(x)
+    }
+
+    fn enter_binder(&mut self) {
+        self.inner.enter_binder()
+    }
+
+    fn exit_binder(&mut self) {
+        self.inner.exit_binder()
+    }
+}
+
+impl<V: AnyVisitorMut> AnyVisitorMut for Budgeted<V> {
+    fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        self.spend()?;
+        self.inner.enter(x)
+    }
+
+    fn exit(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<()> {
+        self.inner.exit(x)
+    }
+
+    fn enter_binder(&mut self) {
+        self.inner.enter_binder()
+    }
+
+    fn exit_binder(&mut self) {
+        self.inner.exit_binder()
+    }
+}
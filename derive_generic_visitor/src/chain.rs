@@ -0,0 +1,199 @@
+use crate::*;
+
+/// A pipeline of type-erased visitors run in a single traversal. On each node, `enter` is called
+/// on each stage in order, and `exit` in reverse order, like nested middleware.
+///
+/// A stage can short-circuit: if it returns `Break`, the traversal stops; if it returns
+/// `Children::Skip`, the contents of the node are skipped for the whole chain (the remaining
+/// stages still see the node itself).
+#[derive(Default)]
+pub struct VisitorChain<'a, 's> {
+    stages: Vec<&'s mut dyn AnyVisitor<'a>>,
+}
+
+impl<'a, 's> VisitorChain<'a, 's> {
+    pub fn new() -> Self {
+        VisitorChain { stages: Vec::new() }
+    }
+
+    /// Add a stage at the end of the chain.
+    pub fn push(&mut self, stage: &'s mut dyn AnyVisitor<'a>) {
+        self.stages.push(stage);
+    }
+
+    /// Add a stage at the end of the chain; convenience for method chaining.
+    pub fn with(mut self, stage: &'s mut dyn AnyVisitor<'a>) -> Self {
+        self.push(stage);
+        self
+    }
+}
+
+impl<'a> AnyVisitor<'a> for VisitorChain<'a, '_> {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        let mut children = Children::Visit;
+        for stage in &mut self.stages {
+            if let Children::Skip = stage.enter(x)? {
+                children = Children::Skip;
+            }
+        }
+        Continue(children)
+    }
+
+    fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+        for stage in self.stages.iter_mut().rev() {
+            stage.exit(x)?;
+        }
+        Continue(())
+    }
+
+    fn enter_binder(&mut self) {
+        for stage in &mut self.stages {
+            stage.enter_binder();
+        }
+    }
+
+    fn exit_binder(&mut self) {
+        for stage in self.stages.iter_mut().rev() {
+            stage.exit_binder();
+        }
+    }
+}
+
+/// A pipeline of type-erased mutable visitors run in a single traversal. See `VisitorChain`.
+#[derive(Default)]
+pub struct VisitorChainMut<'s> {
+    stages: Vec<&'s mut dyn AnyVisitorMut>,
+}
+
+impl<'s> VisitorChainMut<'s> {
+    pub fn new() -> Self {
+        VisitorChainMut { stages: Vec::new() }
+    }
+
+    /// Add a stage at the end of the chain.
+    pub fn push(&mut self, stage: &'s mut dyn AnyVisitorMut) {
+        self.stages.push(stage);
+    }
+
+    /// Add a stage at the end of the chain; convenience for method chaining.
+    pub fn with(mut self, stage: &'s mut dyn AnyVisitorMut) -> Self {
+        self.push(stage);
+        self
+    }
+}
+
+impl AnyVisitorMut for VisitorChainMut<'_> {
+    fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        let mut children = Children::Visit;
+        for stage in &mut self.stages {
+            if let Children::Skip = stage.enter(x)? {
+                children = Children::Skip;
+            }
+        }
+        Continue(children)
+    }
+
+    fn exit(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<()> {
+        for stage in self.stages.iter_mut().rev() {
+            stage.exit(x)?;
+        }
+        Continue(())
+    }
+
+    fn enter_binder(&mut self) {
+        for stage in &mut self.stages {
+            stage.enter_binder();
+        }
+    }
+
+    fn exit_binder(&mut self) {
+        for stage in self.stages.iter_mut().rev() {
+            stage.exit_binder();
+        }
+    }
+}
+
+/// A tuple of type-erased visitors runs them all in a single traversal, like a `VisitorChain`
+/// whose stages are known statically.
+macro_rules! tuple_impls {
+    ($first:ident $(, $rest:ident)*) => {
+        impl<'a, $first: AnyVisitor<'a>, $($rest: AnyVisitor<'a>),*> AnyVisitor<'a>
+            for ($first, $($rest,)*)
+        {
+            #[allow(non_snake_case)]
+            fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+                let ($first, $($rest,)*) = self;
+                let mut children = $first.enter(x)?;
+                $(if let Children::Skip = $rest.enter(x)? {
+                    children = Children::Skip;
+                })*
+                Continue(children)
+            }
+
+            #[allow(non_snake_case)]
+            fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+                let ($first, $($rest,)*) = self;
+                tuple_impls!(@rev [$first $($rest)*] [] (|v| v.exit(x)?));
+                Continue(())
+            }
+
+            #[allow(non_snake_case)]
+            fn enter_binder(&mut self) {
+                let ($first, $($rest,)*) = self;
+                $first.enter_binder();
+                $($rest.enter_binder();)*
+            }
+
+            #[allow(non_snake_case)]
+            fn exit_binder(&mut self) {
+                let ($first, $($rest,)*) = self;
+                tuple_impls!(@rev [$first $($rest)*] [] (|v| v.exit_binder()));
+            }
+        }
+
+        impl<$first: AnyVisitorMut, $($rest: AnyVisitorMut),*> AnyVisitorMut
+            for ($first, $($rest,)*)
+        {
+            #[allow(non_snake_case)]
+            fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+                let ($first, $($rest,)*) = self;
+                let mut children = $first.enter(x)?;
+                $(if let Children::Skip = $rest.enter(x)? {
+                    children = Children::Skip;
+                })*
+                Continue(children)
+            }
+
+            #[allow(non_snake_case)]
+            fn exit(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<()> {
+                let ($first, $($rest,)*) = self;
+                tuple_impls!(@rev [$first $($rest)*] [] (|v| v.exit(x)?));
+                Continue(())
+            }
+
+            #[allow(non_snake_case)]
+            fn enter_binder(&mut self) {
+                let ($first, $($rest,)*) = self;
+                $first.enter_binder();
+                $($rest.enter_binder();)*
+            }
+
+            #[allow(non_snake_case)]
+            fn exit_binder(&mut self) {
+                let ($first, $($rest,)*) = self;
+                tuple_impls!(@rev [$first $($rest)*] [] (|v| v.exit_binder()));
+            }
+        }
+    };
+    // Run `$body` on the visitors in reverse order.
+    (@rev [] [$($reversed:ident)*] (|$v:ident| $body:expr)) => {
+        $({ let $v = $reversed; $body; })*
+    };
+    (@rev [$head:ident $($tail:ident)*] [$($reversed:ident)*] $f:tt) => {
+        tuple_impls!(@rev [$($tail)*] [$head $($reversed)*] $f)
+    };
+}
+
+tuple_impls!(V1, V2);
+tuple_impls!(V1, V2, V3);
+tuple_impls!(V1, V2, V3, V4);
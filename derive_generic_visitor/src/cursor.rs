@@ -0,0 +1,155 @@
+use std::any::Any;
+
+use crate::*;
+
+/// A position in a tree, that can be moved around and used to modify the node it points to (the
+/// focus), e.g. to apply a rewrite at a location chosen interactively.
+///
+/// The cursor stores the path from the root to the focus, as the index of each node among the
+/// contents of its parent (as visited by `drive_any_mut`). Since a mutable borrow of the focus
+/// can't be kept alongside its ancestors, accessing the focus walks down this path again, so
+/// costs time proportional to the depth of the focus and the number of siblings on the way.
+pub struct Cursor<'a> {
+    root: &'a mut dyn DriveAnyMut,
+    path: Vec<usize>,
+}
+
+impl<'a> Cursor<'a> {
+    /// A cursor focused on `root`.
+    pub fn new(root: &'a mut dyn DriveAnyMut) -> Self {
+        Cursor {
+            root,
+            path: Vec::new(),
+        }
+    }
+
+    /// The path from the root to the focus.
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// The number of ancestors of the focus.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Call `f` on the focus.
+    pub fn with_focus<R>(&mut self, f: impl FnOnce(&mut dyn DriveAnyMut) -> R) -> R {
+        if self.path.is_empty() {
+            return f(&mut *self.root);
+        }
+        let mut walk = WalkPath {
+            path: &self.path,
+            depth: 0,
+            seen: 0,
+            f: Some(f),
+            result: None,
+        };
+        let _ = self.root.drive_any_inner_mut(&mut walk);
+        // The path only leads to existing nodes, unless the focus or one of its ancestors was
+        // modified in a way that removed nodes.
+        walk.result
+            .expect("the cursor points to a node that no longer exists")
+    }
+
+    /// Call `f` on the focus if it is of type `T`.
+    pub fn with_focus_as<T: Any, R>(&mut self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        self.with_focus(|x| x.downcast_mut::<T>().map(f))
+    }
+
+    /// The kind of the focus.
+    pub fn kind(&mut self) -> &'static str {
+        self.with_focus(|x| x.kind())
+    }
+
+    /// The number of nodes directly contained in the focus.
+    pub fn num_children(&mut self) -> usize {
+        self.with_focus(|x| {
+            let mut count = CountChildren(0);
+            let _ = x.drive_any_inner_mut(&mut count);
+            count.0
+        })
+    }
+
+    /// Move to the parent of the focus. Returns `false` if the focus is the root.
+    pub fn up(&mut self) -> bool {
+        self.path.pop().is_some()
+    }
+
+    /// Move to the root.
+    pub fn to_root(&mut self) {
+        self.path.clear();
+    }
+
+    /// Move to the `n`-th child of the focus. Returns `false` if there is no such child.
+    pub fn down(&mut self, n: usize) -> bool {
+        if n < self.num_children() {
+            self.path.push(n);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Move to the next sibling of the focus. Returns `false` if there is none.
+    pub fn next_sibling(&mut self) -> bool {
+        let Some(&n) = self.path.last() else {
+            return false;
+        };
+        self.up();
+        if self.down(n + 1) {
+            true
+        } else {
+            self.path.push(n);
+            false
+        }
+    }
+
+    /// Move to the previous sibling of the focus. Returns `false` if there is none.
+    pub fn prev_sibling(&mut self) -> bool {
+        match self.path.last_mut() {
+            Some(n) if *n > 0 => {
+                *n -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Walks down `path` and calls `f` on the node at the end of it.
+struct WalkPath<'p, F, R> {
+    path: &'p [usize],
+    /// The number of nodes of the path entered so far.
+    depth: usize,
+    /// The number of children of the current node seen so far.
+    seen: usize,
+    f: Option<F>,
+    result: Option<R>,
+}
+
+impl<F: FnOnce(&mut dyn DriveAnyMut) -> R, R> AnyVisitorMut for WalkPath<'_, F, R> {
+    fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        if self.seen != self.path[self.depth] {
+            self.seen += 1;
+            return Continue(Children::Skip);
+        }
+        self.depth += 1;
+        self.seen = 0;
+        if self.depth == self.path.len() {
+            self.result = Some((self.f.take().unwrap())(x));
+            return Break(());
+        }
+        Continue(Children::Visit)
+    }
+}
+
+/// Counts the nodes directly contained in a node.
+struct CountChildren(usize);
+
+impl AnyVisitorMut for CountChildren {
+    fn enter(&mut self, _: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        self.0 += 1;
+        Continue(Children::Skip)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::*;
+
+/// Wraps a type-erased visitor so that it doesn't go deeper than a given depth, the root being at
+/// depth 0: the contents of the nodes at `max_depth` are skipped. This is a safety net against
+/// pathologically nested values, and a way to only look at the first levels of a tree, e.g. to
+/// print a summary of it.
+///
+/// The callback set with `on_limit` is called with the inner visitor on each node whose contents
+/// are skipped because of the limit, e.g. to print `...` in their place.
+pub struct DepthLimited<V, F = fn(&mut V, &'static str)> {
+    inner: V,
+    max_depth: usize,
+    depth: usize,
+    truncated: usize,
+    on_limit: F,
+}
+
+impl<V> DepthLimited<V> {
+    pub fn new(inner: V, max_depth: usize) -> Self {
+        DepthLimited {
+            inner,
+            max_depth,
+            depth: 0,
+            truncated: 0,
+            on_limit: |_, _| {},
+        }
+    }
+}
+
+impl<V, F: FnMut(&mut V, &'static str)> DepthLimited<V, F> {
+    /// Call `f(inner, type_name)` on each node whose contents are skipped because of the limit.
+    pub fn on_limit<G: FnMut(&mut V, &'static str)>(self, f: G) -> DepthLimited<V, G> {
+        DepthLimited {
+            inner: self.inner,
+            max_depth: self.max_depth,
+            depth: self.depth,
+            truncated: self.truncated,
+            on_limit: f,
+        }
+    }
+
+    /// The wrapped visitor.
+    pub fn inner(&mut self) -> &mut V {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+
+    /// The number of nodes whose contents were skipped because of the limit.
+    pub fn truncated(&self) -> usize {
+        self.truncated
+    }
+
+    /// Skips the contents of a node at the maximum depth, if it has any and the inner visitor
+    /// wanted to see them.
+    fn limit(
+        &mut self,
+        children: Children,
+        type_name: &'static str,
+        has_contents: impl FnOnce() -> bool,
+    ) -> Children {
+        if children == Children::Visit && self.depth == self.max_depth && has_contents() {
+            self.truncated += 1;
+            (self.on_limit)(&mut self.inner, type_name);
+            Children::Skip
+        } else {
+            children
+        }
+    }
+}
+
+/// Breaks on the first node it sees, to find out whether a node has contents.
+struct AnyContents;
+
+impl AnyVisitor<'_> for AnyContents {
+    fn enter(&mut self, _: &dyn DriveAny) -> ControlFlow<(), Children> {
+        Break(())
+    }
+}
+
+impl AnyVisitorMut for AnyContents {
+    fn enter(&mut self, _: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        Break(())
+    }
+}
+
+impl<'a, V: AnyVisitor<'a>, F: FnMut(&mut V, &'static str)> AnyVisitor<'a> for DepthLimited<V, F> {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        let children = self.inner.enter(x)?;
+        let children = self.limit(children, x.type_name(), || {
+            x.drive_any_inner(&mut AnyContents).is_break()
+        });
+        self.depth += 1;
+        Continue(children)
+    }
+
+    fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+        self.depth -= 1;
+        self.inner.exit(x)
+    }
+
+    fn enter_binder(&mut self) {
+        self.inner.enter_binder()
+    }
+
+    fn exit_binder(&mut self) {
+        self.inner.exit_binder()
+    }
+}
+
+impl<V: AnyVisitorMut, F: FnMut(&mut V, &'static str)> AnyVisitorMut for DepthLimited<V, F> {
+    fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        let children = self.inner.enter(x)?;
+        let children = self.limit(children, x.type_name(), || {
+            x.drive_any_inner_mut(&mut AnyContents).is_break()
+        });
+        self.depth += 1;
+        Continue(children)
+    }
+
+    fn exit(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<()> {
+        self.depth -= 1;
+        self.inner.exit(x)
+    }
+
+    fn enter_binder(&mut self) {
+        self.inner.enter_binder()
+    }
+
+    fn exit_binder(&mut self) {
+        self.inner.exit_binder()
+    }
+}
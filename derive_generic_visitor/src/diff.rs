@@ -0,0 +1,188 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+
+use crate::*;
+
+/// Renders a node, in place of its `Debug` impl.
+type RenderHook = Box<dyn Fn(&dyn Any) -> String>;
+/// Compares two nodes of the same type, in place of comparing their contents.
+type EqHook = Box<dyn Fn(&dyn Any, &dyn Any) -> bool>;
+
+/// A place where two values differ, as found by `diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// The nodes from the root down to the differing one. Each is given by its position among
+    /// the contents of its parent (0 for the root) and the `NodeKind` of the left-hand node.
+    pub path: Vec<(usize, &'static str)>,
+    /// The rendering of the left-hand node.
+    pub left: String,
+    /// The rendering of the right-hand node.
+    pub right: String,
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (index, kind)) in self.path.iter().enumerate() {
+            if i == 0 {
+                write!(f, "{kind}")?;
+            } else {
+                write!(f, "/{index}:{kind}")?;
+            }
+        }
+        write!(f, ": {} != {}", self.left, self.right)
+    }
+}
+
+/// A type that can be compared by `DiffVisitor`. Derive it with `#[derive(DriveDiff)]` on a type
+/// that also implements `DriveTwo`.
+pub trait DriveDiff: Any + NodeKind {
+    /// Call `v.visit()` on the immediate contents of `self` and `other`, as `drive_two_inner`.
+    fn drive_diff_inner<'a>(&'a self, other: &'a Self, v: &mut DiffVisitor) -> ControlFlow<()>;
+}
+
+/// A lockstep visitor that collects the differences between two values.
+///
+/// Nodes are compared with `DriveTwo`: a node is reported when it doesn't match the other side by
+/// itself, e.g. leaves that aren't equal or enums of different variants, and the comparison
+/// continues with the next node. Collections of different lengths have their common prefix
+/// compared, then are reported as a whole. Reported nodes are rendered with their `Debug` impl,
+/// unless a renderer was registered for their type with `with_render`. The nodes of the types
+/// registered with `with_eq` are compared by the given function instead.
+#[derive(Default)]
+pub struct DiffVisitor {
+    differences: Vec<Difference>,
+    path: Vec<(usize, &'static str)>,
+    /// The number of nodes visited so far under each node of `path`.
+    visited: Vec<usize>,
+    hooks: HashMap<TypeId, RenderHook>,
+    eq_hooks: HashMap<TypeId, EqHook>,
+    /// Stop at the first difference, for `equal`.
+    stop_at_first: bool,
+    /// The binders entered so far; differences don't depend on them.
+    binder_depth: usize,
+}
+
+impl DiffVisitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `f` instead of `Debug` to render the differing nodes of type `T`.
+    pub fn with_render<T: Any>(mut self, f: impl Fn(&T) -> String + 'static) -> Self {
+        self.hooks.insert(
+            TypeId::of::<T>(),
+            Box::new(move |x| f(x.downcast_ref::<T>().unwrap())),
+        );
+        self
+    }
+
+    /// Compare the nodes of type `T` with `eq` instead of comparing their contents, e.g. to
+    /// ignore spans or to compare names up to renaming.
+    pub fn with_eq<T: Any>(mut self, eq: impl Fn(&T, &T) -> bool + 'static) -> Self {
+        self.eq_hooks.insert(
+            TypeId::of::<T>(),
+            Box::new(move |a, b| {
+                eq(
+                    a.downcast_ref::<T>().unwrap(),
+                    b.downcast_ref::<T>().unwrap(),
+                )
+            }),
+        );
+        self
+    }
+
+    /// Whether `a` and `b` have no differences. Stops at the first difference found.
+    pub fn equal<'a, T: ?Sized>(mut self, a: &'a T, b: &'a T) -> bool
+    where
+        Self: VisitTwo<'a, T>,
+    {
+        self.stop_at_first = true;
+        let _ = self.visit(a, b);
+        self.differences.is_empty()
+    }
+
+    /// Compare `a` and `b` and return their differences, in traversal order.
+    pub fn diff<'a, T: ?Sized>(mut self, a: &'a T, b: &'a T) -> Vec<Difference>
+    where
+        Self: VisitTwo<'a, T>,
+    {
+        let _ = self.visit(a, b);
+        self.differences
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop_at_first && !self.differences.is_empty()
+    }
+
+    fn render<T: Debug + Any>(&self, x: &T) -> String {
+        match self.hooks.get(&TypeId::of::<T>()) {
+            Some(hook) => hook(x),
+            None => format!("{x:?}"),
+        }
+    }
+}
+
+impl Visitor for DiffVisitor {
+    type Break = ();
+}
+
+impl VisitBinder for DiffVisitor {
+    fn binder_depth(&mut self) -> &mut usize {
+        &mut self.binder_depth
+    }
+}
+
+impl<'a, T> VisitTwo<'a, T> for DiffVisitor
+where
+    T: DriveDiff + Debug,
+{
+    fn visit(&mut self, a: &'a T, b: &'a T) -> ControlFlow<()> {
+        let index = match self.visited.last_mut() {
+            Some(visited) => {
+                *visited += 1;
+                *visited - 1
+            }
+            None => 0,
+        };
+        self.path.push((index, a.kind()));
+        self.visited.push(0);
+        let differ = match self.eq_hooks.get(&TypeId::of::<T>()) {
+            Some(eq) => !eq(a, b),
+            // Unless we stop at the first difference, this never breaks because of the contents,
+            // so a `Break` means the nodes themselves differ.
+            None => a.drive_diff_inner(b, self).is_break() && !self.stopped(),
+        };
+        if differ {
+            self.differences.push(Difference {
+                path: self.path.clone(),
+                left: self.render(a),
+                right: self.render(b),
+            });
+        }
+        self.visited.pop();
+        self.path.pop();
+        if self.stopped() {
+            Break(())
+        } else {
+            Continue(())
+        }
+    }
+}
+
+/// Compare `a` and `b` and return their differences, in traversal order. See `DiffVisitor`.
+pub fn diff<'a, T: ?Sized>(a: &'a T, b: &'a T) -> Vec<Difference>
+where
+    DiffVisitor: VisitTwo<'a, T>,
+{
+    DiffVisitor::new().diff(a, b)
+}
+
+/// Whether `a` and `b` are equal node by node. To override the comparison of some types, use
+/// `DiffVisitor::with_eq` then `DiffVisitor::equal`.
+pub fn structural_eq<'a, T: ?Sized>(a: &'a T, b: &'a T) -> bool
+where
+    DiffVisitor: VisitTwo<'a, T>,
+{
+    DiffVisitor::new().equal(a, b)
+}
@@ -0,0 +1,10 @@
+/// The active variant of an enum, visited before the variant's fields by the `Drive[Mut]` impls
+/// derived with `#[drive(discriminant)]`. This lets visitors observe which variant they're in,
+/// e.g. for hashing or serialization, even when the variant has no fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Discriminant {
+    /// Index of the variant in declaration order (including skipped variants).
+    pub index: usize,
+    /// Name of the variant.
+    pub name: &'static str,
+}
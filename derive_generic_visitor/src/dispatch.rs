@@ -0,0 +1,207 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::*;
+
+type EnterFn<'a, S> = Box<dyn FnMut(&mut S, &'a dyn DriveAny) -> ControlFlow<(), Children> + 'a>;
+type ExitFn<'a, S> = Box<dyn FnMut(&mut S, &'a dyn DriveAny) -> ControlFlow<()> + 'a>;
+
+/// A type-erased visitor assembled from callbacks for the types it's interested in, which are
+/// looked up by `TypeId` when a node is entered or exited. Nodes of other types are walked
+/// through.
+///
+/// A statically-typed visitor is instantiated for every type it traverses, which adds up with
+/// hundreds of types. With this visitor, only the callbacks are specific to a type; the traversal
+/// itself goes through `DriveAny`. Conversely, `AnyDriver` lets a `dyn` visitor be used where a
+/// statically-typed one is expected.
+pub struct DispatchVisitor<'a, S> {
+    state: S,
+    enter: HashMap<TypeId, EnterFn<'a, S>>,
+    exit: HashMap<TypeId, ExitFn<'a, S>>,
+}
+
+impl<'a, S> DispatchVisitor<'a, S> {
+    /// A visitor with no callbacks. The callbacks get `state` as their first argument.
+    pub fn new(state: S) -> Self {
+        DispatchVisitor {
+            state,
+            enter: HashMap::new(),
+            exit: HashMap::new(),
+        }
+    }
+
+    /// Call `f` when entering a node of type `T`. Replaces any previous `on_enter` or `on_visit`
+    /// callback for `T`.
+    pub fn on_enter<T: Any>(
+        mut self,
+        mut f: impl FnMut(&mut S, &'a T) -> ControlFlow<(), Children> + 'a,
+    ) -> Self {
+        self.enter.insert(
+            TypeId::of::<T>(),
+            Box::new(move |s, x| f(s, x.downcast_ref::<T>().unwrap())),
+        );
+        self
+    }
+
+    /// Call `f` when exiting a node of type `T`. Replaces any previous `on_exit` callback for `T`.
+    pub fn on_exit<T: Any>(
+        mut self,
+        mut f: impl FnMut(&mut S, &'a T) -> ControlFlow<()> + 'a,
+    ) -> Self {
+        self.exit.insert(
+            TypeId::of::<T>(),
+            Box::new(move |s, x| f(s, x.downcast_ref::<T>().unwrap())),
+        );
+        self
+    }
+
+    /// Visit the nodes of type `T` with the statically-typed `Visit<T>` impl of the state, which
+    /// takes care of their contents. If it breaks, the traversal stops; the break value is
+    /// discarded, so a state that needs it should store it.
+    pub fn on_visit<T: Any>(self) -> Self
+    where
+        S: Visit<'a, T>,
+    {
+        self.on_enter(|s: &mut S, x: &'a T| match s.visit(x) {
+            Continue(()) => Continue(Children::Skip),
+            Break(_) => Break(()),
+        })
+    }
+
+    pub fn state(&mut self) -> &mut S {
+        &mut self.state
+    }
+
+    pub fn into_state(self) -> S {
+        self.state
+    }
+}
+
+impl<'a, S> AnyVisitor<'a> for DispatchVisitor<'a, S> {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        match self.enter.get_mut(&(x as &dyn Any).type_id()) {
+            Some(f) => f(&mut self.state, x),
+            None => Continue(Children::Visit),
+        }
+    }
+
+    fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+        match self.exit.get_mut(&(x as &dyn Any).type_id()) {
+            Some(f) => f(&mut self.state, x),
+            None => Continue(()),
+        }
+    }
+}
+
+type Handler<'f> = Box<dyn FnMut(&dyn DriveAny) + 'f>;
+type HandlerMut<'f> = Box<dyn FnMut(&mut dyn DriveAnyMut) + 'f>;
+
+/// Assembles a visitor from closures registered for the types it's interested in, e.g. by plugins
+/// that each contribute handlers:
+/// ```rust
+/// # use derive_generic_visitor::*;
+/// #[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+/// enum List {
+///     Nil,
+///     Cons(u32, Box<List>),
+/// }
+/// let mut list = List::Cons(1, Box::new(List::Cons(2, Box::new(List::Nil))));
+/// let mut builder = VisitorBuilder::new();
+/// builder = builder.on_mut(|n: &mut u32| *n *= 10);
+/// let _ = drive_any_mut(&mut list, &mut builder.build());
+///
+/// let mut sum = 0;
+/// let _ = drive_any(&list, &mut VisitorBuilder::new().on(|n: &u32| sum += n).build());
+/// assert_eq!(sum, 30);
+/// ```
+/// The resulting `BuiltVisitor` calls the handler registered for the type of each node, then
+/// visits its contents; nodes of other types are walked through. The `on` handlers are called
+/// when visiting immutably and the `on_mut` handlers when visiting mutably. Like `DispatchVisitor`,
+/// it traverses values with `drive_any[_mut]`, which gives finer control over the traversal.
+pub struct VisitorBuilder<'f> {
+    handlers: HashMap<TypeId, Handler<'f>>,
+    handlers_mut: HashMap<TypeId, HandlerMut<'f>>,
+}
+
+impl<'f> VisitorBuilder<'f> {
+    pub fn new() -> Self {
+        VisitorBuilder {
+            handlers: HashMap::new(),
+            handlers_mut: HashMap::new(),
+        }
+    }
+
+    /// Call `f` on the nodes of type `T`. Replaces any previous `on` handler for `T`.
+    pub fn on<T: Any>(mut self, mut f: impl FnMut(&T) + 'f) -> Self {
+        self.handlers.insert(
+            TypeId::of::<T>(),
+            Box::new(move |x| f(x.downcast_ref::<T>().unwrap())),
+        );
+        self
+    }
+
+    /// Call `f` on the nodes of type `T` when visiting mutably. Their contents are visited after
+    /// `f` has modified them. Replaces any previous `on_mut` handler for `T`.
+    pub fn on_mut<T: Any>(mut self, mut f: impl FnMut(&mut T) + 'f) -> Self {
+        self.handlers_mut.insert(
+            TypeId::of::<T>(),
+            Box::new(move |x| f(x.downcast_mut::<T>().unwrap())),
+        );
+        self
+    }
+
+    pub fn build(self) -> BuiltVisitor<'f> {
+        BuiltVisitor {
+            handlers: self.handlers,
+            handlers_mut: self.handlers_mut,
+        }
+    }
+}
+
+impl Default for VisitorBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A visitor assembled by a `VisitorBuilder`. It can visit any `DriveAny[Mut]` value.
+pub struct BuiltVisitor<'f> {
+    handlers: HashMap<TypeId, Handler<'f>>,
+    handlers_mut: HashMap<TypeId, HandlerMut<'f>>,
+}
+
+impl Visitor for BuiltVisitor<'_> {
+    type Break = Infallible;
+}
+
+impl AnyVisitor<'_> for BuiltVisitor<'_> {
+    fn enter(&mut self, x: &dyn DriveAny) -> ControlFlow<(), Children> {
+        if let Some(f) = self.handlers.get_mut(&(x as &dyn Any).type_id()) {
+            f(x);
+        }
+        Continue(Children::Visit)
+    }
+}
+
+impl AnyVisitorMut for BuiltVisitor<'_> {
+    fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        if let Some(f) = self.handlers_mut.get_mut(&(&*x as &dyn Any).type_id()) {
+            f(x);
+        }
+        Continue(Children::Visit)
+    }
+}
+
+impl<'a, T: DriveAny> Visit<'a, T> for BuiltVisitor<'_> {
+    fn visit(&mut self, x: &'a T) -> ControlFlow<Infallible> {
+        let _ = drive_any(x, self);
+        Continue(())
+    }
+}
+
+impl<'a, T: DriveAnyMut> VisitMut<'a, T> for BuiltVisitor<'_> {
+    fn visit(&mut self, x: &'a mut T) -> ControlFlow<Infallible> {
+        let _ = drive_any_mut(x, self);
+        Continue(())
+    }
+}
@@ -0,0 +1,88 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::*;
+
+type VisitFn<V> = fn(&mut V, &dyn Any) -> ControlFlow<<V as Visitor>::Break>;
+type VisitMutFn<V> = fn(&mut V, &mut dyn Any) -> ControlFlow<<V as Visitor>::Break>;
+
+/// The concrete types that a visitor knows how to visit when they're hidden behind a `dyn Any`,
+/// e.g. in the extension slots of an extensible AST.
+///
+/// A `Box<dyn Any>` field is driven by calling the visitor's `Visit<dyn Any>` impl. To visit the
+/// types it knows about, the visitor implements it by delegating to a registry, which downcasts
+/// the value to each registered type and visits it as that type:
+/// ```ignore
+/// static DOWNCASTS: LazyLock<DowncastRegistry<MyVisitor>> =
+///     LazyLock::new(|| DowncastRegistry::new().register::<MyExt>().register::<OtherExt>());
+/// impl<'a> Visit<'a, dyn Any> for MyVisitor {
+///     fn visit(&mut self, x: &'a dyn Any) -> ControlFlow<Self::Break> {
+///         DOWNCASTS.visit(self, x)
+///     }
+/// }
+/// ```
+/// Values of a type that wasn't registered are skipped.
+pub struct DowncastRegistry<V: Visitor> {
+    visit: HashMap<TypeId, VisitFn<V>>,
+    visit_mut: HashMap<TypeId, VisitMutFn<V>>,
+}
+
+impl<V: Visitor> DowncastRegistry<V> {
+    pub fn new() -> Self {
+        DowncastRegistry {
+            visit: HashMap::new(),
+            visit_mut: HashMap::new(),
+        }
+    }
+
+    /// Visit values of type `T` found behind a `&dyn Any`.
+    pub fn register<T: Any>(mut self) -> Self
+    where
+        V: for<'a> Visit<'a, T>,
+    {
+        self.visit.insert(TypeId::of::<T>(), |v, x| {
+            v.visit(x.downcast_ref::<T>().unwrap())
+        });
+        self
+    }
+
+    /// Visit values of type `T` found behind a `&mut dyn Any`.
+    pub fn register_mut<T: Any>(mut self) -> Self
+    where
+        V: for<'a> VisitMut<'a, T>,
+    {
+        self.visit_mut.insert(TypeId::of::<T>(), |v, x| {
+            v.visit(x.downcast_mut::<T>().unwrap())
+        });
+        self
+    }
+
+    /// Whether values of the type with id `type_id` will be visited.
+    pub fn contains(&self, type_id: TypeId) -> bool {
+        self.visit.contains_key(&type_id) || self.visit_mut.contains_key(&type_id)
+    }
+
+    /// Visit `x` as its concrete type if that type was registered with `register`, otherwise do
+    /// nothing.
+    pub fn visit(&self, v: &mut V, x: &dyn Any) -> ControlFlow<V::Break> {
+        match self.visit.get(&x.type_id()) {
+            Some(visit) => visit(v, x),
+            None => Continue(()),
+        }
+    }
+
+    /// Visit `x` as its concrete type if that type was registered with `register_mut`, otherwise
+    /// do nothing.
+    pub fn visit_mut(&self, v: &mut V, x: &mut dyn Any) -> ControlFlow<V::Break> {
+        match self.visit_mut.get(&(*x).type_id()) {
+            Some(visit) => visit(v, x),
+            None => Continue(()),
+        }
+    }
+}
+
+impl<V: Visitor> Default for DowncastRegistry<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
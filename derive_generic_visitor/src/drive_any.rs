@@ -0,0 +1,216 @@
+//! Type-erased, fully recursive traversal.
+//!
+//! The `Drive`/`Visit` traits are deliberately shallow and statically typed. This module provides
+//! the complementary building block: a `dyn`-compatible traversal that walks a whole tree and
+//! shows every node to the visitor as a `&dyn DriveAny`, from which the concrete type can be
+//! recovered by downcasting. This makes it possible to write visitors that work over any
+//! `DriveAny` type without knowing the set of types involved.
+use std::any::Any;
+
+use crate::*;
+
+/// Returned by `AnyVisitor[Mut]::enter` to choose whether to visit the contents of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Children {
+    /// Visit the contents of the node.
+    Visit,
+    /// Don't visit the contents of the node. `exit` is still called on the node.
+    Skip,
+}
+
+/// A visitor that sees every node of a tree as a `&dyn DriveAny`. Run it on a value with
+/// `drive_any`.
+pub trait AnyVisitor<'a> {
+    /// Called on each node before its contents.
+    fn enter(&mut self, _x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        Continue(Children::Visit)
+    }
+    /// Called on each node after its contents.
+    fn exit(&mut self, _x: &'a dyn DriveAny) -> ControlFlow<()> {
+        Continue(())
+    }
+    /// Called before walking a field marked `#[drive(binder)]`, like `VisitBinder::enter_binder`.
+    fn enter_binder(&mut self) {}
+    /// Called after walking a field marked `#[drive(binder)]`.
+    fn exit_binder(&mut self) {}
+}
+
+impl<'a, V: AnyVisitor<'a> + ?Sized> AnyVisitor<'a> for &mut V {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        (**self).enter(x)
+    }
+    fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+        (**self).exit(x)
+    }
+    fn enter_binder(&mut self) {
+        (**self).enter_binder()
+    }
+    fn exit_binder(&mut self) {
+        (**self).exit_binder()
+    }
+}
+
+/// A visitor that sees every node of a tree as a `&mut dyn DriveAnyMut`. Run it on a value with
+/// `drive_any_mut`.
+pub trait AnyVisitorMut {
+    /// Called on each node before its contents.
+    fn enter(&mut self, _x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        Continue(Children::Visit)
+    }
+    /// Called on each node after its contents.
+    fn exit(&mut self, _x: &mut dyn DriveAnyMut) -> ControlFlow<()> {
+        Continue(())
+    }
+    /// Called before walking a field marked `#[drive(binder)]`, like `VisitBinder::enter_binder`.
+    fn enter_binder(&mut self) {}
+    /// Called after walking a field marked `#[drive(binder)]`.
+    fn exit_binder(&mut self) {}
+}
+
+/// A type that can be walked recursively by an `AnyVisitor`. Derive it with `#[derive(DriveAny)]`
+/// on a type that also implements `Drive`. The `NodeKind` supertrait lets visitors name the nodes
+/// they see.
+pub trait DriveAny: Any + NodeKind {
+    /// Call `drive_any(field, v)` on the immediate contents of `self`.
+    fn drive_any_inner<'a>(&'a self, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()>;
+
+    /// The name of the concrete type, for debugging purposes.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// How the `index`-th node contained in this one (as visited by `drive_any_inner`) is reached
+    /// from it. Derived impls give the names of the fields; other nodes, e.g. collections, give
+    /// the index.
+    fn path_step(&self, index: usize) -> PathStep {
+        PathStep::Index(index)
+    }
+
+    /// The number of bytes this node has allocated on the heap, not counting the allocations of
+    /// its contents (which are nodes themselves). Used by `heap_size`.
+    fn own_heap_size(&self) -> usize {
+        0
+    }
+}
+
+/// A type that can be walked recursively by an `AnyVisitorMut`. Derive it with
+/// `#[derive(DriveAnyMut)]` on a type that also implements `DriveMut`.
+pub trait DriveAnyMut: Any + NodeKind {
+    /// Call `drive_any_mut(field, v)` on the immediate contents of `self`.
+    fn drive_any_inner_mut(&mut self, v: &mut dyn AnyVisitorMut) -> ControlFlow<()>;
+
+    /// The name of the concrete type, for debugging purposes.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+impl dyn DriveAny {
+    /// Whether the node is of type `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        (self as &dyn Any).is::<T>()
+    }
+    /// Returns the node if it is of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref()
+    }
+}
+
+impl dyn DriveAnyMut {
+    /// Whether the node is of type `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        (self as &dyn Any).is::<T>()
+    }
+    /// Returns the node if it is of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref()
+    }
+    /// Returns the node if it is of type `T`.
+    pub fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+        (self as &mut dyn Any).downcast_mut()
+    }
+}
+
+/// Walk `v` over `x`: calls `v.enter(x)`, then recursively walks the contents of `x` (unless
+/// `enter` returned `Children::Skip`), then calls `v.exit(x)`.
+pub fn drive_any<'a>(x: &'a dyn DriveAny, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+    if let Children::Visit = v.enter(x)? {
+        x.drive_any_inner(v)?;
+    }
+    v.exit(x)
+}
+
+/// Walk `v` over `x`: calls `v.enter(x)`, then recursively walks the contents of `x` (unless
+/// `enter` returned `Children::Skip`), then calls `v.exit(x)`.
+pub fn drive_any_mut(x: &mut dyn DriveAnyMut, v: &mut dyn AnyVisitorMut) -> ControlFlow<()> {
+    if let Children::Visit = v.enter(x)? {
+        x.drive_any_inner_mut(v)?;
+    }
+    v.exit(x)
+}
+
+/// Implementation detail: adapter that implements `Visit<T>` for `T: DriveAny` by calling
+/// `drive_any`. This is how `DriveAny` is implemented on top of `Drive`.
+pub struct AnyDriver<'a, 'v>(&'v mut dyn AnyVisitor<'a>, usize);
+
+impl<'a, 'v> AnyDriver<'a, 'v> {
+    pub fn new(v: &'v mut dyn AnyVisitor<'a>) -> Self {
+        AnyDriver(v, 0)
+    }
+}
+impl Visitor for AnyDriver<'_, '_> {
+    type Break = ();
+}
+/// The binders are tracked by the `AnyVisitor`; the depth kept here only counts the binders of the
+/// node being driven.
+impl VisitBinder for AnyDriver<'_, '_> {
+    fn binder_depth(&mut self) -> &mut usize {
+        &mut self.1
+    }
+    fn enter_binder(&mut self) {
+        self.1 += 1;
+        self.0.enter_binder()
+    }
+    fn exit_binder(&mut self) {
+        self.1 -= 1;
+        self.0.exit_binder()
+    }
+}
+impl<'a, T: DriveAny> Visit<'a, T> for AnyDriver<'a, '_> {
+    fn visit(&mut self, x: &'a T) -> ControlFlow<Self::Break> {
+        drive_any(x, self.0)
+    }
+}
+
+/// Implementation detail: adapter that implements `VisitMut<T>` for `T: DriveAnyMut` by calling
+/// `drive_any_mut`. This is how `DriveAnyMut` is implemented on top of `DriveMut`.
+pub struct AnyDriverMut<'v>(&'v mut dyn AnyVisitorMut, usize);
+
+impl<'v> AnyDriverMut<'v> {
+    pub fn new(v: &'v mut dyn AnyVisitorMut) -> Self {
+        AnyDriverMut(v, 0)
+    }
+}
+impl Visitor for AnyDriverMut<'_> {
+    type Break = ();
+}
+/// The binders are tracked by the `AnyVisitorMut`; the depth kept here only counts the binders of
+/// the node being driven.
+impl VisitBinder for AnyDriverMut<'_> {
+    fn binder_depth(&mut self) -> &mut usize {
+        &mut self.1
+    }
+    fn enter_binder(&mut self) {
+        self.1 += 1;
+        self.0.enter_binder()
+    }
+    fn exit_binder(&mut self) {
+        self.1 -= 1;
+        self.0.exit_binder()
+    }
+}
+impl<T: DriveAnyMut> VisitMut<'_, T> for AnyDriverMut<'_> {
+    fn visit(&mut self, x: &mut T) -> ControlFlow<Self::Break> {
+        drive_any_mut(x, self.0)
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+
+use crate::*;
+
+/// What to do with an element of a collection, as decided by `VisitEdit::visit_element`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit<T> {
+    /// Leave the element in place.
+    Keep,
+    /// Remove the element.
+    Remove,
+    /// Replace the element by these ones, in order.
+    ReplaceWith(Vec<T>),
+}
+
+/// A mutable visitor that can remove or replace the elements of the collections of `T` it visits,
+/// e.g. a dead-code elimination pass. The visitor opts in for a given collection type by driving
+/// it with `DriveEdit::drive_edit`, typically from an override:
+/// ```ignore
+/// fn visit_stmts(&mut self, x: &mut Vec<Stmt>) -> ControlFlow<Self::Break> {
+///     x.drive_edit(self)
+/// }
+/// ```
+pub trait VisitEdit<T>: Visitor {
+    /// Visit an element and decide what to do with it. This should visit the element as usual
+    /// (e.g. with `self.visit(x)`) if its contents are of interest.
+    fn visit_element(&mut self, x: &mut T) -> ControlFlow<Self::Break, Edit<T>>;
+}
+
+/// A collection whose elements can be edited by a `VisitEdit` visitor. The elements are visited in
+/// order; the edits are applied once they have all been visited, or when the visitor breaks, so
+/// edits requested before a break still take effect.
+pub trait DriveEdit<T> {
+    fn drive_edit<V: VisitEdit<T>>(&mut self, v: &mut V) -> ControlFlow<V::Break>;
+}
+
+/// Visit the elements of `iter` with `v`, and record the positions of the ones that aren't kept
+/// with their edits.
+fn collect_edits<'a, T: 'a, V: VisitEdit<T>>(
+    iter: impl Iterator<Item = &'a mut T>,
+    v: &mut V,
+    edits: &mut Vec<(usize, Edit<T>)>,
+) -> ControlFlow<V::Break> {
+    for (i, x) in iter.enumerate() {
+        match v.visit_element(x)? {
+            Edit::Keep => {}
+            edit => edits.push((i, edit)),
+        }
+    }
+    Continue(())
+}
+
+/// The element that replaces another in a collection that holds one element per slot. Replacing
+/// it by several items is a logic error: this panics in debug builds, and keeps the first item
+/// otherwise.
+fn single_replacement<T>(edit: Edit<T>) -> Option<T> {
+    match edit {
+        Edit::Keep => unreachable!(),
+        Edit::Remove => None,
+        Edit::ReplaceWith(items) => {
+            debug_assert!(
+                items.len() <= 1,
+                "cannot replace a single-slot element by {} items",
+                items.len()
+            );
+            items.into_iter().next()
+        }
+    }
+}
+
+impl<T> DriveEdit<T> for Vec<T> {
+    fn drive_edit<V: VisitEdit<T>>(&mut self, v: &mut V) -> ControlFlow<V::Break> {
+        let mut edits = Vec::new();
+        let result = collect_edits(self.iter_mut(), v, &mut edits);
+        if !edits.is_empty() {
+            let mut edits = edits.into_iter().peekable();
+            for (i, x) in mem::take(self).into_iter().enumerate() {
+                match edits.next_if(|(j, _)| *j == i) {
+                    Some((_, Edit::ReplaceWith(items))) => self.extend(items),
+                    Some(_) => {}
+                    None => self.push(x),
+                }
+            }
+        }
+        result
+    }
+}
+
+/// An `Option` holds at most one element: an empty replacement removes the element, and replacing
+/// it by several items is a logic error (see `single_replacement`).
+impl<T> DriveEdit<T> for Option<T> {
+    fn drive_edit<V: VisitEdit<T>>(&mut self, v: &mut V) -> ControlFlow<V::Break> {
+        let mut edits = Vec::new();
+        let result = collect_edits(self.iter_mut(), v, &mut edits);
+        if let Some((_, edit)) = edits.into_iter().next() {
+            *self = single_replacement(edit);
+        }
+        result
+    }
+}
+
+/// The elements are the values of the map. A key holds at most one value: an empty replacement
+/// removes the entry, and replacing the value by several items is a logic error (see
+/// `single_replacement`).
+impl<K: Eq + Hash + Clone, T, S: BuildHasher> DriveEdit<T> for HashMap<K, T, S> {
+    fn drive_edit<V: VisitEdit<T>>(&mut self, v: &mut V) -> ControlFlow<V::Break> {
+        let mut edits = Vec::new();
+        let mut result = Continue(());
+        for (k, x) in self.iter_mut() {
+            match v.visit_element(x) {
+                Continue(Edit::Keep) => {}
+                Continue(edit) => edits.push((k.clone(), edit)),
+                Break(b) => {
+                    result = Break(b);
+                    break;
+                }
+            }
+        }
+        for (k, edit) in edits {
+            match single_replacement(edit) {
+                Some(x) => self.insert(k, x),
+                None => self.remove(&k),
+            };
+        }
+        result
+    }
+}
@@ -0,0 +1,192 @@
+use std::any::Any;
+
+use crate::*;
+
+/// An event of a traversal, as yielded by `Events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event<T> {
+    /// The traversal reached this node; the events of its contents follow.
+    Enter(T),
+    /// The traversal is done with the contents of this node.
+    Exit(T),
+    /// The traversal entered a field marked `#[drive(binder)]`, like `AnyVisitor::enter_binder`;
+    /// the events of the contents under the binder follow, then an `ExitBinder`.
+    EnterBinder,
+    /// The traversal left a field marked `#[drive(binder)]`.
+    ExitBinder,
+}
+
+impl<T> Event<T> {
+    /// The node this event is about, if it's an `Enter` or `Exit`.
+    pub fn node(self) -> Option<T> {
+        match self {
+            Event::Enter(x) | Event::Exit(x) => Some(x),
+            Event::EnterBinder | Event::ExitBinder => None,
+        }
+    }
+
+    /// Apply `f` to the node, keeping the kind of event. Returns `None` if `f` does. Binder events
+    /// are kept as they are.
+    pub fn try_map<U>(self, f: impl FnOnce(T) -> Option<U>) -> Option<Event<U>> {
+        Some(match self {
+            Event::Enter(x) => Event::Enter(f(x)?),
+            Event::Exit(x) => Event::Exit(f(x)?),
+            Event::EnterBinder => Event::EnterBinder,
+            Event::ExitBinder => Event::ExitBinder,
+        })
+    }
+}
+
+impl<'a> Event<&'a dyn DriveAny> {
+    /// This event, if its node is of type `T`. Binder events have no node, so they give `None`.
+    pub fn downcast<T: Any>(self) -> Option<Event<&'a T>> {
+        self.node()?.downcast_ref::<T>()?;
+        self.try_map(|x| x.downcast_ref::<T>())
+    }
+}
+
+/// A traversal turned into an iterator: yields `Event::Enter(x)` and `Event::Exit(x)` for each
+/// node `x` of a tree, in the order `drive_any` would visit them, and `Event::EnterBinder` and
+/// `Event::ExitBinder` around the fields marked `#[drive(binder)]`. This suits consumers that pull
+/// nodes as they need them, like pretty-printers, better than a visitor does.
+///
+/// The traversal keeps a stack of the nodes being visited and of their remaining contents, so
+/// it doesn't recurse. Use `of_type` to get the events of the nodes of a given type, statically
+/// typed.
+pub struct Events<'a> {
+    root: Option<&'a dyn DriveAny>,
+    stack: Vec<Frame<'a>>,
+}
+
+/// A node being visited.
+struct Frame<'a> {
+    node: &'a dyn DriveAny,
+    /// The contents of the node that remain to visit, as `Enter` events interleaved with the
+    /// binder events around them; computed on first use.
+    children: Option<std::vec::IntoIter<Event<&'a dyn DriveAny>>>,
+}
+
+/// Collects the immediate contents of a node, and the binders around them.
+struct CollectChildren<'a>(Vec<Event<&'a dyn DriveAny>>);
+
+impl<'a> AnyVisitor<'a> for CollectChildren<'a> {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        self.0.push(Event::Enter(x));
+        Continue(Children::Skip)
+    }
+    fn enter_binder(&mut self) {
+        self.0.push(Event::EnterBinder);
+    }
+    fn exit_binder(&mut self) {
+        self.0.push(Event::ExitBinder);
+    }
+}
+
+impl<'a> Events<'a> {
+    pub fn new(root: &'a dyn DriveAny) -> Self {
+        Events {
+            root: Some(root),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Don't visit the contents of the node that was last entered: the next event is its `Exit`.
+    pub fn skip_children(&mut self) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.children = Some(Vec::new().into_iter());
+        }
+    }
+
+    /// The events of the nodes of type `T`. Binder events are dropped.
+    pub fn of_type<T: Any>(self) -> impl Iterator<Item = Event<&'a T>> {
+        self.filter_map(Event::downcast)
+    }
+
+    /// Advance the traversal to the next node of type `T` and return it, or `None` once the
+    /// traversal is over. Unlike `collect_all`, this pulls nodes one at a time, and unlike
+    /// `of_type`, the traversal can go on with nodes of other types afterwards.
+    pub fn next_node<T: Any>(&mut self) -> Option<&'a T> {
+        self.find_map(|event| match event {
+            Event::Enter(x) => x.downcast_ref::<T>(),
+            _ => None,
+        })
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<&'a dyn DriveAny>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = match self.root.take() {
+            Some(root) => root,
+            None => {
+                let frame = self.stack.last_mut()?;
+                let children = frame.children.get_or_insert_with(|| {
+                    let mut collect = CollectChildren(Vec::new());
+                    let _ = frame.node.drive_any_inner(&mut collect);
+                    collect.0.into_iter()
+                });
+                match children.next() {
+                    Some(Event::Enter(child)) => child,
+                    Some(binder_event) => return Some(binder_event),
+                    None => return Some(Event::Exit(self.stack.pop().unwrap().node)),
+                }
+            }
+        };
+        self.stack.push(Frame {
+            node,
+            children: None,
+        });
+        Some(Event::Enter(node))
+    }
+}
+
+/// Iterate over the events of a traversal of `root`. See `Events`.
+pub fn events<'a>(root: &'a dyn DriveAny) -> Events<'a> {
+    Events::new(root)
+}
+
+/// Walk `v` over `x` like `drive_any`, but without recursing: the nodes being visited are kept on
+/// the heap, so values of any depth (e.g. long linked lists) can be visited without overflowing
+/// the stack. This is slower than `drive_any` since the contents of each node are collected before
+/// being visited.
+pub fn drive_any_iterative<'a>(x: &'a dyn DriveAny, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+    let mut events = events(x);
+    // The binders entered and not yet left, which are left if the visitor breaks, as `drive_any`
+    // does.
+    let mut binders = 0;
+    while let Some(event) = events.next() {
+        let result = deliver_event(event, v, &mut events, &mut binders);
+        if result.is_break() {
+            (0..binders).for_each(|_| v.exit_binder());
+            return result;
+        }
+    }
+    Continue(())
+}
+
+/// Pass an event of `events` to `v`, keeping track of the binders entered and not yet left.
+pub(crate) fn deliver_event<'a>(
+    event: Event<&'a dyn DriveAny>,
+    v: &mut dyn AnyVisitor<'a>,
+    events: &mut Events<'a>,
+    binders: &mut usize,
+) -> ControlFlow<()> {
+    match event {
+        Event::Enter(x) => {
+            if let Children::Skip = v.enter(x)? {
+                events.skip_children();
+            }
+        }
+        Event::Exit(x) => v.exit(x)?,
+        Event::EnterBinder => {
+            *binders += 1;
+            v.enter_binder();
+        }
+        Event::ExitBinder => {
+            *binders -= 1;
+            v.exit_binder();
+        }
+    }
+    Continue(())
+}
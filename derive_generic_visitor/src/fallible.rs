@@ -0,0 +1,63 @@
+use crate::*;
+
+/// Convert a `Result` into the corresponding `ControlFlow`, so that `?` can be used on it in a
+/// visitor method.
+pub trait IntoControlFlow<B, C> {
+    fn into_control_flow(self) -> ControlFlow<B, C>;
+}
+impl<B, C> IntoControlFlow<B, C> for Result<C, B> {
+    fn into_control_flow(self) -> ControlFlow<B, C> {
+        match self {
+            Ok(x) => Continue(x),
+            Err(e) => Break(e),
+        }
+    }
+}
+
+/// Convert a `ControlFlow` into the corresponding `Result`, so that `?` can be used on it in a
+/// function that returns a `Result`.
+pub trait IntoResult<B, C> {
+    fn into_result(self) -> Result<C, B>;
+}
+impl<B, C> IntoResult<B, C> for ControlFlow<B, C> {
+    fn into_result(self) -> Result<C, B> {
+        match self {
+            Continue(x) => Ok(x),
+            Break(e) => Err(e),
+        }
+    }
+}
+
+/// `Visit` for visitors whose `Break` type is an error: the same methods, returning `Result`s.
+pub trait TryVisit<'a, T: ?Sized>: Visit<'a, T> {
+    /// Visit this value.
+    fn try_visit(&mut self, x: &'a T) -> Result<(), Self::Break> {
+        self.visit(x).into_result()
+    }
+
+    /// Visit each of the given values in turn. Stops at the first error.
+    fn try_visit_all(&mut self, xs: impl IntoIterator<Item = &'a T>) -> Result<(), Self::Break>
+    where
+        T: 'a,
+    {
+        self.visit_all(xs).into_result()
+    }
+}
+impl<'a, T: ?Sized, V: Visit<'a, T> + ?Sized> TryVisit<'a, T> for V {}
+
+/// `VisitMut` for visitors whose `Break` type is an error: the same methods, returning `Result`s.
+pub trait TryVisitMut<'a, T: ?Sized>: VisitMut<'a, T> {
+    /// Visit this value.
+    fn try_visit(&mut self, x: &'a mut T) -> Result<(), Self::Break> {
+        self.visit(x).into_result()
+    }
+
+    /// Visit each of the given values in turn. Stops at the first error.
+    fn try_visit_all(&mut self, xs: impl IntoIterator<Item = &'a mut T>) -> Result<(), Self::Break>
+    where
+        T: 'a,
+    {
+        self.visit_all(xs).into_result()
+    }
+}
+impl<'a, T: ?Sized, V: VisitMut<'a, T> + ?Sized> TryVisitMut<'a, T> for V {}
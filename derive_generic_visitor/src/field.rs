@@ -0,0 +1,23 @@
+use crate::*;
+
+/// A field of a type, as reported to a `VisitField` visitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Field {
+    /// The type that has the field, or `Enum::Variant` for a field of an enum variant.
+    pub ty: &'static str,
+    /// The name of the field, or its position for a tuple field.
+    pub name: &'static str,
+}
+
+/// A visitor that is told which field it is visiting, e.g. for serializing or redacting values
+/// generically. The `Drive[Mut]` impls derived with `#[drive(field_names)]` call `enter_field`
+/// before visiting each field and `exit_field` after, and require `V: VisitField` for any visitor
+/// `V`. `exit_field` isn't called if the visit of the field breaks.
+pub trait VisitField: Visitor {
+    fn enter_field(&mut self, field: Field) {
+        let _ = field;
+    }
+    fn exit_field(&mut self, field: Field) {
+        let _ = field;
+    }
+}
@@ -0,0 +1,58 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::*;
+
+/// Computes the allocations of a node, in place of `DriveAny::own_heap_size`.
+type HeapSizeHook = Box<dyn Fn(&dyn DriveAny) -> usize>;
+
+/// A visitor that estimates the heap memory used by a value, by summing the `own_heap_size` of
+/// every node (the capacity of `Vec`s and `String`s, the contents of `Box`es, etc).
+///
+/// This is an estimate: allocator overhead isn't counted, and types whose `DriveAny` impl doesn't
+/// report their allocations (e.g. custom collections) count as zero unless a hook is registered
+/// for them with `with_hook`.
+#[derive(Default)]
+pub struct HeapSizeVisitor {
+    total: usize,
+    hooks: HashMap<TypeId, HeapSizeHook>,
+}
+
+impl HeapSizeVisitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `f` instead of `own_heap_size` to compute the allocations of nodes of type `T`. Their
+    /// contents are still visited.
+    pub fn with_hook<T: Any>(mut self, f: impl Fn(&T) -> usize + 'static) -> Self {
+        self.hooks.insert(
+            TypeId::of::<T>(),
+            Box::new(move |x| x.downcast_ref::<T>().map(&f).unwrap_or(0)),
+        );
+        self
+    }
+
+    /// The number of bytes counted so far.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+impl<'a> AnyVisitor<'a> for HeapSizeVisitor {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        let type_id = (x as &dyn Any).type_id();
+        self.total += match self.hooks.get(&type_id) {
+            Some(hook) => hook(x),
+            None => x.own_heap_size(),
+        };
+        Continue(Children::Visit)
+    }
+}
+
+/// Estimate the heap memory used by `x`, in bytes. See `HeapSizeVisitor`.
+pub fn heap_size(x: &dyn DriveAny) -> usize {
+    let mut v = HeapSizeVisitor::new();
+    let _ = drive_any(x, &mut v);
+    v.total()
+}
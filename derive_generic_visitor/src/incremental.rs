@@ -0,0 +1,131 @@
+use std::any::{Any, TypeId};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::*;
+
+type HashFn = fn(&dyn DriveAny) -> u64;
+
+/// Wraps a type-erased visitor to re-run it on a value that changed a little since the previous
+/// run, e.g. to lint a program again after an edit: the subtrees that are structurally unchanged
+/// are skipped entirely, like with `VisitedSet`. The inner visitor is expected to keep the
+/// results of the previous runs for the subtrees it doesn't see again.
+///
+/// Only the nodes of the types registered with `track` are considered for skipping, typically
+/// coarse-grained ones like functions or items. A node is unchanged if a node of the same type
+/// was at the same address in the previous run, with the same hash as given by its `Hash` impl.
+/// Since that hash covers the whole subtree, tracking small nodes doesn't pay off. A node is only
+/// recorded once the inner visitor is done with it, so a run that breaks doesn't cause anything
+/// to be skipped that wasn't visited entirely.
+pub struct Incremental<V> {
+    inner: V,
+    hashers: HashMap<TypeId, HashFn>,
+    /// The hashes of the tracked nodes visited so far.
+    hashes: HashMap<NodeKey, u64>,
+    /// The nodes being visited, innermost last.
+    stack: Vec<Frame>,
+    skipped: usize,
+}
+
+type NodeKey = (*const (), TypeId);
+
+enum Frame {
+    /// The node is unchanged and hidden from the inner visitor.
+    Skipped,
+    /// The node was passed on to the inner visitor. Holds its hash if it is tracked.
+    Visited(Option<(NodeKey, u64)>),
+}
+
+impl<V> Incremental<V> {
+    pub fn new(inner: V) -> Self {
+        Incremental {
+            inner,
+            hashers: HashMap::new(),
+            hashes: HashMap::new(),
+            stack: Vec::new(),
+            skipped: 0,
+        }
+    }
+
+    /// Skip the unchanged nodes of type `T`.
+    pub fn track<T: DriveAny + Hash>(mut self) -> Self {
+        self.hashers.insert(TypeId::of::<T>(), |x| {
+            let mut hasher = DefaultHasher::new();
+            x.downcast_ref::<T>().unwrap().hash(&mut hasher);
+            hasher.finish()
+        });
+        self
+    }
+
+    /// The wrapped visitor.
+    pub fn inner(&mut self) -> &mut V {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+
+    /// The number of subtrees skipped by the last run.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Forget the previous runs, so that the next one visits everything. The hashes of the nodes
+    /// that are gone are otherwise kept around, so this is also a way to free them.
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+    }
+
+    /// Walk the inner visitor over `x`, skipping the subtrees that didn't change since the
+    /// previous run.
+    pub fn run<'a>(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()>
+    where
+        V: AnyVisitor<'a>,
+    {
+        self.skipped = 0;
+        self.stack.clear();
+        drive_any(x, self)
+    }
+}
+
+impl<'a, V: AnyVisitor<'a>> AnyVisitor<'a> for Incremental<V> {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        let ty = (x as &dyn Any).type_id();
+        let tracked = self.hashers.get(&ty).map(|hash_fn| {
+            let key = (x as *const dyn DriveAny as *const (), ty);
+            (key, hash_fn(x))
+        });
+        if let Some((key, hash)) = tracked {
+            if self.hashes.get(&key) == Some(&hash) {
+                self.stack.push(Frame::Skipped);
+                self.skipped += 1;
+                return Continue(Children::Skip);
+            }
+        }
+        self.stack.push(Frame::Visited(tracked));
+        self.inner.enter(x)
+    }
+
+    fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+        match self.stack.pop() {
+            Some(Frame::Visited(tracked)) => {
+                self.inner.exit(x)?;
+                if let Some((key, hash)) = tracked {
+                    self.hashes.insert(key, hash);
+                }
+                Continue(())
+            }
+            _ => Continue(()),
+        }
+    }
+
+    fn enter_binder(&mut self) {
+        self.inner.enter_binder()
+    }
+
+    fn exit_binder(&mut self) {
+        self.inner.exit_binder()
+    }
+}
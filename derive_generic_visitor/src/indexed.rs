@@ -0,0 +1,148 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::*;
+
+/// A visitor that can visit a `T` together with its position in the parent collection. Used by
+/// the `Drive` impls of `Indexed`.
+pub trait VisitIndexed<'a, T: ?Sized>: Visitor {
+    /// Visit the element at position `index`.
+    fn visit_indexed(&mut self, index: usize, x: &'a T) -> ControlFlow<Self::Break>;
+}
+
+/// A visitor that can mutably visit a `T` together with its position in the parent collection.
+/// Used by the `DriveMut` impls of `Indexed`.
+pub trait VisitIndexedMut<'a, T: ?Sized>: Visitor {
+    /// Visit the element at position `index`.
+    fn visit_indexed(&mut self, index: usize, x: &'a mut T) -> ControlFlow<Self::Break>;
+}
+
+/// Wrapper around a collection that tells the visitor the position of each element. Driving an
+/// `Indexed<Vec<T>>` calls `v.visit_indexed(i, x)` instead of `v.visit(x)` on each element. This
+/// works for any collection that can be iterated by reference, e.g. `Vec`, arrays or `VecDeque`.
+///
+/// This is opt-in to avoid imposing `VisitIndexed` on every visitor: wrap the fields whose
+/// positions matter, e.g. `args: Indexed<Vec<Arg>>`. The wrapper derefs to the collection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Indexed<C>(pub C);
+
+impl<C> Deref for Indexed<C> {
+    type Target = C;
+    fn deref(&self) -> &C {
+        &self.0
+    }
+}
+impl<C> DerefMut for Indexed<C> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.0
+    }
+}
+
+impl<'s, C: 's, T, V> Drive<'s, V> for Indexed<C>
+where
+    &'s C: IntoIterator<Item = &'s T>,
+    T: 's,
+    V: VisitIndexed<'s, T>,
+{
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_indexed(&self.0, v)
+    }
+}
+impl<'s, C: 's, T, V> DriveMut<'s, V> for Indexed<C>
+where
+    &'s mut C: IntoIterator<Item = &'s mut T>,
+    T: 's,
+    V: VisitIndexedMut<'s, T>,
+{
+    fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_indexed_mut(&mut self.0, v)
+    }
+}
+
+/// Drive through an iterable type, calling `v.visit_indexed(i, x)` on the `i`th element.
+pub fn drive_iter_indexed<'a, C, T, V>(iterable: C, v: &mut V) -> ControlFlow<V::Break>
+where
+    C: IntoIterator<Item = &'a T>,
+    V: VisitIndexed<'a, T>,
+    T: 'a,
+{
+    for (i, x) in iterable.into_iter().enumerate() {
+        v.visit_indexed(i, x)?;
+    }
+    Continue(())
+}
+
+/// Drive through an iterable type, calling `v.visit_indexed(i, x)` on the `i`th element.
+pub fn drive_iter_indexed_mut<'a, C, T, V>(iterable: C, v: &mut V) -> ControlFlow<V::Break>
+where
+    C: IntoIterator<Item = &'a mut T>,
+    V: VisitIndexedMut<'a, T>,
+    T: 'a,
+{
+    for (i, x) in iterable.into_iter().enumerate() {
+        v.visit_indexed(i, x)?;
+    }
+    Continue(())
+}
+
+/// A visitor that can visit the values of a map together with their keys. Used by the `Drive`
+/// impls of `Keyed`.
+pub trait VisitKeyed<'a, K: ?Sized, T: ?Sized>: Visitor {
+    /// Visit the value stored under `key`.
+    fn visit_keyed(&mut self, key: &'a K, x: &'a T) -> ControlFlow<Self::Break>;
+}
+
+/// A visitor that can mutably visit the values of a map together with their keys. Used by the
+/// `DriveMut` impls of `Keyed`.
+pub trait VisitKeyedMut<'a, K: ?Sized, T: ?Sized>: Visitor {
+    /// Visit the value stored under `key`.
+    fn visit_keyed(&mut self, key: &'a K, x: &'a mut T) -> ControlFlow<Self::Break>;
+}
+
+/// Like `Indexed`, for maps: driving a `Keyed<HashMap<K, T>>` calls `v.visit_keyed(k, x)` on each
+/// entry instead of visiting the keys and values separately. This works for any map that can be
+/// iterated by reference, e.g. `HashMap` or `BTreeMap`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Keyed<M>(pub M);
+
+impl<M> Deref for Keyed<M> {
+    type Target = M;
+    fn deref(&self) -> &M {
+        &self.0
+    }
+}
+impl<M> DerefMut for Keyed<M> {
+    fn deref_mut(&mut self) -> &mut M {
+        &mut self.0
+    }
+}
+
+impl<'s, M: 's, K, T, V> Drive<'s, V> for Keyed<M>
+where
+    &'s M: IntoIterator<Item = (&'s K, &'s T)>,
+    K: 's,
+    T: 's,
+    V: VisitKeyed<'s, K, T>,
+{
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        for (k, x) in &self.0 {
+            v.visit_keyed(k, x)?;
+        }
+        Continue(())
+    }
+}
+impl<'s, M: 's, K, T, V> DriveMut<'s, V> for Keyed<M>
+where
+    &'s mut M: IntoIterator<Item = (&'s K, &'s mut T)>,
+    K: 's,
+    T: 's,
+    V: VisitKeyedMut<'s, K, T>,
+{
+    fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+        for (k, x) in &mut self.0 {
+            v.visit_keyed(k, x)?;
+        }
+        Continue(())
+    }
+}
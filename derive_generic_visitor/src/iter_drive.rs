@@ -0,0 +1,149 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::*;
+
+/// Wrapper that implements `Drive[Mut]` for any collection that can be iterated by reference, by
+/// visiting each element. This makes it possible to pass a foreign collection where a `Drive`
+/// bound is required (e.g. to `visit_inner`) without writing an impl for it. See also
+/// `drive_iter`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct IterDrive<C>(pub C);
+
+impl<C> IterDrive<C> {
+    /// View a reference to a collection as a reference to the wrapper.
+    pub fn from_ref(x: &C) -> &Self {
+        // SAFETY: `repr(transparent)`
+        unsafe { std::mem::transmute(x) }
+    }
+    /// View a mutable reference to a collection as a mutable reference to the wrapper.
+    pub fn from_mut(x: &mut C) -> &mut Self {
+        // SAFETY: `repr(transparent)`
+        unsafe { std::mem::transmute(x) }
+    }
+}
+
+impl<C> Deref for IterDrive<C> {
+    type Target = C;
+    fn deref(&self) -> &C {
+        &self.0
+    }
+}
+impl<C> DerefMut for IterDrive<C> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.0
+    }
+}
+
+impl<'s, C: 's, T, V> Drive<'s, V> for IterDrive<C>
+where
+    &'s C: IntoIterator<Item = &'s T>,
+    T: 's,
+    V: Visit<'s, T>,
+{
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter(&self.0, v)
+    }
+}
+impl<'s, C: 's, T, V> DriveMut<'s, V> for IterDrive<C>
+where
+    &'s mut C: IntoIterator<Item = &'s mut T>,
+    T: 's,
+    V: VisitMut<'s, T>,
+{
+    fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_mut(&mut self.0, v)
+    }
+}
+impl<'s, C: 's, T, V> DriveTwo<'s, V> for IterDrive<C>
+where
+    &'s C: IntoIterator<Item = &'s T>,
+    T: 's,
+    V: VisitTwo<'s, T>,
+{
+    fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_two(&self.0, &other.0, v)
+    }
+}
+
+/// Wrapper for a map that is visited through its values only, e.g. a `DriveValues<HashMap<Name,
+/// Item>>` field whose keys are of no interest to visitors. Works for any map that can be iterated
+/// by reference, e.g. `HashMap` or `BTreeMap`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct DriveValues<M>(pub M);
+
+/// Wrapper for a map that is visited through its keys only. Keys can't be modified in place, so
+/// mutable visits rebuild the map with `drive_map_keys_mut`; if two keys become equal, the entry
+/// that comes last in iteration order is kept.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct DriveKeys<M>(pub M);
+
+impl<M> Deref for DriveValues<M> {
+    type Target = M;
+    fn deref(&self) -> &M {
+        &self.0
+    }
+}
+impl<M> DerefMut for DriveValues<M> {
+    fn deref_mut(&mut self) -> &mut M {
+        &mut self.0
+    }
+}
+impl<M> Deref for DriveKeys<M> {
+    type Target = M;
+    fn deref(&self) -> &M {
+        &self.0
+    }
+}
+impl<M> DerefMut for DriveKeys<M> {
+    fn deref_mut(&mut self) -> &mut M {
+        &mut self.0
+    }
+}
+
+impl<'s, M: 's, K, T, V> Drive<'s, V> for DriveValues<M>
+where
+    &'s M: IntoIterator<Item = (&'s K, &'s T)>,
+    K: 's,
+    T: 's,
+    V: Visit<'s, T>,
+{
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter(self.0.into_iter().map(|(_, x)| x), v)
+    }
+}
+impl<'s, M: 's, K, T, V> DriveMut<'s, V> for DriveValues<M>
+where
+    &'s mut M: IntoIterator<Item = (&'s K, &'s mut T)>,
+    K: 's,
+    T: 's,
+    V: VisitMut<'s, T>,
+{
+    fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_mut(self.0.into_iter().map(|(_, x)| x), v)
+    }
+}
+
+impl<'s, M: 's, K, T, V> Drive<'s, V> for DriveKeys<M>
+where
+    &'s M: IntoIterator<Item = (&'s K, &'s T)>,
+    K: 's,
+    T: 's,
+    V: Visit<'s, K>,
+{
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter(self.0.into_iter().map(|(k, _)| k), v)
+    }
+}
+impl<'s, M, V> DriveMut<'s, V> for DriveKeys<M>
+where
+    M: RebuildMap,
+    V: for<'a> VisitMut<'a, M::Key>,
+{
+    fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_map_keys_mut(&mut self.0, KeyCollision::KeepLast, v)?;
+        Continue(())
+    }
+}
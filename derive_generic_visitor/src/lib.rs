@@ -53,6 +53,28 @@
 //! As you can see, this is not recursive in any way: `x.drive_inner(v)` simply calls `v.visit()` on
 //! each field of `x`; it is up to the visitor to recurse into nested structures if it wishes to do so.
 //!
+//! Fields and variants marked `#[drive(skip)]` are not visited. On an enum, `#[drive(discriminant)]`
+//! makes `drive_inner` first visit a `Discriminant` giving the index and name of the active
+//! variant, for visitors that need to know which variant they're in (e.g. for hashing).
+//! Similarly, `#[drive(field_names)]` makes `drive_inner` call `v.enter_field(field)` before
+//! visiting each field and `v.exit_field(field)` after, where `field` gives the name of the field
+//! and of the type that has it; the visitor must implement `VisitField`. A field marked
+//! `#[drive(binder)]` is visited under a binder: `drive_inner` calls `v.enter_binder()` before and
+//! `v.exit_binder()` after, so that a visitor implementing `VisitBinder` knows its
+//! `binder_depth()` at any point, e.g. to shift de Bruijn indices. `#[drive(binder)]` on a type puts
+//! all of its fields under a binder. In a `visitable_group`, give the visitor `bounds(VisitBinder)`
+//! so that it can drive types with binder fields. In the type-erased traversal described below,
+//! binders are reported to `AnyVisitor[Mut]::enter_binder` and `exit_binder`, and to the
+//! visitors wrapped by `VisitorChain`, `SplitVisitor`, `VisitedSet` and the like.
+//! For trees stored in an arena, a field that holds the id of a node can be marked
+//! `#[drive(resolve)]`: `drive_inner` then looks the node up with the visitor's `Resolve` impl and
+//! visits it instead of the id (`drive_inner_mut` visits the id itself).
+//! `#[drive(attr(cfg(feature = "visitors"), allow(..)))]` puts the given attributes on the
+//! generated impls, e.g. to compile them out in some configurations.
+//! The traversal of a field can also be customized through its type, e.g. for a foreign type:
+//! a `SkipDrive<T>` is never visited, and a `DriveVia<T, S>` is driven by the `DriveStrategy` impl
+//! of the marker type `S`.
+//!
 //!
 //! ## Defining useful visitors
 //!
@@ -156,15 +178,67 @@
 //! - `enter(Ty)`: call `self.enter_ty(x)` before recursing with `drive_inner`.
 //! - `exit(Ty)`: call `self.exit_ty(x)` after recursing with `drive_inner`.
 //! - `override(Ty)`: call `self.visit_ty(x)?`, which may or may not recurse if it wishes to and can
-//!     also early-return.
+//!   also early-return.
 //! - `drive(Ty)`: recurse with `drive_inner`.
 //! - `skip(Ty)`: do nothing.
 //! - `Ty`: alias for `override(Ty)`
+//! - `attr(...)`: put the given attributes (e.g. `cfg(..)`) on the generated impls.
+//! - `fallible(Error)`: `derive(Visitor)` sets `type Break = Error`, and the `enter_*`, `exit_*`
+//!   and `visit_*` methods return `Result<(), Error>` instead, so they can use `?` on ordinary
+//!   fallible code. `TryVisit`/`TryVisitMut` similarly provide `try_visit`, which returns a
+//!   `Result`, and `IntoResult`/`IntoControlFlow` convert between the two.
 //!
 //! Instead of `Ty`, one can always write `for<A, B, C> Ty<A, B, C>` to make a generic impl. For
 //! `enter`, `exit` and `override`, one may also write `other_name: Ty` so that `visit_other_name` is
 //! called instead of `visit_ty`.
 //!
+//! Lists of types that are shared between several visitors can be given a name with
+//! `visit_types!`, then referenced as `Name!` in any of the options above (as well as in
+//! `visitable_group`, described below):
+//! ```rust
+//! # use derive_generic_visitor::*;
+//! # #[derive(Drive)]
+//! # struct Span;
+//! visit_types! {
+//!     pub set AstLeaves = (String, Span, u32);
+//! }
+//!
+//! #[derive(Visitor, Visit)]
+//! #[visit(skip(AstLeaves!))]
+//! struct MyVisitor;
+//! ```
+//! Sets may reference other sets. Since the set is expanded where it is used, the types it lists
+//! must be nameable from there. `pub` sets are exported at the crate root like other macros.
+//!
+//! A visitor that needs some context, like an arena or an interner, can implement
+//! `VisitWith<Ctx, T>` (or `VisitMutWith`) instead of storing a reference to the context: its
+//! `visit(ctx, x)` method gets the context alongside each value, and `drive_inner_with(x, ctx, v)`
+//! visits the contents of `x` with the given context. Similarly, a visitor implementing
+//! `VisitAcc<A, T>` threads an accumulator through the traversal: `visit(acc, x)` returns the
+//! updated accumulator, and `drive_inner_acc(x, acc, v)` passes it through the contents of `x` in
+//! order.
+//!
+//! A mutable visitor can also remove or replace the elements of the collections it visits, e.g. to
+//! delete dead statements without recording their indices for a second pass: it implements
+//! `VisitEdit<T>`, whose `visit_element` returns an `Edit` (`Keep`, `Remove` or
+//! `ReplaceWith(items)`), and drives the collections of `T` with `DriveEdit::drive_edit`, which
+//! applies the edits once the elements have been visited. This is implemented for `Vec`, `Option`
+//! and the values of `HashMap`; since the latter two hold one element per slot, replacing an
+//! element by several items there is a logic error, which panics in debug builds.
+//!
+//! Since each visitor drives its own recursion, running several independent passes in a single
+//! traversal needs the type-erased layer described below, for which a tuple `(v1, v2)` of visitors
+//! runs both on every node. With the `either` feature, an `Either<V1, V2>` of visitors visits with
+//! the one it holds, which lets the caller pick a visitor at runtime without boxing it.
+//!
+//! For quick one-off traversals, `visit_fn(|x: &T| ..)` makes a visitor out of a closure, without
+//! declaring a type: it visits any value whose type implements `DriveAny` (see below), and calls
+//! the closure on each `T` inside it. `visit_fn_mut` is the mutable counterpart, and
+//! `try_visit_fn[_mut]` take closures that can return `Break`. To visit values of a single type
+//! without recursing, e.g. with `drive_iter`, a `&mut dyn FnMut(&T) -> ControlFlow<B>` closure is
+//! itself a `Visit<T>` visitor, and a `&mut dyn FnMut(&mut T) -> ControlFlow<B>` a `VisitMut<T>`
+//! one.
+//!
 //!
 //! ## Overrideable visitor architecture via traits
 //!
@@ -318,17 +392,65 @@
 //! - calls `<MyVisitor as ListVisitor>::visit(v, &x.field)` on each field of `x`, completing the loop.
 //!
 //! The options available for the `visitable_group` macro are:
-//! - `visitor(drive_method_name(&[mut|two]TraitName)[, infallible][, bounds(Bound1 + Bound2)])`: derive a visitor trait named `TraitName`.
+//! - `visitor(drive_method_name(&[mut|two]TraitName)[, infallible|synthesized][, bounds(Bound1 + Bound2)])`: derive a visitor trait named `TraitName`.
 //!   - the presence of `mut` determines whether the `TraitName` visitor will operate on mutable or immutable borrows.
 //!   - the presence of `two` determines whether the `TraitName` visitor will operate on a single
-//!       value or two values at once (see Lockstep section). Lockstep visitors don't support mutability.
+//!     value or two values at once (see Lockstep section). Lockstep visitors don't support mutability.
 //!   - the optional `infallible` flag enables an infallible-style interface for the visitor, where its methods `visit_$ty` return `()` instead of `ControlFlow<_>`.
+//!     For occasional early-exit queries, such a visitor can override `should_stop(&self)` to
+//!     skip everything that remains to be visited, e.g. once it has stored a result in a field.
+//!     Its `try_visit(x, take_break)` and `try_visit_inner` methods then return `ControlFlow<B>`:
+//!     `Break(b)` if `take_break(self)` returns `Some(b)` after the visit, e.g.
+//!     `v.try_visit(&x, |v| v.found.take())`.
+//!   - the optional `synthesized` flag makes each `visit_$ty` return `Self::Output` instead of
+//!     `ControlFlow<_>`, attribute-grammar style. The trait has a `type Output: Default` and a
+//!     required `combine(&mut self, acc, x)` method; by default, the output of a value is the
+//!     outputs of its contents combined with `combine`, starting from `Output::default()`. An
+//!     override typically handles a few cases and falls back to `self.visit_inner(x)` for the
+//!     others. Synthesized visitors can't be `two` or use `extends`.
+//!   - the optional `ctx(Ctx)` makes the visitor methods take a `ctx: &Ctx` argument before the
+//!     visited value: `visit(ctx, x)`, `visit_inner(ctx, x)`, `visit_ty(ctx, x)` etc. By default
+//!     the same context is passed down to the contents; an override can pass them another one,
+//!     e.g. an environment extended with the variables bound by `x`. `Ctx` must be nameable
+//!     where the trait is defined. Such visitors can't be `two`, `synthesized` or use `extends`,
+//!     and don't get `visit_by_val` or `try_visit`.
 //!   - the optional `bounds(...)` adds super trait bounds to the generated `TraitName` trait.
 //! - `drive(Ty)` and `skip(Ty)`: behave the same as their counterparts in the `Visit` and `VisitMut`
-//!     derives described above.
+//!   derives described above.
 //! - `override(Ty)`: generates `enter_ty` and `exit_ty` methods that do nothing, and a `visit_ty`
-//!     method that calls `enter_ty`, recurses with `self.visit_inner()?`, then calls `exit_ty`.
+//!   method that calls `enter_ty`, recurses with `self.visit_inner()?`, then calls `exit_ty`.
+//!   Types that don't have an obvious name, such as tuples and arrays, must be named explicitly:
+//!   `override(pair: (Expr, Ty), args: [Expr; 2])` generates `visit_pair` and `visit_args`.
 //! - `override_skip(Ty)`: similar to `override(Ty)`, but the default implementation does nothing, and no `enter_Ty` or `exit_Ty` methods are generated.
+//! - `strict`: check at compile time that the contents of every `drive` and `override` type are
+//!   themselves part of the group. Without it, a field whose type isn't listed only causes an error
+//!   when `visit_inner` is called on its parent, or is silently unreachable if nothing recurses into
+//!   the parent.
+//! - `attr(...)`: put the given attributes on the trait and all the generated items.
+//! - `node_ids`: number the nodes visited by each visitor, to build side tables of analysis results
+//!   without modifying the tree. Every visitor trait gets a required
+//!   `fn node_ids(&mut self) -> &mut NodeIds` method, and each `override` type gets the next
+//!   `NodeId` in pre-order before its `visit_ty` method is called; within it,
+//!   `self.node_ids().current()` is the id of the node. Two traversals of the same tree that visit
+//!   the same nodes assign the same ids, so a `SideTable<T>` (a `HashMap<NodeId, T>`) filled by one
+//!   pass can be read by another. Not supported together with `extends`.
+//! - `extends(BaseVisitable)`: extend another group. All the types of the base group become
+//!   visitable by this group's visitors, which must each declare the base visitor they extend with
+//!   `extends(base_drive_method(&[mut|two]BaseVisitor))` in their `visitor(...)` option. The base
+//!   visitor becomes a supertrait of the extended visitor, so overrides of the base group's
+//!   `visit_ty` methods are written in an `impl BaseVisitor` and apply during the extended
+//!   traversal. A base visitor and its extension must have the same mutability and fallibility.
+//!   Types of the base group must not be listed again; for generic containers that appear in both
+//!   groups, list concrete instances such as `drive(Vec<Stmt>)` in the extension. Methods such as
+//!   `visit` exist on both visitor traits, so calls to them may need to be qualified, e.g.
+//!   `StmtVisitor::visit_inner(self, x)`.
+//! - `closure_helpers`: for quick one-off traversals, add a method `x.for_each_ty(f)` to the
+//!   visitable trait for each non-generic `override(Ty)`, which calls `f` on each `Ty` reachable
+//!   from `x`. If the visitor is fallible, `try_for_each_ty` additionally lets `f` stop the
+//!   traversal by returning `Break`. These use the first immutable visitor trait that doesn't have
+//!   extra `bounds`. Likewise, the first mutable visitor trait gives `x.map_each_ty_mut(f)` (and
+//!   `try_map_each_ty_mut`), which calls `f` on each `Ty` bottom-up, for simple rewrite passes.
+//!   The name `ty` is the snake_case name of the type, as for `visit_ty`.
 //!
 //! Note: the `visitable_group` interface makes it possible to write composable
 //! visitor wrappers that provide reusable functionality. For an example, see
@@ -342,23 +464,264 @@
 //!
 //! The structure matches what we've seen so far: `DriveTwo` represents a type that can be
 //! lockstep-visited, and `VisitTwo` represents the corresponding visitors. Both can be derived,
-//! and support the same option as their normal counterparts. There is no mutable version of this
-//! visitor, under the assumption that it's not as useful. `DriveTwo` on a simple value like `u32`
-//! just compares for equality.
+//! and support the same option as their normal counterparts. `DriveTwo` on a simple value like
+//! `u32` just compares for equality.
+//!
+//! For merging or patching trees, `DriveZipMut` (derivable too) walks a `&mut target` and a
+//! `&patch` of the same type in lockstep, calling `VisitZipMut::visit(target, patch)` on
+//! corresponding contents; the visitor decides how to merge or overwrite them. Like `DriveTwo`,
+//! it returns `Break(Default::default())` when the shapes don't match up (different enum variants
+//! or collection lengths), so a visitor that wants to handle these cases, e.g. by replacing the
+//! target with a clone of the patch, should do so before recursing. `DriveZipMut` on a simple
+//! value like `u32` does nothing.
+//!
+//! The `diff` function uses lockstep traversal to list the places where two values differ, e.g. for
+//! snapshot-testing a transformation. It needs types to implement `DriveDiff` (derivable on types
+//! that implement `DriveTwo`, `NodeKind` and `Debug`); `DiffVisitor` can render chosen types
+//! differently. Similarly, `structural_eq` compares two values node by node, and
+//! `DiffVisitor::with_eq` overrides the comparison of chosen types, e.g. to ignore spans.
 //!
 //! Lockstep visitors are supported by the `visitable_group` macro by writing `&two TraitName`
 //! where you would write `&TraitName`/`&mut TraitName`. Being recursive, a visitor with no
 //! overrides or skips is just an equality comparison.
+//!
+//! ## Folding
+//!
+//! `DriveMut` can modify a tree in place, but restructuring it (e.g. replacing an enum variant by
+//! another that reuses its fields) requires taking values out of `&mut` references. For such
+//! rewrites, `Fold` takes a value by value, passes each of its fields to a `VisitFold` visitor and
+//! rebuilds the value from the returned fields. `derive(Fold)` supports the same `#[drive(skip)]`
+//! attributes as `Drive`; skipped fields are moved over unchanged. Since keys are taken by value,
+//! folding a `HashMap` or `HashSet` also folds its keys, and rebuilds it from the folded entries.
+//!
+//! `derive(VisitFold)` takes the same `#[visit(..)]` options as `Visit`. Custom methods take the
+//! value and return the one to replace it with: `enter_foo(&mut self, x: Foo) -> Foo`, and
+//! `visit_foo(&mut self, x: Foo) -> ControlFlow<Self::Break, Foo>` for overrides.
+//! ```rust
+//! # use derive_generic_visitor::*;
+//! #[derive(Debug, PartialEq, Fold)]
+//! enum Expr {
+//!     Lit(u32),
+//!     Add(Box<Expr>, Box<Expr>),
+//! }
+//!
+//! #[derive(Visitor, VisitFold)]
+//! #[visit(drive(for<T> Box<T>), exit(Expr), skip(u32))]
+//! struct ConstFold;
+//! impl ConstFold {
+//!     fn exit_expr(&mut self, x: Expr) -> Expr {
+//!         match x {
+//!             Expr::Add(a, b) => match (*a, *b) {
+//!                 (Expr::Lit(a), Expr::Lit(b)) => Expr::Lit(a + b),
+//!                 (a, b) => Expr::Add(Box::new(a), Box::new(b)),
+//!             },
+//!             x => x,
+//!         }
+//!     }
+//! }
+//!
+//! let expr = Expr::Add(Box::new(Expr::Lit(1)), Box::new(Expr::Lit(2)));
+//! assert_eq!(ConstFold.visit(expr), Continue(Expr::Lit(3)));
+//! ```
+//!
+//! For an occasional rewrite of this kind inside a `VisitMut` visitor, `replace_with(x, f)` takes
+//! the node out of a `&mut` reference and writes back the value returned by `f`, without a
+//! placeholder value in between; `replace_with_and_return` also lets `f` return e.g. a
+//! `ControlFlow`.
+//!
+//! ## Parallel traversal
+//!
+//! With the `rayon` feature, `DrivePar` (derivable with the same `#[drive(..)]` attributes as
+//! `Drive`) visits the fields of a value, or the elements of a `Vec` or slice, in parallel. Each
+//! task gets its own copy of the visitor, made by `ParVisitor::fork`; once they are done, the
+//! copies are combined back into the original visitor with `ParVisitor::join`, in field order.
+//! Only the immediate contents are split up: a visitor calls `drive_par_inner` in its overrides
+//! for the types where the work is worth distributing, and uses `drive_inner` elsewhere.
+//!
+//! ## Type-erased traversal
+//!
+//! Statically-typed visitors need to know the set of types they traverse. For utilities that work
+//! on any tree, the crate provides a complementary `dyn`-based layer: `DriveAny`/`DriveAnyMut`
+//! (derivable on types that implement `Drive`/`DriveMut`) let an `AnyVisitor`/`AnyVisitorMut` walk
+//! a whole value recursively, seeing each node as a `&dyn DriveAny` that can be downcast to its
+//! concrete type. Nodes must also implement `NodeKind` (derivable too), which names the kind of
+//! node (e.g. the enum variant) for the benefit of generic visitors.
+//!
+//! ```rust
+//! # use derive_generic_visitor::*;
+//! #[derive(Drive, DriveAny, NodeKind)]
+//! enum List {
+//!     Nil,
+//!     Cons(u32, Box<List>),
+//! }
+//!
+//! struct SumU32(u32);
+//! impl<'a> AnyVisitor<'a> for SumU32 {
+//!     fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+//!         if let Some(n) = x.downcast_ref::<u32>() {
+//!             self.0 += n;
+//!         }
+//!         Continue(Children::Visit)
+//!     }
+//! }
+//!
+//! let list = List::Cons(1, Box::new(List::Cons(2, Box::new(List::Nil))));
+//! let mut sum = SumU32(0);
+//! let _ = drive_any(&list, &mut sum);
+//! assert_eq!(sum.0, 3);
+//! ```
+//!
+//! Simple queries don't need a visitor type: `collect_all::<T>(&x)`, `find_first(&x, pred)`,
+//! `count::<T>(&x)`, `any(&x, pred)` and `all(&x, pred)` look at the nodes of type `T`, and
+//! `for_each_mut(&mut x, f)` modifies them. `type_counts(&x)` counts the nodes of every type.
+//!
+//! The same traversal is also available as an iterator: `events(&x)` yields `Event::Enter(node)`
+//! and `Event::Exit(node)` for each node, and `Event::EnterBinder` and `Event::ExitBinder` around
+//! the fields marked `#[drive(binder)]`. `.of_type::<T>()` keeps the events of the nodes of
+//! type `T`, as `Event<&T>`; `.next_node::<T>()` pulls the next node of type `T` and leaves the
+//! iterator usable for other types. Since it doesn't recurse, this can walk values too deep for the
+//! call stack; `drive_any_iterative` runs an `AnyVisitor` that way, binders included.
+//!
+//! Since this layer is `dyn`-compatible, it is also a way to avoid instantiating generic code for
+//! every type of a large tree: a `DispatchVisitor` is assembled from callbacks for the types it
+//! cares about, and can hand nodes over to a statically-typed visitor with `on_visit`. When the
+//! handlers are only known at runtime, e.g. when they come from plugins, a `VisitorBuilder`
+//! assembles a visitor from closures registered per type with `.on::<T>(f)` and
+//! `.on_mut::<T>(f)`, which can then visit any `DriveAny[Mut]` value.
+//!
+//! Derived `DriveAny` impls also know the names of the fields they visit, so a visitor can keep
+//! track of where it is with a `PathTracker`, e.g. to report errors at `.items[2].ret`. For
+//! debugging, `tree_string(&x)` prints a value as an indented tree of its nodes, and a
+//! `TreePrinter` does so with more options, e.g. to show these field names.
+//! With the `testing` feature, a `TraversalRecorder` records the nodes entered and exited, so
+//! that tests can check the traversal order of a type, e.g. with `record_traversal(&x).entered()`.
+//!
+//! For edits at a chosen location rather than over a whole tree, a `Cursor` points to a node of a
+//! `DriveAnyMut` value, can be moved to its parent, children or siblings, and gives mutable access
+//! to it.
+//!
+//! Since binders are reported to `AnyVisitor[Mut]`, a type of terms with de Bruijn variables can
+//! implement `BoundVar` to get the usual operations on variables for free:
+//! `shift_bound_vars::<T>(&mut x, by)` shifts the variables that are free in `x`,
+//! `instantiate_bound_var(&mut body, &value)` substitutes the variable bound by the binder around
+//! `body`, and `subst_bound_vars` does any other reindexing or capture-avoiding substitution.
+//!
+//! Values that share nodes, e.g. through `Rc`s, are walked as trees: shared nodes are visited once
+//! per path that reaches them. To visit them only once, wrap the visitor in a `VisitedSet` or use
+//! `visit_acyclic`. Similarly, to re-run a visitor after a small change of a value, an
+//! `Incremental` wrapper skips the subtrees whose hash is the same as in the previous run. To
+//! bound the work done on adversarial inputs, a `Budgeted` wrapper stops the traversal after a
+//! given number of nodes, and a `DepthLimited` wrapper doesn't go deeper than a given depth.
+//!
+//! Beware that visiting `Rc`s and `Arc`s mutably goes through `make_mut`: this only works for
+//! `T: Clone`, and clones the contents of pointers that are shared, which unshares them.
+//!
+//! Since it relies on `std::any::Any`, this only supports types without lifetime parameters.
+#[doc(hidden)]
+pub use derive_generic_visitor_macros::__expand_type_set;
 pub use derive_generic_visitor_macros::{
-    visitable_group, Drive, DriveMut, DriveTwo, Visit, VisitMut, VisitTwo, Visitor,
+    visit_types, visitable_group, Drive, DriveAny, DriveAnyMut, DriveDiff, DriveMut, DrivePar,
+    DriveSchema, DriveTwo, DriveZipMut, Fold, NodeKind, Visit, VisitFold, VisitMut, VisitTwo,
+    Visitor,
 };
 pub use std::convert::Infallible;
 pub use std::ops::ControlFlow;
 pub use ControlFlow::{Break, Continue};
 
+mod acc;
 mod basic_impls;
+mod binder;
+mod budgeted;
+mod chain;
+mod cursor;
+mod depth_limited;
+mod diff;
+mod discriminant;
+mod dispatch;
+mod downcast;
+mod drive_any;
 #[cfg(feature = "dynamic")]
 pub mod dynamic;
+mod edit;
+mod events;
+mod fallible;
+mod field;
+mod heap_size;
+mod incremental;
+mod indexed;
+mod iter_drive;
+mod map_keys;
+mod memo;
+mod node_id;
+mod node_kind;
+#[cfg(feature = "rayon")]
+mod par;
+mod path;
+mod pausable;
+#[cfg(feature = "profiling")]
+mod profiling;
+mod query;
+mod replace;
+mod resolve;
+mod schema;
+mod scoped;
+mod split;
+mod subst;
+#[cfg(feature = "testing")]
+mod testing;
+mod tree_printer;
+mod type_counts;
+mod via;
+mod visit_fn;
+mod visited;
+mod walker;
+mod with_ctx;
+
+pub use acc::*;
+pub use binder::*;
+pub use budgeted::*;
+pub use chain::*;
+pub use cursor::*;
+pub use depth_limited::*;
+pub use diff::*;
+pub use discriminant::*;
+pub use dispatch::*;
+pub use downcast::*;
+pub use drive_any::*;
+pub use edit::*;
+pub use events::*;
+pub use fallible::*;
+pub use field::*;
+pub use heap_size::*;
+pub use incremental::*;
+pub use indexed::*;
+pub use iter_drive::*;
+pub use map_keys::*;
+pub use memo::*;
+pub use node_id::*;
+pub use node_kind::*;
+#[cfg(feature = "rayon")]
+pub use par::*;
+pub use path::*;
+pub use pausable::*;
+#[cfg(feature = "profiling")]
+pub use profiling::*;
+pub use query::*;
+pub use replace::*;
+pub use resolve::*;
+pub use schema::*;
+pub use scoped::*;
+pub use split::*;
+pub use subst::*;
+#[cfg(feature = "testing")]
+pub use testing::*;
+pub use tree_printer::*;
+pub use type_counts::*;
+pub use via::*;
+pub use visit_fn::*;
+pub use visited::*;
+pub use walker::*;
+pub use with_ctx::*;
 
 /// A visitor.
 ///
@@ -370,6 +733,19 @@ pub trait Visitor {
     /// The type used for early-return, if the visitor supports it. Use an empty type like
     /// `std::convert::Infallible` if the visitor does not short-circuit.
     type Break;
+
+    /// Borrow the visitor, e.g. to call `visit_by_val` without giving it up. `&mut V` is a
+    /// visitor whenever `V` is.
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+impl<V: Visitor + ?Sized> Visitor for &mut V {
+    type Break = V::Break;
 }
 
 /// A visitor that can visit a type `T`.
@@ -377,6 +753,18 @@ pub trait Visit<'a, T: ?Sized>: Visitor {
     /// Visit this value.
     fn visit(&mut self, _: &'a T) -> ControlFlow<Self::Break>;
 
+    /// Visit each of the given values in turn, e.g. all the top-level items of a program. Stops at
+    /// the first `Break`.
+    fn visit_all(&mut self, xs: impl IntoIterator<Item = &'a T>) -> ControlFlow<Self::Break>
+    where
+        T: 'a,
+    {
+        for x in xs {
+            self.visit(x)?;
+        }
+        Continue(())
+    }
+
     /// Convenience alias for method chaining.
     fn visit_by_val(mut self, x: &'a T) -> ControlFlow<Self::Break, Self>
     where
@@ -402,6 +790,17 @@ pub trait VisitMut<'a, T: ?Sized>: Visitor {
     /// Visit this value.
     fn visit(&mut self, _: &'a mut T) -> ControlFlow<Self::Break>;
 
+    /// Visit each of the given values in turn. Stops at the first `Break`.
+    fn visit_all(&mut self, xs: impl IntoIterator<Item = &'a mut T>) -> ControlFlow<Self::Break>
+    where
+        T: 'a,
+    {
+        for x in xs {
+            self.visit(x)?;
+        }
+        Continue(())
+    }
+
     /// Convenience alias for method chaining.
     fn visit_by_val(mut self, x: &'a mut T) -> ControlFlow<Self::Break, Self>
     where
@@ -412,6 +811,35 @@ pub trait VisitMut<'a, T: ?Sized>: Visitor {
     }
 }
 
+impl<'a, T: ?Sized, V: Visit<'a, T> + ?Sized> Visit<'a, T> for &mut V {
+    fn visit(&mut self, x: &'a T) -> ControlFlow<Self::Break> {
+        (**self).visit(x)
+    }
+}
+impl<'a, T: ?Sized, V: VisitMut<'a, T> + ?Sized> VisitMut<'a, T> for &mut V {
+    fn visit(&mut self, x: &'a mut T) -> ControlFlow<Self::Break> {
+        (**self).visit(x)
+    }
+}
+
+/// A closure visits a single type, e.g. `drive_iter(xs, &mut (&mut f as &mut dyn FnMut(&T) -> _))`.
+impl<T: ?Sized, B> Visitor for dyn FnMut(&T) -> ControlFlow<B> + '_ {
+    type Break = B;
+}
+impl<'a, T: ?Sized, B> Visit<'a, T> for dyn FnMut(&T) -> ControlFlow<B> + '_ {
+    fn visit(&mut self, x: &'a T) -> ControlFlow<B> {
+        self(x)
+    }
+}
+impl<T: ?Sized, B> Visitor for dyn FnMut(&mut T) -> ControlFlow<B> + '_ {
+    type Break = B;
+}
+impl<'a, T: ?Sized, B> VisitMut<'a, T> for dyn FnMut(&mut T) -> ControlFlow<B> + '_ {
+    fn visit(&mut self, x: &'a mut T) -> ControlFlow<B> {
+        self(x)
+    }
+}
+
 /// A type that can be visited.
 pub trait Drive<'s, V: Visitor> {
     /// Call `v.visit()` on the immediate contents of `self`.
@@ -430,6 +858,11 @@ pub trait VisitTwo<'a, T: ?Sized>: Visitor<Break: Default> {
     /// Visit this value.
     fn visit(&mut self, _: &'a T, _: &'a T) -> ControlFlow<Self::Break>;
 }
+impl<'a, T: ?Sized, V: VisitTwo<'a, T> + ?Sized> VisitTwo<'a, T> for &mut V {
+    fn visit(&mut self, x: &'a T, y: &'a T) -> ControlFlow<Self::Break> {
+        (**self).visit(x, y)
+    }
+}
 
 /// A type that where we can visit to instances in lockstep.
 pub trait DriveTwo<'s, V: Visitor> {
@@ -438,6 +871,44 @@ pub trait DriveTwo<'s, V: Visitor> {
     fn drive_two_inner(&'s self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break>;
 }
 
+/// A visitor that can visit a mutable `target` and a `patch` of the same type in lockstep, e.g. to
+/// merge the patch into the target. If the values don't match up, this returns
+/// `Break(Default::default())`.
+pub trait VisitZipMut<'a, T: ?Sized>: Visitor<Break: Default> {
+    /// Visit this pair of values.
+    fn visit(&mut self, target: &'a mut T, patch: &'a T) -> ControlFlow<Self::Break>;
+}
+impl<'a, T: ?Sized, V: VisitZipMut<'a, T> + ?Sized> VisitZipMut<'a, T> for &mut V {
+    fn visit(&mut self, target: &'a mut T, patch: &'a T) -> ControlFlow<Self::Break> {
+        (**self).visit(target, patch)
+    }
+}
+
+/// A type where we can visit a mutable instance and a shared instance in lockstep.
+pub trait DriveZipMut<'s, V: Visitor> {
+    /// Call `v.visit()` on the immediate contents of `self` and `other`, if they correspond. If
+    /// the values don't match up, this returns `Break(Default::default())`.
+    fn drive_zip_mut_inner(&'s mut self, other: &'s Self, v: &mut V) -> ControlFlow<V::Break>;
+}
+
+/// A visitor that can fold a type `T`, i.e. take it by value and return the value to replace it
+/// with.
+pub trait VisitFold<T>: Visitor {
+    /// Fold this value.
+    fn visit(&mut self, _: T) -> ControlFlow<Self::Break, T>;
+}
+impl<T, V: VisitFold<T> + ?Sized> VisitFold<T> for &mut V {
+    fn visit(&mut self, x: T) -> ControlFlow<Self::Break, T> {
+        (**self).visit(x)
+    }
+}
+
+/// A type that can be folded, i.e. taken apart, visited by value, and rebuilt.
+pub trait Fold<V: Visitor>: Sized {
+    /// Call `v.visit()` on the immediate contents of `self` and rebuild it from the results.
+    fn fold_inner(self, v: &mut V) -> ControlFlow<V::Break, Self>;
+}
+
 /// Drive through an iterable type. Useful for collections in third-party crates for which there
 /// isn't a `Drive` impl.
 pub fn drive_iter<'a, C, T, V>(iterable: C, v: &mut V) -> ControlFlow<<V as Visitor>::Break>
@@ -466,6 +937,44 @@ where
     Continue(())
 }
 
+/// Drive through an iterable of key-value pairs, visiting each key then its value. Useful for
+/// maps in third-party crates for which there isn't a `Drive` impl.
+pub fn drive_iter_pairs<'a, C, K, T, V>(
+    iterable: C,
+    v: &mut V,
+) -> ControlFlow<<V as Visitor>::Break>
+where
+    C: IntoIterator<Item = (&'a K, &'a T)>,
+    V: Visit<'a, K> + Visit<'a, T>,
+    K: 'a,
+    T: 'a,
+{
+    for (k, x) in iterable {
+        v.visit(k)?;
+        v.visit(x)?;
+    }
+    Continue(())
+}
+
+/// Drive through an iterable of key-value pairs, visiting each key then its value mutably. Keys
+/// can't be modified in place, so they are visited immutably.
+pub fn drive_iter_pairs_mut<'a, C, K, T, V>(
+    iterable: C,
+    v: &mut V,
+) -> ControlFlow<<V as Visitor>::Break>
+where
+    C: IntoIterator<Item = (&'a K, &'a mut T)>,
+    V: Visit<'a, K> + VisitMut<'a, T>,
+    K: 'a,
+    T: 'a,
+{
+    for (k, x) in iterable {
+        Visit::visit(v, k)?;
+        VisitMut::visit(v, x)?;
+    }
+    Continue(())
+}
+
 /// Drive through an iterable type. Useful for collections in third-party crates for which there
 /// isn't a `Drive` impl.
 pub fn drive_iter_two<'a, C, D, T, V>(
@@ -489,3 +998,106 @@ where
     }
     Continue(())
 }
+
+/// Drive through an iterable type. Useful for collections in third-party crates for which there
+/// isn't a `Drive` impl.
+pub fn drive_iter_zip_mut<'a, C, D, T, V>(
+    target: C,
+    patch: D,
+    v: &mut V,
+) -> ControlFlow<<V as Visitor>::Break>
+where
+    C: IntoIterator<Item = &'a mut T>,
+    D: IntoIterator<Item = &'a T>,
+    V: VisitZipMut<'a, T>,
+    T: 'a,
+{
+    use itertools::Itertools;
+    for eob in target.into_iter().zip_longest(patch) {
+        match eob.both() {
+            Some((x, y)) => v.visit(x, y)?,
+            // The iterators don't have the same length.
+            None => return Break(Default::default()),
+        }
+    }
+    Continue(())
+}
+
+/// Fold the items of an iterable type and collect them. Useful for collections in third-party
+/// crates for which there isn't a `Fold` impl.
+pub fn fold_iter<C, T, D, V>(iterable: C, v: &mut V) -> ControlFlow<<V as Visitor>::Break, D>
+where
+    C: IntoIterator<Item = T>,
+    D: FromIterator<T>,
+    V: VisitFold<T>,
+{
+    let mut result = Continue(());
+    let folded = iterable
+        .into_iter()
+        .map_while(|x| match v.visit(x) {
+            Continue(x) => Some(x),
+            Break(b) => {
+                result = Break(b);
+                None
+            }
+        })
+        .collect();
+    result?;
+    Continue(folded)
+}
+
+/// Run `collector` on `x`, then build a mutating visitor from the collected state and run it on
+/// `x`. Returns the mutating visitor.
+///
+/// This is the plumbing for the common "collect data from the whole tree, then rewrite it"
+/// pattern, which can't be done in a single pass since it needs to mutate while looking at other
+/// parts of the tree. The higher-ranked bound on `C` ensures the collected state doesn't borrow
+/// from `x`.
+pub fn collect_then_mutate<T, C, M>(
+    x: &mut T,
+    collector: C,
+    make_mutator: impl FnOnce(C) -> M,
+) -> ControlFlow<C::Break, M>
+where
+    T: ?Sized,
+    C: for<'a> Visit<'a, T>,
+    M: for<'a> VisitMut<'a, T> + Visitor<Break = C::Break>,
+{
+    let collector = collector.visit_by_val(x)?;
+    make_mutator(collector).visit_by_val(x)
+}
+
+/// Outcome of `rewrite_until_fixpoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fixpoint {
+    /// A pass made no changes. `iterations` counts the passes that ran, including that last one.
+    Reached { iterations: usize },
+    /// The maximum number of passes ran and the last one still made changes.
+    Capped,
+}
+
+/// Run mutating passes on `x` until one of them makes no change, or `max_iterations` passes have
+/// run. Each pass runs a fresh visitor built by `make_pass`; `changed` is then called on it to
+/// find out whether it modified anything, typically by looking at a flag the visitor sets when it
+/// rewrites something. If a pass breaks, this stops and returns the `Break`.
+///
+/// This is the loop of simplification passes that need to run again on their own output, e.g.
+/// because a rewrite of a node can enable a rewrite of its parent.
+pub fn rewrite_until_fixpoint<T, V>(
+    x: &mut T,
+    max_iterations: usize,
+    mut make_pass: impl FnMut() -> V,
+    changed: impl Fn(&V) -> bool,
+) -> ControlFlow<V::Break, Fixpoint>
+where
+    T: ?Sized,
+    V: for<'a> VisitMut<'a, T>,
+{
+    for iterations in 1..=max_iterations {
+        let pass = make_pass().visit_by_val(x)?;
+        if !changed(&pass) {
+            return Continue(Fixpoint::Reached { iterations });
+        }
+    }
+    Continue(Fixpoint::Capped)
+}
@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+
+use crate::*;
+
+/// What `drive_map_keys_mut` does when two keys become equal after visiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCollision {
+    /// Keep the entry that was reinserted first.
+    KeepFirst,
+    /// Keep the entry that was reinserted last.
+    KeepLast,
+}
+
+/// A map that can be taken apart and rebuilt entry by entry. Used by `drive_map_keys_mut`.
+pub trait RebuildMap: Default + IntoIterator<Item = (Self::Key, Self::Value)> {
+    type Key;
+    type Value;
+    /// Insert an entry, resolving a collision with an existing key according to `policy`. Returns
+    /// the entry that was discarded, if any.
+    fn insert_entry(
+        &mut self,
+        k: Self::Key,
+        v: Self::Value,
+        policy: KeyCollision,
+    ) -> Option<(Self::Key, Self::Value)>;
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher + Default> RebuildMap for HashMap<K, V, S> {
+    type Key = K;
+    type Value = V;
+    fn insert_entry(&mut self, k: K, v: V, policy: KeyCollision) -> Option<(K, V)> {
+        match policy {
+            KeyCollision::KeepFirst if self.contains_key(&k) => Some((k, v)),
+            KeyCollision::KeepFirst => {
+                self.insert(k, v);
+                None
+            }
+            KeyCollision::KeepLast => {
+                let old = self.remove_entry(&k);
+                self.insert(k, v);
+                old
+            }
+        }
+    }
+}
+
+impl<K: Ord, V> RebuildMap for BTreeMap<K, V> {
+    type Key = K;
+    type Value = V;
+    fn insert_entry(&mut self, k: K, v: V, policy: KeyCollision) -> Option<(K, V)> {
+        match policy {
+            KeyCollision::KeepFirst if self.contains_key(&k) => Some((k, v)),
+            KeyCollision::KeepFirst => {
+                self.insert(k, v);
+                None
+            }
+            KeyCollision::KeepLast => {
+                let old = self.remove_entry(&k);
+                self.insert(k, v);
+                old
+            }
+        }
+    }
+}
+
+/// The entries of a `RebuildMap`, as returned by `drive_map_keys_mut`.
+pub type MapEntries<M> = Vec<(<M as RebuildMap>::Key, <M as RebuildMap>::Value)>;
+
+/// Mutably visit the keys of a map. Since keys can't be mutated in place, this drains the map,
+/// visits each key by value and reinserts it. If two keys become equal, only one of the entries is
+/// kept, as chosen by `policy`; the discarded entries are returned.
+///
+/// If the visitor returns `Break`, the remaining entries are reinserted unchanged.
+pub fn drive_map_keys_mut<M, V>(
+    map: &mut M,
+    policy: KeyCollision,
+    v: &mut V,
+) -> ControlFlow<V::Break, MapEntries<M>>
+where
+    M: RebuildMap,
+    V: for<'a> VisitMut<'a, M::Key>,
+{
+    let mut result = Continue(());
+    let mut discarded = Vec::new();
+    for (mut k, val) in std::mem::take(map) {
+        if result.is_continue() {
+            result = v.visit(&mut k);
+        }
+        discarded.extend(map.insert_entry(k, val, policy));
+    }
+    result?;
+    Continue(discarded)
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::*;
+
+/// Identifies a node by its address. Only meaningful while the node is alive: once it is dropped,
+/// another node can be allocated at the same address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeAddr(*const ());
+
+impl NodeAddr {
+    pub fn of<T: ?Sized>(x: &T) -> Self {
+        NodeAddr(x as *const T as *const ())
+    }
+}
+
+/// A cache of the results computed by a visitor for the nodes it visited, keyed on node identity
+/// (`NodeAddr`) or on a key of the user's choosing, e.g. the id of an interned value. A visitor
+/// owns one of these and exposes it via `Memoize::memo`.
+#[derive(Debug, Clone)]
+pub struct Memo<R, K = NodeAddr> {
+    cache: HashMap<K, R>,
+}
+
+impl<R, K: Hash + Eq> Memo<R, K> {
+    pub fn new() -> Self {
+        Memo {
+            cache: HashMap::new(),
+        }
+    }
+
+    /// The result cached for `key`, if any.
+    pub fn get(&self, key: &K) -> Option<&R> {
+        self.cache.get(key)
+    }
+
+    /// The number of cached results.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Forget all the cached results, e.g. when the nodes they were keyed on are dropped.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
+
+impl<R, K: Hash + Eq> Default for Memo<R, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A visitor that caches the results of some of its visits in a `Memo`, so that visiting the same
+/// node again (e.g. a subtree shared through an `Arc`, or the same interned value) reuses the
+/// result instead of visiting it again:
+/// ```ignore
+/// fn visit_ty(&mut self, ty: &Arc<Ty>) -> ControlFlow<Self::Break> {
+///     let normalized = self.memoized(NodeAddr::of(&**ty), |this| this.normalize(ty))?;
+///     ..
+/// }
+/// ```
+pub trait Memoize<R, K: Hash + Eq = NodeAddr>: Visitor + Sized {
+    /// The cache used by `memoized`.
+    fn memo(&mut self) -> &mut Memo<R, K>;
+
+    /// Return the result cached for `key`, or compute it with `compute` and cache it. Nothing is
+    /// cached if `compute` breaks.
+    fn memoized(
+        &mut self,
+        key: K,
+        compute: impl FnOnce(&mut Self) -> ControlFlow<Self::Break, R>,
+    ) -> ControlFlow<Self::Break, R>
+    where
+        R: Clone,
+    {
+        if let Some(result) = self.memo().get(&key) {
+            return Continue(result.clone());
+        }
+        let result = compute(self)?;
+        self.memo().cache.insert(key, result.clone());
+        Continue(result)
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+/// Identifies a node by its position in a traversal: the n-th node entered gets `NodeId(n)`, in
+/// pre-order. Two traversals of the same tree that visit the same nodes assign the same ids, so
+/// these can be used to key data computed by one pass and consumed by another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub usize);
+
+/// Data attached to nodes without modifying them.
+pub type SideTable<T> = HashMap<NodeId, T>;
+
+/// Assigns `NodeId`s during a traversal. A visitor of a `visitable_group` with the `node_ids`
+/// option owns one of these and exposes it via its `node_ids` method.
+#[derive(Debug, Clone, Default)]
+pub struct NodeIds {
+    next: usize,
+    /// The ids of the nodes currently being visited, innermost last.
+    stack: Vec<NodeId>,
+}
+
+impl NodeIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The id of the innermost node being visited.
+    pub fn current(&self) -> Option<NodeId> {
+        self.stack.last().copied()
+    }
+
+    /// The id of the node that contains the current node.
+    pub fn parent(&self) -> Option<NodeId> {
+        self.stack.iter().rev().nth(1).copied()
+    }
+
+    /// The number of ids assigned so far.
+    pub fn count(&self) -> usize {
+        self.next
+    }
+
+    /// Start numbering from zero again, e.g. before visiting another tree.
+    pub fn reset(&mut self) {
+        self.next = 0;
+        self.stack.clear();
+    }
+
+    #[doc(hidden)]
+    pub fn __enter(&mut self) -> NodeId {
+        let id = NodeId(self.next);
+        self.next += 1;
+        self.stack.push(id);
+        id
+    }
+
+    #[doc(hidden)]
+    pub fn __exit(&mut self) {
+        self.stack.pop();
+    }
+}
@@ -0,0 +1,26 @@
+/// A type that can name the kind of node it is. This gives generic visitors (e.g. debugging
+/// utilities) a portable way to describe what they're looking at. Derive it with
+/// `#[derive(NodeKind)]`.
+///
+/// This is a supertrait of `DriveAny`, so the same information is available on type-erased nodes.
+pub trait NodeKind {
+    /// The kind of node: `"Expr::Add"` for a variant `Add` of an enum `Expr`, the name of the type
+    /// for other types.
+    fn kind(&self) -> &'static str;
+
+    /// For enums, the index of the variant in declaration order; 0 for other types.
+    fn kind_index(&self) -> usize {
+        0
+    }
+}
+
+/// The name of a type without its module path or generic arguments, e.g. `Vec` for
+/// `alloc::vec::Vec<u32>`. Tuples, arrays and references are returned in full.
+pub fn short_type_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    if name.starts_with(['(', '[', '&', '*']) {
+        return name;
+    }
+    let name = name.split('<').next().unwrap();
+    name.rsplit("::").next().unwrap()
+}
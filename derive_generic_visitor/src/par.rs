@@ -0,0 +1,94 @@
+use rayon::prelude::*;
+
+use crate::*;
+
+/// A visitor that can be split to visit several values in parallel, then recombined.
+pub trait ParVisitor: Visitor<Break: Send> + Send + Sync + Sized {
+    /// Make a visitor to visit part of the contents of the current value, possibly on another
+    /// thread. This typically starts with empty state, but should keep the binder depth of a
+    /// `VisitBinder`: the fork enters the binders of the fields it visits itself.
+    fn fork(&self) -> Self;
+    /// Merge the state of a forked visitor back into `self`. Forks are joined in the order of the
+    /// contents they visited.
+    fn join(&mut self, other: Self);
+}
+
+/// A type whose contents can be visited in parallel.
+///
+/// Parallelism is opt-in per value: `drive_par_inner` visits the immediate contents of `self` on
+/// forks of the visitor, in parallel, and these continue sequentially unless their own `visit`
+/// calls `drive_par_inner` again, typically in an override for the types worth splitting up.
+pub trait DrivePar<'s, V: ParVisitor> {
+    /// Call `v.visit()` on the immediate contents of `self`, in parallel. If several of these
+    /// break, the first one in order is returned.
+    fn drive_par_inner(&'s self, v: &mut V) -> ControlFlow<V::Break>;
+}
+
+/// Visit `items` in parallel on forks of `v`, and join the forks into `v` in order. Stops at the
+/// first `Break` in order, joining only the forks that visited the preceding items.
+pub fn drive_par_iter<'a, I, T, V>(items: I, v: &mut V) -> ControlFlow<V::Break>
+where
+    I: IntoParallelIterator<Item = &'a T>,
+    T: Sync + 'a,
+    V: ParVisitor + Visit<'a, T>,
+{
+    run_on_forks(items.into_par_iter(), v, |fork, x| fork.visit(x))
+}
+
+/// A visit to run on a fork of `V`. Used by `derive(DrivePar)` to visit fields of different
+/// types.
+pub type ParTask<'t, V> = &'t (dyn Fn(&mut V) -> ControlFlow<<V as Visitor>::Break> + Sync);
+
+/// Run the given visits in parallel on forks of `v`, as for `drive_par_iter`.
+pub fn drive_par_tasks<V: ParVisitor>(
+    tasks: &[ParTask<'_, V>],
+    v: &mut V,
+) -> ControlFlow<V::Break> {
+    run_on_forks(tasks.par_iter(), v, |fork, task| task(fork))
+}
+
+fn run_on_forks<I, V>(
+    items: I,
+    v: &mut V,
+    visit: impl Fn(&mut V, I::Item) -> ControlFlow<V::Break> + Sync,
+) -> ControlFlow<V::Break>
+where
+    I: ParallelIterator,
+    V: ParVisitor,
+{
+    let shared = &*v;
+    // Each fork visits a contiguous range of items, so collecting them keeps them in order.
+    let forks: Vec<(V, ControlFlow<V::Break>)> = items
+        .fold(
+            || (shared.fork(), Continue(())),
+            |(mut fork, result), x| {
+                let result = match result {
+                    Continue(()) => visit(&mut fork, x),
+                    Break(b) => Break(b),
+                };
+                (fork, result)
+            },
+        )
+        .collect();
+    for (fork, result) in forks {
+        v.join(fork);
+        result?;
+    }
+    Continue(())
+}
+
+impl<'s, T: Sync, V: ParVisitor + Visit<'s, T>> DrivePar<'s, V> for [T] {
+    fn drive_par_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_par_iter(self, v)
+    }
+}
+impl<'s, T: Sync, const N: usize, V: ParVisitor + Visit<'s, T>> DrivePar<'s, V> for [T; N] {
+    fn drive_par_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_par_iter(self, v)
+    }
+}
+impl<'s, T: Sync, V: ParVisitor + Visit<'s, T>> DrivePar<'s, V> for Vec<T> {
+    fn drive_par_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        drive_par_iter(self, v)
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt::{self, Display};
+
+use crate::*;
+
+/// How a node is reached from the node that contains it. See `DriveAny::path_step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathStep {
+    /// A named field of a struct or enum variant.
+    Field(&'static str),
+    /// A field of a tuple struct or tuple variant, by position.
+    TupleField(usize),
+    /// The `n`-th node contained in the parent, e.g. the `n`-th element of a collection.
+    Index(usize),
+}
+
+/// The location of a node in a tree, as the steps from the root. Displays like a Rust place
+/// expression, e.g. `.items[2].0.name`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Path(pub Vec<PathStep>);
+
+impl Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.0 {
+            match step {
+                PathStep::Field(name) => write!(f, ".{name}")?,
+                PathStep::TupleField(index) => write!(f, ".{index}")?,
+                PathStep::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tracks the path to the current node during a `drive_any` traversal, e.g. to report where
+/// errors were found. A visitor owns one of these, calls `enter` first thing in its own `enter`
+/// and `exit` in its own `exit`; in between, `path` is the path from the root to the node being
+/// visited.
+#[derive(Default)]
+pub struct PathTracker<'a> {
+    path: Vec<PathStep>,
+    /// The nodes being visited, with the number of their contents entered so far.
+    stack: Vec<(&'a dyn DriveAny, usize)>,
+}
+
+impl<'a> PathTracker<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `x` is being entered.
+    pub fn enter(&mut self, x: &'a dyn DriveAny) {
+        if let Some((parent, entered)) = self.stack.last_mut() {
+            self.path.push(parent.path_step(*entered));
+            *entered += 1;
+        }
+        self.stack.push((x, 0));
+    }
+
+    /// Record that the current node is being exited.
+    pub fn exit(&mut self) {
+        self.stack.pop();
+        self.path.pop();
+    }
+
+    /// The steps from the root to the current node.
+    pub fn steps(&self) -> &[PathStep] {
+        &self.path
+    }
+
+    /// The path from the root to the current node.
+    pub fn path(&self) -> Path {
+        Path(self.path.clone())
+    }
+}
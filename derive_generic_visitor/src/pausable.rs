@@ -0,0 +1,71 @@
+use crate::*;
+
+/// Outcome of a call to `PausableTraversal::resume`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// The node budget ran out; call `resume` again to continue.
+    Paused,
+    /// The whole value has been visited.
+    Finished,
+}
+
+/// A traversal that can be suspended after a given number of nodes and resumed later, e.g. to
+/// yield to an event loop during a long traversal.
+///
+/// The traversal is driven by an `Events` iterator, which keeps the nodes being visited on an
+/// explicit stack, so resuming carries on from where the previous slice stopped. The value being
+/// visited is borrowed for the lifetime of the traversal so it can't change between two slices.
+pub struct PausableTraversal<'a> {
+    events: Events<'a>,
+    /// The event that was pulled from `events` but not delivered when the budget ran out.
+    pending: Option<Event<&'a dyn DriveAny>>,
+    /// The binders entered and not yet left.
+    binders: usize,
+    finished: bool,
+}
+
+impl<'a> PausableTraversal<'a> {
+    pub fn new(root: &'a dyn DriveAny) -> Self {
+        PausableTraversal {
+            events: events(root),
+            pending: None,
+            binders: 0,
+            finished: false,
+        }
+    }
+
+    /// Whether the traversal has visited the whole value (or was interrupted by the visitor).
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Continue the traversal, entering at most `budget` more nodes. Binders are reported to `v`
+    /// like nodes, but don't count towards the budget. Returns `Break` if the visitor returned
+    /// `Break`, in which case the traversal is over and `v` leaves the binders it was in, like
+    /// `drive_any` does.
+    pub fn resume(
+        &mut self,
+        mut budget: usize,
+        v: &mut dyn AnyVisitor<'a>,
+    ) -> ControlFlow<(), Progress> {
+        while !self.finished {
+            let Some(event) = self.pending.take().or_else(|| self.events.next()) else {
+                self.finished = true;
+                break;
+            };
+            if let Event::Enter(_) = event {
+                if budget == 0 {
+                    self.pending = Some(event);
+                    return Continue(Progress::Paused);
+                }
+                budget -= 1;
+            }
+            if deliver_event(event, v, &mut self.events, &mut self.binders).is_break() {
+                (0..self.binders).for_each(|_| v.exit_binder());
+                self.finished = true;
+                return Break(());
+            }
+        }
+        Continue(Progress::Finished)
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::*;
+
+/// Statistics recorded by a `ProfilingVisitor` for one type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeStats {
+    /// Number of nodes of this type that were visited.
+    pub count: usize,
+    /// Time spent between entering and exiting nodes of this type, including their contents.
+    pub total: Duration,
+    /// Like `total`, but excluding the time spent in nodes nested inside nodes of this type.
+    pub self_time: Duration,
+}
+
+/// A node currently being visited.
+struct Frame {
+    start: Instant,
+    /// Total time spent in the children of this node so far.
+    children: Duration,
+}
+
+/// Wraps a type-erased visitor and records, for each visited type, how many nodes were visited and
+/// how long was spent in them. This is meant to find out which types dominate the running time of
+/// a pass.
+///
+/// The timing of a node starts before the inner visitor's `enter` and stops after its `exit`, so it
+/// covers the work done by the visitor as well as the traversal itself. If the traversal is
+/// interrupted by a `Break`, the nodes being visited at that point are not recorded.
+pub struct ProfilingVisitor<V> {
+    inner: V,
+    stack: Vec<Frame>,
+    stats: HashMap<&'static str, TypeStats>,
+}
+
+impl<V> ProfilingVisitor<V> {
+    pub fn new(inner: V) -> Self {
+        ProfilingVisitor {
+            inner,
+            stack: Vec::new(),
+            stats: HashMap::new(),
+        }
+    }
+
+    /// The wrapped visitor.
+    pub fn inner(&mut self) -> &mut V {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+
+    /// The statistics recorded so far, indexed by type name.
+    pub fn stats(&self) -> &HashMap<&'static str, TypeStats> {
+        &self.stats
+    }
+
+    /// The statistics recorded so far, sorted by decreasing `self_time`.
+    pub fn report(&self) -> Vec<(&'static str, TypeStats)> {
+        let mut report: Vec<_> = self.stats.iter().map(|(&ty, &stats)| (ty, stats)).collect();
+        report.sort_by(|(ty1, s1), (ty2, s2)| s2.self_time.cmp(&s1.self_time).then(ty1.cmp(ty2)));
+        report
+    }
+
+    /// Forget the statistics recorded so far.
+    pub fn reset(&mut self) {
+        self.stack.clear();
+        self.stats.clear();
+    }
+
+    fn start(&mut self, start: Instant) {
+        self.stack.push(Frame {
+            start,
+            children: Duration::ZERO,
+        });
+    }
+
+    fn finish(&mut self, type_name: &'static str) {
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+        let elapsed = frame.start.elapsed();
+        let stats = self.stats.entry(type_name).or_default();
+        stats.count += 1;
+        stats.total += elapsed;
+        stats.self_time += elapsed.saturating_sub(frame.children);
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += elapsed;
+        }
+    }
+}
+
+impl<'a, V: AnyVisitor<'a>> AnyVisitor<'a> for ProfilingVisitor<V> {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        let start = Instant::now();
+        let children = self.inner.enter(x)?;
+        self.start(start);
+        Continue(children)
+    }
+
+    fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+        let result = self.inner.exit(x);
+        self.finish(x.type_name());
+        result
+    }
+
+    fn enter_binder(&mut self) {
+        self.inner.enter_binder()
+    }
+
+    fn exit_binder(&mut self) {
+        self.inner.exit_binder()
+    }
+}
+
+impl<V: AnyVisitorMut> AnyVisitorMut for ProfilingVisitor<V> {
+    fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        let start = Instant::now();
+        let children = self.inner.enter(x)?;
+        self.start(start);
+        Continue(children)
+    }
+
+    fn exit(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<()> {
+        let result = self.inner.exit(x);
+        self.finish(x.type_name());
+        result
+    }
+
+    fn enter_binder(&mut self) {
+        self.inner.enter_binder()
+    }
+
+    fn exit_binder(&mut self) {
+        self.inner.exit_binder()
+    }
+}
@@ -0,0 +1,151 @@
+use std::any::Any;
+use std::marker::PhantomData;
+
+use crate::*;
+
+/// Returns the values of type `T` inside `x` that aren't nested inside another `T`, in pre-order.
+/// `x` itself is not included even if it is a `T`. This is the "stop at nested matches" query
+/// needed e.g. to find the direct sub-scopes of a scope.
+pub fn children_of<T: Any>(x: &dyn DriveAny) -> Vec<&T> {
+    struct Collect<'a, T>(Vec<&'a T>);
+    impl<'a, T: Any> AnyVisitor<'a> for Collect<'a, T> {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            Continue(match x.downcast_ref::<T>() {
+                Some(x) => {
+                    self.0.push(x);
+                    Children::Skip
+                }
+                None => Children::Visit,
+            })
+        }
+    }
+    let mut collect = Collect(Vec::new());
+    let _ = x.drive_any_inner(&mut collect);
+    collect.0
+}
+
+/// Calls `f` on the values of type `T` inside `x` that aren't nested inside another `T`, in
+/// pre-order. `x` itself is not included even if it is a `T`. See `children_of`.
+pub fn for_each_child_of_mut<T: Any>(x: &mut dyn DriveAnyMut, f: impl FnMut(&mut T)) {
+    struct Apply<T, F>(F, PhantomData<fn(&mut T)>);
+    impl<T: Any, F: FnMut(&mut T)> AnyVisitorMut for Apply<T, F> {
+        fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+            Continue(match x.downcast_mut::<T>() {
+                Some(x) => {
+                    (self.0)(x);
+                    Children::Skip
+                }
+                None => Children::Visit,
+            })
+        }
+    }
+    let _ = x.drive_any_inner_mut(&mut Apply(f, PhantomData));
+}
+
+/// Returns all the values of type `T` in `x`, including `x` itself and values nested inside
+/// other `T`s, in pre-order.
+pub fn collect_all<T: Any>(x: &dyn DriveAny) -> Vec<&T> {
+    struct Collect<'a, T>(Vec<&'a T>);
+    impl<'a, T: Any> AnyVisitor<'a> for Collect<'a, T> {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            if let Some(x) = x.downcast_ref::<T>() {
+                self.0.push(x);
+            }
+            Continue(Children::Visit)
+        }
+    }
+    let mut collect = Collect(Vec::new());
+    let _ = drive_any(x, &mut collect);
+    collect.0
+}
+
+/// Calls `f` on all the values of type `T` in `x`, including `x` itself and values nested inside
+/// other `T`s, in pre-order. The contents of a value are visited after `f` has modified it. This
+/// is the mutable counterpart of `collect_all`, which can't return several `&mut` references to
+/// values that may be nested in one another.
+pub fn for_each_mut<T: Any>(x: &mut dyn DriveAnyMut, f: impl FnMut(&mut T)) {
+    struct Apply<T, F>(F, PhantomData<fn(&mut T)>);
+    impl<T: Any, F: FnMut(&mut T)> AnyVisitorMut for Apply<T, F> {
+        fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+            if let Some(x) = x.downcast_mut::<T>() {
+                (self.0)(x);
+            }
+            Continue(Children::Visit)
+        }
+    }
+    let _ = drive_any_mut(x, &mut Apply(f, PhantomData));
+}
+
+/// Returns the first value of type `T` in `x` (possibly `x` itself), in pre-order, for which
+/// `pred` returns `true`. The traversal stops there.
+pub fn find_first<T: Any>(x: &dyn DriveAny, pred: impl FnMut(&T) -> bool) -> Option<&T> {
+    struct Find<'a, T, P>(P, Option<&'a T>);
+    impl<'a, T: Any, P: FnMut(&T) -> bool> AnyVisitor<'a> for Find<'a, T, P> {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            if let Some(x) = x.downcast_ref::<T>() {
+                if (self.0)(x) {
+                    self.1 = Some(x);
+                    return Break(());
+                }
+            }
+            Continue(Children::Visit)
+        }
+    }
+    let mut find = Find(pred, None);
+    let _ = drive_any(x, &mut find);
+    find.1
+}
+
+/// Calls `f` on the first value of type `T` in `x` (possibly `x` itself), in pre-order, for which
+/// `pred` returns `true`, and returns its result. The traversal stops there.
+///
+/// Unlike `find_first`, this can't return the value itself: the visitor only gets temporary
+/// access to each node.
+pub fn find_first_mut<T: Any, R>(
+    x: &mut dyn DriveAnyMut,
+    pred: impl FnMut(&T) -> bool,
+    f: impl FnOnce(&mut T) -> R,
+) -> Option<R> {
+    struct Find<T, P, F, R>(P, Option<F>, Option<R>, PhantomData<fn(&mut T)>);
+    impl<T: Any, P: FnMut(&T) -> bool, F: FnOnce(&mut T) -> R, R> AnyVisitorMut for Find<T, P, F, R> {
+        fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+            if let Some(x) = x.downcast_mut::<T>() {
+                if (self.0)(x) {
+                    self.2 = Some((self.1.take().unwrap())(x));
+                    return Break(());
+                }
+            }
+            Continue(Children::Visit)
+        }
+    }
+    let mut find = Find(pred, Some(f), None, PhantomData);
+    let _ = drive_any_mut(x, &mut find);
+    find.2
+}
+
+/// The number of values of type `T` in `x`, including `x` itself and nested values.
+pub fn count<T: Any>(x: &dyn DriveAny) -> usize {
+    struct Count<T>(usize, PhantomData<fn(&T)>);
+    impl<T: Any> AnyVisitor<'_> for Count<T> {
+        fn enter(&mut self, x: &dyn DriveAny) -> ControlFlow<(), Children> {
+            if x.is::<T>() {
+                self.0 += 1;
+            }
+            Continue(Children::Visit)
+        }
+    }
+    let mut count = Count::<T>(0, PhantomData);
+    let _ = drive_any(x, &mut count);
+    count.0
+}
+
+/// Whether `pred` returns `true` for some value of type `T` in `x`. Stops at the first one.
+pub fn any<T: Any>(x: &dyn DriveAny, pred: impl FnMut(&T) -> bool) -> bool {
+    find_first(x, pred).is_some()
+}
+
+/// Whether `pred` returns `true` for all the values of type `T` in `x`. Stops at the first one
+/// for which it doesn't.
+pub fn all<T: Any>(x: &dyn DriveAny, mut pred: impl FnMut(&T) -> bool) -> bool {
+    find_first(x, |x: &T| !pred(x)).is_none()
+}
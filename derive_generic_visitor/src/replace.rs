@@ -0,0 +1,49 @@
+use std::{mem, ptr};
+
+/// Aborts the process if dropped, i.e. if a panic unwinds past it.
+struct AbortOnUnwind;
+
+impl Drop for AbortOnUnwind {
+    fn drop(&mut self) {
+        std::process::abort();
+    }
+}
+
+/// Replace `*x` by `f(*x)`, taking ownership of the old value without needing a placeholder to
+/// put in its place in the meantime. Useful in `VisitMut` impls to restructure a node:
+/// ```rust
+/// # use derive_generic_visitor::*;
+/// enum Expr {
+///     Lit(u32),
+///     Neg(Box<Expr>),
+/// }
+/// let mut x = Expr::Neg(Box::new(Expr::Neg(Box::new(Expr::Lit(1)))));
+/// replace_with(&mut x, |x| match x {
+///     Expr::Neg(x) => match *x {
+///         Expr::Neg(x) => *x,
+///         x => Expr::Neg(Box::new(x)),
+///     },
+///     x => x,
+/// });
+/// assert!(matches!(x, Expr::Lit(1)));
+/// ```
+///
+/// If `f` panics, there is no value to leave in `*x`, so the process is aborted.
+pub fn replace_with<T>(x: &mut T, f: impl FnOnce(T) -> T) {
+    replace_with_and_return(x, |x| (f(x), ()))
+}
+
+/// Like `replace_with`, but `f` also returns a value, e.g. the `ControlFlow` of visiting the
+/// contents of the old value.
+pub fn replace_with_and_return<T, R>(x: &mut T, f: impl FnOnce(T) -> (T, R)) -> R {
+    let guard = AbortOnUnwind;
+    // SAFETY: `*x` is moved out then written back before anything else can observe it. If `f`
+    // panics, the guard aborts before `*x` can be dropped or used again.
+    let result = unsafe {
+        let (new, result) = f(ptr::read(x));
+        ptr::write(x, new);
+        result
+    };
+    mem::forget(guard);
+    result
+}
@@ -0,0 +1,22 @@
+use crate::*;
+
+/// A visitor that can look up the node an `Id` stands for, e.g. in an arena it holds a reference
+/// to. The `Drive` impls derived with `#[drive(resolve)]` on a field of type `Id` visit the node
+/// the field resolves to instead of the id, so that a traversal goes on through the arena:
+/// ```ignore
+/// impl<'a> Resolve<'a, ExprId> for MyVisitor<'a> {
+///     type Node = Expr;
+///     fn resolve(&mut self, id: &'a ExprId) -> Option<&'a Expr> {
+///         self.arena.get(id)
+///     }
+/// }
+/// ```
+/// The `DriveMut` impls visit the id itself, since the arena can't be borrowed mutably for each
+/// node.
+pub trait Resolve<'a, Id: ?Sized>: Visitor {
+    type Node: ?Sized + 'a;
+
+    /// The node `id` stands for, or `None` if it isn't available, in which case nothing is
+    /// visited.
+    fn resolve(&mut self, id: &'a Id) -> Option<&'a Self::Node>;
+}
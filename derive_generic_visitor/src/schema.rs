@@ -0,0 +1,67 @@
+/// A type whose visitable structure is known at compile time. Derive it with
+/// `#[derive(DriveSchema)]`, alongside `Drive`; it honors the same `#[drive(skip)]` attributes.
+///
+/// This is meant for tools that need to know the shape of a tree without traversing a value, e.g.
+/// code generators or documentation.
+pub trait DriveSchema {
+    const SCHEMA: Schema;
+}
+
+/// The visitable structure of a type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schema {
+    /// The name of the type, without generic arguments.
+    pub name: &'static str,
+    /// Whether the type is marked `#[drive(skip)]`, i.e. has no visitable contents.
+    pub skip: bool,
+    pub shape: Shape,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Struct(&'static [FieldSchema]),
+    Enum(&'static [VariantSchema]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VariantSchema {
+    pub name: &'static str,
+    /// Whether the variant is marked `#[drive(skip)]`.
+    pub skip: bool,
+    pub fields: &'static [FieldSchema],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSchema {
+    /// The name of the field, or `None` for tuple fields.
+    pub name: Option<&'static str>,
+    /// The type of the field, as written in the source.
+    pub ty: &'static str,
+    /// Whether the field is marked `#[drive(skip)]`.
+    pub skip: bool,
+}
+
+impl Schema {
+    /// Iterate over the fields that are visited when driving a value of this type, across all
+    /// variants.
+    pub fn visited_fields(&self) -> impl Iterator<Item = &'static FieldSchema> {
+        let variants: &'static [VariantSchema] = match self.shape {
+            Shape::Enum(variants) => variants,
+            Shape::Struct(_) => &[],
+        };
+        let struct_fields: &'static [FieldSchema] = match self.shape {
+            Shape::Struct(fields) => fields,
+            Shape::Enum(_) => &[],
+        };
+        let skip = self.skip;
+        struct_fields
+            .iter()
+            .chain(
+                variants
+                    .iter()
+                    .filter(|v| !v.skip)
+                    .flat_map(|v| v.fields.iter()),
+            )
+            .filter(move |f| !skip && !f.skip)
+    }
+}
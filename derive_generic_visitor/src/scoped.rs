@@ -0,0 +1,69 @@
+use crate::*;
+
+/// A stack of scopes maintained by a visitor, e.g. the variables bound by the enclosing binders
+/// during name resolution. A visitor owns one of these and exposes it via `Scoped::scopes`.
+#[derive(Debug, Clone)]
+pub struct ScopeStack<S> {
+    scopes: Vec<S>,
+}
+
+impl<S> ScopeStack<S> {
+    pub fn new() -> Self {
+        ScopeStack { scopes: Vec::new() }
+    }
+
+    /// The innermost scope, if any.
+    pub fn innermost(&self) -> Option<&S> {
+        self.scopes.last()
+    }
+
+    pub fn innermost_mut(&mut self) -> Option<&mut S> {
+        self.scopes.last_mut()
+    }
+
+    /// The scopes, innermost first.
+    pub fn iter(&self) -> impl Iterator<Item = &S> {
+        self.scopes.iter().rev()
+    }
+
+    /// The innermost result of `f` on the scopes, e.g. the innermost binding of a name.
+    pub fn find_map<R>(&self, f: impl FnMut(&S) -> Option<R>) -> Option<R> {
+        self.iter().find_map(f)
+    }
+
+    /// The number of scopes entered.
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+}
+
+impl<S> Default for ScopeStack<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A visitor that enters scopes as it goes down a tree. Scopes are entered around a visit with
+/// `in_scope`, which takes care of leaving them even if the visit breaks, so that a visitor that
+/// recovers from a break (e.g. to report several errors) doesn't keep stale scopes around:
+/// ```ignore
+/// fn visit_function(&mut self, f: &Function) -> ControlFlow<Self::Break> {
+///     self.in_scope(Scope::params_of(f), |this| this.visit_inner(f))
+/// }
+/// ```
+pub trait Scoped<S>: Visitor + Sized {
+    /// The scopes entered so far.
+    fn scopes(&mut self) -> &mut ScopeStack<S>;
+
+    /// Run `f` with `scope` as the innermost scope, and leave it afterwards whatever `f` returns.
+    fn in_scope<R>(&mut self, scope: S, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.scopes().scopes.push(scope);
+        let result = f(self);
+        self.scopes().scopes.pop();
+        result
+    }
+}
@@ -0,0 +1,141 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::*;
+
+/// Routes each node to one of several type-erased visitors depending on its type, in a single
+/// traversal. This makes it possible to split a large visitor into focused components, e.g. one
+/// that handles expressions and one that handles types.
+///
+/// Nodes whose type isn't routed are traversed without calling any visitor. A routed visitor sees
+/// `enter` and `exit` only for the nodes routed to it, and controls whether their contents are
+/// visited. Binders are reported to all the visitors.
+#[derive(Default)]
+pub struct SplitVisitor<'a, 's> {
+    visitors: Vec<&'s mut dyn AnyVisitor<'a>>,
+    routes: HashMap<TypeId, usize>,
+}
+
+impl<'a, 's> SplitVisitor<'a, 's> {
+    pub fn new() -> Self {
+        SplitVisitor {
+            visitors: Vec::new(),
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Route the nodes of the given types to `v`. A type that is already routed to another visitor
+    /// stays with that visitor.
+    pub fn push(&mut self, types: &[TypeId], v: &'s mut dyn AnyVisitor<'a>) {
+        let idx = self.visitors.len();
+        self.visitors.push(v);
+        for ty in types {
+            self.routes.entry(*ty).or_insert(idx);
+        }
+    }
+
+    /// Route the nodes of the given types to `v`; convenience for method chaining.
+    pub fn with(mut self, types: &[TypeId], v: &'s mut dyn AnyVisitor<'a>) -> Self {
+        self.push(types, v);
+        self
+    }
+
+    fn route(&mut self, x: &'a dyn DriveAny) -> Option<&mut &'s mut dyn AnyVisitor<'a>> {
+        let idx = *self.routes.get(&(x as &dyn Any).type_id())?;
+        Some(&mut self.visitors[idx])
+    }
+}
+
+impl<'a> AnyVisitor<'a> for SplitVisitor<'a, '_> {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        match self.route(x) {
+            Some(v) => v.enter(x),
+            None => Continue(Children::Visit),
+        }
+    }
+
+    fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+        match self.route(x) {
+            Some(v) => v.exit(x),
+            None => Continue(()),
+        }
+    }
+
+    fn enter_binder(&mut self) {
+        for v in &mut self.visitors {
+            v.enter_binder();
+        }
+    }
+
+    fn exit_binder(&mut self) {
+        for v in &mut self.visitors {
+            v.exit_binder();
+        }
+    }
+}
+
+/// Routes each node to one of several type-erased mutable visitors depending on its type. See
+/// `SplitVisitor`.
+#[derive(Default)]
+pub struct SplitVisitorMut<'s> {
+    visitors: Vec<&'s mut dyn AnyVisitorMut>,
+    routes: HashMap<TypeId, usize>,
+}
+
+impl<'s> SplitVisitorMut<'s> {
+    pub fn new() -> Self {
+        SplitVisitorMut {
+            visitors: Vec::new(),
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Route the nodes of the given types to `v`. A type that is already routed to another visitor
+    /// stays with that visitor.
+    pub fn push(&mut self, types: &[TypeId], v: &'s mut dyn AnyVisitorMut) {
+        let idx = self.visitors.len();
+        self.visitors.push(v);
+        for ty in types {
+            self.routes.entry(*ty).or_insert(idx);
+        }
+    }
+
+    /// Route the nodes of the given types to `v`; convenience for method chaining.
+    pub fn with(mut self, types: &[TypeId], v: &'s mut dyn AnyVisitorMut) -> Self {
+        self.push(types, v);
+        self
+    }
+
+    fn route(&mut self, x: &dyn DriveAnyMut) -> Option<&mut &'s mut dyn AnyVisitorMut> {
+        let idx = *self.routes.get(&(x as &dyn Any).type_id())?;
+        Some(&mut self.visitors[idx])
+    }
+}
+
+impl AnyVisitorMut for SplitVisitorMut<'_> {
+    fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        match self.route(x) {
+            Some(v) => v.enter(x),
+            None => Continue(Children::Visit),
+        }
+    }
+
+    fn exit(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<()> {
+        match self.route(x) {
+            Some(v) => v.exit(x),
+            None => Continue(()),
+        }
+    }
+
+    fn enter_binder(&mut self) {
+        for v in &mut self.visitors {
+            v.enter_binder();
+        }
+    }
+
+    fn exit_binder(&mut self) {
+        for v in &mut self.visitors {
+            v.exit_binder();
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::marker::PhantomData;
+
+use crate::*;
+
+/// A node that can be a variable bound by an enclosing binder (a field marked `#[drive(binder)]`),
+/// identified by its de Bruijn index: the number of binders between the variable and the one that
+/// binds it. Implement this on the type that variables are a variant of, e.g. `Expr`, to use
+/// `shift_bound_vars`, `subst_bound_vars` and `instantiate_bound_var` on the values that contain it.
+pub trait BoundVar: DriveAnyMut + Sized {
+    /// The index of `self` if it is a bound variable.
+    fn bound_var_mut(&mut self) -> Option<&mut usize>;
+}
+
+/// What to do with a variable, as decided by the callback of `subst_bound_vars`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VarSubst<T> {
+    /// Leave the variable as it is.
+    Keep,
+    /// Give it this index.
+    Reindex(usize),
+    /// Replace it by this value.
+    Replace(T),
+}
+
+/// Rewrite the variables of `x` that are bound outside of it. `f` gets the index of each such
+/// variable as seen from outside of `x`, and the indices and values it returns are seen from
+/// outside of `x` too: `subst_bound_vars` adjusts them for the binders of `x` the variable is
+/// under. In particular, the free variables of a replacement value are shifted so that they aren't
+/// captured by these binders. Variables bound inside `x` are left alone.
+pub fn subst_bound_vars<T: BoundVar>(x: &mut dyn DriveAnyMut, f: impl FnMut(usize) -> VarSubst<T>) {
+    let mut v = SubstVars {
+        depth: 0,
+        f,
+        _t: PhantomData,
+    };
+    let _ = drive_any_mut(x, &mut v);
+}
+
+/// Add `by` to the indices of the variables of `x` bound outside of it, e.g. to move `x` under
+/// `by` more binders, or from under `-by` binders.
+///
+/// Panics if an index would become negative, i.e. if `x` uses a variable bound by one of the
+/// binders it is moved out of.
+pub fn shift_bound_vars<T: BoundVar>(x: &mut dyn DriveAnyMut, by: isize) {
+    subst_bound_vars::<T>(x, |i| {
+        let i = i
+            .checked_add_signed(by)
+            .expect("shifted a variable out of its binder");
+        VarSubst::Reindex(i)
+    })
+}
+
+/// Remove the innermost binder around `body`: replace the variable it binds by `value`, and
+/// decrement the indices of the other variables bound outside of `body`. This is the substitution
+/// of beta-reduction, i.e. turns the body of `\x. body` into `body[x := value]`.
+pub fn instantiate_bound_var<T: BoundVar + Clone>(body: &mut dyn DriveAnyMut, value: &T) {
+    subst_bound_vars(body, |i| match i {
+        0 => VarSubst::Replace(value.clone()),
+        i => VarSubst::Reindex(i - 1),
+    })
+}
+
+struct SubstVars<T, F> {
+    /// The number of binders of the value being rewritten that we're under.
+    depth: usize,
+    f: F,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<T: BoundVar, F: FnMut(usize) -> VarSubst<T>> AnyVisitorMut for SubstVars<T, F> {
+    fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        let Some(node) = x.downcast_mut::<T>() else {
+            return Continue(Children::Visit);
+        };
+        let Some(index) = node.bound_var_mut() else {
+            return Continue(Children::Visit);
+        };
+        if *index < self.depth {
+            return Continue(Children::Visit);
+        }
+        match (self.f)(*index - self.depth) {
+            VarSubst::Keep => {}
+            VarSubst::Reindex(new) => *index = new + self.depth,
+            VarSubst::Replace(mut value) => {
+                if self.depth != 0 {
+                    shift_bound_vars::<T>(&mut value, self.depth as isize);
+                }
+                *node = value;
+                return Continue(Children::Skip);
+            }
+        }
+        Continue(Children::Visit)
+    }
+    fn enter_binder(&mut self) {
+        self.depth += 1;
+    }
+    fn exit_binder(&mut self) {
+        self.depth -= 1;
+    }
+}
@@ -0,0 +1,96 @@
+use std::any::{Any, TypeId};
+use std::collections::HashSet;
+
+use crate::*;
+
+/// A visitor that records the nodes it enters and exits, to check in tests that a type is
+/// traversed in the expected order and that the nodes of interest are reached. Nodes are recorded
+/// by their `NodeKind`, e.g. `Event::Enter("Expr::Add")`.
+///
+/// To check what another visitor sees, run both in a `VisitorChain`.
+#[derive(Debug, Clone, Default)]
+pub struct TraversalRecorder {
+    events: Vec<Event<&'static str>>,
+    /// The types of the nodes entered so far.
+    types: HashSet<TypeId>,
+}
+
+impl TraversalRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The events recorded so far, in order.
+    pub fn events(&self) -> &[Event<&'static str>] {
+        &self.events
+    }
+
+    /// The kinds of the nodes entered so far, in order.
+    pub fn entered(&self) -> Vec<&'static str> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Enter(kind) => Some(*kind),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether a node of type `T` was entered.
+    pub fn has_visited<T: ?Sized + Any>(&self) -> bool {
+        self.types.contains(&TypeId::of::<T>())
+    }
+
+    /// Panics if no node of type `T` was entered.
+    #[track_caller]
+    pub fn assert_visited<T: ?Sized + Any>(&self) {
+        assert!(
+            self.has_visited::<T>(),
+            "no `{}` was visited; visited: {:?}",
+            std::any::type_name::<T>(),
+            self.entered(),
+        );
+    }
+
+    /// Forget the events recorded so far.
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.types.clear();
+    }
+
+    fn enter(&mut self, type_id: TypeId, kind: &'static str) {
+        self.types.insert(type_id);
+        self.events.push(Event::Enter(kind));
+    }
+}
+
+impl<'a> AnyVisitor<'a> for TraversalRecorder {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        TraversalRecorder::enter(self, (x as &dyn Any).type_id(), x.kind());
+        Continue(Children::Visit)
+    }
+
+    fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+        self.events.push(Event::Exit(x.kind()));
+        Continue(())
+    }
+}
+
+impl AnyVisitorMut for TraversalRecorder {
+    fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        TraversalRecorder::enter(self, (x as &dyn Any).type_id(), x.kind());
+        Continue(Children::Visit)
+    }
+
+    fn exit(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<()> {
+        self.events.push(Event::Exit(x.kind()));
+        Continue(())
+    }
+}
+
+/// Record the traversal of `x`. See `TraversalRecorder`.
+pub fn record_traversal(x: &dyn DriveAny) -> TraversalRecorder {
+    let mut recorder = TraversalRecorder::new();
+    let _ = drive_any(x, &mut recorder);
+    recorder
+}
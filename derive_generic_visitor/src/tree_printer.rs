@@ -0,0 +1,128 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::{Debug, Write};
+
+use crate::*;
+
+type DebugFn = fn(&dyn DriveAny, &mut String);
+
+/// A visitor that prints the nodes of a `DriveAny` value as an indented tree, one node per line
+/// with its `NodeKind`, e.g. to find out why a visitor doesn't reach some node:
+/// ```text
+/// Expr::Add
+///   Box
+///     Expr::Lit
+///       u32 = 1
+/// ```
+/// The nodes of the types registered with `debug` are printed with their `Debug` impl instead of
+/// their contents; numbers, `bool`, `char` and `String` are registered by default. With
+/// `with_field_names`, each node is prefixed with the field or index it is found at in its
+/// parent, e.g. `.lhs: Box`.
+pub struct TreePrinter<'a> {
+    out: String,
+    indent: &'static str,
+    debug: HashMap<TypeId, DebugFn>,
+    field_names: bool,
+    path: PathTracker<'a>,
+}
+
+impl<'a> TreePrinter<'a> {
+    pub fn new() -> Self {
+        let printer = TreePrinter {
+            out: String::new(),
+            indent: "  ",
+            debug: HashMap::new(),
+            field_names: false,
+            path: PathTracker::new(),
+        };
+        printer
+            .debug::<u8>()
+            .debug::<u16>()
+            .debug::<u32>()
+            .debug::<u64>()
+            .debug::<u128>()
+            .debug::<usize>()
+            .debug::<i8>()
+            .debug::<i16>()
+            .debug::<i32>()
+            .debug::<i64>()
+            .debug::<i128>()
+            .debug::<isize>()
+            .debug::<f32>()
+            .debug::<f64>()
+            .debug::<bool>()
+            .debug::<char>()
+            .debug::<String>()
+    }
+
+    /// Print the nodes of type `T` with their `Debug` impl, in place of their contents.
+    pub fn debug<T: Debug + Any>(mut self) -> Self {
+        self.debug.insert(TypeId::of::<T>(), |x, out| {
+            let _ = write!(out, "{:?}", x.downcast_ref::<T>().unwrap());
+        });
+        self
+    }
+
+    /// Prefix each node with the field or index it is found at in its parent.
+    pub fn with_field_names(mut self) -> Self {
+        self.field_names = true;
+        self
+    }
+
+    /// Indent each level with `indent` instead of two spaces.
+    pub fn with_indent(mut self, indent: &'static str) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// The tree printed so far.
+    pub fn output(&self) -> &str {
+        &self.out
+    }
+
+    pub fn into_output(self) -> String {
+        self.out
+    }
+}
+
+impl Default for TreePrinter<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> AnyVisitor<'a> for TreePrinter<'a> {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        self.path.enter(x);
+        let steps = self.path.steps();
+        for _ in 0..steps.len() {
+            self.out.push_str(self.indent);
+        }
+        if let (true, Some(step)) = (self.field_names, steps.last()) {
+            let _ = write!(self.out, "{}: ", Path(vec![*step]));
+        }
+        self.out.push_str(x.kind());
+        let children = match self.debug.get(&(x as &dyn Any).type_id()) {
+            Some(debug) => {
+                self.out.push_str(" = ");
+                debug(x, &mut self.out);
+                Children::Skip
+            }
+            None => Children::Visit,
+        };
+        self.out.push('\n');
+        Continue(children)
+    }
+
+    fn exit(&mut self, _x: &'a dyn DriveAny) -> ControlFlow<()> {
+        self.path.exit();
+        Continue(())
+    }
+}
+
+/// Print `x` as an indented tree. See `TreePrinter`.
+pub fn tree_string(x: &dyn DriveAny) -> String {
+    let mut printer = TreePrinter::new();
+    let _ = drive_any(x, &mut printer);
+    printer.into_output()
+}
@@ -0,0 +1,88 @@
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+
+use crate::*;
+
+/// Counts the nodes of each type in a value, e.g. to check in CI that a change doesn't blow up
+/// the size of an IR. Types are identified by their `std::any::type_name`.
+///
+/// As an `AnyVisitor`, this counts the nodes of any `DriveAny` value; `type_counts(&x)` does
+/// that in one go. Statically-typed visitors, e.g. those of a `visitable_group`, can own one and
+/// call `record` from the `enter_$ty` methods of the types they want counted.
+#[derive(Debug, Clone, Default)]
+pub struct TypeCounts {
+    counts: HashMap<&'static str, usize>,
+    /// The types to count, or `None` to count all of them.
+    registered: Option<HashSet<TypeId>>,
+}
+
+impl TypeCounts {
+    /// Count the nodes of all types.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the nodes of type `T`. Once a type is registered, only the nodes of registered types
+    /// are counted.
+    pub fn register<T: ?Sized + Any>(mut self) -> Self {
+        self.registered
+            .get_or_insert_with(HashSet::new)
+            .insert(TypeId::of::<T>());
+        self
+    }
+
+    /// Count `x`, if its type is counted.
+    pub fn record<T: ?Sized + Any>(&mut self, _x: &T) {
+        self.record_type(TypeId::of::<T>(), std::any::type_name::<T>());
+    }
+
+    fn record_type(&mut self, type_id: TypeId, type_name: &'static str) {
+        if self
+            .registered
+            .as_ref()
+            .is_none_or(|registered| registered.contains(&type_id))
+        {
+            *self.counts.entry(type_name).or_default() += 1;
+        }
+    }
+
+    /// The number of nodes of type `T` counted so far.
+    pub fn get<T: ?Sized>(&self) -> usize {
+        self.counts
+            .get(std::any::type_name::<T>())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The counts so far, indexed by type name.
+    pub fn counts(&self) -> &HashMap<&'static str, usize> {
+        &self.counts
+    }
+
+    /// The total number of nodes counted so far.
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    /// The counts so far, by decreasing count then type name, e.g. to print them or compare them
+    /// with a snapshot.
+    pub fn report(&self) -> Vec<(&'static str, usize)> {
+        let mut report: Vec<_> = self.counts.iter().map(|(&ty, &n)| (ty, n)).collect();
+        report.sort_by(|(ty1, n1), (ty2, n2)| n2.cmp(n1).then(ty1.cmp(ty2)));
+        report
+    }
+}
+
+impl<'a> AnyVisitor<'a> for TypeCounts {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        self.record_type((x as &dyn Any).type_id(), x.type_name());
+        Continue(Children::Visit)
+    }
+}
+
+/// Count the nodes of each type in `x`. See `TypeCounts`.
+pub fn type_counts(x: &dyn DriveAny) -> TypeCounts {
+    let mut counts = TypeCounts::new();
+    let _ = drive_any(x, &mut counts);
+    counts
+}
@@ -0,0 +1,170 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use crate::*;
+
+/// A way to drive a `T`, used by `DriveVia<T, S>`. Implemented by marker types, typically for a
+/// foreign type that has no `Drive` impl, or whose impl doesn't visit what we want.
+pub trait DriveStrategy<'s, T: ?Sized, V: Visitor> {
+    /// Call `v.visit()` on the contents of `x`.
+    fn drive_inner(x: &'s T, v: &mut V) -> ControlFlow<V::Break>;
+}
+
+/// Like `DriveStrategy`, for `DriveMut`.
+pub trait DriveMutStrategy<'s, T: ?Sized, V: Visitor> {
+    /// Call `v.visit()` on the contents of `x`, mutably.
+    fn drive_inner_mut(x: &'s mut T, v: &mut V) -> ControlFlow<V::Break>;
+}
+
+/// Field wrapper that implements `Drive[Mut]` for a `T` by delegating to the strategy `S`. This
+/// customizes the traversal of a field at the type level, e.g. for a foreign type:
+/// ```rust
+/// # use derive_generic_visitor::*;
+/// /// From another crate.
+/// pub struct Span {
+///     pub lo: u32,
+///     pub hi: u32,
+/// }
+///
+/// /// Visits the bounds of a span.
+/// struct Bounds;
+/// impl<'s, V: Visit<'s, u32>> DriveStrategy<'s, Span, V> for Bounds {
+///     fn drive_inner(x: &'s Span, v: &mut V) -> ControlFlow<V::Break> {
+///         v.visit(&x.lo)?;
+///         v.visit(&x.hi)
+///     }
+/// }
+///
+/// #[derive(Drive)]
+/// struct Token {
+///     span: DriveVia<Span, Bounds>,
+///     #[drive(skip)]
+///     text: String,
+/// }
+/// ```
+#[repr(transparent)]
+pub struct DriveVia<T, S>(pub T, PhantomData<fn() -> S>);
+
+impl<T, S> DriveVia<T, S> {
+    pub fn new(x: T) -> Self {
+        DriveVia(x, PhantomData)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, S> Deref for DriveVia<T, S> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T, S> DerefMut for DriveVia<T, S> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+// Implemented by hand, so as not to require anything of the strategy.
+impl<T: fmt::Debug, S> fmt::Debug for DriveVia<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DriveVia").field(&self.0).finish()
+    }
+}
+impl<T: Default, S> Default for DriveVia<T, S> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+impl<T: Clone, S> Clone for DriveVia<T, S> {
+    fn clone(&self) -> Self {
+        Self::new(self.0.clone())
+    }
+}
+impl<T: Copy, S> Copy for DriveVia<T, S> {}
+impl<T: PartialEq, S> PartialEq for DriveVia<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl<T: Eq, S> Eq for DriveVia<T, S> {}
+impl<T: Hash, S> Hash for DriveVia<T, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<'s, T, S, V> Drive<'s, V> for DriveVia<T, S>
+where
+    S: DriveStrategy<'s, T, V>,
+    V: Visitor,
+{
+    fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+        S::drive_inner(&self.0, v)
+    }
+}
+impl<'s, T, S, V> DriveMut<'s, V> for DriveVia<T, S>
+where
+    S: DriveMutStrategy<'s, T, V>,
+    V: Visitor,
+{
+    fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+        S::drive_inner_mut(&mut self.0, v)
+    }
+}
+
+/// Strategy that drives a collection through its elements, like `IterDrive`.
+pub struct ViaIter;
+
+impl<'s, C: 's, T: 's, V> DriveStrategy<'s, C, V> for ViaIter
+where
+    &'s C: IntoIterator<Item = &'s T>,
+    V: Visit<'s, T>,
+{
+    fn drive_inner(x: &'s C, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter(x, v)
+    }
+}
+impl<'s, C: 's, T: 's, V> DriveMutStrategy<'s, C, V> for ViaIter
+where
+    &'s mut C: IntoIterator<Item = &'s mut T>,
+    V: VisitMut<'s, T>,
+{
+    fn drive_inner_mut(x: &'s mut C, v: &mut V) -> ControlFlow<V::Break> {
+        drive_iter_mut(x, v)
+    }
+}
+
+/// Field wrapper whose contents are never visited, for fields of types that visitors should not
+/// see, or that have no `Drive` impl. Unlike `#[drive(skip)]`, this is part of the type, so it
+/// also applies to e.g. the elements of a `Vec<SkipDrive<T>>`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct SkipDrive<T>(pub T);
+
+impl<T> Deref for SkipDrive<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T> DerefMut for SkipDrive<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<'s, T, V: Visitor> Drive<'s, V> for SkipDrive<T> {
+    fn drive_inner(&'s self, _: &mut V) -> ControlFlow<V::Break> {
+        Continue(())
+    }
+}
+impl<'s, T, V: Visitor> DriveMut<'s, V> for SkipDrive<T> {
+    fn drive_inner_mut(&'s mut self, _: &mut V) -> ControlFlow<V::Break> {
+        Continue(())
+    }
+}
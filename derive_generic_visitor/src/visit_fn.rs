@@ -0,0 +1,150 @@
+use std::any::Any;
+use std::marker::PhantomData;
+
+use crate::*;
+
+/// A visitor made of a closure called on each `T`, as returned by `visit_fn`. It can visit any
+/// `DriveAny` value, recursively: the closure is called on the value and on all the `T`s inside
+/// it, in pre-order.
+pub struct VisitFn<T: ?Sized, B, F> {
+    f: F,
+    _t: PhantomData<fn(&T) -> B>,
+}
+
+/// Like `VisitFn`, for mutable visits. See `visit_fn_mut`.
+pub struct VisitFnMut<T: ?Sized, B, F> {
+    f: F,
+    _t: PhantomData<fn(&mut T) -> B>,
+}
+
+/// A visitor that calls `f` on each `T` in the values it visits, for quick one-off traversals:
+/// ```rust
+/// # use derive_generic_visitor::*;
+/// #[derive(Drive, DriveAny, NodeKind)]
+/// enum List {
+///     Nil,
+///     Cons(u32, Box<List>),
+/// }
+/// let list = List::Cons(1, Box::new(List::Cons(2, Box::new(List::Nil))));
+/// let mut sum = 0;
+/// let _ = visit_fn(|n: &u32| sum += n).visit(&list);
+/// assert_eq!(sum, 3);
+/// ```
+/// The values are traversed with `drive_any`, so their types must implement `DriveAny`.
+pub fn visit_fn<T: Any>(
+    mut f: impl FnMut(&T),
+) -> VisitFn<T, Infallible, impl FnMut(&T) -> ControlFlow<Infallible>> {
+    try_visit_fn(move |x| {
+        f(x);
+        Continue(())
+    })
+}
+
+/// Like `visit_fn`, but `f` can stop the traversal by returning `Break`.
+pub fn try_visit_fn<T: Any, B>(
+    f: impl FnMut(&T) -> ControlFlow<B>,
+) -> VisitFn<T, B, impl FnMut(&T) -> ControlFlow<B>> {
+    VisitFn { f, _t: PhantomData }
+}
+
+/// A mutable visitor that calls `f` on each `T` in the values it visits. The contents of a `T`
+/// are visited after `f` has modified it.
+pub fn visit_fn_mut<T: Any>(
+    mut f: impl FnMut(&mut T),
+) -> VisitFnMut<T, Infallible, impl FnMut(&mut T) -> ControlFlow<Infallible>> {
+    try_visit_fn_mut(move |x| {
+        f(x);
+        Continue(())
+    })
+}
+
+/// Like `visit_fn_mut`, but `f` can stop the traversal by returning `Break`.
+pub fn try_visit_fn_mut<T: Any, B>(
+    f: impl FnMut(&mut T) -> ControlFlow<B>,
+) -> VisitFnMut<T, B, impl FnMut(&mut T) -> ControlFlow<B>> {
+    VisitFnMut { f, _t: PhantomData }
+}
+
+impl<T: ?Sized, B, F> Visitor for VisitFn<T, B, F> {
+    type Break = B;
+}
+
+impl<T: ?Sized, B, F> Visitor for VisitFnMut<T, B, F> {
+    type Break = B;
+}
+
+/// Calls the closure on the `T`s of a `drive_any` traversal, and keeps the `Break` value it
+/// returns, if any.
+struct CallFn<'v, T: ?Sized, B, F> {
+    f: &'v mut F,
+    result: ControlFlow<B>,
+    _t: PhantomData<fn(&T)>,
+}
+
+impl<'v, T: ?Sized, B, F> CallFn<'v, T, B, F> {
+    fn new(f: &'v mut F) -> Self {
+        CallFn {
+            f,
+            result: Continue(()),
+            _t: PhantomData,
+        }
+    }
+
+    fn call(&mut self, result: ControlFlow<B>) -> ControlFlow<(), Children> {
+        self.result = result;
+        match self.result {
+            Continue(()) => Continue(Children::Visit),
+            Break(_) => Break(()),
+        }
+    }
+}
+
+impl<T: Any, B, F: FnMut(&T) -> ControlFlow<B>> AnyVisitor<'_> for CallFn<'_, T, B, F> {
+    fn enter(&mut self, x: &dyn DriveAny) -> ControlFlow<(), Children> {
+        match x.downcast_ref::<T>() {
+            Some(x) => {
+                let result = (self.f)(x);
+                self.call(result)
+            }
+            None => Continue(Children::Visit),
+        }
+    }
+}
+
+impl<T: Any, B, F: FnMut(&mut T) -> ControlFlow<B>> AnyVisitorMut for CallFn<'_, T, B, F> {
+    fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+        match x.downcast_mut::<T>() {
+            Some(x) => {
+                let result = (self.f)(x);
+                self.call(result)
+            }
+            None => Continue(Children::Visit),
+        }
+    }
+}
+
+impl<'a, U, T, B, F> Visit<'a, U> for VisitFn<T, B, F>
+where
+    U: DriveAny,
+    T: Any,
+    F: FnMut(&T) -> ControlFlow<B>,
+{
+    fn visit(&mut self, x: &'a U) -> ControlFlow<B> {
+        let mut call = CallFn::new(&mut self.f);
+        let _ = drive_any(x, &mut call);
+        call.result
+    }
+}
+
+impl<'a, U, T, B, F> VisitMut<'a, U> for VisitFnMut<T, B, F>
+where
+    U: DriveAnyMut,
+    T: Any,
+    F: FnMut(&mut T) -> ControlFlow<B>,
+{
+    fn visit(&mut self, x: &'a mut U) -> ControlFlow<B> {
+        let mut call = CallFn::new(&mut self.f);
+        let _ = drive_any_mut(x, &mut call);
+        call.result
+    }
+}
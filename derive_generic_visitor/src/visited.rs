@@ -0,0 +1,88 @@
+use std::any::{Any, TypeId};
+use std::collections::HashSet;
+
+use crate::*;
+
+/// Wraps a type-erased visitor so that it sees each node only once, even if the value shares
+/// nodes, e.g. through `Rc`s pointing to the same allocation. A node that was already visited is
+/// skipped entirely: neither the inner visitor's `enter` nor its `exit` are called on it, and its
+/// contents aren't visited again.
+///
+/// Nodes are identified by their address and type, since a value and its first field can share
+/// an address. Zero-sized nodes have no identity and are always visited.
+pub struct VisitedSet<V> {
+    inner: V,
+    visited: HashSet<(*const (), TypeId)>,
+    /// For each node being visited, whether it was passed on to the inner visitor.
+    stack: Vec<bool>,
+}
+
+impl<V> VisitedSet<V> {
+    pub fn new(inner: V) -> Self {
+        VisitedSet {
+            inner,
+            visited: HashSet::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// The wrapped visitor.
+    pub fn inner(&mut self) -> &mut V {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+
+    /// The number of distinct nodes seen so far.
+    pub fn len(&self) -> usize {
+        self.visited.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visited.is_empty()
+    }
+
+    /// Records `x` as visited. Returns whether it wasn't already.
+    fn insert(&mut self, x: &dyn DriveAny) -> bool {
+        if size_of_val(x) == 0 {
+            return true;
+        }
+        let addr = x as *const dyn DriveAny as *const ();
+        self.visited.insert((addr, (x as &dyn Any).type_id()))
+    }
+}
+
+impl<'a, V: AnyVisitor<'a>> AnyVisitor<'a> for VisitedSet<V> {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        let first_time = self.insert(x);
+        self.stack.push(first_time);
+        if first_time {
+            self.inner.enter(x)
+        } else {
+            Continue(Children::Skip)
+        }
+    }
+
+    fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+        match self.stack.pop() {
+            Some(true) => self.inner.exit(x),
+            _ => Continue(()),
+        }
+    }
+
+    fn enter_binder(&mut self) {
+        self.inner.enter_binder()
+    }
+
+    fn exit_binder(&mut self) {
+        self.inner.exit_binder()
+    }
+}
+
+/// Walk `v` over `x` like `drive_any`, but visiting each node only once even if it is reachable
+/// several times, e.g. in a graph built with `Rc`s. See `VisitedSet`.
+pub fn visit_acyclic<'a>(x: &'a dyn DriveAny, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+    drive_any(x, &mut VisitedSet::new(v))
+}
@@ -0,0 +1,71 @@
+use std::any::Any;
+use std::marker::PhantomData;
+
+use crate::*;
+
+/// A list of per-type closures accumulated by `Walker::on`.
+pub trait Handlers<'a> {
+    /// Call the closures that apply to the type of `x`, in the order they were registered.
+    fn handle(&mut self, x: &'a dyn DriveAny);
+}
+
+impl<'a> Handlers<'a> for () {
+    fn handle(&mut self, _: &'a dyn DriveAny) {}
+}
+
+/// A closure for nodes of type `T`, followed by the previously-registered closures.
+pub struct On<T, F, Prev> {
+    f: F,
+    prev: Prev,
+    _ty: PhantomData<fn(&T)>,
+}
+
+impl<'a, T: Any, F: FnMut(&'a T), Prev: Handlers<'a>> Handlers<'a> for On<T, F, Prev> {
+    fn handle(&mut self, x: &'a dyn DriveAny) {
+        self.prev.handle(x);
+        if let Some(x) = x.downcast_ref::<T>() {
+            (self.f)(x)
+        }
+    }
+}
+
+/// A builder for one-off traversals that need to look at a few different types, without writing
+/// a visitor type: `Walker::new(&x).on(|e: &Expr| ..).on(|s: &Stmt| ..).run()`. The closures are
+/// called in pre-order; when several closures apply to the same node they are called in the order
+/// they were registered.
+pub struct Walker<'a, H> {
+    root: &'a dyn DriveAny,
+    handlers: H,
+}
+
+impl<'a> Walker<'a, ()> {
+    pub fn new<R: DriveAny>(root: &'a R) -> Self {
+        Walker { root, handlers: () }
+    }
+}
+
+impl<'a, H: Handlers<'a>> Walker<'a, H> {
+    /// Call `f` on each node of type `T`.
+    pub fn on<T: Any, F: FnMut(&'a T)>(self, f: F) -> Walker<'a, On<T, F, H>> {
+        Walker {
+            root: self.root,
+            handlers: On {
+                f,
+                prev: self.handlers,
+                _ty: PhantomData,
+            },
+        }
+    }
+
+    /// Walk the whole value, calling the matching closures on each node.
+    pub fn run(self) {
+        struct Run<H>(H);
+        impl<'a, H: Handlers<'a>> AnyVisitor<'a> for Run<H> {
+            fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+                self.0.handle(x);
+                Continue(Children::Visit)
+            }
+        }
+        let _ = drive_any(self.root, &mut Run(self.handlers));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::*;
+
+/// A visitor that gets an extra context argument, like an arena, an interner or some options,
+/// alongside each value it visits. Compared to storing a reference to the context in the visitor,
+/// this avoids a lifetime parameter on the visitor, and lets a `visit` method pass a different
+/// context to the contents of a value, e.g. an extended environment.
+///
+/// To recurse, use `drive_inner_with(x, ctx, self)`, which calls `visit(ctx, _)` on the contents
+/// of `x`.
+pub trait VisitWith<'a, Ctx: ?Sized, T: ?Sized>: Visitor {
+    fn visit(&mut self, ctx: &Ctx, x: &'a T) -> ControlFlow<Self::Break>;
+}
+
+/// Like `VisitWith`, for mutable visitors. To recurse, use `drive_inner_mut_with(x, ctx, self)`.
+pub trait VisitMutWith<'a, Ctx: ?Sized, T: ?Sized>: Visitor {
+    fn visit(&mut self, ctx: &Ctx, x: &'a mut T) -> ControlFlow<Self::Break>;
+}
+
+/// Adapts a `VisitWith` visitor to `Visit` by passing it the same context for every value.
+pub struct WithCtx<'w, Ctx: ?Sized, V: ?Sized> {
+    pub ctx: &'w Ctx,
+    pub v: &'w mut V,
+}
+
+impl<'w, Ctx: ?Sized, V: ?Sized> WithCtx<'w, Ctx, V> {
+    pub fn new(ctx: &'w Ctx, v: &'w mut V) -> Self {
+        WithCtx { ctx, v }
+    }
+}
+
+impl<Ctx: ?Sized, V: Visitor + ?Sized> Visitor for WithCtx<'_, Ctx, V> {
+    type Break = V::Break;
+}
+
+impl<'a, Ctx: ?Sized, T: ?Sized, V: VisitWith<'a, Ctx, T> + ?Sized> Visit<'a, T>
+    for WithCtx<'_, Ctx, V>
+{
+    fn visit(&mut self, x: &'a T) -> ControlFlow<Self::Break> {
+        self.v.visit(self.ctx, x)
+    }
+}
+
+impl<'a, Ctx: ?Sized, T: ?Sized, V: VisitMutWith<'a, Ctx, T> + ?Sized> VisitMut<'a, T>
+    for WithCtx<'_, Ctx, V>
+{
+    fn visit(&mut self, x: &'a mut T) -> ControlFlow<Self::Break> {
+        self.v.visit(self.ctx, x)
+    }
+}
+
+/// Call `v.visit(ctx, _)` on the contents of `x`.
+pub fn drive_inner_with<'a, Ctx, T, V>(x: &'a T, ctx: &Ctx, v: &mut V) -> ControlFlow<V::Break>
+where
+    Ctx: ?Sized,
+    T: ?Sized + for<'w> Drive<'a, WithCtx<'w, Ctx, V>>,
+    V: Visitor,
+{
+    x.drive_inner(&mut WithCtx::new(ctx, v))
+}
+
+/// Call `v.visit(ctx, _)` on the contents of `x`, mutably.
+pub fn drive_inner_mut_with<'a, Ctx, T, V>(
+    x: &'a mut T,
+    ctx: &Ctx,
+    v: &mut V,
+) -> ControlFlow<V::Break>
+where
+    Ctx: ?Sized,
+    T: ?Sized + for<'w> DriveMut<'a, WithCtx<'w, Ctx, V>>,
+    V: Visitor,
+{
+    x.drive_inner_mut(&mut WithCtx::new(ctx, v))
+}
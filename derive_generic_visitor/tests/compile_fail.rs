@@ -0,0 +1,8 @@
+//! Checks the diagnostics of the macros on invalid input. Regenerate the expected output with
+//! `TRYBUILD=overwrite cargo test --test compile_fail`.
+
+#[test]
+fn compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,1352 @@
+use derive_generic_visitor::*;
+
+#[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+enum List<T> {
+    Nil,
+    Cons(Node<T>),
+}
+
+#[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+struct Node<T> {
+    val: T,
+    next: Box<List<T>>,
+}
+
+impl<T> List<T> {
+    fn from_slice(slice: &[T]) -> Self
+    where
+        T: Clone,
+    {
+        let mut ret = List::Nil;
+        for x in slice.iter().rev() {
+            ret = List::Cons(Node {
+                val: x.clone(),
+                next: Box::new(ret),
+            });
+        }
+        ret
+    }
+}
+
+/// Records the name of each visited type, in pre-order.
+#[derive(Default)]
+struct RecordTypes(Vec<&'static str>);
+impl<'a> AnyVisitor<'a> for RecordTypes {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        self.0.push(x.type_name());
+        Continue(Children::Visit)
+    }
+}
+
+#[test]
+fn test_drive_any() {
+    let list = List::from_slice(&[1u32, 2]);
+    let mut v = RecordTypes::default();
+    let _ = drive_any(&list, &mut v);
+    use std::any::type_name;
+    let list_ty = type_name::<List<u32>>();
+    let node_ty = type_name::<Node<u32>>();
+    let u32_ty = type_name::<u32>();
+    let box_ty = type_name::<Box<List<u32>>>();
+    assert_eq!(
+        v.0,
+        vec![list_ty, node_ty, u32_ty, box_ty, list_ty, node_ty, u32_ty, box_ty, list_ty]
+    );
+}
+
+#[test]
+fn test_drive_any_skip_and_break() {
+    /// Collects the values of the list, without looking inside of nodes with value 0, and stopping
+    /// at 100.
+    struct Collect<'a>(Vec<&'a u32>);
+    impl<'a> AnyVisitor<'a> for Collect<'a> {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            if let Some(node) = x.downcast_ref::<Node<u32>>() {
+                if node.val == 0 {
+                    return Continue(Children::Skip);
+                }
+            }
+            if let Some(n) = x.downcast_ref::<u32>() {
+                if *n == 100 {
+                    return Break(());
+                }
+                self.0.push(n);
+            }
+            Continue(Children::Visit)
+        }
+    }
+
+    let list = List::from_slice(&[1u32, 2, 0, 3]);
+    let mut v = Collect(vec![]);
+    assert!(drive_any(&list, &mut v).is_continue());
+    assert_eq!(v.0, vec![&1, &2]);
+
+    let list = List::from_slice(&[1u32, 100, 2]);
+    let mut v = Collect(vec![]);
+    assert!(drive_any(&list, &mut v).is_break());
+    assert_eq!(v.0, vec![&1]);
+}
+
+#[test]
+fn test_drive_any_mut() {
+    struct Double;
+    impl AnyVisitorMut for Double {
+        fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+            if let Some(n) = x.downcast_mut::<u32>() {
+                *n *= 2;
+            }
+            Continue(Children::Visit)
+        }
+    }
+
+    let mut list = List::from_slice(&[1u32, 2, 3]);
+    let _ = drive_any_mut(&mut list, &mut Double);
+    let mut v = RecordValues::default();
+    let _ = drive_any(&list, &mut v);
+    assert_eq!(v.0, vec![2, 4, 6]);
+}
+
+/// Records the `u32`s in the tree.
+#[derive(Default)]
+struct RecordValues(Vec<u32>);
+impl<'a> AnyVisitor<'a> for RecordValues {
+    fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+        if let Some(n) = x.downcast_ref::<u32>() {
+            self.0.push(*n);
+        }
+        Continue(Children::Visit)
+    }
+}
+
+#[test]
+fn test_pausable() {
+    let list = List::from_slice(&(0..10u32).collect::<Vec<_>>());
+
+    // Visiting in one go.
+    let mut expected = RecordTypes::default();
+    let _ = drive_any(&list, &mut expected);
+
+    // Visiting in slices of 3 nodes gives the same sequence of nodes.
+    let mut traversal = PausableTraversal::new(&list);
+    let mut v = RecordTypes::default();
+    let mut slices = 0;
+    loop {
+        slices += 1;
+        match traversal.resume(3, &mut v) {
+            Continue(Progress::Paused) => assert_eq!(v.0.len(), 3 * slices),
+            Continue(Progress::Finished) => break,
+            Break(()) => unreachable!(),
+        }
+    }
+    assert!(traversal.is_finished());
+    assert_eq!(v.0, expected.0);
+    assert_eq!(slices, expected.0.len().div_ceil(3));
+}
+
+#[test]
+fn test_pausable_resumes_where_it_stopped() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static WALKS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts how many times its contents are walked.
+    struct Counted(u32);
+    impl NodeKind for Counted {
+        fn kind(&self) -> &'static str {
+            "Counted"
+        }
+    }
+    impl DriveAny for Counted {
+        fn drive_any_inner<'a>(&'a self, v: &mut dyn AnyVisitor<'a>) -> ControlFlow<()> {
+            WALKS.fetch_add(1, Ordering::Relaxed);
+            drive_any(&self.0, v)
+        }
+    }
+
+    let xs: Vec<Counted> = (0..10).map(Counted).collect();
+    let mut traversal = PausableTraversal::new(&xs);
+    let mut v = RecordTypes::default();
+    while let Continue(Progress::Paused) = traversal.resume(1, &mut v) {}
+    assert_eq!(v.0.len(), 21);
+    // Each node is walked once, however many slices the traversal took.
+    assert_eq!(WALKS.load(Ordering::Relaxed), 10);
+}
+
+#[test]
+fn test_pausable_skip() {
+    /// Skips the contents of every other `Node`.
+    #[derive(Default)]
+    struct SkipSome {
+        nodes: usize,
+        vals: Vec<u32>,
+    }
+    impl<'a> AnyVisitor<'a> for SkipSome {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            if x.is::<Node<u32>>() {
+                self.nodes += 1;
+                if self.nodes.is_multiple_of(2) {
+                    return Continue(Children::Skip);
+                }
+            }
+            if let Some(n) = x.downcast_ref::<u32>() {
+                self.vals.push(*n);
+            }
+            Continue(Children::Visit)
+        }
+    }
+
+    let list = List::from_slice(&[0, 1, 2, 3, 4u32]);
+    let mut traversal = PausableTraversal::new(&list);
+    let mut v = SkipSome::default();
+    while let Continue(Progress::Paused) = traversal.resume(1, &mut v) {}
+    assert_eq!(v.vals, vec![0]);
+    assert_eq!(v.nodes, 2);
+}
+
+#[test]
+fn test_pausable_binders() {
+    #[derive(Drive, DriveAny, NodeKind)]
+    enum Term {
+        Var(u32),
+        Lam(#[drive(binder)] Box<Term>),
+    }
+    let t = Term::Lam(Box::new(Term::Lam(Box::new(Term::Var(0)))));
+
+    /// Records the binder depth at each node, and stops at variable `stop_at`.
+    struct Depths {
+        depth: usize,
+        seen: Vec<usize>,
+        stop_at: Option<u32>,
+    }
+    impl<'a> AnyVisitor<'a> for Depths {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            self.seen.push(self.depth);
+            if self.stop_at.is_some() && x.downcast_ref::<u32>() == self.stop_at.as_ref() {
+                return Break(());
+            }
+            Continue(Children::Visit)
+        }
+        fn enter_binder(&mut self) {
+            self.depth += 1;
+        }
+        fn exit_binder(&mut self) {
+            self.depth -= 1;
+        }
+    }
+
+    let new = |stop_at| Depths {
+        depth: 0,
+        seen: vec![],
+        stop_at,
+    };
+    let mut expected = new(None);
+    let _ = drive_any(&t, &mut expected);
+    let mut traversal = PausableTraversal::new(&t);
+    let mut v = new(None);
+    let mut slices = 0;
+    while let Continue(Progress::Paused) = traversal.resume(1, &mut v) {
+        slices += 1;
+        // Binders don't count towards the budget.
+        assert_eq!(v.seen.len(), slices);
+    }
+    assert_eq!(v.seen, expected.seen);
+    assert_eq!(v.seen, [0, 1, 1, 2, 2, 2]);
+    assert_eq!(v.depth, 0);
+
+    // The binders are left when the visitor breaks.
+    let mut traversal = PausableTraversal::new(&t);
+    let mut v = new(Some(0));
+    while let Continue(Progress::Paused) = traversal.resume(1, &mut v) {}
+    assert_eq!(v.depth, 0);
+}
+
+#[test]
+fn test_visitor_chain() {
+    /// Stops the traversal at the given value.
+    struct StopAt(u32);
+    impl<'a> AnyVisitor<'a> for StopAt {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            if x.downcast_ref::<u32>() == Some(&self.0) {
+                return Break(());
+            }
+            Continue(Children::Visit)
+        }
+    }
+
+    let list = List::from_slice(&[1u32, 2, 3, 4]);
+    let mut types = RecordTypes::default();
+    let mut stop = StopAt(3);
+    let mut values = RecordValues::default();
+    let mut chain = VisitorChain::new()
+        .with(&mut types)
+        .with(&mut stop)
+        .with(&mut values);
+    assert!(drive_any(&list, &mut chain).is_break());
+    // The stages before the one that stopped saw the last node, the ones after didn't.
+    assert_eq!(types.0.last(), Some(&std::any::type_name::<u32>()));
+    assert_eq!(types.0.iter().filter(|ty| ty.ends_with("u32")).count(), 3);
+    assert_eq!(values.0, vec![1, 2]);
+}
+
+#[test]
+fn test_split_visitor() {
+    use std::any::TypeId;
+
+    /// Counts the nodes it sees.
+    #[derive(Default)]
+    struct Count(usize);
+    impl<'a> AnyVisitor<'a> for Count {
+        fn enter(&mut self, _: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            self.0 += 1;
+            Continue(Children::Visit)
+        }
+    }
+
+    let list = List::from_slice(&[1u32, 2, 3]);
+    let mut nodes = Count::default();
+    let mut values = RecordValues::default();
+    let mut split = SplitVisitor::new()
+        .with(&[TypeId::of::<Node<u32>>()], &mut nodes)
+        .with(
+            &[TypeId::of::<u32>(), TypeId::of::<Node<u32>>()],
+            &mut values,
+        );
+    let _ = drive_any(&list, &mut split);
+    assert_eq!(nodes.0, 3);
+    assert_eq!(values.0, vec![1, 2, 3]);
+}
+
+#[test]
+fn test_wrapped_any_visitor_binder() {
+    #[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+    enum Term {
+        Var(u32),
+        Lam(#[drive(binder)] Box<Term>),
+    }
+    let mut t = Term::Lam(Box::new(Term::Lam(Box::new(Term::Var(0)))));
+
+    /// Records the binder depth at which each variable is found.
+    #[derive(Default)]
+    struct Depths {
+        depth: usize,
+        seen: Vec<usize>,
+    }
+    impl<'a> AnyVisitor<'a> for Depths {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            if x.downcast_ref::<u32>().is_some() {
+                self.seen.push(self.depth);
+            }
+            Continue(Children::Visit)
+        }
+        fn enter_binder(&mut self) {
+            self.depth += 1;
+        }
+        fn exit_binder(&mut self) {
+            self.depth -= 1;
+        }
+    }
+    impl AnyVisitorMut for Depths {
+        fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+            if x.downcast_mut::<u32>().is_some() {
+                self.seen.push(self.depth);
+            }
+            Continue(Children::Visit)
+        }
+        fn enter_binder(&mut self) {
+            self.depth += 1;
+        }
+        fn exit_binder(&mut self) {
+            self.depth -= 1;
+        }
+    }
+
+    let mut v = Depths::default();
+    let _ = drive_any(&t, &mut VisitorChain::new().with(&mut v));
+    assert_eq!((v.depth, v.seen), (0, vec![2]));
+
+    let mut v = Depths::default();
+    let _ = drive_any_mut(&mut t, &mut VisitorChainMut::new().with(&mut v));
+    assert_eq!((v.depth, v.seen), (0, vec![2]));
+
+    let mut v = Depths::default();
+    let types = [std::any::TypeId::of::<u32>()];
+    let _ = drive_any(&t, &mut SplitVisitor::new().with(&types, &mut v));
+    assert_eq!((v.depth, v.seen), (0, vec![2]));
+
+    let mut v = Depths::default();
+    let _ = drive_any_mut(&mut t, &mut SplitVisitorMut::new().with(&types, &mut v));
+    assert_eq!((v.depth, v.seen), (0, vec![2]));
+
+    #[cfg(feature = "profiling")]
+    {
+        let mut v = ProfilingVisitor::new(Depths::default());
+        let _ = drive_any(&t, &mut v);
+        assert_eq!(v.into_inner().seen, [2]);
+
+        let mut v = ProfilingVisitor::new(Depths::default());
+        let _ = drive_any_mut(&mut t, &mut v);
+        assert_eq!(v.into_inner().seen, [2]);
+    }
+
+    let mut v = VisitedSet::new(Depths::default());
+    let _ = drive_any(&t, &mut v);
+    assert_eq!(v.into_inner().seen, [2]);
+}
+
+#[test]
+fn test_node_kind() {
+    let list = List::from_slice(&[1u32]);
+    assert_eq!(list.kind(), "List::Cons");
+    assert_eq!(list.kind_index(), 1);
+    assert_eq!(List::<u32>::Nil.kind_index(), 0);
+    assert_eq!(vec![1u32].kind(), "Vec");
+
+    /// Records the kind of each visited node.
+    #[derive(Default)]
+    struct RecordKinds(Vec<&'static str>);
+    impl<'a> AnyVisitor<'a> for RecordKinds {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            self.0.push(x.kind());
+            Continue(Children::Visit)
+        }
+    }
+    let mut v = RecordKinds::default();
+    let _ = drive_any(&list, &mut v);
+    assert_eq!(v.0, ["List::Cons", "Node", "u32", "Box", "List::Nil"]);
+}
+
+#[cfg(feature = "profiling")]
+#[test]
+fn test_profiling() {
+    use std::any::type_name;
+
+    let list = List::from_slice(&[1u32, 2, 3]);
+    let mut v = ProfilingVisitor::new(RecordValues::default());
+    let _ = drive_any(&list, &mut v);
+    let stats = v.stats();
+    assert_eq!(stats[type_name::<List<u32>>()].count, 4);
+    assert_eq!(stats[type_name::<Node<u32>>()].count, 3);
+    assert_eq!(stats[type_name::<u32>()].count, 3);
+    let list_stats = stats[type_name::<List<u32>>()];
+    assert!(list_stats.self_time <= list_stats.total);
+    assert_eq!(v.report().len(), 4);
+    assert_eq!(v.into_inner().0, vec![1, 2, 3]);
+}
+
+#[test]
+fn test_walker() {
+    let list = List::from_slice(&[1u32, 2, 3]);
+    let mut events = vec![];
+    let mut nodes = 0;
+    Walker::new(&list)
+        .on(|n: &u32| events.push(*n))
+        .on(|_: &Node<u32>| nodes += 1)
+        .run();
+    assert_eq!(events, vec![1, 2, 3]);
+    assert_eq!(nodes, 3);
+}
+
+#[test]
+fn test_events() {
+    let list = List::from_slice(&[1u32, 2]);
+    let kinds: Vec<String> = events(&list)
+        .map(|e| match e {
+            Event::Enter(x) => format!("+{}", x.kind()),
+            Event::Exit(x) => format!("-{}", x.kind()),
+            Event::EnterBinder => "+binder".to_owned(),
+            Event::ExitBinder => "-binder".to_owned(),
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "+List::Cons",
+            "+Node",
+            "+u32",
+            "-u32",
+            "+Box",
+            "+List::Cons",
+            "+Node",
+            "+u32",
+            "-u32",
+            "+Box",
+            "+List::Nil",
+            "-List::Nil",
+            "-Box",
+            "-Node",
+            "-List::Cons",
+            "-Box",
+            "-Node",
+            "-List::Cons",
+        ]
+    );
+
+    let values: Vec<Event<u32>> = events(&list)
+        .of_type::<u32>()
+        .map(|e| e.try_map(|x| Some(*x)).unwrap())
+        .collect();
+    assert_eq!(
+        values,
+        [
+            Event::Enter(1),
+            Event::Exit(1),
+            Event::Enter(2),
+            Event::Exit(2)
+        ]
+    );
+
+    // Pretty-print the list, without looking inside the nodes with value 2.
+    let mut out = String::new();
+    let mut events = events(&list);
+    while let Some(event) = events.next() {
+        if let Some(event) = event.downcast::<Node<u32>>() {
+            match event {
+                Event::Enter(node) if node.val == 2 => {
+                    out += "[..]";
+                    events.skip_children();
+                }
+                Event::Enter(node) => out += &format!("[{}", node.val),
+                Event::Exit(node) if node.val == 2 => {}
+                Event::Exit(_) => out += "]",
+                Event::EnterBinder | Event::ExitBinder => {}
+            }
+        }
+    }
+    assert_eq!(out, "[1[..]]");
+
+    // Pull nodes one at a time.
+    let list = List::from_slice(&[1u32, 2, 3]);
+    let mut nodes = Events::new(&list);
+    assert_eq!(nodes.next_node::<u32>(), Some(&1));
+    assert_eq!(nodes.next_node::<Node<u32>>().map(|node| node.val), Some(2));
+    assert_eq!(nodes.next_node::<u32>(), Some(&2));
+    // The rest of the list is inside the node with value 2.
+    let mut nodes = Events::new(&list);
+    nodes.next_node::<Node<u32>>();
+    assert_eq!(nodes.next_node::<u32>(), Some(&1));
+    assert!(nodes.next_node::<Node<u32>>().is_some());
+    nodes.skip_children();
+    assert_eq!(nodes.next_node::<u32>(), None);
+}
+
+#[test]
+fn test_drive_any_iterative() {
+    let list = List::from_slice(&[1u32, 2, 3]);
+    let mut expected = RecordTypes::default();
+    let _ = drive_any(&list, &mut expected);
+    let mut v = RecordTypes::default();
+    let _ = drive_any_iterative(&list, &mut v);
+    assert_eq!(v.0, expected.0);
+
+    // Deep enough to overflow the stack with `drive_any`.
+    let depth = 1_000_000;
+    let list = List::from_slice(&vec![1u32; depth]);
+    let mut v = RecordValues::default();
+    let _ = drive_any_iterative(&list, &mut v);
+    assert_eq!(v.0.len(), depth);
+    // Dropping recursively would overflow too.
+    let mut list = list;
+    while let List::Cons(node) = list {
+        list = *node.next;
+    }
+}
+
+#[test]
+fn test_drive_any_iterative_binders() {
+    #[derive(Drive, DriveAny, NodeKind)]
+    enum Term {
+        Var(u32),
+        Lam(#[drive(binder)] Box<Term>),
+        App(Box<Term>, Box<Term>),
+    }
+    let lam = |t| Term::Lam(Box::new(t));
+    let app = |a, b| Term::App(Box::new(a), Box::new(b));
+    let t = app(lam(lam(Term::Var(0))), lam(app(Term::Var(1), Term::Var(2))));
+
+    /// Records the binder depth at which each variable is found, and stops at variable `stop_at`.
+    struct Depths {
+        depth: usize,
+        seen: Vec<(u32, usize)>,
+        stop_at: Option<u32>,
+    }
+    impl<'a> AnyVisitor<'a> for Depths {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            if let Some(&n) = x.downcast_ref::<u32>() {
+                self.seen.push((n, self.depth));
+                if self.stop_at == Some(n) {
+                    return Break(());
+                }
+            }
+            Continue(Children::Visit)
+        }
+        fn enter_binder(&mut self) {
+            self.depth += 1;
+        }
+        fn exit_binder(&mut self) {
+            self.depth -= 1;
+        }
+    }
+
+    for stop_at in [None, Some(0), Some(1)] {
+        let new = || Depths {
+            depth: 0,
+            seen: vec![],
+            stop_at,
+        };
+        let mut expected = new();
+        let expected_result = drive_any(&t, &mut expected);
+        let mut v = new();
+        assert_eq!(drive_any_iterative(&t, &mut v), expected_result);
+        assert_eq!(v.seen, expected.seen);
+        if stop_at.is_none() {
+            assert_eq!(v.seen, [(0, 2), (1, 1), (2, 1)]);
+        }
+        // The binders are left even when the visitor breaks.
+        assert_eq!((v.depth, expected.depth), (0, 0));
+    }
+
+    let binders = events(&t)
+        .filter(|e| matches!(e, Event::EnterBinder | Event::ExitBinder))
+        .count();
+    assert_eq!(binders, 6);
+}
+
+#[test]
+fn test_visit_acyclic() {
+    use std::rc::Rc;
+
+    #[derive(Drive, DriveAny, NodeKind)]
+    enum Dag {
+        Leaf(u32),
+        Pair(Rc<Dag>, Rc<Dag>),
+    }
+    let leaf = Rc::new(Dag::Leaf(1));
+    let mid = Rc::new(Dag::Pair(leaf.clone(), leaf.clone()));
+    let root = Dag::Pair(mid.clone(), Rc::new(Dag::Pair(mid, Rc::new(Dag::Leaf(2)))));
+
+    let mut v = RecordValues::default();
+    let _ = drive_any(&root, &mut v);
+    assert_eq!(v.0, vec![1, 1, 1, 1, 2]);
+
+    let mut v = RecordValues::default();
+    let _ = visit_acyclic(&root, &mut v);
+    assert_eq!(v.0, vec![1, 2]);
+
+    // Each `Rc` is a node of its own, but the shared allocations are only counted once.
+    let mut v = VisitedSet::new(RecordTypes::default());
+    let _ = drive_any(&root, &mut v);
+    let kinds = v.into_inner().0.len();
+    let mut all = RecordTypes::default();
+    let _ = drive_any(&root, &mut all);
+    assert_eq!(all.0.len(), 22);
+    assert_eq!(kinds, 13);
+}
+
+#[test]
+fn test_dispatch_visitor() {
+    let list = List::from_slice(&[1u32, 2, 3]);
+
+    /// Records the values and depth of the nodes.
+    #[derive(Default)]
+    struct State {
+        depth: usize,
+        values: Vec<(usize, u32)>,
+    }
+    let mut v = DispatchVisitor::new(State::default())
+        .on_enter(|s, x: &u32| {
+            s.values.push((s.depth, *x));
+            Continue(Children::Visit)
+        })
+        .on_enter(|s, _: &Node<u32>| {
+            s.depth += 1;
+            Continue(Children::Visit)
+        })
+        .on_exit(|s, _: &Node<u32>| {
+            s.depth -= 1;
+            Continue(())
+        });
+    let _ = drive_any(&list, &mut v);
+    assert_eq!(v.into_state().values, vec![(1, 1), (2, 2), (3, 3)]);
+
+    /// A statically-typed visitor for `Node`s, which stops at 2.
+    #[derive(Default)]
+    struct Sum(u32);
+    impl Visitor for Sum {
+        type Break = ();
+    }
+    impl<'a> Visit<'a, Node<u32>> for Sum {
+        fn visit(&mut self, x: &'a Node<u32>) -> ControlFlow<()> {
+            self.0 += x.val;
+            if x.val == 2 {
+                return Break(());
+            }
+            (*x.next).drive_inner(self)
+        }
+    }
+    let mut v = DispatchVisitor::new(Sum::default()).on_visit::<Node<u32>>();
+    assert_eq!(drive_any(&list, &mut v), Break(()));
+    assert_eq!(v.into_state().0, 3);
+}
+
+#[test]
+fn test_cursor() {
+    #[derive(Debug, PartialEq, Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+    enum Expr {
+        Lit(u32),
+        Neg(Box<Expr>),
+        Add(Box<Expr>, Box<Expr>),
+    }
+    use Expr::*;
+
+    // -(1 + 2)
+    let mut expr = Neg(Box::new(Add(Box::new(Lit(1)), Box::new(Lit(2)))));
+    let mut cursor = Cursor::new(&mut expr);
+    assert_eq!(cursor.kind(), "Expr::Neg");
+    assert_eq!(cursor.num_children(), 1);
+    assert!(!cursor.up());
+    assert!(!cursor.next_sibling());
+
+    // Go to the `Add`, through the `Box`.
+    assert!(cursor.down(0));
+    assert_eq!(cursor.kind(), "Box");
+    assert!(cursor.down(0));
+    assert_eq!(cursor.kind(), "Expr::Add");
+    assert!(!cursor.down(2));
+    assert!(cursor.down(0) && cursor.down(0));
+    assert_eq!(cursor.kind(), "Expr::Lit");
+    assert_eq!(cursor.path(), [0, 0, 0, 0]);
+
+    // Move to the other operand and change it.
+    assert!(cursor.up() && cursor.next_sibling() && cursor.down(0));
+    assert_eq!(cursor.path(), [0, 0, 1, 0]);
+    cursor.with_focus_as(|x: &mut Expr| *x = Neg(Box::new(Lit(3))));
+    assert!(cursor.down(0) && cursor.down(0) && cursor.down(0));
+    assert_eq!(cursor.with_focus_as(|x: &mut u32| *x), Some(3));
+    assert_eq!(cursor.with_focus_as(|_: &mut Expr| ()), None);
+    assert!(!cursor.next_sibling());
+
+    cursor.to_root();
+    assert!(cursor.down(0) && cursor.down(0) && cursor.down(1));
+    assert!(cursor.prev_sibling());
+    assert!(!cursor.prev_sibling());
+    cursor.with_focus_as(|x: &mut Box<Expr>| **x = Lit(0));
+
+    assert_eq!(
+        expr,
+        Neg(Box::new(Add(
+            Box::new(Lit(0)),
+            Box::new(Neg(Box::new(Lit(3))))
+        )))
+    );
+}
+
+#[test]
+fn test_path_tracker() {
+    #![allow(dead_code)]
+    #[derive(Drive, DriveAny, NodeKind)]
+    struct Module {
+        #[drive(skip)]
+        name: String,
+        items: Vec<Item>,
+    }
+    #[derive(Drive, DriveAny, NodeKind)]
+    #[drive(discriminant)]
+    enum Item {
+        Fn { params: Vec<u32>, ret: u32 },
+        Const(#[drive(skip)] String, u32),
+    }
+
+    /// Reports the `u32`s over 10, with where they were found.
+    #[derive(Default)]
+    struct Validate<'a> {
+        path: PathTracker<'a>,
+        errors: Vec<String>,
+    }
+    impl<'a> AnyVisitor<'a> for Validate<'a> {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            self.path.enter(x);
+            if let Some(n) = x.downcast_ref::<u32>() {
+                if *n > 10 {
+                    self.errors.push(format!("{}: {n}", self.path.path()));
+                }
+            }
+            Continue(Children::Visit)
+        }
+        fn exit(&mut self, _: &'a dyn DriveAny) -> ControlFlow<()> {
+            self.path.exit();
+            Continue(())
+        }
+    }
+
+    let module = Module {
+        name: "m".into(),
+        items: vec![
+            Item::Const("a".into(), 1),
+            Item::Fn {
+                params: vec![1, 20, 3],
+                ret: 40,
+            },
+            Item::Const("b".into(), 50),
+        ],
+    };
+    let mut v = Validate::default();
+    let _ = drive_any(&module, &mut v);
+    assert_eq!(
+        v.errors,
+        [
+            ".items[1].params[1]: 20",
+            ".items[1].ret: 40",
+            ".items[2].1: 50"
+        ]
+    );
+    assert!(v.path.steps().is_empty());
+}
+
+#[test]
+fn test_children_of() {
+    /// A tree of nested scopes.
+    #[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+    struct Scope {
+        depth: u32,
+        items: Vec<Item>,
+    }
+    #[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+    enum Item {
+        Value(u32),
+        Nested(Scope),
+    }
+
+    let scope = |depth, items| Item::Nested(Scope { depth, items });
+    let mut root = Scope {
+        depth: 0,
+        items: vec![
+            scope(1, vec![scope(2, vec![])]),
+            Item::Value(42),
+            scope(1, vec![]),
+        ],
+    };
+    let children: Vec<u32> = children_of::<Scope>(&root)
+        .iter()
+        .map(|s| s.depth)
+        .collect();
+    assert_eq!(children, vec![1, 1]);
+
+    for_each_child_of_mut(&mut root, |s: &mut Scope| s.depth += 10);
+    let depths: Vec<u32> = children_of::<Scope>(&root)
+        .iter()
+        .flat_map(|s| {
+            let nested = children_of::<Scope>(*s).into_iter().map(|s| s.depth);
+            std::iter::once(s.depth).chain(nested)
+        })
+        .collect();
+    assert_eq!(depths, vec![11, 2, 11]);
+}
+
+#[test]
+fn test_find_first() {
+    let mut list = List::from_slice(&[1u32, 2, 3, 4]);
+    assert_eq!(find_first(&list, |x: &u32| *x >= 2), Some(&2));
+    assert_eq!(find_first(&list, |x: &u32| *x > 10), None);
+    let node = find_first(&list, |x: &Node<u32>| x.val > 2).unwrap();
+    assert_eq!(collect_all::<u32>(node), [&3, &4]);
+
+    // Stops at the first match.
+    let mut seen = vec![];
+    let found = find_first_mut(
+        &mut list,
+        |x: &u32| {
+            seen.push(*x);
+            *x > 1
+        },
+        |x| {
+            *x = 0;
+            "found"
+        },
+    );
+    assert_eq!(found, Some("found"));
+    assert_eq!(seen, [1, 2]);
+    assert_eq!(collect_all::<u32>(&list), [&1, &0, &3, &4]);
+}
+
+#[test]
+fn test_count_any_all() {
+    let list = List::from_slice(&[1u32, 2, 3]);
+    assert_eq!(count::<u32>(&list), 3);
+    assert_eq!(count::<List<u32>>(&list), 4);
+    assert_eq!(count::<String>(&list), 0);
+    assert!(any(&list, |x: &u32| *x == 2));
+    assert!(!any(&list, |x: &u32| *x == 5));
+    assert!(all(&list, |x: &u32| *x > 0));
+    assert!(!all(&list, |x: &u32| *x > 1));
+    // Vacuously true.
+    assert!(all(&list, |_: &String| false));
+}
+
+#[test]
+fn test_collect_all() {
+    let mut list = List::from_slice(&[1u32, 2, 3]);
+    let values: Vec<u32> = collect_all::<u32>(&list).into_iter().copied().collect();
+    assert_eq!(values, vec![1, 2, 3]);
+    // Includes the root and nested values.
+    assert_eq!(collect_all::<List<u32>>(&list).len(), 4);
+
+    for_each_mut(&mut list, |x: &mut u32| *x *= 10);
+    // Each node is visited after its parent has been modified.
+    for_each_mut(&mut list, |x: &mut List<u32>| {
+        if let List::Cons(node) = x {
+            if node.val == 20 {
+                *node.next = List::Nil;
+            }
+        }
+    });
+    let values: Vec<u32> = collect_all::<u32>(&list).into_iter().copied().collect();
+    assert_eq!(values, vec![10, 20]);
+}
+
+#[test]
+fn test_heap_size() {
+    use std::mem::size_of;
+
+    let list = List::from_slice(&[1u32, 2]);
+    // Two boxed lists.
+    assert_eq!(heap_size(&list), 2 * size_of::<List<u32>>());
+
+    let mut names = Vec::with_capacity(4);
+    names.push(String::with_capacity(10));
+    names.push("abc".to_owned());
+    let expected = 4 * size_of::<String>() + 10 + names[1].capacity();
+    assert_eq!(heap_size(&names), expected);
+
+    // Hooks replace the size reported by the type.
+    let mut v = HeapSizeVisitor::new().with_hook(|s: &String| s.len());
+    let _ = drive_any(&names, &mut v);
+    assert_eq!(v.total(), 4 * size_of::<String>() + 3);
+}
+
+#[test]
+fn test_subst_bound_vars() {
+    /// A lambda term with de Bruijn indices.
+    #[derive(Debug, Clone, PartialEq, DriveMut, DriveAnyMut, NodeKind)]
+    enum Term {
+        Var(#[drive(skip)] usize),
+        Lam(#[drive(binder)] Box<Term>),
+        App(Box<Term>, Box<Term>),
+    }
+    use Term::*;
+    impl BoundVar for Term {
+        fn bound_var_mut(&mut self) -> Option<&mut usize> {
+            match self {
+                Var(i) => Some(i),
+                _ => None,
+            }
+        }
+    }
+    let app = |a, b| App(Box::new(a), Box::new(b));
+    let lam = |x| Lam(Box::new(x));
+
+    let mut x = lam(app(Var(0), Var(1)));
+    shift_bound_vars::<Term>(&mut x, 2);
+    assert_eq!(x, lam(app(Var(0), Var(3))));
+    shift_bound_vars::<Term>(&mut x, -2);
+    assert_eq!(x, lam(app(Var(0), Var(1))));
+
+    // With `z` free: `(\x. \y. x y z) (z z)` reduces to `\y. (z z) y z`.
+    let Lam(mut body) = lam(lam(app(app(Var(1), Var(0)), Var(2)))) else {
+        unreachable!()
+    };
+    instantiate_bound_var(&mut *body, &app(Var(0), Var(0)));
+    assert_eq!(*body, lam(app(app(app(Var(1), Var(1)), Var(0)), Var(1))));
+
+    // Replace the free variables by their index, as seen from the root.
+    let mut x = app(Var(0), lam(app(Var(1), Var(2))));
+    subst_bound_vars(&mut x, |i| match i {
+        0 => VarSubst::Keep,
+        _ => VarSubst::Replace(lam(Var(i + 1))),
+    });
+    assert_eq!(x, app(Var(0), lam(app(Var(1), lam(Var(3))))));
+}
+
+#[test]
+fn test_incremental() {
+    #[derive(Drive, DriveAny, NodeKind)]
+    struct Program {
+        items: Vec<Item>,
+    }
+    #[derive(Hash, Drive, DriveAny, NodeKind)]
+    struct Item {
+        name: String,
+        body: Vec<u32>,
+    }
+
+    /// Records the items it sees.
+    #[derive(Default)]
+    struct Lint(Vec<String>);
+    impl<'a> AnyVisitor<'a> for Lint {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            if let Some(item) = x.downcast_ref::<Item>() {
+                self.0.push(item.name.clone());
+            }
+            Continue(Children::Visit)
+        }
+    }
+
+    let item = |name: &str, body| Item {
+        name: name.into(),
+        body,
+    };
+    let mut program = Program {
+        items: vec![item("f", vec![1, 2]), item("g", vec![3])],
+    };
+    let mut lint = Incremental::new(Lint::default()).track::<Item>();
+    let _ = lint.run(&program);
+    assert_eq!(lint.inner().0, ["f", "g"]);
+    assert_eq!(lint.skipped(), 0);
+
+    lint.inner().0.clear();
+    let _ = lint.run(&program);
+    assert!(lint.inner().0.is_empty());
+    assert_eq!(lint.skipped(), 2);
+
+    program.items[1].body[0] = 4;
+    let _ = lint.run(&program);
+    assert_eq!(lint.inner().0, ["g"]);
+    assert_eq!(lint.skipped(), 1);
+
+    lint.clear();
+    lint.inner().0.clear();
+    let _ = lint.run(&program);
+    assert_eq!(lint.inner().0, ["f", "g"]);
+}
+
+#[test]
+fn test_budgeted() {
+    #[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+    enum List {
+        Nil,
+        Cons(u32, Box<List>),
+    }
+    let list = List::Cons(1, Box::new(List::Cons(2, Box::new(List::Nil))));
+
+    #[derive(Default)]
+    struct SumU32(u32);
+    impl<'a> AnyVisitor<'a> for SumU32 {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            if let Some(n) = x.downcast_ref::<u32>() {
+                self.0 += n;
+            }
+            Continue(Children::Visit)
+        }
+    }
+    impl AnyVisitorMut for SumU32 {
+        fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+            if let Some(n) = x.downcast_mut::<u32>() {
+                self.0 += *n;
+                *n = 0;
+            }
+            Continue(Children::Visit)
+        }
+    }
+
+    // The nodes are: list, 1, box, list, 2, box, list.
+    let mut v = Budgeted::new(SumU32::default(), 7);
+    assert_eq!(drive_any(&list, &mut v), Continue(()));
+    assert!(!v.is_truncated());
+    assert_eq!((v.entered(), v.remaining(), v.inner().0), (7, 0, 3));
+
+    let mut v = Budgeted::new(SumU32::default(), 4);
+    assert_eq!(drive_any(&list, &mut v), Break(()));
+    assert!(v.is_truncated());
+    assert_eq!((v.entered(), v.into_inner().0), (4, 1));
+
+    let mut list = list;
+    let mut v = Budgeted::new(SumU32::default(), 5);
+    assert_eq!(drive_any_mut(&mut list, &mut v), Break(()));
+    assert!(v.is_truncated());
+    assert_eq!(v.into_inner().0, 3);
+    let List::Cons(first, rest) = &list else {
+        unreachable!()
+    };
+    assert!(*first == 0 && matches!(**rest, List::Cons(0, _)));
+}
+
+#[test]
+fn test_depth_limited() {
+    #[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+    enum Tree {
+        Leaf(u32),
+        Node(Vec<Tree>),
+    }
+    use Tree::*;
+    // Node[Leaf(1), Node[Leaf(2)]]
+    let mut tree = Node(vec![Leaf(1), Node(vec![Leaf(2)])]);
+
+    /// Sums the leaves, writing down where the tree was cut.
+    #[derive(Default)]
+    struct Sum {
+        sum: u32,
+        cut: Vec<&'static str>,
+    }
+    impl<'a> AnyVisitor<'a> for Sum {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            if let Some(n) = x.downcast_ref::<u32>() {
+                self.sum += n;
+            }
+            Continue(Children::Visit)
+        }
+    }
+    impl AnyVisitorMut for Sum {
+        fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+            if let Some(n) = x.downcast_mut::<u32>() {
+                self.sum += *n;
+                *n += 10;
+            }
+            Continue(Children::Visit)
+        }
+    }
+
+    // Depths: root tree 0, vec 1, trees 2, u32 3 / vec 3, tree 4, u32 5.
+    let mut v = DepthLimited::new(Sum::default(), 3).on_limit(|v: &mut Sum, ty| v.cut.push(ty));
+    let _ = drive_any(&tree, &mut v);
+    assert_eq!(v.truncated(), 1);
+    let v = v.into_inner();
+    assert_eq!(v.sum, 1);
+    assert!(v.cut.len() == 1 && v.cut[0].starts_with("alloc::vec::Vec<"));
+
+    let mut v = DepthLimited::new(Sum::default(), 2);
+    let _ = drive_any_mut(&mut tree, &mut v);
+    assert_eq!(v.truncated(), 2);
+    assert_eq!(v.inner().sum, 0);
+
+    let mut v = DepthLimited::new(Sum::default(), 10);
+    let _ = drive_any_mut(&mut tree, &mut v);
+    assert_eq!(v.truncated(), 0);
+    assert_eq!(v.inner().sum, 3);
+    let mut v = Sum::default();
+    let _ = drive_any(&tree, &mut v);
+    assert_eq!(v.sum, 23);
+}
+
+#[test]
+fn test_type_counts() {
+    #[derive(Drive, DriveAny, NodeKind)]
+    enum Expr {
+        Lit(u32),
+        Add(Box<Expr>, Box<Expr>),
+    }
+    use Expr::*;
+    // 1 + (2 + 3)
+    let e = Add(
+        Box::new(Lit(1)),
+        Box::new(Add(Box::new(Lit(2)), Box::new(Lit(3)))),
+    );
+
+    let counts = type_counts(&e);
+    assert_eq!((counts.get::<Expr>(), counts.get::<u32>()), (5, 3));
+    assert_eq!(counts.get::<Box<Expr>>(), 4);
+    assert_eq!(counts.total(), 12);
+    assert_eq!(counts.report()[0], (std::any::type_name::<Expr>(), 5));
+
+    let mut counts = TypeCounts::new().register::<u32>();
+    let _ = drive_any(&e, &mut counts);
+    assert_eq!(counts.report(), [("u32", 3)]);
+
+    // A statically-typed visitor records the types it wants.
+    #[visitable_group(
+        visitor(drive(&ExprVisitor), infallible),
+        skip(u32),
+        drive(Box<Expr>),
+        override(Expr),
+    )]
+    trait ExprVisitable {}
+    struct Count(TypeCounts);
+    impl ExprVisitor for Count {
+        fn enter_expr(&mut self, x: &Expr) {
+            self.0.record(x);
+        }
+    }
+    let counts = Count(TypeCounts::new()).visit_by_val(&e).0;
+    assert_eq!(counts.report(), [(std::any::type_name::<Expr>(), 5)]);
+}
+
+#[test]
+fn test_tree_printer() {
+    #[derive(Debug, Drive, DriveAny, NodeKind)]
+    struct Ident(String);
+    #[derive(Drive, DriveAny, NodeKind)]
+    enum Expr {
+        Lit(u32),
+        Var(Ident),
+        Call { f: Ident, args: Vec<Expr> },
+    }
+    use Expr::*;
+    let e = Call {
+        f: Ident("f".into()),
+        args: vec![Lit(1), Var(Ident("x".into()))],
+    };
+
+    assert_eq!(
+        tree_string(&e),
+        "\
+Expr::Call
+  Ident
+    String = \"f\"
+  Vec
+    Expr::Lit
+      u32 = 1
+    Expr::Var
+      Ident
+        String = \"x\"
+"
+    );
+
+    let mut printer = TreePrinter::new()
+        .debug::<Ident>()
+        .with_field_names()
+        .with_indent("| ");
+    let _ = drive_any(&e, &mut printer);
+    assert_eq!(
+        printer.output(),
+        "\
+Expr::Call
+| .f: Ident = Ident(\"f\")
+| .args: Vec
+| | [0]: Expr::Lit
+| | | .0: u32 = 1
+| | [1]: Expr::Var
+| | | .0: Ident = Ident(\"x\")
+"
+    );
+}
+
+#[test]
+fn test_tuple_any_visitor() {
+    #[derive(Drive, DriveAny, NodeKind)]
+    enum List {
+        Nil,
+        Cons(u32, Box<List>),
+    }
+    let list = List::Cons(1, Box::new(List::Cons(2, Box::new(List::Nil))));
+
+    struct Log(&'static str, Vec<String>);
+    impl<'a> AnyVisitor<'a> for Log {
+        fn enter(&mut self, x: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+            self.1.push(format!("{} enter {}", self.0, x.kind()));
+            Continue(match x.downcast_ref::<List>() {
+                Some(List::Cons(2, _)) if self.0 == "b" => Children::Skip,
+                _ => Children::Visit,
+            })
+        }
+        fn exit(&mut self, x: &'a dyn DriveAny) -> ControlFlow<()> {
+            self.1.push(format!("{} exit {}", self.0, x.kind()));
+            Continue(())
+        }
+    }
+
+    // A single traversal; `b` skipping the contents of a node skips them for both.
+    let mut v = (Log("a", vec![]), Log("b", vec![]));
+    let _ = drive_any(&list, &mut v);
+    assert_eq!(v.0 .1.len(), 8);
+    assert_eq!(
+        v.0 .1,
+        v.1 .1
+            .iter()
+            .map(|s| s.replacen('b', "a", 1))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(v.0 .1[4..6], ["a enter List::Cons", "a exit List::Cons"]);
+}
+
+#[test]
+fn test_visit_fn() {
+    #[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+    enum List {
+        Nil,
+        Cons(u32, Box<List>),
+    }
+    let mut list = List::Cons(
+        1,
+        Box::new(List::Cons(2, Box::new(List::Cons(3, Box::new(List::Nil))))),
+    );
+
+    let mut seen = vec![];
+    let _ = visit_fn(|n: &u32| seen.push(*n)).visit(&list);
+    assert_eq!(seen, [1, 2, 3]);
+
+    // Usable wherever a typed visitor is expected, e.g. to visit the contents of a value.
+    let mut seen = vec![];
+    let _ = list.drive_inner(&mut visit_fn(|n: &u32| seen.push(*n)));
+    assert_eq!(seen, [1, 2, 3]);
+
+    let found = try_visit_fn(|n: &u32| if *n > 1 { Break(*n) } else { Continue(()) }).visit(&list);
+    assert_eq!(found, Break(2));
+
+    let _ = visit_fn_mut(|n: &mut u32| *n *= 10).visit(&mut list);
+    let mut seen = vec![];
+    let _ = visit_fn(|n: &u32| seen.push(*n)).visit(&list);
+    assert_eq!(seen, [10, 20, 30]);
+
+    // The closure runs before the contents of its argument are visited.
+    let _ = visit_fn_mut(|l: &mut List| {
+        if let List::Cons(20, tail) = l {
+            *l = std::mem::replace(tail, List::Nil);
+        }
+    })
+    .visit(&mut list);
+    let mut seen = vec![];
+    let _ = visit_fn(|n: &u32| seen.push(*n)).visit(&list);
+    assert_eq!(seen, [10, 30]);
+}
+
+#[test]
+fn test_visitor_builder() {
+    #[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+    enum Expr {
+        Lit(u32),
+        Var(String),
+        Add(Box<Expr>, Box<Expr>),
+    }
+    let mut e = Expr::Add(
+        Box::new(Expr::Lit(1)),
+        Box::new(Expr::Add(
+            Box::new(Expr::Var("x".into())),
+            Box::new(Expr::Lit(2)),
+        )),
+    );
+
+    // Handlers registered one by one, as plugins would.
+    let mut lits = vec![];
+    let mut vars = vec![];
+    let mut builder = VisitorBuilder::new();
+    builder = builder.on(|x: &u32| lits.push(*x));
+    builder = builder.on(|x: &String| vars.push(x.clone()));
+    let mut v = builder.build();
+    let _ = drive_any(&e, &mut v);
+    // Usable as a statically-typed visitor too.
+    let _ = e.drive_inner(&mut v);
+    drop(v);
+    assert_eq!(lits, [1, 2, 1, 2]);
+    assert_eq!(vars, ["x", "x"]);
+
+    // `on_mut` handlers run before the contents are visited, and `on` handlers are ignored.
+    let mut count = 0;
+    let mut v = VisitorBuilder::new()
+        .on(|_: &u32| panic!())
+        .on_mut(|x: &mut Expr| {
+            if let Expr::Var(_) = x {
+                *x = Expr::Lit(3)
+            }
+        })
+        .on_mut(|x: &mut u32| {
+            *x *= 10;
+            count += 1;
+        })
+        .build();
+    let _ = drive_any_mut(&mut e, &mut v);
+    drop(v);
+    assert_eq!(count, 3);
+    let mut lits = vec![];
+    let _ = drive_any(
+        &e,
+        &mut VisitorBuilder::new().on(|x: &u32| lits.push(*x)).build(),
+    );
+    assert_eq!(lits, [10, 30, 20]);
+}
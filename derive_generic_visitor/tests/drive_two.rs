@@ -202,3 +202,185 @@ fn test_unit_struct() {
     let mut v = EqVisitor;
     assert!(a.drive_two_inner(&b, &mut v).is_continue());
 }
+
+#[derive(DriveZipMut)]
+struct Settings {
+    name: Option<String>,
+    limits: Vec<Limit>,
+}
+
+#[derive(DriveZipMut)]
+enum Limit {
+    Unlimited,
+    Max(u32),
+}
+
+/// A visitor that merges a patch into a tree: options set in the patch override the target, and
+/// numeric limits are raised to the patch's values.
+struct MergeVisitor;
+
+impl Visitor for MergeVisitor {
+    type Break = ();
+}
+
+impl<'a> VisitZipMut<'a, Option<String>> for MergeVisitor {
+    fn visit(
+        &mut self,
+        target: &'a mut Option<String>,
+        patch: &'a Option<String>,
+    ) -> ControlFlow<()> {
+        if patch.is_some() {
+            target.clone_from(patch);
+        }
+        Continue(())
+    }
+}
+
+impl<'a> VisitZipMut<'a, Vec<Limit>> for MergeVisitor {
+    fn visit(&mut self, target: &'a mut Vec<Limit>, patch: &'a Vec<Limit>) -> ControlFlow<()> {
+        target.drive_zip_mut_inner(patch, self)
+    }
+}
+
+impl<'a> VisitZipMut<'a, Limit> for MergeVisitor {
+    fn visit(&mut self, target: &'a mut Limit, patch: &'a Limit) -> ControlFlow<()> {
+        target.drive_zip_mut_inner(patch, self)
+    }
+}
+
+impl<'a> VisitZipMut<'a, u32> for MergeVisitor {
+    fn visit(&mut self, target: &'a mut u32, patch: &'a u32) -> ControlFlow<()> {
+        *target = (*target).max(*patch);
+        Continue(())
+    }
+}
+
+#[test]
+fn test_zip_mut_merge() {
+    let mut target = Settings {
+        name: Some("a".into()),
+        limits: vec![Limit::Max(3), Limit::Max(10), Limit::Unlimited],
+    };
+    let patch = Settings {
+        name: None,
+        limits: vec![Limit::Max(5), Limit::Max(7), Limit::Unlimited],
+    };
+    assert!(target
+        .drive_zip_mut_inner(&patch, &mut MergeVisitor)
+        .is_continue());
+    assert_eq!(target.name.as_deref(), Some("a"));
+    let maxes: Vec<_> = target
+        .limits
+        .iter()
+        .map(|l| match l {
+            Limit::Max(n) => Some(*n),
+            Limit::Unlimited => None,
+        })
+        .collect();
+    assert_eq!(maxes, [Some(5), Some(10), None]);
+
+    let patch = Settings {
+        name: Some("b".into()),
+        limits: vec![Limit::Unlimited],
+    };
+    // The lists don't match up.
+    assert!(target
+        .drive_zip_mut_inner(&patch, &mut MergeVisitor)
+        .is_break());
+    assert_eq!(target.name.as_deref(), Some("b"));
+}
+
+#[test]
+fn test_diff() {
+    #[derive(Debug, DriveTwo, DriveDiff, NodeKind)]
+    enum Expr {
+        Lit(u32),
+        Var(String),
+        Add(Box<Expr>, Box<Expr>),
+    }
+    #[derive(Debug, DriveTwo, DriveDiff, NodeKind)]
+    struct Program {
+        name: String,
+        exprs: Vec<Expr>,
+    }
+    use Expr::*;
+
+    let a = Program {
+        name: "p".into(),
+        exprs: vec![Add(Box::new(Lit(1)), Box::new(Var("x".into()))), Lit(3)],
+    };
+    assert_eq!(diff(&a, &a), vec![]);
+
+    let b = Program {
+        name: "q".into(),
+        exprs: vec![Add(Box::new(Lit(2)), Box::new(Lit(0))), Lit(3), Lit(4)],
+    };
+    let diffs: Vec<String> = diff(&a, &b).iter().map(|d| d.to_string()).collect();
+    assert_eq!(
+        diffs,
+        vec![
+            r#"Program/0:String: "p" != "q""#,
+            "Program/1:Vec/0:Expr::Add/0:Box/0:Expr::Lit/0:u32: 1 != 2",
+            r#"Program/1:Vec/0:Expr::Add/1:Box/0:Expr::Var: Var("x") != Lit(0)"#,
+            "Program/1:Vec: [Add(Lit(1), Var(\"x\")), Lit(3)] != \
+                [Add(Lit(2), Lit(0)), Lit(3), Lit(4)]",
+        ]
+    );
+
+    // Custom rendering.
+    let diffs = DiffVisitor::new()
+        .with_render(|x: &Vec<Expr>| format!("{} exprs", x.len()))
+        .diff(&a, &b);
+    assert_eq!(diffs[3].left, "2 exprs");
+    assert_eq!(diffs[3].right, "3 exprs");
+    assert_eq!(diffs[3].path, vec![(0, "Program"), (1, "Vec")]);
+}
+
+#[test]
+fn test_structural_eq() {
+    #[derive(Debug, DriveTwo, DriveDiff, NodeKind)]
+    struct Span {
+        lo: u32,
+        hi: u32,
+    }
+    #[derive(Debug, DriveTwo, DriveDiff, NodeKind)]
+    enum Expr {
+        Var(String, Span),
+        Lam(String, Box<Expr>, Span),
+    }
+    use Expr::*;
+    let span = |lo, hi| Span { lo, hi };
+
+    let a = Lam(
+        "x".into(),
+        Box::new(Var("x".into(), span(4, 5))),
+        span(0, 5),
+    );
+    let b = Lam(
+        "x".into(),
+        Box::new(Var("x".into(), span(6, 7))),
+        span(2, 7),
+    );
+    let c = Lam(
+        "y".into(),
+        Box::new(Var("y".into(), span(4, 5))),
+        span(0, 5),
+    );
+    assert!(structural_eq(&a, &a));
+    assert!(!structural_eq(&a, &b));
+    assert!(!structural_eq(&a, &c));
+
+    // Ignore spans.
+    let ignore_spans = || DiffVisitor::new().with_eq(|_: &Span, _: &Span| true);
+    assert!(ignore_spans().equal(&a, &b));
+    assert!(!ignore_spans().equal(&a, &c));
+    // Also ignore names, e.g. for terms whose variables are already known to be bound the same way.
+    assert!(ignore_spans()
+        .with_eq(|_: &String, _: &String| true)
+        .equal(&b, &c));
+
+    // `diff` uses the overrides too.
+    let diffs = ignore_spans().diff(&a, &c);
+    assert_eq!(diffs.len(), 2);
+    assert_eq!(diffs[0].to_string(), r#"Expr::Lam/0:String: "x" != "y""#);
+}
@@ -0,0 +1,796 @@
+//! Tests for the impls on third-party types, enabled by the corresponding features.
+#![allow(unused)]
+use derive_generic_visitor::*;
+
+/// Counts `true` bools, and negates them when visiting mutably.
+#[derive(Default)]
+struct Bools(usize);
+impl Visitor for Bools {
+    type Break = Infallible;
+}
+impl Visit<'_, bool> for Bools {
+    fn visit(&mut self, x: &bool) -> ControlFlow<Infallible> {
+        self.0 += *x as usize;
+        Continue(())
+    }
+}
+impl VisitMut<'_, bool> for Bools {
+    fn visit(&mut self, x: &mut bool) -> ControlFlow<Infallible> {
+        *x = !*x;
+        Continue(())
+    }
+}
+
+#[cfg(feature = "bitvec")]
+#[test]
+fn test_bitvec() {
+    use bitvec::prelude::*;
+    let mut bits = bitvec![1, 0, 1, 1];
+    let mut count = Bools::default();
+    let _ = bits.drive_inner(&mut count);
+    assert_eq!(count.0, 3);
+    let _ = bits.drive_inner_mut(&mut Bools::default());
+    assert_eq!(bits, bitvec![0, 1, 0, 0]);
+}
+
+#[cfg(feature = "ndarray")]
+#[test]
+fn test_ndarray() {
+    use ndarray::array;
+
+    #[derive(Visitor, Visit)]
+    #[visit(u32)]
+    struct Sum(u32);
+    impl Sum {
+        fn visit_u32(&mut self, x: &u32) -> ControlFlow<Infallible> {
+            self.0 += x;
+            Continue(())
+        }
+    }
+    let a = array![[1u32, 2], [3, 4]];
+    let mut sum = Sum(0);
+    let _ = a.drive_inner(&mut sum);
+    assert_eq!(sum.0, 10);
+}
+
+#[cfg(all(feature = "camino", feature = "url"))]
+#[test]
+fn test_path_and_url_leaves() {
+    #[derive(Drive, DriveTwo)]
+    struct Manifest {
+        path: camino::Utf8PathBuf,
+        homepage: url::Url,
+        version: u32,
+    }
+
+    #[derive(Visitor, Visit)]
+    #[visit(drive(Manifest), skip(camino::Utf8PathBuf, url::Url), u32)]
+    struct FindVersion(Option<u32>);
+    impl FindVersion {
+        fn visit_u32(&mut self, x: &u32) -> ControlFlow<Infallible> {
+            self.0 = Some(*x);
+            Continue(())
+        }
+    }
+
+    let m = Manifest {
+        path: "Cargo.toml".into(),
+        homepage: url::Url::parse("https://example.com").unwrap(),
+        version: 2,
+    };
+    assert_eq!(FindVersion(None).visit_by_val_infallible(&m).0, Some(2));
+}
+
+#[cfg(feature = "camino")]
+#[test]
+fn test_camino_paths() {
+    use camino::{Utf8Path, Utf8PathBuf};
+
+    #[derive(Drive)]
+    struct Target<'a> {
+        src: Box<Utf8Path>,
+        out_dir: &'a Utf8Path,
+        deps: Vec<Utf8PathBuf>,
+    }
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(drive(Target<'_>, for<T: ?Sized> Box<T>, for<T: ?Sized> &T, Vec<Utf8PathBuf>))]
+    #[visit(enter(Utf8Path), enter(Utf8PathBuf))]
+    struct Collect(Vec<String>);
+    impl Collect {
+        fn enter_utf8_path(&mut self, x: &Utf8Path) {
+            self.0.push(x.to_string());
+        }
+        fn enter_utf8_path_buf(&mut self, x: &Utf8PathBuf) {
+            self.0.push(x.to_string());
+        }
+    }
+
+    let target = Target {
+        src: Utf8Path::new("src/lib.rs").into(),
+        out_dir: Utf8Path::new("target"),
+        deps: vec!["a.rlib".into()],
+    };
+    let collect = Collect::default().visit_by_val_infallible(&target);
+    assert_eq!(collect.0, ["src/lib.rs", "target", "a.rlib"]);
+}
+
+#[cfg(all(feature = "smol_str", feature = "compact_str"))]
+#[test]
+fn test_small_strings() {
+    #[derive(Drive, DriveTwo)]
+    struct Ident {
+        name: smol_str::SmolStr,
+        module: compact_str::CompactString,
+    }
+
+    /// Compares for equality.
+    #[derive(VisitTwo)]
+    #[visit_two(drive(Ident, smol_str::SmolStr, compact_str::CompactString))]
+    struct Eq;
+    impl Visitor for Eq {
+        type Break = ();
+    }
+
+    let a = Ident {
+        name: "x".into(),
+        module: "m".into(),
+    };
+    let b = Ident {
+        name: "x".into(),
+        module: "n".into(),
+    };
+    assert_eq!(a.drive_two_inner(&a, &mut Eq), Continue(()));
+    assert_eq!(a.drive_two_inner(&b, &mut Eq), Break(()));
+}
+
+#[cfg(feature = "ecow")]
+#[test]
+fn test_ecow() {
+    use ecow::{eco_vec, EcoString, EcoVec};
+
+    #[derive(Clone, Drive, DriveMut)]
+    struct Call {
+        func: EcoString,
+        args: EcoVec<u32>,
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Call, for<T: Clone> EcoVec<T>), skip(EcoString), u32)]
+    struct Incr;
+    impl Incr {
+        fn visit_u32(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            *x += 1;
+            Continue(())
+        }
+    }
+
+    let original = Call {
+        func: "f".into(),
+        args: eco_vec![1, 2],
+    };
+    let mut call = original.clone();
+    let _ = call.drive_inner_mut(&mut Incr);
+    assert_eq!(call.func, "f");
+    assert_eq!(call.args, [2, 3]);
+    // The shared vector was cloned before being modified.
+    assert_eq!(original.args, [1, 2]);
+}
+
+#[cfg(all(feature = "nonempty", feature = "vec1"))]
+#[test]
+fn test_non_empty_vecs() {
+    #[derive(Drive, DriveMut)]
+    struct Block {
+        stmts: nonempty::NonEmpty<u32>,
+        labels: vec1::Vec1<u32>,
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Block, for<T> nonempty::NonEmpty<T>, for<T> vec1::Vec1<T>), u32)]
+    struct Incr;
+    impl Incr {
+        fn visit_u32(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            *x += 1;
+            Continue(())
+        }
+    }
+
+    let mut block = Block {
+        stmts: nonempty::nonempty![1, 2],
+        labels: vec1::vec1![3],
+    };
+    let _ = block.drive_inner_mut(&mut Incr);
+    assert_eq!(block.stmts, nonempty::nonempty![2, 3]);
+    assert_eq!(block.labels, vec1::vec1![4]);
+}
+
+#[cfg(feature = "bumpalo")]
+#[test]
+fn test_bumpalo() {
+    use bumpalo::{boxed::Box, collections::Vec, Bump};
+
+    #[derive(Drive, DriveMut)]
+    struct Node<'bump> {
+        flag: bool,
+        children: Vec<'bump, Box<'bump, Node<'bump>>>,
+    }
+
+    #[derive(Visitor, Visit)]
+    #[visit(drive(for<'a> Node<'a>, for<'a, T> Vec<'a, T>, for<'a, T> Box<'a, T>), bool)]
+    struct CountTrue(usize);
+    impl CountTrue {
+        fn visit_bool(&mut self, x: &bool) -> ControlFlow<Infallible> {
+            self.0 += *x as usize;
+            Continue(())
+        }
+    }
+
+    let bump = Bump::new();
+    let leaf = |flag| {
+        Box::new_in(
+            Node {
+                flag,
+                children: Vec::new_in(&bump),
+            },
+            &bump,
+        )
+    };
+    let mut children = Vec::new_in(&bump);
+    children.push(leaf(true));
+    children.push(leaf(false));
+    children.push(leaf(true));
+    let mut tree = Node {
+        flag: true,
+        children,
+    };
+    assert_eq!(CountTrue(0).visit_by_val_infallible(&tree).0, 3);
+
+    let mut flags = Vec::new_in(&bump);
+    flags.extend([true, false]);
+    let _ = flags.drive_inner_mut(&mut Bools::default());
+    assert_eq!(flags.as_slice(), [false, true]);
+}
+
+#[cfg(feature = "triomphe")]
+#[test]
+fn test_triomphe() {
+    use triomphe::Arc;
+    let mut x = Arc::new(true);
+    let y = x.clone();
+    let mut count = Bools::default();
+    let _ = x.drive_inner(&mut count);
+    assert_eq!(count.0, 1);
+    // The contents are cloned since they're shared.
+    let _ = x.drive_inner_mut(&mut Bools::default());
+    assert!(!*x);
+    assert!(*y);
+
+    // An unshared `Arc` is modified in place.
+    let ptr = Arc::as_ptr(&x);
+    let _ = x.drive_inner_mut(&mut Bools::default());
+    assert!(*x);
+    assert_eq!(Arc::as_ptr(&x), ptr);
+
+    // `triomphe::Arc` fields work with derived visitors and diffs.
+    #[derive(Clone, Debug, Drive, DriveMut)]
+    struct Shared {
+        flags: Arc<Vec<bool>>,
+    }
+    #[derive(Visitor, Visit)]
+    #[visit(drive(Shared, for<T> Arc<T>, for<T> Vec<T>), enter(bool))]
+    struct CountTrue(usize);
+    impl CountTrue {
+        fn enter_bool(&mut self, x: &bool) {
+            self.0 += *x as usize;
+        }
+    }
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Shared, for<T: Clone> Arc<T>, for<T> Vec<T>), enter(bool))]
+    struct Negate;
+    impl Negate {
+        fn enter_bool(&mut self, x: &mut bool) {
+            *x = !*x;
+        }
+    }
+    let mut x = Shared {
+        flags: Arc::new(vec![true, false]),
+    };
+    let y = x.clone();
+    assert_eq!(CountTrue(0).visit_by_val_infallible(&x).0, 1);
+    let _ = Negate.visit(&mut x);
+    assert_eq!(*x.flags, [false, true]);
+    assert_eq!(*y.flags, [true, false]);
+    assert_eq!(diff(&x.flags, &y.flags).len(), 2);
+}
+
+#[cfg(feature = "servo_arc")]
+#[test]
+fn test_servo_arc() {
+    use servo_arc::Arc;
+    let mut x = Arc::new(true);
+    let y = x.clone();
+    let _ = x.drive_inner_mut(&mut Bools::default());
+    assert!(!*x);
+    assert!(*y);
+}
+
+#[cfg(feature = "smallvec")]
+#[test]
+fn test_smallvec() {
+    use smallvec::{smallvec, SmallVec};
+
+    #[derive(Drive, DriveMut)]
+    struct Block {
+        stmts: SmallVec<[u32; 2]>,
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Block, for<T, const N: usize> SmallVec<[T; N]>), u32)]
+    struct Incr;
+    impl Incr {
+        fn visit_u32(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            *x += 1;
+            Continue(())
+        }
+    }
+
+    let mut block = Block {
+        stmts: smallvec![1, 2, 3],
+    };
+    let _ = block.drive_inner_mut(&mut Incr);
+    assert_eq!(block.stmts.as_slice(), [2, 3, 4]);
+    assert!(heap_size(&block.stmts) >= 3 * size_of::<u32>());
+}
+
+#[cfg(feature = "either")]
+#[test]
+fn test_either() {
+    use either::Either::{self, Left, Right};
+
+    #[derive(Drive, DriveMut)]
+    struct Operand(Either<u32, String>);
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Operand, for<L, R> Either<L, R>), skip(String), u32)]
+    struct Incr;
+    impl Incr {
+        fn visit_u32(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            *x += 1;
+            Continue(())
+        }
+    }
+
+    let mut ops = [Operand(Left(1)), Operand(Right("x".into()))];
+    let _ = Incr.visit_all(&mut ops);
+    assert_eq!(ops[0].0, Left(2));
+    assert_eq!(ops[1].0, Right("x".to_owned()));
+}
+
+#[cfg(all(feature = "im", feature = "rpds"))]
+#[test]
+fn test_persistent_collections() {
+    #[derive(Drive)]
+    struct Module {
+        items: im::Vector<u32>,
+        exports: im::OrdMap<u32, u32>,
+        imports: im::HashMap<u32, u32>,
+        history: rpds::Vector<u32>,
+        scopes: rpds::RedBlackTreeMap<u32, u32>,
+        names: rpds::HashTrieMap<u32, u32>,
+    }
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(drive(Module, for<T: Clone> im::Vector<T>, for<K: Ord, V> im::OrdMap<K, V>))]
+    #[visit(drive(im::HashMap<u32, u32>, rpds::Vector<u32>, rpds::RedBlackTreeMap<u32, u32>))]
+    #[visit(drive(rpds::HashTrieMap<u32, u32>), u32)]
+    struct Collect(Vec<u32>);
+    impl Collect {
+        fn visit_u32(&mut self, x: &u32) -> ControlFlow<Infallible> {
+            self.0.push(*x);
+            Continue(())
+        }
+    }
+
+    let module = Module {
+        items: im::vector![1, 2],
+        exports: im::ordmap! {3 => 4},
+        imports: im::hashmap! {5 => 6},
+        history: rpds::vector![7],
+        scopes: rpds::rbt_map![8 => 9],
+        names: rpds::ht_map![10 => 11],
+    };
+    let mut v = Collect::default();
+    let _ = v.visit(&module);
+    assert_eq!(v.0, (1..=11).collect::<Vec<_>>());
+}
+
+#[cfg(feature = "dashmap")]
+#[test]
+fn test_dashmap() {
+    use dashmap::DashMap;
+
+    #[derive(Drive, DriveMut)]
+    struct Interner {
+        symbols: DashMap<String, u32>,
+    }
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(drive(Interner, DashMap<String, u32>), enter(String), u32)]
+    struct Collect(Vec<String>, u32);
+    impl Collect {
+        fn enter_string(&mut self, x: &str) {
+            self.0.push(x.to_owned());
+        }
+        fn visit_u32(&mut self, x: &u32) -> ControlFlow<Infallible> {
+            self.1 += x;
+            Continue(())
+        }
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Interner, DashMap<String, u32>), u32)]
+    struct Incr;
+    impl Incr {
+        fn visit_u32(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            *x += 1;
+            Continue(())
+        }
+    }
+
+    let mut interner = Interner {
+        symbols: DashMap::new(),
+    };
+    interner.symbols.insert("a".into(), 0);
+    interner.symbols.insert("b".into(), 1);
+    let _ = Incr.visit(&mut interner);
+    let mut v = Collect::default();
+    let _ = v.visit(&interner);
+    v.0.sort();
+    assert_eq!(v.0, ["a", "b"]);
+    assert_eq!(v.1, 3);
+}
+
+#[cfg(feature = "id_arena")]
+#[test]
+fn test_id_arena() {
+    use id_arena::{Arena, Id};
+
+    #[derive(Drive, DriveMut)]
+    enum Expr {
+        Lit(u32),
+        Add(Id<Expr>, Id<Expr>),
+    }
+
+    #[derive(Drive, DriveMut)]
+    struct Body {
+        exprs: Arena<Expr>,
+        root: Id<Expr>,
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Body, Arena<Expr>, Expr, Id<Expr>), u32)]
+    struct Incr;
+    impl Incr {
+        fn visit_u32(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            *x += 1;
+            Continue(())
+        }
+    }
+
+    let mut exprs = Arena::new();
+    let a = exprs.alloc(Expr::Lit(1));
+    let b = exprs.alloc(Expr::Lit(2));
+    let root = exprs.alloc(Expr::Add(a, b));
+    let mut body = Body { exprs, root };
+    let _ = Incr.visit(&mut body);
+    let lits: Vec<_> = body
+        .exprs
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Expr::Lit(n) => Some(*n),
+            Expr::Add(..) => None,
+        })
+        .collect();
+    assert_eq!(lits, [2, 3]);
+    assert_eq!(body.root, root);
+}
+
+#[cfg(feature = "la_arena")]
+#[test]
+fn test_la_arena() {
+    use la_arena::{Arena, Idx};
+
+    #[derive(Drive, DriveMut)]
+    enum Expr {
+        Lit(u32),
+        Add(Idx<Expr>, Idx<Expr>),
+    }
+
+    #[derive(Drive, DriveMut)]
+    struct Body {
+        exprs: Arena<Expr>,
+        root: Idx<Expr>,
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Body, Arena<Expr>, Expr, Idx<Expr>), u32)]
+    struct Incr;
+    impl Incr {
+        fn visit_u32(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            *x += 1;
+            Continue(())
+        }
+    }
+
+    let mut exprs = Arena::new();
+    let a = exprs.alloc(Expr::Lit(1));
+    let b = exprs.alloc(Expr::Lit(2));
+    let root = exprs.alloc(Expr::Add(a, b));
+    let mut body = Body { exprs, root };
+    let _ = Incr.visit(&mut body);
+    let lits: Vec<_> = body
+        .exprs
+        .values()
+        .filter_map(|e| match e {
+            Expr::Lit(n) => Some(*n),
+            Expr::Add(..) => None,
+        })
+        .collect();
+    assert_eq!(lits, [2, 3]);
+    assert_eq!(body.root, root);
+}
+
+#[cfg(feature = "parking_lot")]
+#[test]
+fn test_parking_lot() {
+    use parking_lot::{Mutex, RwLock};
+
+    #[derive(Drive)]
+    struct Flags {
+        a: Mutex<bool>,
+        b: RwLock<bool>,
+    }
+    #[derive(Visitor, Visit, Default)]
+    #[visit(drive(Flags, Mutex<bool>, RwLock<bool>), bool)]
+    struct Count(usize);
+    impl Count {
+        fn visit_bool(&mut self, x: &bool) -> ControlFlow<Infallible> {
+            self.0 += *x as usize;
+            Continue(())
+        }
+    }
+
+    let mut flags = Flags {
+        a: Mutex::new(true),
+        b: RwLock::new(false),
+    };
+    assert_eq!(Count::default().visit_by_val_infallible(&flags).0, 1);
+    let _ = flags.a.drive_inner_mut(&mut Bools::default());
+    let _ = flags.b.drive_inner_mut(&mut Bools::default());
+    assert!(!*flags.a.lock());
+    assert!(*flags.b.read());
+}
+
+#[cfg(feature = "petgraph")]
+#[test]
+fn test_petgraph() {
+    use petgraph::graph::Graph;
+    use petgraph::stable_graph::StableGraph;
+
+    let mut graph: Graph<bool, bool> = Graph::new();
+    let a = graph.add_node(true);
+    let b = graph.add_node(false);
+    let c = graph.add_node(true);
+    graph.add_edge(a, b, true);
+    graph.add_edge(b, c, false);
+
+    let mut count = Bools::default();
+    let _ = graph.drive_inner(&mut count);
+    assert_eq!(count.0, 3);
+    let _ = graph.drive_inner_mut(&mut Bools::default());
+    assert_eq!(
+        graph.node_weights().copied().collect::<Vec<_>>(),
+        [false, true, false]
+    );
+    assert_eq!(
+        graph.edge_weights().copied().collect::<Vec<_>>(),
+        [false, true]
+    );
+
+    let mut graph: StableGraph<bool, bool> = graph.into();
+    graph.remove_node(a);
+    let mut count = Bools::default();
+    let _ = graph.drive_inner(&mut count);
+    assert_eq!(count.0, 2);
+    let _ = graph.drive_inner_mut(&mut Bools::default());
+    let mut count = Bools::default();
+    let _ = graph.drive_inner(&mut count);
+    assert_eq!(count.0, 1);
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn test_serde_json() {
+    use serde_json::{json, Value};
+
+    /// Replaces the value of every `"password"` field.
+    #[derive(Visitor, VisitMut)]
+    #[visit(enter(Value))]
+    struct Scrub;
+    impl Scrub {
+        fn enter_value(&mut self, x: &mut Value) {
+            if let Some(password) = x.get_mut("password") {
+                *password = Value::String("<redacted>".to_owned());
+            }
+        }
+    }
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(enter(Value))]
+    struct CountStrings(usize);
+    impl CountStrings {
+        fn enter_value(&mut self, x: &Value) {
+            self.0 += x.is_string() as usize;
+        }
+    }
+
+    let mut value = json!({
+        "name": "admin",
+        "password": "hunter2",
+        "children": [{ "name": "guest", "password": { "hash": "abc" } }, null, 3],
+    });
+    let _ = Scrub.visit(&mut value);
+    assert_eq!(
+        value,
+        json!({
+            "name": "admin",
+            "password": "<redacted>",
+            "children": [{ "name": "guest", "password": "<redacted>" }, null, 3],
+        })
+    );
+    let mut count = CountStrings::default();
+    let _ = count.visit(&value);
+    assert_eq!(count.0, 4);
+}
+
+#[cfg(all(feature = "toml", feature = "serde_yaml"))]
+#[test]
+fn test_toml_and_yaml() {
+    /// Rewrites every `"localhost"` string, whatever the config format.
+    #[derive(Visitor, VisitMut)]
+    #[visit(enter(toml: toml::Value), enter(yaml: serde_yaml::Value))]
+    struct Rehost;
+    impl Rehost {
+        fn enter_toml(&mut self, x: &mut toml::Value) {
+            if x.as_str() == Some("localhost") {
+                *x = toml::Value::String("example.com".to_owned());
+            }
+        }
+        fn enter_yaml(&mut self, x: &mut serde_yaml::Value) {
+            // Not `as_str`, which looks through tags.
+            if let serde_yaml::Value::String(s) = x {
+                if s == "localhost" {
+                    *s = "example.com".to_owned();
+                }
+            }
+        }
+    }
+
+    let mut config: toml::Value = toml::from_str(
+        r#"
+        host = "localhost"
+        [server]
+        mirrors = ["localhost", "other"]
+        "#,
+    )
+    .unwrap();
+    let _ = Rehost.visit(&mut config);
+    assert_eq!(config["host"].as_str(), Some("example.com"));
+    assert_eq!(
+        config["server"]["mirrors"],
+        toml::Value::Array(vec!["example.com".into(), "other".into()])
+    );
+
+    let mut config: serde_yaml::Value = serde_yaml::from_str(
+        "
+        host: localhost
+        server:
+          mirrors: [localhost, other]
+          backup: !remote localhost
+        ",
+    )
+    .unwrap();
+    let _ = Rehost.visit(&mut config);
+    let expected: serde_yaml::Value = serde_yaml::from_str(
+        "
+        host: example.com
+        server:
+          mirrors: [example.com, other]
+          backup: !remote example.com
+        ",
+    )
+    .unwrap();
+    assert_eq!(config, expected);
+}
+
+#[cfg(all(feature = "bytes", feature = "thin_vec"))]
+#[test]
+fn test_bytes_and_thin_vec() {
+    use bytes::Bytes;
+    use thin_vec::{thin_vec, ThinVec};
+
+    #[derive(Drive, DriveMut)]
+    struct Frame {
+        fields: ThinVec<u32>,
+        payload: Bytes,
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Frame, for<T> ThinVec<T>), skip(Bytes), u32)]
+    struct Incr;
+    impl Incr {
+        fn visit_u32(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            *x += 1;
+            Continue(())
+        }
+    }
+
+    let mut frame = Frame {
+        fields: thin_vec![1, 2],
+        payload: Bytes::from_static(b"\x00\x01"),
+    };
+    let _ = frame.drive_inner_mut(&mut Incr);
+    assert_eq!(frame.fields.as_slice(), [2, 3]);
+    assert_eq!(frame.payload, b"\x00\x01"[..]);
+    assert!(heap_size(&frame.fields) >= 2 * size_of::<u32>());
+}
+
+#[cfg(feature = "either")]
+#[test]
+fn test_either_visitor() {
+    use either::Either::{self, Left, Right};
+
+    #[derive(Drive)]
+    struct Pair(u32, u32);
+
+    struct Sum(u32);
+    impl Visitor for Sum {
+        type Break = ();
+    }
+    impl Visit<'_, u32> for Sum {
+        fn visit(&mut self, x: &u32) -> ControlFlow<()> {
+            self.0 += x;
+            Continue(())
+        }
+    }
+    struct First(Option<u32>);
+    impl Visitor for First {
+        type Break = ();
+    }
+    impl Visit<'_, u32> for First {
+        fn visit(&mut self, x: &u32) -> ControlFlow<()> {
+            self.0 = Some(*x);
+            Break(())
+        }
+    }
+
+    let pick = |sum: bool| -> Either<Sum, First> {
+        if sum {
+            Left(Sum(0))
+        } else {
+            Right(First(None))
+        }
+    };
+    let pair = Pair(1, 2);
+    let mut v = pick(true);
+    assert_eq!(pair.drive_inner(&mut v), Continue(()));
+    assert!(matches!(v, Left(Sum(3))));
+    let mut v = pick(false);
+    assert_eq!(pair.drive_inner(&mut v), Break(()));
+    assert!(matches!(v, Right(First(Some(1)))));
+
+    let mut v: Either<TypeCounts, HeapSizeVisitor> = Left(TypeCounts::new());
+    let _ = drive_any(&Some(1u32), &mut v);
+    assert!(matches!(v, Left(counts) if counts.get::<u32>() == 1));
+}
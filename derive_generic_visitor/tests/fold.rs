@@ -0,0 +1,164 @@
+use derive_generic_visitor::*;
+
+#[derive(Debug, PartialEq, Fold)]
+enum Expr {
+    Lit(u32),
+    Var(String),
+    Add(Box<Expr>, Box<Expr>),
+    Let {
+        name: String,
+        value: Box<Expr>,
+        body: Box<Expr>,
+    },
+    #[drive(skip)]
+    Opaque(Box<Expr>),
+}
+
+#[derive(Debug, PartialEq, Fold)]
+struct Program {
+    exprs: Vec<Expr>,
+    #[drive(skip)]
+    version: u32,
+}
+
+fn lit(n: u32) -> Box<Expr> {
+    Box::new(Expr::Lit(n))
+}
+fn var(name: &str) -> Box<Expr> {
+    Box::new(Expr::Var(name.to_owned()))
+}
+
+#[test]
+fn test_constant_folding() {
+    /// Replaces additions of literals with their sum, bottom-up.
+    #[derive(Visitor, VisitFold)]
+    #[visit(drive(Program, Vec<Expr>, for<T> Box<T>), exit(Expr), skip(String, u32))]
+    struct ConstFold;
+    impl ConstFold {
+        fn exit_expr(&mut self, x: Expr) -> Expr {
+            match x {
+                Expr::Add(a, b) => match (*a, *b) {
+                    (Expr::Lit(a), Expr::Lit(b)) => Expr::Lit(a + b),
+                    (a, b) => Expr::Add(Box::new(a), Box::new(b)),
+                },
+                x => x,
+            }
+        }
+    }
+
+    let program = Program {
+        exprs: vec![
+            Expr::Add(Box::new(Expr::Add(lit(1), lit(2))), lit(3)),
+            Expr::Let {
+                name: "x".to_owned(),
+                value: Box::new(Expr::Add(lit(1), lit(1))),
+                body: Box::new(Expr::Add(var("x"), lit(1))),
+            },
+            Expr::Opaque(Box::new(Expr::Add(lit(1), lit(1)))),
+        ],
+        version: 3,
+    };
+    let Continue(program) = ConstFold.visit(program);
+    assert_eq!(
+        program,
+        Program {
+            exprs: vec![
+                Expr::Lit(6),
+                Expr::Let {
+                    name: "x".to_owned(),
+                    value: lit(2),
+                    body: Box::new(Expr::Add(var("x"), lit(1))),
+                },
+                // Skipped variants are left alone.
+                Expr::Opaque(Box::new(Expr::Add(lit(1), lit(1)))),
+            ],
+            version: 3,
+        }
+    );
+}
+
+#[test]
+fn test_fold_break() {
+    /// Fails on large literals.
+    #[derive(VisitFold)]
+    #[visit(drive(Program, Vec<Expr>, for<T> Box<T>, Expr), skip(String), u32)]
+    struct CheckLits;
+    impl Visitor for CheckLits {
+        type Break = String;
+    }
+    impl CheckLits {
+        fn visit_u32(&mut self, x: u32) -> ControlFlow<String, u32> {
+            if x > 100 {
+                Break(format!("{x} is too large"))
+            } else {
+                Continue(x)
+            }
+        }
+    }
+
+    let program = Program {
+        exprs: vec![Expr::Add(lit(1), lit(2)), Expr::Add(lit(1000), lit(2))],
+        version: 0,
+    };
+    assert_eq!(
+        CheckLits.visit(program),
+        Break("1000 is too large".to_owned())
+    );
+
+    let pair = (vec![1u32, 2], [3u32, 4]);
+    #[derive(VisitFold)]
+    #[visit(drive(for<T> Vec<T>, for<T, const N: usize> [T; N]), u32)]
+    struct Double;
+    impl Visitor for Double {
+        type Break = ();
+    }
+    impl Double {
+        fn visit_u32(&mut self, x: u32) -> ControlFlow<(), u32> {
+            Continue(x * 2)
+        }
+    }
+    assert_eq!(pair.fold_inner(&mut Double), Continue((vec![2, 4], [6, 8])));
+}
+
+#[test]
+fn test_fold_maps() {
+    use std::collections::{HashMap, HashSet};
+
+    /// Uppercases names and replaces the variable `one` by its value.
+    #[derive(Visitor, VisitFold)]
+    #[visit(
+        drive(HashMap<String, Expr>, HashSet<String>, for<T> Box<T>),
+        enter(Expr, String),
+        skip(u32)
+    )]
+    struct Rename;
+    impl Rename {
+        fn enter_expr(&mut self, x: Expr) -> Expr {
+            match x {
+                Expr::Var(name) if name == "one" => Expr::Lit(1),
+                x => x,
+            }
+        }
+        fn enter_string(&mut self, x: String) -> String {
+            x.to_uppercase()
+        }
+    }
+
+    let env: HashMap<String, Expr> = [
+        ("x".to_owned(), Expr::Var("one".into())),
+        ("y".to_owned(), Expr::Var("z".into())),
+    ]
+    .into();
+    let Continue(env) = Rename.visit(env);
+    let expected: HashMap<String, Expr> = [
+        ("X".to_owned(), Expr::Lit(1)),
+        ("Y".to_owned(), Expr::Var("Z".into())),
+    ]
+    .into();
+    assert_eq!(env, expected);
+
+    // Keys that fold to the same value are merged.
+    let names: HashSet<String> = ["a".to_owned(), "A".to_owned(), "b".to_owned()].into();
+    let Continue(names) = Rename.visit(names);
+    assert_eq!(names, ["A".to_owned(), "B".to_owned()].into());
+}
@@ -0,0 +1,442 @@
+use derive_generic_visitor::*;
+use std::collections::{BTreeMap, HashMap};
+
+#[test]
+fn test_drive_map_keys_mut() {
+    /// Lowercases strings.
+    #[derive(Visitor, VisitMut)]
+    #[visit(String)]
+    struct Lowercase;
+    impl Lowercase {
+        fn visit_string(&mut self, x: &mut String) -> ControlFlow<Infallible> {
+            *x = x.to_lowercase();
+            Continue(())
+        }
+    }
+
+    let mut map: BTreeMap<String, u32> =
+        BTreeMap::from([("A".into(), 0), ("a".into(), 1), ("B".into(), 2)]);
+    let discarded = drive_map_keys_mut(&mut map, KeyCollision::KeepFirst, &mut Lowercase)
+        .continue_value()
+        .unwrap();
+    // Entries are reinserted in iteration order, so "A" comes first.
+    assert_eq!(map, BTreeMap::from([("a".into(), 0), ("b".into(), 2)]));
+    assert_eq!(discarded, vec![("a".into(), 1)]);
+
+    let mut map: HashMap<String, u32> = HashMap::from([("X".into(), 0), ("x".into(), 1)]);
+    let discarded = drive_map_keys_mut(&mut map, KeyCollision::KeepLast, &mut Lowercase)
+        .continue_value()
+        .unwrap();
+    assert_eq!(map.len(), 1);
+    assert_eq!(discarded.len(), 1);
+    assert_ne!(map["x"], discarded[0].1);
+}
+
+#[test]
+fn test_drive_map_keys_mut_break() {
+    /// Increments keys, stopping at the first odd key.
+    struct IncrEven;
+    impl Visitor for IncrEven {
+        type Break = u32;
+    }
+    impl VisitMut<'_, u32> for IncrEven {
+        fn visit(&mut self, x: &mut u32) -> ControlFlow<u32> {
+            if *x % 2 == 1 {
+                return Break(*x);
+            }
+            *x += 10;
+            Continue(())
+        }
+    }
+
+    let mut map: BTreeMap<u32, ()> = BTreeMap::from([(0, ()), (2, ()), (3, ()), (4, ())]);
+    let result = drive_map_keys_mut(&mut map, KeyCollision::KeepFirst, &mut IncrEven);
+    assert_eq!(result, Break(3));
+    // Entries after the break are reinserted unchanged.
+    assert_eq!(map.into_keys().collect::<Vec<_>>(), vec![3, 4, 10, 12]);
+}
+
+#[test]
+fn test_indexed() {
+    #[derive(Drive, DriveMut)]
+    struct Call {
+        name: String,
+        args: Indexed<Vec<Arg>>,
+    }
+    #[derive(Drive, DriveMut)]
+    struct Arg(i32);
+
+    /// Reports the position of the first negative argument.
+    struct FindNegative;
+    impl Visitor for FindNegative {
+        type Break = usize;
+    }
+    impl<'a> Visit<'a, String> for FindNegative {
+        fn visit(&mut self, _: &'a String) -> ControlFlow<usize> {
+            Continue(())
+        }
+    }
+    impl<'a> Visit<'a, Indexed<Vec<Arg>>> for FindNegative {
+        fn visit(&mut self, x: &'a Indexed<Vec<Arg>>) -> ControlFlow<usize> {
+            x.drive_inner(self)
+        }
+    }
+    impl<'a> VisitIndexed<'a, Arg> for FindNegative {
+        fn visit_indexed(&mut self, index: usize, x: &'a Arg) -> ControlFlow<usize> {
+            if x.0 < 0 {
+                Break(index)
+            } else {
+                Continue(())
+            }
+        }
+    }
+
+    /// Sets each argument to its position.
+    struct Renumber;
+    impl Visitor for Renumber {
+        type Break = Infallible;
+    }
+    impl VisitIndexedMut<'_, Arg> for Renumber {
+        fn visit_indexed(&mut self, index: usize, x: &mut Arg) -> ControlFlow<Infallible> {
+            x.0 = index as i32;
+            Continue(())
+        }
+    }
+
+    let mut call = Call {
+        name: "f".into(),
+        args: Indexed(vec![Arg(1), Arg(2), Arg(-3), Arg(-4)]),
+    };
+    assert_eq!(call.drive_inner(&mut FindNegative), Break(2));
+    let _ = call.args.drive_inner_mut(&mut Renumber);
+    assert_eq!(
+        call.args.iter().map(|a| a.0).collect::<Vec<_>>(),
+        [0, 1, 2, 3]
+    );
+    assert_eq!(call.drive_inner(&mut FindNegative), Continue(()));
+}
+
+#[test]
+fn test_keyed() {
+    use std::collections::BTreeMap;
+
+    #[derive(Drive, DriveMut)]
+    struct Struct {
+        fields: Keyed<BTreeMap<String, Ty>>,
+    }
+    #[derive(Drive, DriveMut)]
+    enum Ty {
+        Int,
+        Unknown,
+    }
+
+    /// Reports the first field of unknown type.
+    struct FindUnknown;
+    impl Visitor for FindUnknown {
+        type Break = String;
+    }
+    impl<'a> VisitKeyed<'a, String, Ty> for FindUnknown {
+        fn visit_keyed(&mut self, name: &'a String, x: &'a Ty) -> ControlFlow<String> {
+            match x {
+                Ty::Unknown => Break(format!("field `{name}` has an unknown type")),
+                Ty::Int => Continue(()),
+            }
+        }
+    }
+
+    /// Defaults the fields whose names start with `n` to `Int`.
+    struct DefaultToInt;
+    impl Visitor for DefaultToInt {
+        type Break = Infallible;
+    }
+    impl VisitKeyedMut<'_, String, Ty> for DefaultToInt {
+        fn visit_keyed(&mut self, name: &String, x: &mut Ty) -> ControlFlow<Infallible> {
+            if name.starts_with('n') {
+                *x = Ty::Int;
+            }
+            Continue(())
+        }
+    }
+
+    let mut s = Struct {
+        fields: Keyed(
+            [
+                ("a".to_owned(), Ty::Int),
+                ("n".to_owned(), Ty::Unknown),
+                ("z".to_owned(), Ty::Unknown),
+            ]
+            .into(),
+        ),
+    };
+    assert_eq!(
+        s.fields.drive_inner(&mut FindUnknown),
+        Break("field `n` has an unknown type".into())
+    );
+    let _ = s.fields.drive_inner_mut(&mut DefaultToInt);
+    assert_eq!(
+        s.fields.drive_inner(&mut FindUnknown),
+        Break("field `z` has an unknown type".into())
+    );
+}
+
+#[test]
+fn test_iter_drive() {
+    use std::collections::VecDeque;
+
+    /// Sums the `u32`s.
+    #[derive(Visitor, Visit)]
+    #[visit(u32)]
+    struct Sum(u32);
+    impl Sum {
+        fn visit_u32(&mut self, x: &u32) -> ControlFlow<Infallible> {
+            self.0 += x;
+            Continue(())
+        }
+    }
+
+    fn sum_contents<T>(x: &T) -> u32
+    where
+        T: for<'a> Drive<'a, Sum>,
+    {
+        let mut sum = Sum(0);
+        let _ = x.drive_inner(&mut sum);
+        sum.0
+    }
+
+    let queue: VecDeque<u32> = VecDeque::from([1, 2, 3]);
+    assert_eq!(sum_contents(IterDrive::from_ref(&queue)), 6);
+    let set = IterDrive(std::collections::BTreeSet::from([4, 5]));
+    assert_eq!(sum_contents(&set), 9);
+}
+
+#[test]
+fn test_visit_all() {
+    /// Sums numbers, stopping at the first zero.
+    #[derive(Default)]
+    struct Sum(u32);
+    impl Visitor for Sum {
+        type Break = ();
+    }
+    impl Visit<'_, u32> for Sum {
+        fn visit(&mut self, x: &u32) -> ControlFlow<()> {
+            if *x == 0 {
+                return Break(());
+            }
+            self.0 += x;
+            Continue(())
+        }
+    }
+
+    let mut sum = Sum::default();
+    assert!(sum.visit_all(&[1, 2, 3]).is_continue());
+    assert!(sum.visit_all(&[4, 0, 5]).is_break());
+    assert_eq!(sum.0, 10);
+
+    // Visiting by reference keeps the visitor around.
+    let mut sum = Sum::default();
+    for x in [1, 2] {
+        let _ = sum.by_ref().visit_by_val(&x);
+    }
+    fn visit_three<V: for<'a> Visit<'a, u32>>(v: V) {
+        let _ = v.visit_by_val(&3);
+    }
+    visit_three(&mut sum);
+    assert_eq!(sum.0, 6);
+}
+
+#[test]
+fn test_downcast_registry() {
+    use std::any::Any;
+    use std::sync::OnceLock;
+
+    #[derive(Drive, DriveMut)]
+    struct Node {
+        value: u32,
+        ext: Vec<Box<dyn Any>>,
+    }
+    #[derive(Drive, DriveMut)]
+    struct Ext(u32);
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(enter(u32))]
+    #[visit(drive(Node, Ext, Vec<Box<dyn Any>>, Box<dyn Any>))]
+    struct Sum(u32);
+    impl Sum {
+        fn enter_u32(&mut self, x: &u32) {
+            self.0 += x;
+        }
+    }
+    impl<'a> Visit<'a, dyn Any> for Sum {
+        fn visit(&mut self, x: &'a dyn Any) -> ControlFlow<Infallible> {
+            static DOWNCASTS: OnceLock<DowncastRegistry<Sum>> = OnceLock::new();
+            DOWNCASTS
+                .get_or_init(|| DowncastRegistry::new().register::<Ext>().register::<Node>())
+                .visit(self, x)
+        }
+    }
+
+    /// Doubles numbers.
+    #[derive(Visitor, VisitMut)]
+    #[visit(enter(u32))]
+    #[visit(drive(Node, Ext, Vec<Box<dyn Any>>, Box<dyn Any>))]
+    struct Double;
+    impl Double {
+        fn enter_u32(&mut self, x: &mut u32) {
+            *x *= 2;
+        }
+    }
+    impl<'a> VisitMut<'a, dyn Any> for Double {
+        fn visit(&mut self, x: &'a mut dyn Any) -> ControlFlow<Infallible> {
+            // `Node` isn't registered, so nested nodes are skipped.
+            DowncastRegistry::new()
+                .register_mut::<Ext>()
+                .visit_mut(self, x)
+        }
+    }
+
+    let nested = Node {
+        value: 10,
+        ext: vec![Box::new(Ext(20))],
+    };
+    let mut node = Node {
+        value: 1,
+        ext: vec![Box::new(Ext(2)), Box::new("unknown"), Box::new(nested)],
+    };
+    let _ = Double.visit(&mut node);
+    let mut sum = Sum::default();
+    let _ = sum.visit(&node);
+    assert_eq!(sum.0, 2 + 4 + 10 + 20);
+}
+
+#[test]
+fn test_drive_keys_values() {
+    /// Records the strings and numbers it visits.
+    #[derive(Default)]
+    struct Log(Vec<String>);
+    impl Visitor for Log {
+        type Break = Infallible;
+    }
+    impl<'a> Visit<'a, String> for Log {
+        fn visit(&mut self, x: &'a String) -> ControlFlow<Infallible> {
+            self.0.push(x.clone());
+            Continue(())
+        }
+    }
+    impl<'a> Visit<'a, u32> for Log {
+        fn visit(&mut self, x: &'a u32) -> ControlFlow<Infallible> {
+            self.0.push(x.to_string());
+            Continue(())
+        }
+    }
+
+    /// Lowercases strings and multiplies numbers by 10.
+    struct Edit;
+    impl Visitor for Edit {
+        type Break = Infallible;
+    }
+    impl<'a> VisitMut<'a, String> for Edit {
+        fn visit(&mut self, x: &'a mut String) -> ControlFlow<Infallible> {
+            *x = x.to_lowercase();
+            Continue(())
+        }
+    }
+    impl<'a> VisitMut<'a, u32> for Edit {
+        fn visit(&mut self, x: &'a mut u32) -> ControlFlow<Infallible> {
+            *x *= 10;
+            Continue(())
+        }
+    }
+
+    let map = BTreeMap::from([("A".to_string(), 1u32), ("B".to_string(), 2)]);
+    let mut values = DriveValues(map.clone());
+    let mut keys = DriveKeys(map);
+    let mut log = Log::default();
+    let _ = values.drive_inner(&mut log);
+    let _ = keys.drive_inner(&mut log);
+    assert_eq!(log.0, ["1", "2", "A", "B"]);
+
+    let _ = values.drive_inner_mut(&mut Edit);
+    let _ = keys.drive_inner_mut(&mut Edit);
+    assert_eq!(
+        *values,
+        BTreeMap::from([("A".to_string(), 10), ("B".to_string(), 20)])
+    );
+    assert_eq!(
+        *keys,
+        BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+    );
+
+    // Keys that become equal are merged, keeping the last entry.
+    let mut keys = DriveKeys(HashMap::from([("x".to_string(), 1), ("X".to_string(), 2)]));
+    let _ = keys.drive_inner_mut(&mut Edit);
+    assert_eq!(keys.len(), 1);
+}
+
+#[test]
+fn test_drive_via() {
+    use std::collections::VecDeque;
+
+    /// Stands for a type from another crate, with no `Drive` impl.
+    #[derive(Debug, PartialEq)]
+    struct Span {
+        lo: u32,
+        hi: u32,
+    }
+
+    /// Visits the bounds of a span.
+    struct Bounds;
+    impl<'s, V: Visit<'s, u32>> DriveStrategy<'s, Span, V> for Bounds {
+        fn drive_inner(x: &'s Span, v: &mut V) -> ControlFlow<V::Break> {
+            v.visit(&x.lo)?;
+            v.visit(&x.hi)
+        }
+    }
+    impl<'s, V: VisitMut<'s, u32>> DriveMutStrategy<'s, Span, V> for Bounds {
+        fn drive_inner_mut(x: &'s mut Span, v: &mut V) -> ControlFlow<V::Break> {
+            v.visit(&mut x.lo)?;
+            v.visit(&mut x.hi)
+        }
+    }
+
+    #[derive(Drive, DriveMut)]
+    struct Token {
+        span: DriveVia<Span, Bounds>,
+        lines: DriveVia<VecDeque<u32>, ViaIter>,
+        id: SkipDrive<u32>,
+    }
+
+    #[derive(Visitor, Visit)]
+    #[visit(drive(DriveVia<Span, Bounds>, DriveVia<VecDeque<u32>, ViaIter>, SkipDrive<u32>))]
+    #[visit(u32)]
+    struct Sum(u32);
+    impl Sum {
+        fn visit_u32(&mut self, x: &u32) -> ControlFlow<Infallible> {
+            self.0 += x;
+            Continue(())
+        }
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(DriveVia<Span, Bounds>, DriveVia<VecDeque<u32>, ViaIter>, SkipDrive<u32>))]
+    #[visit(u32)]
+    struct Shift(u32);
+    impl Shift {
+        fn visit_u32(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            *x += self.0;
+            Continue(())
+        }
+    }
+
+    let mut tok = Token {
+        span: DriveVia::new(Span { lo: 1, hi: 2 }),
+        lines: DriveVia::new(VecDeque::from([3])),
+        id: SkipDrive(100),
+    };
+    let mut sum = Sum(0);
+    let _ = tok.drive_inner(&mut sum);
+    assert_eq!(sum.0, 6);
+    let _ = tok.drive_inner_mut(&mut Shift(10));
+    assert_eq!(*tok.span, Span { lo: 11, hi: 12 });
+    assert_eq!(*tok.lines, [13]);
+    assert_eq!(*tok.id, 100);
+}
@@ -0,0 +1,162 @@
+#![cfg(feature = "rayon")]
+#![allow(unused)]
+use derive_generic_visitor::*;
+
+#[derive(Drive, DrivePar)]
+struct Module {
+    name: String,
+    items: Vec<Item>,
+    #[drive(skip)]
+    version: u32,
+}
+
+#[derive(Drive, DrivePar)]
+enum Item {
+    Const(u32),
+    Pair(u32, u32),
+    #[drive(skip)]
+    Opaque(u32),
+}
+
+/// Collects the `u32`s in order, visiting `Vec<Item>`s in parallel.
+#[derive(Default, Visit)]
+#[visit(skip(String), drive(Item), u32, vec_item: Vec<Item>)]
+struct Collect {
+    seen: Vec<u32>,
+    /// Breaks with the first number above this.
+    limit: u32,
+}
+impl Visitor for Collect {
+    type Break = u32;
+}
+impl ParVisitor for Collect {
+    fn fork(&self) -> Self {
+        Collect {
+            seen: Vec::new(),
+            limit: self.limit,
+        }
+    }
+    fn join(&mut self, other: Self) {
+        self.seen.extend(other.seen);
+    }
+}
+impl Collect {
+    fn visit_u32(&mut self, x: &u32) -> ControlFlow<u32> {
+        if *x > self.limit {
+            return Break(*x);
+        }
+        self.seen.push(*x);
+        Continue(())
+    }
+    fn visit_vec_item(&mut self, x: &Vec<Item>) -> ControlFlow<u32> {
+        x.drive_par_inner(self)
+    }
+}
+
+fn module() -> Module {
+    Module {
+        name: "m".to_owned(),
+        items: (0..100)
+            .map(|i| match i % 3 {
+                0 => Item::Const(i),
+                1 => Item::Pair(i, i + 1000),
+                _ => Item::Opaque(i),
+            })
+            .collect(),
+        version: 1,
+    }
+}
+
+#[test]
+fn test_drive_par() {
+    let module = module();
+    let mut par = Collect {
+        limit: u32::MAX,
+        ..Collect::default()
+    };
+    let _ = module.drive_par_inner(&mut par);
+
+    let mut seq = Collect {
+        limit: u32::MAX,
+        ..Collect::default()
+    };
+    let _ = module.drive_inner(&mut seq);
+    assert_eq!(par.seen, seq.seen);
+    assert_eq!(par.seen.len(), 34 + 2 * 33);
+}
+
+#[test]
+fn test_drive_par_break() {
+    let module = module();
+    let mut par = Collect {
+        limit: 1010,
+        ..Collect::default()
+    };
+    // Several items break; the first one wins, and only what precedes it is kept.
+    assert_eq!(module.drive_par_inner(&mut par), Break(1013));
+
+    let mut seq = Collect {
+        limit: 1010,
+        ..Collect::default()
+    };
+    assert_eq!(module.drive_inner(&mut seq), Break(1013));
+    assert_eq!(par.seen, seq.seen);
+}
+
+#[test]
+fn test_drive_par_binder() {
+    #[derive(Drive, DrivePar)]
+    enum Term {
+        Var(u32),
+        Lam(#[drive(binder)] Box<Term>),
+        App(Box<Term>, Box<Term>),
+    }
+    use Term::*;
+
+    /// Records the depth at which each variable is found, visiting every term in parallel.
+    #[derive(Default)]
+    struct Depths {
+        depth: usize,
+        seen: Vec<(u32, usize)>,
+    }
+    impl Visitor for Depths {
+        type Break = Infallible;
+    }
+    impl VisitBinder for Depths {
+        fn binder_depth(&mut self) -> &mut usize {
+            &mut self.depth
+        }
+    }
+    impl ParVisitor for Depths {
+        fn fork(&self) -> Self {
+            Depths {
+                depth: self.depth,
+                seen: Vec::new(),
+            }
+        }
+        fn join(&mut self, other: Self) {
+            self.seen.extend(other.seen);
+        }
+    }
+    impl<'a> Visit<'a, Box<Term>> for Depths {
+        fn visit(&mut self, x: &'a Box<Term>) -> ControlFlow<Infallible> {
+            (**x).drive_par_inner(self)
+        }
+    }
+    impl<'a> Visit<'a, u32> for Depths {
+        fn visit(&mut self, x: &'a u32) -> ControlFlow<Infallible> {
+            self.seen.push((*x, self.depth));
+            Continue(())
+        }
+    }
+
+    // \x. x (\y. y x)
+    let t = Lam(Box::new(App(
+        Box::new(Var(0)),
+        Box::new(Lam(Box::new(App(Box::new(Var(0)), Box::new(Var(1)))))),
+    )));
+    let mut v = Depths::default();
+    let _ = t.drive_par_inner(&mut v);
+    assert_eq!(v.depth, 0);
+    assert_eq!(v.seen, [(0, 1), (0, 2), (1, 2)]);
+}
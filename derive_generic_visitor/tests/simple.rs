@@ -157,3 +157,1655 @@ fn test_early_exit() {
     let list: List<i32> = List::Nil.cons(42).cons(-1);
     assert!(SumVisitor::default().visit_by_val(&list).is_break());
 }
+
+#[test]
+fn test_collect_then_mutate() {
+    use std::collections::HashMap;
+
+    #[derive(Drive, DriveMut)]
+    enum Stmt {
+        Let(#[drive(skip)] String, u32),
+        Use(#[drive(skip)] String),
+    }
+
+    /// Collects the names that are defined.
+    #[derive(Default, Visitor, Visit)]
+    #[visit(drive(for<T> Vec<T>), skip(u32), Stmt)]
+    struct CollectDefs(Vec<String>);
+    impl CollectDefs {
+        fn visit_stmt(&mut self, x: &Stmt) -> ControlFlow<Infallible> {
+            if let Stmt::Let(name, _) = x {
+                self.0.push(name.clone());
+            }
+            Continue(())
+        }
+    }
+
+    /// Renames the uses of the given names.
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(for<T> Vec<T>), skip(u32), Stmt)]
+    struct Rename(HashMap<String, String>);
+    impl Rename {
+        fn visit_stmt(&mut self, x: &mut Stmt) -> ControlFlow<Infallible> {
+            if let Stmt::Use(name) = x {
+                if let Some(new_name) = self.0.get(name) {
+                    *name = new_name.clone();
+                }
+            }
+            Continue(())
+        }
+    }
+
+    let mut stmts = vec![
+        Stmt::Let("x".into(), 0),
+        Stmt::Use("x".into()),
+        Stmt::Use("y".into()),
+    ];
+    let rename = collect_then_mutate(&mut stmts, CollectDefs::default(), |defs| {
+        Rename(
+            defs.0
+                .into_iter()
+                .enumerate()
+                .map(|(i, name)| (name, format!("v{i}")))
+                .collect(),
+        )
+    })
+    .continue_value()
+    .unwrap();
+    assert_eq!(rename.0.len(), 1);
+    let uses: Vec<_> = stmts
+        .iter()
+        .filter_map(|s| match s {
+            Stmt::Use(name) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(uses, vec!["v0", "y"]);
+}
+
+#[test]
+fn test_rewrite_until_fixpoint() {
+    #[derive(Debug, PartialEq, Drive, DriveMut)]
+    enum Expr {
+        Lit(u32),
+        Add(Box<Expr>, Box<Expr>),
+    }
+    use Expr::*;
+
+    /// Folds the additions of two literals, outermost first: an addition that only has literals as
+    /// operands after its contents are folded is left to the next pass.
+    #[derive(Default, Visitor, VisitMut)]
+    #[visit(drive(for<T> Box<T>), enter(Expr), skip(u32))]
+    struct FoldOnce {
+        changed: bool,
+    }
+    impl FoldOnce {
+        fn enter_expr(&mut self, x: &mut Expr) {
+            if let Add(a, b) = x {
+                if let (Lit(a), Lit(b)) = (&**a, &**b) {
+                    *x = Lit(a + b);
+                    self.changed = true;
+                }
+            }
+        }
+    }
+
+    let lit = |n| Box::new(Lit(n));
+    let add = |a, b| Box::new(Add(a, b));
+    let mut expr = *add(add(lit(1), add(lit(2), lit(3))), lit(4));
+    let result = rewrite_until_fixpoint(&mut expr, 10, FoldOnce::default, |pass: &FoldOnce| {
+        pass.changed
+    });
+    assert_eq!(result, Continue(Fixpoint::Reached { iterations: 4 }));
+    assert_eq!(expr, Lit(10));
+
+    let mut expr = *add(add(lit(1), add(lit(2), lit(3))), lit(4));
+    let result = rewrite_until_fixpoint(&mut expr, 2, FoldOnce::default, |pass| pass.changed);
+    assert_eq!(result, Continue(Fixpoint::Capped));
+    assert_eq!(expr, *add(lit(6), lit(4)));
+}
+
+#[test]
+fn test_drive_schema() {
+    #![allow(dead_code)]
+    #[derive(Drive, DriveSchema)]
+    enum Expr {
+        Var(String),
+        Call {
+            func: Box<Expr>,
+            args: Vec<Expr>,
+        },
+        #[drive(skip)]
+        Error,
+    }
+    #[derive(Drive, DriveSchema)]
+    struct Spanned<T> {
+        val: T,
+        #[drive(skip)]
+        span: (usize, usize),
+    }
+
+    const SCHEMA: Schema = Expr::SCHEMA;
+    assert_eq!(SCHEMA.name, "Expr");
+    let Shape::Enum(variants) = SCHEMA.shape else {
+        panic!()
+    };
+    assert_eq!(
+        variants.iter().map(|v| v.name).collect::<Vec<_>>(),
+        ["Var", "Call", "Error"]
+    );
+    assert!(variants[2].skip);
+    assert_eq!(
+        variants[1].fields[0],
+        FieldSchema {
+            name: Some("func"),
+            ty: "Box<Expr>",
+            skip: false
+        }
+    );
+    assert_eq!(
+        Expr::SCHEMA
+            .visited_fields()
+            .map(|f| f.ty)
+            .collect::<Vec<_>>(),
+        ["String", "Box<Expr>", "Vec<Expr>"]
+    );
+
+    let Shape::Struct(fields) = Spanned::<u32>::SCHEMA.shape else {
+        panic!()
+    };
+    assert_eq!(fields[1].ty, "(usize, usize)");
+    assert_eq!(
+        Spanned::<u32>::SCHEMA
+            .visited_fields()
+            .map(|f| f.ty)
+            .collect::<Vec<_>>(),
+        ["T"]
+    );
+}
+
+#[test]
+fn test_drive_discriminant() {
+    #[derive(Drive, DriveMut)]
+    #[drive(discriminant)]
+    enum Shape {
+        Point,
+        Circle(u32),
+        #[drive(skip)]
+        Hidden,
+        Rect {
+            w: u32,
+            h: u32,
+        },
+    }
+
+    /// Records the variants and values it sees.
+    #[derive(Default)]
+    struct Record(Vec<String>);
+    impl Visitor for Record {
+        type Break = Infallible;
+    }
+    impl Visit<'_, Discriminant> for Record {
+        fn visit(&mut self, x: &Discriminant) -> ControlFlow<Infallible> {
+            self.0.push(format!("{}#{}", x.name, x.index));
+            Continue(())
+        }
+    }
+    impl VisitMut<'_, Discriminant> for Record {
+        fn visit(&mut self, x: &mut Discriminant) -> ControlFlow<Infallible> {
+            self.0.push(x.name.to_owned());
+            Continue(())
+        }
+    }
+    impl Visit<'_, u32> for Record {
+        fn visit(&mut self, x: &u32) -> ControlFlow<Infallible> {
+            self.0.push(x.to_string());
+            Continue(())
+        }
+    }
+    impl VisitMut<'_, u32> for Record {
+        fn visit(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            self.0.push(x.to_string());
+            Continue(())
+        }
+    }
+
+    let mut v = Record::default();
+    for shape in [
+        Shape::Point,
+        Shape::Circle(1),
+        Shape::Hidden,
+        Shape::Rect { w: 2, h: 3 },
+    ] {
+        let _ = shape.drive_inner(&mut v);
+    }
+    assert_eq!(v.0, ["Point#0", "Circle#1", "1", "Rect#3", "2", "3"]);
+
+    let mut v = Record::default();
+    let _ = Shape::Circle(4).drive_inner_mut(&mut v);
+    assert_eq!(v.0, ["Circle", "4"]);
+}
+
+#[test]
+fn test_forwarded_attrs() {
+    // The derived impls are compiled out, so they don't conflict with the manual ones.
+    #[derive(Drive, Visitor, Visit)]
+    #[drive(attr(cfg(any())))]
+    #[visit(attr(cfg(any())), skip(u32))]
+    struct Leaf(#[allow(dead_code)] u32);
+    impl<'s, V: Visitor> Drive<'s, V> for Leaf {
+        fn drive_inner(&'s self, _: &mut V) -> ControlFlow<V::Break> {
+            Continue(())
+        }
+    }
+    impl Visitor for Leaf {
+        type Break = ();
+    }
+    impl Visit<'_, u32> for Leaf {
+        fn visit(&mut self, _: &u32) -> ControlFlow<()> {
+            Break(())
+        }
+    }
+    assert!(Leaf(0).visit(&0).is_break());
+
+    // The whole group is compiled out.
+    #[visitable_group(
+        attr(cfg(any())),
+        visitor(drive(&AstVisitor)),
+        override(u32),
+    )]
+    trait AstVisitable {}
+    // Doesn't conflict with the compiled-out visitor trait.
+    #[allow(dead_code)]
+    trait AstVisitor {}
+}
+
+#[test]
+fn test_drive_rc_arc() {
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[derive(Clone, Drive, DriveMut)]
+    enum Expr {
+        Lit(u32),
+        Shared(Arc<u32>),
+        Local(Rc<Expr>, Rc<Expr>),
+    }
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(enter(u32))]
+    #[visit(drive(Expr, for<T> Arc<T>, for<T> Rc<T>))]
+    struct Sum(u32);
+    impl Sum {
+        fn enter_u32(&mut self, x: &u32) {
+            self.0 += x;
+        }
+    }
+
+    let shared = Arc::new(1);
+    let local = Rc::new(Expr::Shared(shared.clone()));
+    let mut expr = Expr::Local(local.clone(), Rc::new(Expr::Lit(2)));
+    let mut sum = Sum::default();
+    let _ = sum.visit(&expr);
+    assert_eq!(sum.0, 3);
+
+    // Mutable visits clone the shared contents instead of modifying them.
+    #[derive(Visitor, VisitMut)]
+    #[visit(enter(u32))]
+    #[visit(drive(Expr, for<T: Clone> Arc<T>, for<T: Clone> Rc<T>))]
+    struct Double;
+    impl Double {
+        fn enter_u32(&mut self, x: &mut u32) {
+            *x *= 2;
+        }
+    }
+    let _ = Double.visit(&mut expr);
+    let mut sum = Sum::default();
+    let _ = sum.visit(&expr);
+    assert_eq!(sum.0, 6);
+    assert_eq!(*shared, 1);
+    assert!(matches!(&*local, Expr::Shared(n) if **n == 1));
+
+    // Shared subtrees are cloned once per pointer, so they're no longer shared afterwards.
+    let mut expr = Expr::Local(local.clone(), local.clone());
+    let _ = Double.visit(&mut expr);
+    let Expr::Local(x, y) = &expr else { panic!() };
+    assert!(!Rc::ptr_eq(x, y));
+}
+
+#[test]
+fn test_drive_slice_and_str() {
+    #[derive(Drive, DriveMut)]
+    struct Block<'a> {
+        label: Box<str>,
+        stmts: Box<[Stmt]>,
+        comments: &'a [&'a str],
+    }
+    #[derive(Drive, DriveMut)]
+    struct Stmt(u32);
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(enter(u32), enter(str))]
+    #[visit(drive(for<T: ?Sized> Box<T>, for<T: ?Sized> &T, [Stmt], [&str], Stmt))]
+    struct Collect {
+        nums: Vec<u32>,
+        strs: Vec<String>,
+    }
+    impl Collect {
+        fn enter_u32(&mut self, x: &u32) {
+            self.nums.push(*x);
+        }
+        fn enter_str(&mut self, x: &str) {
+            self.strs.push(x.to_owned());
+        }
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(enter(u32))]
+    #[visit(skip(str, &[&str]))]
+    #[visit(drive(for<T: ?Sized> Box<T>, [Stmt], Stmt))]
+    struct Increment;
+    impl Increment {
+        fn enter_u32(&mut self, x: &mut u32) {
+            *x += 1;
+        }
+    }
+
+    let comments = ["hello", "world"];
+    let mut block = Block {
+        label: "entry".into(),
+        stmts: vec![Stmt(1), Stmt(2)].into_boxed_slice(),
+        comments: &comments,
+    };
+    let _ = block.drive_inner_mut(&mut Increment);
+    let mut v = Collect::default();
+    let _ = block.drive_inner(&mut v);
+    assert_eq!(v.nums, [2, 3]);
+    assert_eq!(v.strs, ["entry", "hello", "world"]);
+}
+
+#[test]
+fn test_drive_strings() {
+    use std::ffi::{CStr, CString};
+
+    #[derive(Drive, DriveMut)]
+    struct Symbol {
+        name: String,
+        c_name: CString,
+        section: &'static str,
+        raw: Box<CStr>,
+        id: u32,
+    }
+
+    // Strings are leaves, so they can be driven through without custom behavior.
+    #[derive(Default, Visitor, Visit)]
+    #[visit(enter(u32))]
+    #[visit(drive(String, CString, &'static str, str, Box<CStr>, CStr))]
+    struct Ids(Vec<u32>);
+    impl Ids {
+        fn enter_u32(&mut self, x: &u32) {
+            self.0.push(*x);
+        }
+    }
+
+    let sym = Symbol {
+        name: "main".into(),
+        c_name: CString::new("main").unwrap(),
+        section: ".text",
+        raw: CString::new("main").unwrap().into_boxed_c_str(),
+        id: 3,
+    };
+    let mut ids = Ids::default();
+    let _ = sym.drive_inner(&mut ids);
+    assert_eq!(ids.0, [3]);
+}
+
+#[test]
+fn test_drive_numeric() {
+    use std::num::{NonZeroU32, Saturating, Wrapping};
+
+    #[derive(Drive, DriveMut)]
+    struct Sample {
+        weight: f64,
+        count: NonZeroU32,
+        seq: Wrapping<u8>,
+        level: Saturating<i32>,
+    }
+
+    // Floats and non-zero integers are leaves; the wrappers drive into the wrapped value.
+    #[derive(Visitor, VisitMut)]
+    #[visit(enter(u8), enter(i32))]
+    #[visit(drive(f64, NonZeroU32, Wrapping<u8>, Saturating<i32>))]
+    struct Bump;
+    impl Bump {
+        fn enter_u8(&mut self, x: &mut u8) {
+            *x = x.wrapping_add(1);
+        }
+        fn enter_i32(&mut self, x: &mut i32) {
+            *x += 1;
+        }
+    }
+
+    let mut sample = Sample {
+        weight: 0.5,
+        count: NonZeroU32::new(2).unwrap(),
+        seq: Wrapping(255),
+        level: Saturating(-1),
+    };
+    let _ = sample.drive_inner_mut(&mut Bump);
+    assert_eq!(sample.weight, 0.5);
+    assert_eq!(sample.count.get(), 2);
+    assert_eq!(sample.seq, Wrapping(0));
+    assert_eq!(sample.level, Saturating(0));
+}
+
+#[test]
+fn test_drive_opaque_std_values() {
+    use std::ffi::{OsStr, OsString};
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant, SystemTime};
+
+    #[derive(Drive)]
+    struct Manifest<'a> {
+        root: PathBuf,
+        include: Vec<&'a Path>,
+        env: Vec<(OsString, Box<OsStr>)>,
+        timeout: Duration,
+        started: Instant,
+        modified: SystemTime,
+        retries: u32,
+    }
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(enter(u32))]
+    #[visit(drive(PathBuf, for<T: ?Sized> &T, Path, for<T> Vec<T>, OsString, Box<OsStr>, OsStr))]
+    #[visit(drive((OsString, Box<OsStr>), Duration, Instant, SystemTime))]
+    struct Retries(u32);
+    impl Retries {
+        fn enter_u32(&mut self, x: &u32) {
+            self.0 += x;
+        }
+    }
+
+    let manifest = Manifest {
+        root: "/srv".into(),
+        include: vec![Path::new("src")],
+        env: vec![("HOME".into(), OsStr::new("/root").into())],
+        timeout: Duration::from_secs(1),
+        started: Instant::now(),
+        modified: SystemTime::UNIX_EPOCH,
+        retries: 3,
+    };
+    let mut v = Retries::default();
+    let _ = manifest.drive_inner(&mut v);
+    assert_eq!(v.0, 3);
+}
+
+#[test]
+fn test_drive_reverse_and_ordering() {
+    use std::cmp::{Ordering, Reverse};
+
+    #[derive(Drive, DriveMut)]
+    struct Task {
+        priority: Reverse<u32>,
+        tie_break: Ordering,
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(enter(u32))]
+    #[visit(drive(Reverse<u32>, Ordering))]
+    struct Bump;
+    impl Bump {
+        fn enter_u32(&mut self, x: &mut u32) {
+            *x += 1;
+        }
+    }
+
+    let mut task = Task {
+        priority: Reverse(1),
+        tie_break: Ordering::Less,
+    };
+    let _ = task.drive_inner_mut(&mut Bump);
+    assert_eq!(task.priority, Reverse(2));
+    assert_eq!(task.tie_break, Ordering::Less);
+}
+
+#[test]
+fn test_drive_pin_and_manually_drop() {
+    use std::mem::ManuallyDrop;
+    use std::pin::Pin;
+
+    #[derive(Drive, DriveMut)]
+    struct State {
+        pinned: Pin<Box<u32>>,
+        slot: ManuallyDrop<u32>,
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(enter(u32))]
+    #[visit(drive(Pin<Box<u32>>, ManuallyDrop<u32>))]
+    struct Bump;
+    impl Bump {
+        fn enter_u32(&mut self, x: &mut u32) {
+            *x += 1;
+        }
+    }
+
+    let mut state = State {
+        pinned: Box::pin(1),
+        slot: ManuallyDrop::new(2),
+    };
+    let _ = state.drive_inner_mut(&mut Bump);
+    assert_eq!(*state.pinned, 2);
+    assert_eq!(*state.slot, 3);
+}
+
+#[test]
+fn test_drive_shared_slices() {
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[derive(Drive)]
+    struct Module {
+        items: Box<[u32]>,
+        shared_items: Rc<[u32]>,
+        sync_items: Arc<[u32]>,
+        name: Rc<str>,
+        path: Arc<str>,
+    }
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(enter(u32))]
+    #[visit(drive(Box<[u32]>, Rc<[u32]>, Arc<[u32]>, [u32], Rc<str>, Arc<str>, str))]
+    struct Sum(u32);
+    impl Sum {
+        fn enter_u32(&mut self, x: &u32) {
+            self.0 += x;
+        }
+    }
+
+    let module = Module {
+        items: vec![1, 2].into(),
+        shared_items: vec![3].into(),
+        sync_items: vec![4].into(),
+        name: "m".into(),
+        path: "a::m".into(),
+    };
+    let mut sum = Sum::default();
+    let _ = module.drive_inner(&mut sum);
+    assert_eq!(sum.0, 10);
+}
+
+#[test]
+fn test_drive_uninhabited() {
+    /// A disabled extension point.
+    #[derive(Drive, DriveMut, DriveTwo, DriveAny, DriveAnyMut, NodeKind)]
+    enum NoExt {}
+
+    /// `X` and `Y` are set to uninhabited types to disable the corresponding variants.
+    #[derive(Drive, DriveMut, DriveAny, NodeKind)]
+    #[allow(dead_code)]
+    enum Expr<X, Y> {
+        Lit(u32),
+        Ext(X),
+        Other(Box<Y>),
+    }
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(enter(u32))]
+    #[visit(drive(Expr<NoExt, Infallible>, NoExt, Infallible, Box<Infallible>))]
+    struct Sum(u32);
+    impl Sum {
+        fn enter_u32(&mut self, x: &u32) {
+            self.0 += x;
+        }
+    }
+
+    let expr: Expr<NoExt, Infallible> = Expr::Lit(3);
+    let mut sum = Sum::default();
+    let _ = sum.visit(&expr);
+    assert_eq!(sum.0, 3);
+    assert_eq!(drive_any_count(&expr), 2);
+
+    fn drive_any_count(x: &dyn DriveAny) -> usize {
+        struct Count(usize);
+        impl<'a> AnyVisitor<'a> for Count {
+            fn enter(&mut self, _: &'a dyn DriveAny) -> ControlFlow<(), Children> {
+                self.0 += 1;
+                Continue(Children::Visit)
+            }
+        }
+        let mut count = Count(0);
+        let _ = drive_any(x, &mut count);
+        count.0
+    }
+}
+
+#[test]
+fn test_drive_hash_collections() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::{HashMap, HashSet};
+    use std::hash::BuildHasherDefault;
+
+    /// Stands for hashers like `FxHasher`.
+    type CustomMap<K, V> = HashMap<K, V, BuildHasherDefault<DefaultHasher>>;
+
+    #[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+    struct Scope {
+        locals: CustomMap<String, u32>,
+        #[drive(skip)]
+        used: HashSet<u32>,
+    }
+
+    #[derive(Default, Visitor, Visit)]
+    #[visit(drive(Scope, CustomMap<String, u32>, HashSet<u32>), enter(String), enter(u32))]
+    struct Collect {
+        names: Vec<String>,
+        nums: Vec<u32>,
+    }
+    impl Collect {
+        fn enter_string(&mut self, x: &str) {
+            self.names.push(x.to_owned());
+        }
+        fn enter_u32(&mut self, x: &u32) {
+            self.nums.push(*x);
+        }
+    }
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Scope, CustomMap<String, u32>), u32)]
+    struct Incr;
+    impl Incr {
+        fn visit_u32(&mut self, x: &mut u32) -> ControlFlow<Infallible> {
+            *x += 1;
+            Continue(())
+        }
+    }
+
+    let mut scope = Scope {
+        locals: [("x".to_owned(), 1), ("y".to_owned(), 2)]
+            .into_iter()
+            .collect(),
+        used: [1].into_iter().collect(),
+    };
+    let _ = scope.drive_inner_mut(&mut Incr);
+    let mut collect = Collect::default();
+    let _ = scope.drive_inner(&mut collect);
+    collect.names.sort();
+    collect.nums.sort();
+    assert_eq!(collect.names, ["x", "y"]);
+    assert_eq!(collect.nums, [2, 3]);
+
+    let mut collect = Collect::default();
+    let _ = collect.visit(&scope.used);
+    assert_eq!(collect.nums, [1]);
+
+    // The type-erased layer goes through the same impls.
+    struct IncrAny;
+    impl AnyVisitorMut for IncrAny {
+        fn enter(&mut self, x: &mut dyn DriveAnyMut) -> ControlFlow<(), Children> {
+            if let Some(x) = x.downcast_mut::<u32>() {
+                *x += 1;
+            }
+            Continue(Children::Visit)
+        }
+    }
+    let _ = drive_any_mut(&mut scope, &mut IncrAny);
+    fn entered<T>(e: Event<T>) -> Option<T> {
+        match e {
+            Event::Enter(x) => Some(x),
+            _ => None,
+        }
+    }
+    let mut nums: Vec<u32> = events(&scope)
+        .of_type::<u32>()
+        .filter_map(entered)
+        .copied()
+        .collect();
+    nums.sort();
+    assert_eq!(nums, [3, 4]);
+    let kinds: Vec<&str> = events(&scope)
+        .filter_map(entered)
+        .map(|x| x.kind())
+        .filter(|k| k.starts_with("Hash"))
+        .collect();
+    assert_eq!(kinds, ["HashMap"]);
+    // Sets are only visited immutably.
+    let _ = drive_any_mut(&mut scope.used, &mut IncrAny);
+    let nums: Vec<u32> = events(&scope.used)
+        .of_type::<u32>()
+        .filter_map(entered)
+        .copied()
+        .collect();
+    assert_eq!(nums, [1]);
+    assert_eq!(scope.used.kind(), "HashSet");
+}
+
+#[test]
+fn test_fallible_visitor() {
+    #[derive(Drive)]
+    struct Func {
+        name: String,
+        params: Vec<Param>,
+    }
+    #[derive(Drive)]
+    struct Param {
+        name: String,
+        arity: u32,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        EmptyName,
+        BadArity(u32),
+    }
+
+    /// Checks that names are non-empty and arities small.
+    #[derive(Visitor, Visit)]
+    #[visit(fallible(Error))]
+    #[visit(drive(Func, Vec<Param>, Param), enter(String), u32)]
+    struct Validate;
+    impl Validate {
+        fn enter_string(&mut self, x: &str) -> Result<(), Error> {
+            if x.is_empty() {
+                return Err(Error::EmptyName);
+            }
+            Ok(())
+        }
+        fn visit_u32(&mut self, x: &u32) -> Result<(), Error> {
+            check_arity(*x)?;
+            Ok(())
+        }
+    }
+    fn check_arity(x: u32) -> Result<(), Error> {
+        if x > 3 {
+            Err(Error::BadArity(x))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn validate(f: &Func) -> Result<usize, Error> {
+        Validate.try_visit(f)?;
+        Ok(f.params.len())
+    }
+
+    let param = |name: &str, arity| Param {
+        name: name.to_owned(),
+        arity,
+    };
+    let f = Func {
+        name: "f".to_owned(),
+        params: vec![param("x", 1), param("y", 2)],
+    };
+    assert_eq!(validate(&f), Ok(2));
+    let f = Func {
+        name: "f".to_owned(),
+        params: vec![param("x", 7), param("", 2)],
+    };
+    assert_eq!(validate(&f), Err(Error::BadArity(7)));
+    assert_eq!(Validate.visit(&f.params[1]), Break(Error::EmptyName));
+}
+
+#[test]
+fn test_memoize() {
+    use std::sync::Arc;
+
+    /// Interned types: equal types share the same allocation.
+    #[derive(Drive)]
+    enum Ty {
+        Int,
+        Ref(Arc<Ty>),
+        Tuple(Vec<Arc<Ty>>),
+    }
+
+    /// Counts the `Int`s in a type, visiting each interned type only once.
+    #[derive(Default, Visitor)]
+    struct CountInts {
+        count: usize,
+        /// The number of `Int`s in each type already visited.
+        memo: Memo<usize>,
+        /// The number of types visited, for testing.
+        visited: usize,
+    }
+    impl Memoize<usize> for CountInts {
+        fn memo(&mut self) -> &mut Memo<usize> {
+            &mut self.memo
+        }
+    }
+    impl Visit<'_, Vec<Arc<Ty>>> for CountInts {
+        fn visit(&mut self, x: &Vec<Arc<Ty>>) -> ControlFlow<Infallible> {
+            x.drive_inner(self)
+        }
+    }
+    impl Visit<'_, Arc<Ty>> for CountInts {
+        fn visit(&mut self, ty: &Arc<Ty>) -> ControlFlow<Infallible> {
+            let ints = self.memoized(NodeAddr::of(&**ty), |this| {
+                this.visited += 1;
+                let before = this.count;
+                if let Ty::Int = **ty {
+                    this.count += 1;
+                }
+                (**ty).drive_inner(this)?;
+                let ints = this.count - before;
+                this.count = before;
+                Continue(ints)
+            })?;
+            self.count += ints;
+            Continue(())
+        }
+    }
+
+    let int = Arc::new(Ty::Int);
+    let pair = Arc::new(Ty::Tuple(vec![int.clone(), Arc::new(Ty::Ref(int.clone()))]));
+    let quad = Arc::new(Ty::Tuple(vec![pair.clone(), pair.clone()]));
+    let tys = vec![quad.clone(), quad, pair];
+
+    let mut v = CountInts::default();
+    let _ = v.visit(&tys);
+    assert_eq!(v.count, 4 + 4 + 2);
+    // `int`, `Ref(int)`, `pair` and `quad`.
+    assert_eq!(v.visited, 4);
+    assert_eq!(v.memo.len(), 4);
+    assert_eq!(v.memo.get(&NodeAddr::of(&*int)), Some(&1));
+}
+
+#[test]
+fn test_drive_edit() {
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, DriveMut)]
+    enum Stmt {
+        Nop,
+        Assign(String, u32),
+        Block(Vec<Stmt>),
+        /// A block whose statements belong to the enclosing block.
+        Inline(Vec<Stmt>),
+    }
+    use Stmt::*;
+
+    /// Removes no-ops and empty blocks, and splices inline blocks into their parent.
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Stmt), override(stmts: Vec<Stmt>), skip(String, u32))]
+    struct Cleanup;
+    impl Cleanup {
+        fn visit_stmts(&mut self, x: &mut Vec<Stmt>) -> ControlFlow<Infallible> {
+            x.drive_edit(self)
+        }
+    }
+    impl VisitEdit<Stmt> for Cleanup {
+        fn visit_element(&mut self, x: &mut Stmt) -> ControlFlow<Infallible, Edit<Stmt>> {
+            self.visit(x)?;
+            Continue(match x {
+                Nop => Edit::Remove,
+                Block(stmts) if stmts.is_empty() => Edit::Remove,
+                Inline(stmts) => Edit::ReplaceWith(std::mem::take(stmts)),
+                _ => Edit::Keep,
+            })
+        }
+    }
+
+    let assign = |n| Assign("x".into(), n);
+    let mut block = Block(vec![
+        assign(0),
+        Nop,
+        Inline(vec![assign(1), Nop, Inline(vec![assign(2)])]),
+        Block(vec![Nop, Block(vec![])]),
+        assign(3),
+    ]);
+    let _ = Cleanup.visit(&mut block);
+    assert_eq!(
+        block,
+        Block(vec![assign(0), assign(1), assign(2), assign(3)])
+    );
+
+    /// Removes zeroes, caps values at 10, splits 7 into 3 and 4 and stops at the first 99.
+    struct Cap;
+    impl Visitor for Cap {
+        type Break = ();
+    }
+    impl VisitEdit<u32> for Cap {
+        fn visit_element(&mut self, x: &mut u32) -> ControlFlow<(), Edit<u32>> {
+            Continue(match *x {
+                0 => Edit::Remove,
+                99 => return Break(()),
+                n if n > 10 => Edit::ReplaceWith(vec![10]),
+                7 => Edit::ReplaceWith(vec![3, 4]),
+                _ => Edit::Keep,
+            })
+        }
+    }
+
+    let mut opt = Some(0);
+    assert_eq!(opt.drive_edit(&mut Cap), Continue(()));
+    assert_eq!(opt, None);
+    let mut opt = Some(42);
+    assert_eq!(opt.drive_edit(&mut Cap), Continue(()));
+    assert_eq!(opt, Some(10));
+
+    let mut map: HashMap<&str, u32> = [("a", 0), ("b", 5), ("c", 20)].into();
+    assert_eq!(map.drive_edit(&mut Cap), Continue(()));
+    assert_eq!(map, [("b", 5), ("c", 10)].into());
+    let mut vec = vec![7, 5];
+    assert_eq!(vec.drive_edit(&mut Cap), Continue(()));
+    assert_eq!(vec, vec![3, 4, 5]);
+
+    // The edits requested before a break are applied.
+    let mut vec = vec![0, 20, 99, 0, 20];
+    assert_eq!(vec.drive_edit(&mut Cap), Break(()));
+    assert_eq!(vec, vec![10, 99, 0, 20]);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic = "cannot replace a single-slot element by 2 items"]
+fn test_drive_edit_several_items_in_option() {
+    struct Split;
+    impl Visitor for Split {
+        type Break = ();
+    }
+    impl VisitEdit<u32> for Split {
+        fn visit_element(&mut self, x: &mut u32) -> ControlFlow<(), Edit<u32>> {
+            Continue(Edit::ReplaceWith(vec![*x, *x]))
+        }
+    }
+    let _ = Some(7).drive_edit(&mut Split);
+}
+
+#[test]
+fn test_replace_with() {
+    #[derive(Debug, PartialEq, DriveMut)]
+    enum Expr {
+        Lit(u32),
+        Var(String),
+        Add(Box<Expr>, Box<Expr>),
+    }
+    use Expr::*;
+
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(for<T> Box<T>), exit(Expr), skip(u32, String))]
+    struct ConstFold;
+    impl ConstFold {
+        fn exit_expr(&mut self, x: &mut Expr) {
+            replace_with(x, |x| match x {
+                Add(a, b) => match (*a, *b) {
+                    (Lit(a), Lit(b)) => Lit(a + b),
+                    (a, b) => Add(Box::new(a), Box::new(b)),
+                },
+                x => x,
+            });
+        }
+    }
+
+    let mut x = Add(
+        Box::new(Add(Box::new(Lit(1)), Box::new(Lit(2)))),
+        Box::new(Add(Box::new(Var("x".into())), Box::new(Lit(3)))),
+    );
+    let _ = ConstFold.visit(&mut x);
+    assert_eq!(
+        x,
+        Add(
+            Box::new(Lit(3)),
+            Box::new(Add(Box::new(Var("x".into())), Box::new(Lit(3))))
+        )
+    );
+
+    let mut x = Var("x".into());
+    let old = replace_with_and_return(&mut x, |x| (Lit(0), x));
+    assert_eq!((x, old), (Lit(0), Var("x".into())));
+}
+
+#[test]
+fn test_scoped() {
+    #![allow(dead_code)]
+    enum Expr {
+        Lit(u32),
+        Var(String),
+        Let(String, Box<Expr>, Box<Expr>),
+        Add(Box<Expr>, Box<Expr>),
+    }
+    use Expr::*;
+
+    /// Reports the unbound variables of each expression, stopping at the first one in each.
+    #[derive(Default)]
+    struct Resolve {
+        scopes: ScopeStack<String>,
+        errors: Vec<String>,
+    }
+    impl Visitor for Resolve {
+        type Break = String;
+    }
+    impl Scoped<String> for Resolve {
+        fn scopes(&mut self) -> &mut ScopeStack<String> {
+            &mut self.scopes
+        }
+    }
+    impl Visit<'_, Box<Expr>> for Resolve {
+        fn visit(&mut self, x: &Box<Expr>) -> ControlFlow<String> {
+            self.visit(&**x)
+        }
+    }
+    impl Visit<'_, Expr> for Resolve {
+        fn visit(&mut self, x: &Expr) -> ControlFlow<String> {
+            match x {
+                Var(name) => {
+                    if self
+                        .scopes
+                        .find_map(|s| (s == name).then_some(()))
+                        .is_none()
+                    {
+                        return Break(name.clone());
+                    }
+                    Continue(())
+                }
+                Let(name, value, body) => {
+                    self.visit(value)?;
+                    self.in_scope(name.clone(), |this| this.visit(body))
+                }
+                Add(a, b) => {
+                    self.visit(a)?;
+                    self.visit(b)
+                }
+                Lit(_) => Continue(()),
+            }
+        }
+    }
+    impl Resolve {
+        fn check(&mut self, x: &Expr) {
+            if let Break(name) = self.visit(x) {
+                self.errors.push(name);
+            }
+        }
+    }
+
+    let var = |name: &str| Box::new(Var(name.into()));
+    let mut v = Resolve::default();
+    // let x = 0 in let z = x in (z + w)
+    let e1 = Let(
+        "x".into(),
+        Box::new(Lit(0)),
+        Box::new(Let("z".into(), var("x"), Box::new(Add(var("z"), var("w"))))),
+    );
+    v.check(&e1);
+    assert!(v.scopes.is_empty());
+    // `x` and `z` went out of scope.
+    v.check(&Add(var("x"), var("z")));
+    assert_eq!(v.errors, ["w", "x"]);
+
+    let mut v = Resolve::default();
+    v.check(&Let("x".into(), var("a"), var("x")));
+    v.check(&Let("a".into(), var("x"), var("a")));
+    assert_eq!(v.errors, ["a", "x"]);
+}
+
+#[test]
+fn test_visit_with() {
+    #[derive(Drive, DriveMut)]
+    enum Ty {
+        Int,
+        Param(#[drive(skip)] usize),
+        Fn(Vec<Ty>, Box<Ty>),
+    }
+
+    /// Checks that type parameters are in bounds.
+    struct CheckParams;
+    impl Visitor for CheckParams {
+        type Break = usize;
+    }
+    impl VisitWith<'_, usize, Box<Ty>> for CheckParams {
+        fn visit(&mut self, num_params: &usize, x: &Box<Ty>) -> ControlFlow<usize> {
+            drive_inner_with(x, num_params, self)
+        }
+    }
+    impl VisitWith<'_, usize, Vec<Ty>> for CheckParams {
+        fn visit(&mut self, num_params: &usize, x: &Vec<Ty>) -> ControlFlow<usize> {
+            drive_inner_with(x, num_params, self)
+        }
+    }
+    impl VisitWith<'_, usize, Ty> for CheckParams {
+        fn visit(&mut self, num_params: &usize, x: &Ty) -> ControlFlow<usize> {
+            match x {
+                Ty::Param(i) if i >= num_params => Break(*i),
+                _ => drive_inner_with(x, num_params, self),
+            }
+        }
+    }
+
+    /// Shifts the type parameters by the given amount.
+    struct Shift;
+    impl Visitor for Shift {
+        type Break = Infallible;
+    }
+    impl VisitMutWith<'_, usize, Box<Ty>> for Shift {
+        fn visit(&mut self, by: &usize, x: &mut Box<Ty>) -> ControlFlow<Infallible> {
+            drive_inner_mut_with(x, by, self)
+        }
+    }
+    impl VisitMutWith<'_, usize, Vec<Ty>> for Shift {
+        fn visit(&mut self, by: &usize, x: &mut Vec<Ty>) -> ControlFlow<Infallible> {
+            drive_inner_mut_with(x, by, self)
+        }
+    }
+    impl VisitMutWith<'_, usize, Ty> for Shift {
+        fn visit(&mut self, by: &usize, x: &mut Ty) -> ControlFlow<Infallible> {
+            if let Ty::Param(i) = x {
+                *i += by;
+            }
+            drive_inner_mut_with(x, by, self)
+        }
+    }
+
+    let mut ty = Ty::Fn(vec![Ty::Int, Ty::Param(0)], Box::new(Ty::Param(1)));
+    assert_eq!(CheckParams.visit(&2, &ty), Continue(()));
+    assert_eq!(CheckParams.visit(&1, &ty), Break(1));
+    let _ = Shift.visit(&1, &mut ty);
+    assert_eq!(CheckParams.visit(&2, &ty), Break(2));
+    assert_eq!(CheckParams.visit(&3, &ty), Continue(()));
+}
+
+#[test]
+fn test_visit_acc() {
+    use std::collections::HashMap;
+
+    #[derive(Drive)]
+    enum Stmt {
+        Assign(#[drive(skip)] &'static str, #[drive(skip)] Expr),
+        Block(Vec<Stmt>),
+        /// Runs either branch: variables are only known afterwards if both agree.
+        Choice(Box<Stmt>, Box<Stmt>),
+    }
+    enum Expr {
+        Lit(i64),
+        Var(&'static str),
+        Add(Box<Expr>, Box<Expr>),
+    }
+    use Expr::*;
+    use Stmt::*;
+
+    type Env = HashMap<&'static str, i64>;
+    fn eval(env: &Env, e: &Expr) -> Option<i64> {
+        match e {
+            Lit(n) => Some(*n),
+            Var(x) => env.get(x).copied(),
+            Add(a, b) => Some(eval(env, a)? + eval(env, b)?),
+        }
+    }
+
+    /// Computes the known values of variables after running some statements, and fails on the
+    /// use of an unknown variable.
+    struct Exec;
+    impl Visitor for Exec {
+        type Break = &'static str;
+    }
+    impl VisitAcc<'_, Env, Vec<Stmt>> for Exec {
+        fn visit(&mut self, env: Env, x: &Vec<Stmt>) -> ControlFlow<&'static str, Env> {
+            drive_inner_acc(x, env, self)
+        }
+    }
+    impl VisitAcc<'_, Env, Box<Stmt>> for Exec {
+        fn visit(&mut self, env: Env, x: &Box<Stmt>) -> ControlFlow<&'static str, Env> {
+            self.visit(env, &**x)
+        }
+    }
+    impl VisitAcc<'_, Env, Stmt> for Exec {
+        fn visit(&mut self, mut env: Env, x: &Stmt) -> ControlFlow<&'static str, Env> {
+            match x {
+                Assign(var, e) => {
+                    let Some(value) = eval(&env, e) else {
+                        return Break(var);
+                    };
+                    env.insert(var, value);
+                    Continue(env)
+                }
+                Block(_) => drive_inner_acc(x, env, self),
+                Choice(a, b) => {
+                    let left = self.visit(env.clone(), a)?;
+                    let right = self.visit(env, b)?;
+                    Continue(
+                        left.into_iter()
+                            .filter(|(k, v)| right.get(k) == Some(v))
+                            .collect(),
+                    )
+                }
+            }
+        }
+    }
+
+    let prog = Block(vec![
+        Assign("x", Lit(1)),
+        Assign("y", Add(Box::new(Var("x")), Box::new(Lit(2)))),
+        Choice(
+            Box::new(Block(vec![Assign("x", Lit(10)), Assign("z", Lit(0))])),
+            Box::new(Assign("z", Lit(0))),
+        ),
+    ]);
+    let env = Exec.visit(Env::new(), &prog);
+    assert_eq!(env, Continue([("y", 3), ("z", 0)].into()));
+
+    let prog = Block(vec![
+        Choice(Box::new(Assign("x", Lit(1))), Box::new(Assign("x", Lit(2)))),
+        Assign("y", Var("x")),
+    ]);
+    assert_eq!(Exec.visit(Env::new(), &prog), Break("y"));
+}
+
+#[test]
+fn test_drive_field_names() {
+    #[derive(Drive, DriveMut)]
+    #[drive(field_names)]
+    struct User {
+        name: String,
+        password: String,
+    }
+    #[derive(Drive, DriveMut)]
+    #[drive(field_names)]
+    enum Login {
+        Token(String),
+        Basic { user: User, retries: u32 },
+    }
+
+    /// Redacts the strings in `password` fields, and records the fields it goes through.
+    #[derive(Default, Visitor, VisitMut)]
+    #[visit(drive(User, Login), skip(u32), String)]
+    struct Redact {
+        fields: Vec<Field>,
+        seen: Vec<String>,
+    }
+    impl VisitField for Redact {
+        fn enter_field(&mut self, field: Field) {
+            self.fields.push(field);
+            self.seen.push(format!("{}.{}", field.ty, field.name));
+        }
+        fn exit_field(&mut self, _: Field) {
+            self.fields.pop();
+        }
+    }
+    impl Redact {
+        fn visit_string(&mut self, x: &mut String) -> ControlFlow<Infallible> {
+            if self.fields.last().is_some_and(|f| f.name == "password") {
+                *x = "***".into();
+            }
+            Continue(())
+        }
+    }
+
+    let mut login = Login::Basic {
+        user: User {
+            name: "ann".into(),
+            password: "hunter2".into(),
+        },
+        retries: 0,
+    };
+    let mut v = Redact::default();
+    let _ = v.visit(&mut login);
+    let Login::Basic { user, .. } = &login else {
+        unreachable!()
+    };
+    assert_eq!((&*user.name, &*user.password), ("ann", "***"));
+    assert_eq!(
+        v.seen,
+        [
+            "Login::Basic.user",
+            "User.name",
+            "User.password",
+            "Login::Basic.retries"
+        ]
+    );
+    assert!(v.fields.is_empty());
+
+    let mut v = Redact::default();
+    let _ = v.visit(&mut Login::Token("t".into()));
+    assert_eq!(v.seen, ["Login::Token.0"]);
+}
+
+#[test]
+fn test_drive_binder() {
+    /// A lambda term with de Bruijn indices.
+    #[derive(Debug, PartialEq, Drive, DriveMut)]
+    enum Term {
+        Var(#[drive(skip)] usize),
+        Lam(#[drive(binder)] Box<Term>),
+        App(Box<Term>, Box<Term>),
+        Let(Box<Term>, #[drive(binder)] Box<Term>),
+    }
+    use Term::*;
+
+    /// Shifts the variables that are free in the visited term.
+    #[derive(Visitor, VisitMut)]
+    #[visit(drive(Box<Term>), enter(Term))]
+    struct Shift {
+        by: usize,
+        depth: usize,
+    }
+    impl VisitBinder for Shift {
+        fn binder_depth(&mut self) -> &mut usize {
+            &mut self.depth
+        }
+    }
+    impl Shift {
+        fn enter_term(&mut self, x: &mut Term) {
+            if let Var(i) = x {
+                if *i >= self.depth {
+                    *i += self.by;
+                }
+            }
+        }
+    }
+
+    // let x = 0 in \y. x y 1
+    let mut t = Let(
+        Box::new(Var(0)),
+        Box::new(Lam(Box::new(App(
+            Box::new(App(Box::new(Var(1)), Box::new(Var(0)))),
+            Box::new(Var(2)),
+        )))),
+    );
+    let mut v = Shift { by: 3, depth: 0 };
+    let _ = v.visit(&mut t);
+    assert_eq!(v.depth, 0);
+    assert_eq!(
+        t,
+        Let(
+            Box::new(Var(3)),
+            Box::new(Lam(Box::new(App(
+                Box::new(App(Box::new(Var(1)), Box::new(Var(0)))),
+                Box::new(Var(5)),
+            )))),
+        )
+    );
+
+    /// A type whose contents are all under a binder.
+    #[derive(Drive)]
+    #[drive(binder)]
+    struct Bound(Box<Term>, Box<Term>);
+
+    /// Records the depth at which each variable is found, and stops at the first free one.
+    #[derive(Default)]
+    struct Depths {
+        depth: usize,
+        seen: Vec<(usize, usize)>,
+    }
+    impl Visitor for Depths {
+        type Break = usize;
+    }
+    impl VisitBinder for Depths {
+        fn binder_depth(&mut self) -> &mut usize {
+            &mut self.depth
+        }
+    }
+    impl<'a> Visit<'a, Box<Term>> for Depths {
+        fn visit(&mut self, x: &'a Box<Term>) -> ControlFlow<usize> {
+            if let Var(i) = **x {
+                self.seen.push((i, self.depth));
+                if i >= self.depth {
+                    return Break(i);
+                }
+            }
+            (**x).drive_inner(self)
+        }
+    }
+    impl<'a> Visit<'a, Bound> for Depths {
+        fn visit(&mut self, x: &'a Bound) -> ControlFlow<usize> {
+            x.drive_inner(self)
+        }
+    }
+
+    let bound = Bound(
+        Box::new(Lam(Box::new(Var(1)))),
+        Box::new(App(Box::new(Var(0)), Box::new(Var(1)))),
+    );
+    let mut v = Depths::default();
+    // The binder is left even though the visit breaks.
+    assert_eq!(v.visit(&bound), Break(1));
+    assert_eq!(v.depth, 0);
+    assert_eq!(v.seen, [(1, 2), (0, 1), (1, 1)]);
+}
+
+#[test]
+fn test_drive_binder_two_and_fold() {
+    /// A lambda term with de Bruijn indices.
+    #[derive(Debug, PartialEq, DriveTwo, DriveDiff, NodeKind, Fold)]
+    enum Term {
+        Var(usize),
+        Lam(#[drive(binder)] Box<Term>),
+        App(Box<Term>, Box<Term>),
+    }
+    use Term::*;
+
+    /// Records the depth at which each pair of variables is found.
+    #[derive(Default)]
+    struct Depths {
+        depth: usize,
+        seen: Vec<usize>,
+    }
+    impl Visitor for Depths {
+        type Break = ();
+    }
+    impl VisitBinder for Depths {
+        fn binder_depth(&mut self) -> &mut usize {
+            &mut self.depth
+        }
+    }
+    impl<'a> VisitTwo<'a, Box<Term>> for Depths {
+        fn visit(&mut self, x: &'a Box<Term>, y: &'a Box<Term>) -> ControlFlow<()> {
+            if let (Var(_), Var(_)) = (&**x, &**y) {
+                self.seen.push(self.depth);
+            }
+            (**x).drive_two_inner(y, self)
+        }
+    }
+    impl<'a> VisitTwo<'a, usize> for Depths {
+        fn visit(&mut self, _: &'a usize, _: &'a usize) -> ControlFlow<()> {
+            Continue(())
+        }
+    }
+
+    // \x. x (\y. y 0)
+    let t = Lam(Box::new(App(
+        Box::new(Var(0)),
+        Box::new(Lam(Box::new(App(Box::new(Var(0)), Box::new(Var(2)))))),
+    )));
+    let mut v = Depths::default();
+    assert_eq!(t.drive_two_inner(&t, &mut v), Continue(()));
+    assert_eq!(v.depth, 0);
+    assert_eq!(v.seen, [1, 2, 2]);
+
+    /// Shifts the variables that are free in the folded term.
+    struct Shift {
+        by: usize,
+        depth: usize,
+    }
+    impl Visitor for Shift {
+        type Break = Infallible;
+    }
+    impl VisitBinder for Shift {
+        fn binder_depth(&mut self) -> &mut usize {
+            &mut self.depth
+        }
+    }
+    impl VisitFold<Box<Term>> for Shift {
+        fn visit(&mut self, x: Box<Term>) -> ControlFlow<Infallible, Box<Term>> {
+            match *x {
+                Var(i) if i >= self.depth => Continue(Box::new(Var(i + self.by))),
+                x => Continue(Box::new(x.fold_inner(self)?)),
+            }
+        }
+    }
+    impl VisitFold<usize> for Shift {
+        fn visit(&mut self, x: usize) -> ControlFlow<Infallible, usize> {
+            Continue(x)
+        }
+    }
+
+    let mut v = Shift { by: 3, depth: 0 };
+    let Continue(shifted) = t.fold_inner(&mut v);
+    assert_eq!(v.depth, 0);
+    let expected = Lam(Box::new(App(
+        Box::new(Var(0)),
+        Box::new(Lam(Box::new(App(Box::new(Var(0)), Box::new(Var(5)))))),
+    )));
+    assert_eq!(shifted, expected);
+
+    // Diffing goes through `DriveTwo` and doesn't care about binders.
+    assert_eq!(diff(&shifted, &expected), vec![]);
+    let t = Lam(Box::new(Var(0)));
+    let diffs: Vec<String> = diff(&t, &Lam(Box::new(Var(1))))
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+    assert_eq!(diffs, ["Term::Lam/0:Box/0:Term::Var/0:usize: 0 != 1"]);
+}
+
+#[test]
+fn test_drive_resolve() {
+    use std::collections::HashMap;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+    struct ExprId(u32);
+    #[derive(Drive, DriveMut)]
+    enum Expr {
+        Lit(#[drive(skip)] u32),
+        Add(#[drive(resolve)] ExprId, #[drive(resolve)] ExprId),
+        Unknown(#[drive(resolve)] ExprId),
+    }
+    type Arena = HashMap<ExprId, Expr>;
+
+    /// Sums the literals of an expression, looking up the subexpressions in the arena.
+    struct Sum<'a> {
+        arena: &'a Arena,
+        sum: u32,
+    }
+    impl Visitor for Sum<'_> {
+        type Break = Infallible;
+    }
+    impl<'a> Resolve<'a, ExprId> for Sum<'a> {
+        type Node = Expr;
+        fn resolve(&mut self, id: &'a ExprId) -> Option<&'a Expr> {
+            self.arena.get(id)
+        }
+    }
+    impl<'a> Visit<'a, Expr> for Sum<'a> {
+        fn visit(&mut self, x: &'a Expr) -> ControlFlow<Infallible> {
+            if let Expr::Lit(n) = x {
+                self.sum += n;
+            }
+            x.drive_inner(self)
+        }
+    }
+
+    // 1 + (2 + ?)
+    let mut arena = Arena::new();
+    arena.insert(ExprId(0), Expr::Add(ExprId(1), ExprId(2)));
+    arena.insert(ExprId(1), Expr::Lit(1));
+    arena.insert(ExprId(2), Expr::Add(ExprId(3), ExprId(4)));
+    arena.insert(ExprId(3), Expr::Lit(2));
+    arena.insert(ExprId(4), Expr::Unknown(ExprId(42)));
+    let mut v = Sum {
+        arena: &arena,
+        sum: 0,
+    };
+    let _ = v.visit(&arena[&ExprId(0)]);
+    assert_eq!(v.sum, 3);
+
+    /// Mutable visitors see the ids.
+    struct Renumber;
+    impl Visitor for Renumber {
+        type Break = Infallible;
+    }
+    impl VisitMut<'_, ExprId> for Renumber {
+        fn visit(&mut self, x: &mut ExprId) -> ControlFlow<Infallible> {
+            x.0 += 10;
+            Continue(())
+        }
+    }
+    let mut root = Expr::Add(ExprId(1), ExprId(2));
+    let _ = root.drive_inner_mut(&mut Renumber);
+    assert!(matches!(root, Expr::Add(ExprId(11), ExprId(12))));
+}
+
+#[test]
+fn test_closure_visitor() {
+    let xs = vec![1u32, 2, 3, 4];
+    let mut sum = 0;
+    let mut add = |x: &u32| {
+        sum += x;
+        if sum > 5 {
+            Break(*x)
+        } else {
+            Continue(())
+        }
+    };
+    let result = drive_iter(
+        &xs,
+        &mut (&mut add as &mut dyn FnMut(&u32) -> ControlFlow<u32>),
+    );
+    assert_eq!(result, Break(3));
+    assert_eq!(sum, 6);
+
+    let mut xs = xs;
+    let mut double = |x: &mut u32| -> ControlFlow<Infallible> {
+        *x *= 2;
+        Continue(())
+    };
+    let f: &mut dyn FnMut(&mut u32) -> _ = &mut double;
+    let _ = drive_iter_mut(&mut xs, &mut { f });
+    assert_eq!(xs, [2, 4, 6, 8]);
+}
+
+#[test]
+fn test_drive_iter_pairs() {
+    /// A map from a crate without `Drive` impls.
+    struct AssocList<K, T>(Vec<(K, T)>);
+    impl<'s, K, T, V: Visit<'s, K> + Visit<'s, T>> Drive<'s, V> for AssocList<K, T> {
+        fn drive_inner(&'s self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_pairs(self.0.iter().map(|(k, x)| (k, x)), v)
+        }
+    }
+    impl<'s, K, T, V: Visit<'s, K> + VisitMut<'s, T>> DriveMut<'s, V> for AssocList<K, T> {
+        fn drive_inner_mut(&'s mut self, v: &mut V) -> ControlFlow<V::Break> {
+            drive_iter_pairs_mut(self.0.iter_mut().map(|(k, x)| (&*k, x)), v)
+        }
+    }
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+    impl Visitor for Log {
+        type Break = ();
+    }
+    impl<'a> Visit<'a, String> for Log {
+        fn visit(&mut self, x: &'a String) -> ControlFlow<()> {
+            self.0.push(x.clone());
+            Continue(())
+        }
+    }
+    impl<'a> Visit<'a, u32> for Log {
+        fn visit(&mut self, x: &'a u32) -> ControlFlow<()> {
+            self.0.push(x.to_string());
+            if *x == 0 {
+                return Break(());
+            }
+            Continue(())
+        }
+    }
+    impl<'a> VisitMut<'a, u32> for Log {
+        fn visit(&mut self, x: &'a mut u32) -> ControlFlow<()> {
+            *x += 1;
+            Continue(())
+        }
+    }
+
+    let mut map = AssocList(vec![("a".to_string(), 1u32), ("b".to_string(), 2)]);
+    let mut log = Log::default();
+    let _ = map.drive_inner(&mut log);
+    assert_eq!(log.0, ["a", "1", "b", "2"]);
+
+    let mut log = Log::default();
+    let _ = map.drive_inner_mut(&mut log);
+    assert_eq!(log.0, ["a", "b"]);
+    assert_eq!(map.0, [("a".to_string(), 2), ("b".to_string(), 3)]);
+
+    map.0[0].1 = 0;
+    let mut log = Log::default();
+    assert_eq!(map.drive_inner(&mut log), Break(()));
+    assert_eq!(log.0, ["a", "0"]);
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "testing")]
+use derive_generic_visitor::*;
+
+#[derive(Drive, DriveMut, DriveAny, DriveAnyMut, NodeKind)]
+enum Expr {
+    Lit(u32),
+    Neg(Box<Expr>),
+}
+
+#[test]
+fn test_traversal_recorder() {
+    let e = Expr::Neg(Box::new(Expr::Lit(1)));
+    let recorder = record_traversal(&e);
+    assert_eq!(recorder.entered(), ["Expr::Neg", "Box", "Expr::Lit", "u32"]);
+    assert_eq!(
+        recorder.events()[4..],
+        [
+            Event::Exit("u32"),
+            Event::Exit("Expr::Lit"),
+            Event::Exit("Box"),
+            Event::Exit("Expr::Neg"),
+        ]
+    );
+    recorder.assert_visited::<u32>();
+    assert!(!recorder.has_visited::<String>());
+
+    let mut e = e;
+    let mut recorder = TraversalRecorder::new();
+    let _ = drive_any_mut(&mut e, &mut recorder);
+    assert_eq!(recorder.events().len(), 8);
+    recorder.clear();
+    assert!(recorder.entered().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "no `alloc::string::String` was visited")]
+fn test_traversal_recorder_assert_visited() {
+    record_traversal(&Expr::Lit(1)).assert_visited::<String>();
+}
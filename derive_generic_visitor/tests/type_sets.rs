@@ -0,0 +1,59 @@
+use derive_generic_visitor::*;
+
+#[derive(Drive, DriveMut)]
+struct Span(usize, usize);
+
+#[derive(Drive, DriveMut)]
+enum Expr {
+    Var(String, Span),
+    Lit(u32, Span),
+    Add(Box<Expr>, Box<Expr>),
+}
+
+visit_types! {
+    /// Types that contain no expressions.
+    set Atoms = (usize, u32, String);
+    set AstLeaves = (Span, Atoms!);
+}
+
+#[test]
+fn test_type_set_in_derive() {
+    #[derive(Visitor, Visit)]
+    #[visit(drive(for<T> Box<T>), skip(AstLeaves!), enter(Expr))]
+    struct CountExprs(usize);
+    impl CountExprs {
+        fn enter_expr(&mut self, _: &Expr) {
+            self.0 += 1;
+        }
+    }
+
+    let e = Expr::Add(
+        Box::new(Expr::Var("x".into(), Span(0, 1))),
+        Box::new(Expr::Lit(1, Span(4, 5))),
+    );
+    assert_eq!(CountExprs(0).visit_by_val_infallible(&e).0, 3);
+}
+
+#[test]
+fn test_type_set_in_group() {
+    #[visitable_group(
+        visitor(drive_mut(&mut AstVisitorMut), infallible),
+        skip(AstLeaves!),
+        drive(for<T: AstVisitable> Box<T>),
+        override(Expr),
+        closure_helpers,
+    )]
+    trait AstVisitable {}
+
+    let mut e = Expr::Add(
+        Box::new(Expr::Lit(1, Span(0, 1))),
+        Box::new(Expr::Lit(2, Span(4, 5))),
+    );
+    e.map_each_expr_mut(|e| {
+        if let Expr::Lit(n, _) = e {
+            *n *= 10
+        }
+    });
+    let Expr::Add(x, y) = e else { panic!() };
+    assert!(matches!((*x, *y), (Expr::Lit(10, _), Expr::Lit(20, _))));
+}
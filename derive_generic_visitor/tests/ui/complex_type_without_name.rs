@@ -0,0 +1,10 @@
+use derive_generic_visitor::*;
+
+#[derive(Drive)]
+struct Expr(u32);
+
+#[derive(Visitor, Visit)]
+#[visit(enter((Expr, u32)), skip(u32))]
+struct CountPairs(usize);
+
+fn main() {}
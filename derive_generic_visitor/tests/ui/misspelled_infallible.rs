@@ -0,0 +1,13 @@
+use derive_generic_visitor::*;
+
+#[derive(Drive)]
+struct Expr(u32);
+
+#[visitable_group(
+    visitor(drive(&AstVisitor), infaillible),
+    skip(u32),
+    override(Expr),
+)]
+trait AstVisitable {}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use derive_generic_visitor::*;
+
+#[derive(Drive)]
+struct Expr(u32);
+
+#[derive(Visitor, Visit)]
+#[visit(ovveride(Expr), skip(u32))]
+struct CountExprs(usize);
+
+fn main() {}
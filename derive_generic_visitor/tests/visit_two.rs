@@ -271,3 +271,78 @@ fn test_early_exit() {
     let mut v = StringEqVisitor;
     assert!(a.drive_two_inner(&c, &mut v).is_break());
 }
+
+/// Lockstep visitors can compare terms up to renaming of bound variables.
+#[test]
+fn test_alpha_equivalence() {
+    #[derive(DriveTwo)]
+    enum Term {
+        Var(String),
+        Lam(#[drive(skip)] String, Box<Term>),
+        App(Box<Term>, Box<Term>),
+    }
+
+    #[derive(VisitTwo)]
+    #[visit_two(drive(for<T> Box<T>), skip(String), Term)]
+    struct AlphaEq {
+        /// The pairs of variables bound so far, innermost last.
+        binders: Vec<(String, String)>,
+    }
+    impl Visitor for AlphaEq {
+        type Break = ();
+    }
+    impl AlphaEq {
+        fn visit_term(&mut self, a: &Term, b: &Term) -> ControlFlow<()> {
+            match (a, b) {
+                (Term::Var(x), Term::Var(y)) => {
+                    let bound = self
+                        .binders
+                        .iter()
+                        .rev()
+                        .find(|(bx, by)| bx == x || by == y);
+                    match bound {
+                        Some((bx, by)) if bx == x && by == y => Continue(()),
+                        None if x == y => Continue(()),
+                        _ => Break(()),
+                    }
+                }
+                (Term::Lam(x, _), Term::Lam(y, _)) => {
+                    self.binders.push((x.clone(), y.clone()));
+                    a.drive_two_inner(b, self)?;
+                    self.binders.pop();
+                    Continue(())
+                }
+                _ => a.drive_two_inner(b, self),
+            }
+        }
+    }
+    fn alpha_eq(a: &Term, b: &Term) -> bool {
+        let mut v = AlphaEq {
+            binders: Vec::new(),
+        };
+        VisitTwo::visit(&mut v, a, b).is_continue()
+    }
+
+    let var = |x: &str| Box::new(Term::Var(x.into()));
+    let lam = |x: &str, body| Box::new(Term::Lam(x.into(), body));
+    let app = |f, x| Box::new(Term::App(f, x));
+
+    // \x. \y. x y z
+    let a = lam("x", lam("y", app(app(var("x"), var("y")), var("z"))));
+    // \a. \b. a b z
+    let b = lam("a", lam("b", app(app(var("a"), var("b")), var("z"))));
+    // \a. \b. b a z
+    let c = lam("a", lam("b", app(app(var("b"), var("a")), var("z"))));
+    // \y. \y. y y z
+    let d = lam("y", lam("y", app(app(var("y"), var("y")), var("z"))));
+    assert!(alpha_eq(&a, &b));
+    assert!(!alpha_eq(&a, &c));
+    assert!(!alpha_eq(&a, &d));
+    // Free variables must match exactly.
+    assert!(!alpha_eq(
+        &a,
+        &lam("x", lam("y", app(app(var("x"), var("y")), var("w"))))
+    ));
+    // So must the shape.
+    assert!(!alpha_eq(&a, &lam("x", var("x"))));
+}
@@ -116,7 +116,7 @@ fn visitable_group_with_super_bounds() {
     ]);
 
     let mut visitor = CollectVars {
-        arena: arena,
+        arena,
         vars: vec![],
     };
     visitor.visit(&Expr(4));
@@ -224,3 +224,616 @@ fn visitable_group_two_override_skip() {
     assert!(v.visit(&a, &c).is_break());
     assert!(v.called);
 }
+
+#[test]
+fn visitable_group_strict() {
+    #[derive(Drive, DriveMut)]
+    enum Expr {
+        Literal(usize),
+        Neg(Box<Expr>),
+        Call(Path, Vec<Expr>),
+    }
+    #[derive(Drive, DriveMut)]
+    struct Path(Vec<String>);
+
+    // Forgetting e.g. `String` here would be a compile error.
+    #[visitable_group(
+        visitor(drive(&AstVisitor)),
+        visitor(drive_mut(&mut AstVisitorMut)),
+        strict,
+        skip(usize, String),
+        drive(Path, for<T: AstVisitable> Box<T>, for<T: AstVisitable> Vec<T>),
+        override(Expr),
+    )]
+    trait AstVisitable {}
+
+    #[derive(Visitor)]
+    struct CountCalls(usize);
+    impl AstVisitor for CountCalls {
+        fn enter_expr(&mut self, x: &Expr) {
+            if let Expr::Call(..) = x {
+                self.0 += 1;
+            }
+        }
+    }
+
+    let expr = Expr::Call(
+        Path(vec!["f".into()]),
+        vec![
+            Expr::Literal(0),
+            Expr::Neg(Box::new(Expr::Call(Path(vec![]), vec![]))),
+        ],
+    );
+    assert_eq!(CountCalls(0).visit_by_val_infallible(&expr).0, 2);
+}
+
+#[test]
+fn visitable_group_for_each() {
+    #[derive(Drive)]
+    enum Expr {
+        Literal(usize),
+        Var(Id),
+        Add(Box<Expr>, Box<Expr>),
+    }
+    #[derive(Drive)]
+    struct Id(String);
+
+    #[visitable_group(
+        visitor(drive(&AstVisitor)),
+        skip(usize, String),
+        drive(for<T: AstVisitable> Box<T>),
+        override(Expr, Id),
+        closure_helpers,
+    )]
+    trait AstVisitable {}
+
+    let expr = Expr::Add(
+        Box::new(Expr::Var(Id("x".into()))),
+        Box::new(Expr::Add(
+            Box::new(Expr::Literal(1)),
+            Box::new(Expr::Var(Id("y".into()))),
+        )),
+    );
+
+    let mut names = vec![];
+    expr.for_each_id(|id| names.push(id.0.clone()));
+    assert_eq!(names, ["x", "y"]);
+
+    let mut count = 0;
+    expr.for_each_expr(|_| count += 1);
+    assert_eq!(count, 5);
+
+    let first_literal = expr.try_for_each_expr(|e| match e {
+        Expr::Literal(n) => Break(*n),
+        _ => Continue(()),
+    });
+    assert_eq!(first_literal, Break(1));
+}
+
+#[test]
+fn visitable_group_map_mut() {
+    #[derive(Debug, PartialEq, Drive, DriveMut)]
+    enum Expr {
+        Literal(usize),
+        Add(Box<Expr>, Box<Expr>),
+    }
+
+    #[visitable_group(
+        visitor(drive_mut(&mut AstVisitorMut), infallible),
+        skip(usize),
+        drive(for<T: AstVisitable> Box<T>),
+        override(Expr),
+        closure_helpers,
+    )]
+    trait AstVisitable {}
+
+    let mut expr = Expr::Add(
+        Box::new(Expr::Literal(1)),
+        Box::new(Expr::Add(
+            Box::new(Expr::Literal(2)),
+            Box::new(Expr::Literal(3)),
+        )),
+    );
+    // Constant folding: since the closure is called bottom-up, the contents are already folded.
+    expr.map_each_expr_mut(|e| {
+        if let Expr::Add(x, y) = e {
+            if let (Expr::Literal(x), Expr::Literal(y)) = (&**x, &**y) {
+                *e = Expr::Literal(x + y);
+            }
+        }
+    });
+    assert_eq!(expr, Expr::Literal(6));
+}
+
+#[test]
+fn visitable_group_closure_helpers_in_one_scope() {
+    #[derive(Drive, DriveMut)]
+    enum Expr {
+        Literal(usize),
+        Neg(Box<Expr>),
+    }
+
+    // Both groups override `Expr` and get `for_each_expr`, as methods of their own trait.
+    #[visitable_group(
+        visitor(drive(&AstVisitor), infallible),
+        skip(usize),
+        drive(Box<Expr>),
+        override(Expr),
+        closure_helpers,
+    )]
+    trait AstVisitable {}
+
+    #[visitable_group(
+        visitor(drive(&OtherVisitor), infallible),
+        visitor(drive_mut(&mut OtherVisitorMut), infallible),
+        skip(usize),
+        drive(Box<Expr>),
+        override(Expr),
+        closure_helpers,
+    )]
+    trait OtherVisitable {}
+
+    // A user function of the same name doesn't clash either.
+    fn for_each_expr() {}
+    for_each_expr();
+
+    let mut expr = Expr::Neg(Box::new(Expr::Literal(1)));
+    let mut count = 0;
+    AstVisitable::for_each_expr(&expr, |_| count += 1);
+    OtherVisitable::for_each_expr(&expr, |_| count += 1);
+    assert_eq!(count, 4);
+    expr.map_each_expr_mut(|e| {
+        if let Expr::Literal(n) = e {
+            *n += 1
+        }
+    });
+    assert!(matches!(expr, Expr::Neg(x) if matches!(*x, Expr::Literal(2))));
+}
+
+#[test]
+fn visitable_group_extends() {
+    #[derive(Drive)]
+    enum Expr {
+        Var(String),
+        Neg(Box<Expr>),
+    }
+
+    #[visitable_group(
+        visitor(drive_expr(&ExprVisitor), infallible),
+        skip(String),
+        drive(Box<Expr>),
+        override(Expr),
+        closure_helpers,
+    )]
+    trait ExprVisitable {}
+
+    #[derive(Drive)]
+    enum Stmt {
+        Let(String, Expr),
+        Block(Vec<Stmt>),
+    }
+
+    // The types of `ExprVisitable` are visitable by `StmtVisitor` too.
+    #[visitable_group(
+        extends(ExprVisitable),
+        visitor(drive_stmt(&StmtVisitor), infallible, extends(drive_expr(&ExprVisitor))),
+        drive(Vec<Stmt>),
+        override(Stmt),
+        closure_helpers,
+    )]
+    trait StmtVisitable {}
+
+    #[derive(Default)]
+    struct Count {
+        stmts: usize,
+        exprs: usize,
+    }
+    impl ExprVisitor for Count {
+        fn enter_expr(&mut self, _: &Expr) {
+            self.exprs += 1;
+        }
+    }
+    impl StmtVisitor for Count {
+        fn enter_stmt(&mut self, _: &Stmt) {
+            self.stmts += 1;
+        }
+    }
+
+    let stmt = Stmt::Block(vec![
+        Stmt::Let("x".into(), Expr::Neg(Box::new(Expr::Var("y".into())))),
+        Stmt::Block(vec![Stmt::Let("z".into(), Expr::Var("x".into()))]),
+    ]);
+    // The base and extension visitor traits have methods of the same name.
+    let count = StmtVisitor::visit_by_val(Count::default(), &stmt);
+    assert_eq!(count.stmts, 4);
+    assert_eq!(count.exprs, 3);
+
+    let mut exprs = 0;
+    Expr::Neg(Box::new(Expr::Var("y".into()))).for_each_expr(|_| exprs += 1);
+    assert_eq!(exprs, 2);
+    let mut stmts = 0;
+    stmt.for_each_stmt(|_| stmts += 1);
+    assert_eq!(stmts, 4);
+}
+
+#[test]
+fn visitable_group_try_visit() {
+    #[derive(Drive)]
+    enum Expr {
+        Var(String),
+        Add(Box<Expr>, Box<Expr>),
+    }
+
+    #[visitable_group(
+        visitor(drive(&AstVisitor), infallible),
+        skip(String),
+        drive(Box<Expr>),
+        override(Expr),
+    )]
+    trait AstVisitable {}
+
+    /// Counts variables, stopping at the first variable named `stop`.
+    #[derive(Default)]
+    struct CountVars {
+        count: usize,
+        stopped_at: Option<usize>,
+    }
+    impl AstVisitor for CountVars {
+        fn should_stop(&self) -> bool {
+            self.stopped_at.is_some()
+        }
+        fn enter_expr(&mut self, x: &Expr) {
+            if let Expr::Var(name) = x {
+                if name == "stop" {
+                    self.stopped_at = Some(self.count);
+                }
+                self.count += 1;
+            }
+        }
+    }
+
+    let var = |name: &str| Box::new(Expr::Var(name.into()));
+    let expr = Expr::Add(
+        var("a"),
+        Box::new(Expr::Add(
+            Box::new(Expr::Add(var("stop"), var("b"))),
+            var("c"),
+        )),
+    );
+    let mut v = CountVars::default();
+    assert_eq!(v.try_visit(&expr, |v| v.stopped_at.take()), Break(1));
+    // Nothing is visited after `stop`.
+    assert_eq!(v.count, 2);
+    assert_eq!(
+        CountVars::default().try_visit_inner(&*var("a"), |v| v.stopped_at.take()),
+        Continue(())
+    );
+    let expr = Expr::Add(var("a"), var("b"));
+    let mut v = CountVars::default();
+    assert_eq!(v.try_visit(&expr, |v| v.stopped_at.take()), Continue(()));
+    assert_eq!(v.count, 2);
+}
+
+#[test]
+fn visitable_group_override_tuple_and_array() {
+    #[derive(Drive)]
+    enum Expr {
+        Lit(u32),
+        Pair(Box<(Expr, Ty)>),
+        Call([Box<Expr>; 2]),
+    }
+    #[derive(Drive)]
+    struct Ty(String);
+
+    #[visitable_group(
+        visitor(drive(&AstVisitor), infallible),
+        skip(u32, String),
+        drive(Box<Expr>, Box<(Expr, Ty)>, Ty, Expr),
+        override(pair: (Expr, Ty), args: [Box<Expr>; 2]),
+    )]
+    trait AstVisitable {}
+
+    #[derive(Default)]
+    struct Count {
+        pairs: usize,
+        args: usize,
+    }
+    impl AstVisitor for Count {
+        fn enter_pair(&mut self, _: &(Expr, Ty)) {
+            self.pairs += 1;
+        }
+        fn visit_args(&mut self, x: &[Box<Expr>; 2]) {
+            self.args += 1;
+            // Only look at the first argument.
+            self.visit(&x[0]);
+        }
+    }
+
+    let pair = |e| Expr::Pair(Box::new((e, Ty("u32".into()))));
+    let call = |a, b| Expr::Call([Box::new(a), Box::new(b)]);
+    let expr = call(pair(Expr::Lit(0)), call(pair(Expr::Lit(1)), Expr::Lit(2)));
+    let count = Count::default().visit_by_val(&expr);
+    assert_eq!(count.pairs, 1);
+    assert_eq!(count.args, 1);
+}
+
+#[test]
+fn visitable_group_synthesized() {
+    #[derive(Drive, DriveMut)]
+    enum Expr {
+        Lit(u32),
+        Var(String),
+        Add(Box<Expr>, Box<Expr>),
+        Let(String, Box<Expr>, Box<Expr>),
+    }
+
+    #[visitable_group(
+        visitor(drive(&AstVisitor)),
+        visitor(drive_free_vars(&FreeVarsVisitor), synthesized),
+        visitor(drive_mut_size(&mut SizeVisitor), synthesized),
+        skip(u32, String),
+        drive(Box<Expr>),
+        override(Expr),
+    )]
+    trait AstVisitable {}
+
+    /// Computes the free variables of an expression.
+    struct FreeVars;
+    impl FreeVarsVisitor for FreeVars {
+        type Output = Vec<String>;
+        fn combine(&mut self, mut acc: Vec<String>, x: Vec<String>) -> Vec<String> {
+            acc.extend(x);
+            acc
+        }
+        fn visit_expr(&mut self, x: &Expr) -> Vec<String> {
+            match x {
+                Expr::Var(name) => vec![name.clone()],
+                Expr::Let(name, val, body) => {
+                    let mut vars = self.visit(val);
+                    vars.extend(self.visit(body).into_iter().filter(|v| v != name));
+                    vars
+                }
+                _ => self.visit_inner(x),
+            }
+        }
+    }
+
+    /// Counts the nodes of an expression, incrementing literals on the way.
+    struct Size;
+    impl SizeVisitor for Size {
+        type Output = usize;
+        fn combine(&mut self, acc: usize, x: usize) -> usize {
+            acc + x
+        }
+        fn enter_expr(&mut self, x: &mut Expr) {
+            if let Expr::Lit(n) = x {
+                *n += 1;
+            }
+        }
+        fn visit_expr(&mut self, x: &mut Expr) -> usize {
+            self.enter_expr(x);
+            1 + self.visit_inner(x)
+        }
+    }
+
+    let var = |name: &str| Box::new(Expr::Var(name.into()));
+    let mut expr = Expr::Let(
+        "x".into(),
+        Box::new(Expr::Add(var("y"), Box::new(Expr::Lit(0)))),
+        Box::new(Expr::Add(var("x"), var("z"))),
+    );
+    assert_eq!(FreeVars.visit(&expr), ["y", "z"]);
+    assert_eq!(Size.visit(&mut expr), 7);
+    let Expr::Let(_, val, _) = &expr else {
+        unreachable!()
+    };
+    let Expr::Add(_, lit) = &**val else {
+        unreachable!()
+    };
+    assert!(matches!(**lit, Expr::Lit(1)));
+}
+
+#[test]
+fn visitable_group_node_ids() {
+    #[derive(Drive, DriveMut)]
+    enum Expr {
+        Lit(u32),
+        Add(Box<Expr>, Box<Expr>),
+    }
+
+    #[visitable_group(
+        visitor(drive(&AstVisitor), infallible),
+        visitor(drive_mut(&mut AstVisitorMut), infallible),
+        skip(u32),
+        drive(Box<Expr>),
+        override(Expr),
+        node_ids,
+        closure_helpers,
+    )]
+    trait AstVisitable {}
+
+    /// Records the depth of each expression.
+    #[derive(Default)]
+    struct Depths {
+        ids: NodeIds,
+        depths: SideTable<usize>,
+    }
+    impl AstVisitor for Depths {
+        fn node_ids(&mut self) -> &mut NodeIds {
+            &mut self.ids
+        }
+        fn enter_expr(&mut self, _: &Expr) {
+            let depth = self
+                .ids
+                .parent()
+                .map_or(0, |parent| self.depths[&parent] + 1);
+            self.depths.insert(self.ids.current().unwrap(), depth);
+        }
+    }
+
+    /// Replaces each literal by its depth, as computed by a previous pass.
+    struct ApplyDepths {
+        ids: NodeIds,
+        depths: SideTable<usize>,
+    }
+    impl AstVisitorMut for ApplyDepths {
+        fn node_ids(&mut self) -> &mut NodeIds {
+            &mut self.ids
+        }
+        fn enter_expr(&mut self, x: &mut Expr) {
+            if let Expr::Lit(n) = x {
+                *n = self.depths[&self.ids.current().unwrap()] as u32;
+            }
+        }
+    }
+
+    let lit = |n| Box::new(Expr::Lit(n));
+    let mut expr = Expr::Add(lit(0), Box::new(Expr::Add(lit(0), lit(0))));
+    let mut depths = Depths::default();
+    depths.visit(&expr);
+    assert_eq!(depths.ids.count(), 5);
+    assert_eq!(depths.depths[&NodeId(0)], 0);
+    assert_eq!(depths.depths[&NodeId(3)], 2);
+
+    ApplyDepths {
+        ids: NodeIds::new(),
+        depths: depths.depths,
+    }
+    .visit(&mut expr);
+    let mut lits = vec![];
+    AstVisitable::for_each_expr(&expr, |x| {
+        if let Expr::Lit(n) = x {
+            lits.push(*n)
+        }
+    });
+    assert_eq!(lits, [1, 2, 2]);
+}
+
+#[test]
+fn visitable_group_ctx() {
+    #[derive(Debug, PartialEq, Drive, DriveMut)]
+    enum Expr {
+        Lit(u32),
+        Var(String),
+        Add(Box<Expr>, Box<Expr>),
+        Let(String, Box<Expr>, Box<Expr>),
+    }
+    /// The variables in scope.
+    type Scope = Vec<String>;
+
+    #[visitable_group(
+        visitor(drive_check(&CheckVisitor), ctx(Scope)),
+        visitor(drive_mut_number(&mut NumberVisitor), ctx(Scope), infallible),
+        skip(u32, String),
+        drive(Box<Expr>),
+        override(Expr),
+    )]
+    trait AstVisitable {}
+
+    /// Fails on the first unbound variable.
+    struct Check;
+    impl Visitor for Check {
+        type Break = String;
+    }
+    impl CheckVisitor for Check {
+        fn visit_expr(&mut self, scope: &Scope, x: &Expr) -> ControlFlow<String> {
+            match x {
+                Expr::Var(name) if !scope.contains(name) => Break(name.clone()),
+                Expr::Let(name, val, body) => {
+                    self.visit(scope, val)?;
+                    let mut inner = scope.clone();
+                    inner.push(name.clone());
+                    self.visit(&inner, body)
+                }
+                _ => self.visit_inner(scope, x),
+            }
+        }
+    }
+
+    /// Replaces the variables of the scope by their position in it.
+    struct Number;
+    impl NumberVisitor for Number {
+        fn exit_expr(&mut self, scope: &Scope, x: &mut Expr) {
+            if let Expr::Var(name) = x {
+                if let Some(i) = scope.iter().position(|v| v == name) {
+                    *x = Expr::Lit(i as u32);
+                }
+            }
+        }
+    }
+
+    let var = |name: &str| Box::new(Expr::Var(name.into()));
+    let mut expr = Expr::Let(
+        "x".into(),
+        Box::new(Expr::Add(var("y"), var("z"))),
+        Box::new(Expr::Add(var("x"), var("z"))),
+    );
+    assert_eq!(Check.visit(&vec![], &expr), Break("y".into()));
+    assert_eq!(Check.visit(&vec!["y".into()], &expr), Break("z".into()));
+    assert_eq!(
+        Check.visit(&vec!["y".into(), "z".into()], &expr),
+        Continue(())
+    );
+
+    Number.visit(&vec!["z".into(), "y".into()], &mut expr);
+    let lit = |n| Box::new(Expr::Lit(n));
+    assert_eq!(
+        expr,
+        Expr::Let(
+            "x".into(),
+            Box::new(Expr::Add(lit(1), lit(0))),
+            Box::new(Expr::Add(var("x"), lit(0))),
+        )
+    );
+}
+
+#[test]
+fn visitable_group_binder() {
+    #[derive(Debug, PartialEq, Drive, DriveMut)]
+    enum Ty {
+        Bound(#[drive(skip)] usize),
+        Fn(Box<Ty>, Box<Ty>),
+        Forall(#[drive(binder)] Box<Ty>),
+    }
+
+    #[visitable_group(
+        visitor(drive_mut(&mut TyVisitor), infallible, bounds(VisitBinder)),
+        drive(Box<Ty>),
+        override(Ty),
+    )]
+    trait TyVisitable {}
+
+    /// Replaces the variable bound by an outer `Forall` with `Ty::Bound(usize::MAX)`.
+    struct Instantiate {
+        depth: usize,
+    }
+    impl Visitor for Instantiate {
+        type Break = std::convert::Infallible;
+    }
+    impl VisitBinder for Instantiate {
+        fn binder_depth(&mut self) -> &mut usize {
+            &mut self.depth
+        }
+    }
+    impl TyVisitor for Instantiate {
+        fn enter_ty(&mut self, x: &mut Ty) {
+            if *x == Ty::Bound(self.depth) {
+                *x = Ty::Bound(usize::MAX);
+            }
+        }
+    }
+
+    let bound = |i| Box::new(Ty::Bound(i));
+    let mut ty = Ty::Fn(
+        bound(0),
+        Box::new(Ty::Forall(Box::new(Ty::Fn(bound(0), bound(1))))),
+    );
+    Instantiate { depth: 0 }.visit(&mut ty);
+    assert_eq!(
+        ty,
+        Ty::Fn(
+            bound(usize::MAX),
+            Box::new(Ty::Forall(Box::new(Ty::Fn(bound(0), bound(usize::MAX)))))
+        )
+    );
+}
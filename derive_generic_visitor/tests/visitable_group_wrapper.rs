@@ -142,7 +142,7 @@ fn test_visitor_wrapper() {
         }
     }
     impl ListVisitor for MyVisitor {
-        fn visit<'a, T: ListVisitable>(&'a mut self, x: &T) -> ControlFlow<Self::Break> {
+        fn visit<T: ListVisitable>(&mut self, x: &T) -> ControlFlow<Self::Break> {
             DepthWrapper(&mut SumWrapper(self)).visit(x)
         }
 
@@ -155,13 +155,13 @@ fn test_visitor_wrapper() {
     let slice = &[0, 1, 2, 3, 4, 5, 6];
     let list = List::from_list(slice);
     let visitor = MyVisitor::default().visit_by_val_infallible(&list);
-    assert_eq!(visitor.sum, slice.iter().sum());
+    assert_eq!(visitor.sum, slice.iter().sum::<u32>());
     assert_eq!(
         visitor.total,
         slice
             .iter()
             .enumerate()
             .map(|(i, val)| (i as u32 + 1) * val)
-            .sum()
+            .sum::<u32>()
     );
 }
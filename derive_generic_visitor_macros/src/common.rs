@@ -1,9 +1,12 @@
 use convert_case::{Boundary, Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
 use syn::{
     parse::{Parse, ParseStream},
     parse_quote,
+    punctuated::Punctuated,
     token::Mut,
-    Error, Generics, Ident, Lifetime, Path, Result, Token, Type,
+    Error, Generics, Ident, Lifetime, Meta, Path, Result, Token, Type,
 };
 
 /// Shared logic to get the important paths and identifiers for this crate.
@@ -71,8 +74,7 @@ impl Parse for GenericTy {
     fn parse(input: ParseStream) -> Result<Self> {
         let generics = if input.peek(Token![for]) {
             let _: Token![for] = input.parse()?;
-            let generics = input.parse()?;
-            generics
+            input.parse()?
         } else {
             Generics::default()
         };
@@ -106,18 +108,48 @@ impl NamedGenericTy {
                         ident.span(),
                     )
                 }
-                _ => {
+                ty => {
+                    let suggestion = suggest_name(ty).unwrap_or_else(|| "foo".to_owned());
                     return Err(Error::new_spanned(
-                        &self.ty.ty,
-                        "Cannot make up a method name for this type; \
-                        provide one by writing `foo: ` before the type",
-                    ))
+                        ty,
+                        format!(
+                            "Cannot make up a method name for this type; \
+                            provide one by writing `{suggestion}: ` before the type"
+                        ),
+                    ));
                 }
             },
         })
     }
 }
 
+/// Make up a method name for a type that doesn't have an obvious one, for use in error messages.
+/// Uses the last path segment, e.g. `expr_and_ty` for `(Expr, Ty)` and `exprs` for `[Expr; 2]`.
+fn suggest_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|seg| {
+            seg.ident
+                .to_string()
+                .from_case(Case::Pascal)
+                .without_boundaries(&[Boundary::UpperDigit, Boundary::LowerDigit])
+                .to_case(Case::Snake)
+        }),
+        Type::Tuple(tuple) => {
+            let names = tuple
+                .elems
+                .iter()
+                .map(suggest_name)
+                .collect::<Option<Vec<_>>>()?;
+            (!names.is_empty()).then(|| names.join("_and_"))
+        }
+        Type::Array(array) => suggest_name(&array.elem).map(|name| format!("{name}s")),
+        Type::Slice(slice) => suggest_name(&slice.elem).map(|name| format!("{name}s")),
+        Type::Reference(reference) => suggest_name(&reference.elem),
+        Type::Paren(paren) => suggest_name(&paren.elem),
+        _ => None,
+    }
+}
+
 impl Parse for NamedGenericTy {
     fn parse(input: ParseStream) -> Result<Self> {
         let name = if input.peek2(Token![:]) && !input.peek3(Token![:]) {
@@ -131,3 +163,84 @@ impl Parse for NamedGenericTy {
         })
     }
 }
+
+/// Attributes to put on generated items, given as `attr(cfg(feature = "foo"), allow(..))`.
+#[derive(Default)]
+pub struct ForwardedAttrs(pub Vec<Meta>);
+
+impl Parse for ForwardedAttrs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let metas: Punctuated<Meta, Token![,]> = Punctuated::parse_terminated(input)?;
+        Ok(ForwardedAttrs(metas.into_iter().collect()))
+    }
+}
+
+impl darling::FromMeta for ForwardedAttrs {
+    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
+        items
+            .iter()
+            .map(|item| match item {
+                darling::ast::NestedMeta::Meta(meta) => Ok(meta.clone()),
+                darling::ast::NestedMeta::Lit(lit) => Err(darling::Error::unexpected_lit_type(lit)),
+            })
+            .collect::<darling::Result<_>>()
+            .map(ForwardedAttrs)
+    }
+}
+
+impl ToTokens for ForwardedAttrs {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        for meta in &self.0 {
+            tokens.extend(quote!(#[#meta]));
+        }
+    }
+}
+
+/// Error for an unknown keyword in a macro argument, pointing at the keyword and suggesting the
+/// closest valid one if there's a plausible candidate.
+pub fn unknown_keyword(ident: &Ident, expected: &[&str]) -> Error {
+    let found = ident.to_string();
+    let closest = expected
+        .iter()
+        .map(|kw| (edit_distance(&found, kw), kw))
+        .min()
+        .filter(|(dist, kw)| *dist <= kw.len().max(3) / 3);
+    let msg = match closest {
+        Some((_, kw)) => format!("unknown option `{found}`; did you mean `{kw}`?"),
+        None => {
+            let expected: Vec<_> = expected.iter().map(|kw| format!("`{kw}`")).collect();
+            format!(
+                "unknown option `{found}`; expected one of {}",
+                expected.join(", ")
+            )
+        }
+    };
+    Error::new(ident.span(), msg)
+}
+
+/// Edit distance between two strings, counting insertions, deletions, substitutions and
+/// transpositions of adjacent characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // `d[i][j]` is the distance between `a[..i]` and `b[..j]`.
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, x) in d[0].iter_mut().enumerate() {
+        *x = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
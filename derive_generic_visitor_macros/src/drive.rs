@@ -4,7 +4,7 @@ use proc_macro2::{Span, TokenStream};
 use quote::{quote, ToTokens};
 use syn::{parse_quote, DeriveInput, GenericParam, Generics, Ident, Index, Path, Result, Type};
 
-use crate::Names;
+use crate::{ForwardedAttrs, Names};
 
 #[derive(FromDeriveInput)]
 #[darling(attributes(drive))]
@@ -13,6 +13,15 @@ struct MyTypeDecl {
     generics: Generics,
     data: Data<MyVariant, MyField>,
     skip: Option<()>,
+    /// Visit a `Discriminant` describing the active variant before its fields.
+    discriminant: Option<()>,
+    /// Tell the visitor the name of each field before and after visiting it, via `VisitField`.
+    field_names: Option<()>,
+    /// All the fields are under a binder, as if they were each marked `#[drive(binder)]`.
+    binder: Option<()>,
+    /// Attributes to put on the generated impls.
+    #[darling(multiple)]
+    attr: Vec<ForwardedAttrs>,
 }
 
 #[derive(FromVariant)]
@@ -29,6 +38,42 @@ struct MyField {
     ident: Option<Ident>,
     ty: Type,
     skip: Option<()>,
+    /// The field is under a binder: the visitor's `enter_binder` and `exit_binder` are called
+    /// around its visit.
+    binder: Option<()>,
+    /// The field is an id: `Drive` visits the node the visitor's `Resolve` impl looks it up to
+    /// instead. `DriveMut` visits the id itself.
+    resolve: Option<()>,
+}
+
+impl MyTypeDecl {
+    /// Whether some fields are under a binder, in which case the visitor must be a `VisitBinder`.
+    fn has_binders(&self) -> bool {
+        self.binder.is_some()
+            || match &self.data {
+                Data::Struct(fields) => fields.iter().any(|f| f.binder.is_some()),
+                Data::Enum(variants) => variants
+                    .iter()
+                    .flat_map(|v| v.fields.iter())
+                    .any(|f| f.binder.is_some()),
+            }
+    }
+}
+
+/// Wrap the visit of a field that is under a binder with calls to `enter_binder` and `exit_binder`
+/// on `visitor`. The binder is left even if the visit breaks, so that a visitor that recovers from
+/// the break keeps an accurate depth.
+fn under_binder(binder: bool, visitor: &Ident, visit: TokenStream) -> TokenStream {
+    if binder {
+        quote!({
+            ::derive_generic_visitor::VisitBinder::enter_binder(#visitor);
+            let result = #visit;
+            ::derive_generic_visitor::VisitBinder::exit_binder(#visitor);
+            result
+        })
+    } else {
+        visit
+    }
 }
 
 pub fn impl_drive(input: DeriveInput, mutable: bool) -> Result<TokenStream> {
@@ -64,27 +109,84 @@ pub fn impl_drive(input: DeriveInput, mutable: bool) -> Result<TokenStream> {
     where_clause
         .predicates
         .push(parse_quote!(#visitor_param: #visitor_trait));
-    // Adds a `V: Visit<'s, FieldTy>` clause for each field.
-    let mut need_visit_type = |f: &MyField| {
-        let field_ty = &f.ty;
+    let crate_path = quote!(::derive_generic_visitor);
+    if input.discriminant.is_some() {
+        // Mutable visitors get a reference to a temporary, hence the higher-ranked bound.
+        where_clause.predicates.push(if mutable {
+            parse_quote!(#visitor_param: for<'d> #visit_trait<'d, #crate_path::Discriminant>)
+        } else {
+            parse_quote!(#visitor_param: #visit_trait<#lifetime_param, #crate_path::Discriminant>)
+        });
+    }
+    if input.field_names.is_some() {
         where_clause
             .predicates
-            .push(parse_quote!(#visitor_param: #visit_trait<#lifetime_param, #field_ty>));
+            .push(parse_quote!(#visitor_param: #crate_path::VisitField));
+    }
+    let field_names = input.field_names.is_some();
+    let type_binder = input.binder.is_some();
+    if input.has_binders() {
+        where_clause
+            .predicates
+            .push(parse_quote!(#visitor_param: #crate_path::VisitBinder));
+    }
+    // Adds a `V: Visit<'s, FieldTy>` clause for each field, or `V: Visit<'s, Node>` for the node
+    // the field resolves to.
+    let mut need_visit_type = |f: &MyField| {
+        let field_ty = &f.ty;
+        if f.resolve.is_some() && !mutable {
+            let resolve = quote!(#crate_path::Resolve<#lifetime_param, #field_ty>);
+            where_clause
+                .predicates
+                .push(parse_quote!(#visitor_param: #resolve));
+            where_clause.predicates.push(parse_quote!(
+                #visitor_param: #visit_trait<#lifetime_param, <#visitor_param as #resolve>::Node>
+            ));
+        } else {
+            where_clause
+                .predicates
+                .push(parse_quote!(#visitor_param: #visit_trait<#lifetime_param, #field_ty>));
+        }
     };
 
     let arms = match input.data {
         _ if input.skip.is_some() => quote!(),
-        Data::Struct(fields) => {
-            match_variant(&names, parse_quote!(Self), fields.iter(), need_visit_type)
+        Data::Struct(_) if input.discriminant.is_some() => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "`#[drive(discriminant)]` is only supported on enums",
+            ))
         }
+        Data::Struct(fields) => match_variant(
+            &names,
+            parse_quote!(Self),
+            quote!(),
+            field_names.then(|| name.to_string()),
+            type_binder,
+            fields.iter(),
+            need_visit_type,
+        ),
         Data::Enum(variants) => variants
             .iter()
-            .filter(|variant| variant.skip.is_none())
-            .map(|variant| {
+            .enumerate()
+            .filter(|(_, variant)| variant.skip.is_none())
+            .map(|(index, variant)| {
                 let name = &variant.ident;
+                let visit_discriminant = input.discriminant.is_some().then(|| {
+                    let name = name.to_string();
+                    quote!(
+                        <#visitor_param as #visit_trait<#crate_path::Discriminant>>::visit(
+                            visitor,
+                            &#mut_modifier #crate_path::Discriminant { index: #index, name: #name },
+                        )?;
+                    )
+                });
                 match_variant(
                     &names,
                     parse_quote!(Self::#name),
+                    quote!(#visit_discriminant),
+                    field_names.then(|| format!("{}::{name}", input.ident)),
+                    type_binder,
                     variant.fields.iter(),
                     &mut need_visit_type,
                 )
@@ -92,8 +194,10 @@ pub fn impl_drive(input: DeriveInput, mutable: bool) -> Result<TokenStream> {
             .collect(),
     };
 
+    let attrs = &input.attr;
     let (impl_generics, _, where_clause) = generics.split_for_impl();
     Ok(quote! {
+        #(#attrs)*
         impl #impl_generics #drive_trait<#lifetime_param, #visitor_param> for #impl_subject
         #where_clause {
             #[inline]
@@ -111,10 +215,14 @@ pub fn impl_drive(input: DeriveInput, mutable: bool) -> Result<TokenStream> {
 }
 
 /// Generate a match arm that destructures the fields of the given variant and visits each of these
-/// fields.
+/// fields, after running `prefix`. With `field_names`, the name of the type or variant, each visit
+/// is surrounded by calls to `VisitField`. With `all_binders`, every field is under a binder.
 fn match_variant<'a>(
     names: &Names,
     name: Path,
+    prefix: TokenStream,
+    field_names: Option<String>,
+    all_binders: bool,
     fields: impl Iterator<Item = &'a MyField>,
     mut for_each_field: impl FnMut(&'a MyField),
 ) -> TokenStream {
@@ -135,27 +243,68 @@ fn match_variant<'a>(
                 None => Ident::new(&format!("i{}", index), Span::call_site()).into_token_stream(),
                 Some(name) => name.into_token_stream(),
             };
+            let crate_path = quote!(::derive_generic_visitor);
+            // Call `visitor.visit()` on the field, or on the node it resolves to.
+            let visit = if field.resolve.is_some() && names.mut_modifier.is_none() {
+                let lifetime_param = &names.lifetime_param;
+                let resolve = quote!(#crate_path::Resolve<#lifetime_param, #field_ty>);
+                quote!(
+                    match <#visitor_param as #resolve>::resolve(visitor, #var) {
+                        Some(node) => <#visitor_param as #visit_trait<
+                            <#visitor_param as #resolve>::Node
+                        >>::visit(visitor, node),
+                        None => ::std::ops::ControlFlow::Continue(()),
+                    }
+                )
+            } else {
+                quote!( <#visitor_param as #visit_trait<#field_ty>>::visit(visitor, #var) )
+            };
+            let binder = all_binders || field.binder.is_some();
+            let visit = under_binder(binder, &parse_quote!(visitor), visit);
+            let mut visit = quote!( #visit?; );
+            if let Some(ty) = &field_names {
+                let field_name = field_id.to_string();
+                let field = quote!(#crate_path::Field { ty: #ty, name: #field_name });
+                visit = quote!(
+                    #crate_path::VisitField::enter_field(visitor, #field);
+                    #visit
+                    #crate_path::VisitField::exit_field(visitor, #field);
+                );
+            }
             (
                 // Destructure this field
                 quote!( #field_id : #var, ),
-                // Call `visitor.visit()` on the field.
-                quote!( <#visitor_param as #visit_trait<#field_ty>>::visit(visitor, #var)?; ),
+                visit,
             )
         })
         .collect();
     quote! {
         #name { #destructuring .. } => {
+            #prefix
             #visit_fields
         }
     }
 }
 
-pub fn impl_drive_two(input: DeriveInput) -> Result<TokenStream> {
+/// Implement `DriveTwo`, or `DriveZipMut` if `mutable` is set.
+pub fn impl_drive_two(input: DeriveInput, mutable: bool) -> Result<TokenStream> {
     let crate_path: Path = parse_quote! { ::derive_generic_visitor };
     let control_flow: Path = parse_quote!(::std::ops::ControlFlow);
     let visitor_trait: Path = parse_quote!( #crate_path::Visitor );
-    let visit_two_trait: Path = parse_quote!( #crate_path::VisitTwo );
-    let drive_two_trait: Path = parse_quote!( #crate_path::DriveTwo );
+    let (visit_two_trait, drive_two_trait, drive_method): (Path, Path, Ident) = if mutable {
+        (
+            parse_quote!( #crate_path::VisitZipMut ),
+            parse_quote!( #crate_path::DriveZipMut ),
+            parse_quote!(drive_zip_mut_inner),
+        )
+    } else {
+        (
+            parse_quote!( #crate_path::VisitTwo ),
+            parse_quote!( #crate_path::DriveTwo ),
+            parse_quote!(drive_two_inner),
+        )
+    };
+    let mut_modifier = mutable.then(|| quote!(mut));
 
     let input = MyTypeDecl::from_derive_input(&input)?;
 
@@ -178,6 +327,12 @@ pub fn impl_drive_two(input: DeriveInput) -> Result<TokenStream> {
     where_clause
         .predicates
         .push(parse_quote!(#visitor_param: #visitor_trait<Break: Default>));
+    if input.has_binders() {
+        where_clause
+            .predicates
+            .push(parse_quote!(#visitor_param: #crate_path::VisitBinder));
+    }
+    let type_binder = input.binder.is_some();
 
     let mut need_visit_type = |f: &MyField| {
         let field_ty = &f.ty;
@@ -191,6 +346,7 @@ pub fn impl_drive_two(input: DeriveInput) -> Result<TokenStream> {
         Data::Struct(fields) => {
             let arm = match_variant_two(
                 parse_quote!(Self),
+                type_binder,
                 fields.iter(),
                 &mut need_visit_type,
                 &visitor_param,
@@ -211,6 +367,7 @@ pub fn impl_drive_two(input: DeriveInput) -> Result<TokenStream> {
                     let vname = &variant.ident;
                     match_variant_two(
                         parse_quote!(Self::#vname),
+                        type_binder,
                         variant.fields.iter(),
                         &mut need_visit_type,
                         &visitor_param,
@@ -233,13 +390,15 @@ pub fn impl_drive_two(input: DeriveInput) -> Result<TokenStream> {
         }
     };
 
+    let attrs = &input.attr;
     let (impl_generics, _, where_clause) = generics.split_for_impl();
     Ok(quote! {
+        #(#attrs)*
         impl #impl_generics #drive_two_trait<#lifetime_param, #visitor_param> for #impl_subject
         #where_clause {
             #[inline]
             #[allow(non_shorthand_field_patterns, unused_variables)]
-            fn drive_two_inner(&#lifetime_param self, other: &#lifetime_param Self, visitor: &mut #visitor_param)
+            fn #drive_method(&#lifetime_param #mut_modifier self, other: &#lifetime_param Self, visitor: &mut #visitor_param)
                     -> #control_flow<#visitor_param::Break> {
                 #body
                 #control_flow::Continue(())
@@ -249,8 +408,10 @@ pub fn impl_drive_two(input: DeriveInput) -> Result<TokenStream> {
 }
 
 /// Generate a match arm for `(self, other)` that destructures both values and visits fields pairwise.
+/// With `all_binders`, every field is under a binder.
 fn match_variant_two<'a>(
     name: Path,
+    all_binders: bool,
     fields: impl Iterator<Item = &'a MyField>,
     mut for_each_field: impl FnMut(&'a MyField),
     visitor_param: &Ident,
@@ -276,7 +437,12 @@ fn match_variant_two<'a>(
         };
         destructuring_a.extend(quote!( #field_id : #var_a, ));
         destructuring_b.extend(quote!( #field_id : #var_b, ));
-        visit_fields.extend(quote!( <#visitor_param as #visit_two_trait<#field_ty>>::visit(visitor, #var_a, #var_b)?; ));
+        let visit = under_binder(
+            all_binders || field.binder.is_some(),
+            &parse_quote!(visitor),
+            quote!( <#visitor_param as #visit_two_trait<#field_ty>>::visit(visitor, #var_a, #var_b) ),
+        );
+        visit_fields.extend(quote!( #visit?; ));
     }
     quote! {
         (#name { #destructuring_a .. }, #name { #destructuring_b .. }) => {
@@ -284,3 +450,457 @@ fn match_variant_two<'a>(
         }
     }
 }
+
+/// Implement `DrivePar`, which visits each field on its own fork of the visitor.
+pub fn impl_drive_par(input: DeriveInput) -> Result<TokenStream> {
+    let crate_path: Path = parse_quote! { ::derive_generic_visitor };
+    let control_flow: Path = parse_quote!(::std::ops::ControlFlow);
+    let visit_trait: Path = parse_quote!( #crate_path::Visit );
+    let lifetime_param: syn::Lifetime = parse_quote!('s);
+    let visitor_param: Ident = parse_quote!(V);
+
+    let input = MyTypeDecl::from_derive_input(&input)?;
+
+    let name = &input.ident;
+    if input.discriminant.is_some() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "`#[drive(discriminant)]` is not supported by `DrivePar`",
+        ));
+    }
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let impl_subject = quote! { #name #ty_generics };
+
+    let mut generics = input.generics.clone();
+    generics
+        .params
+        .push(GenericParam::Lifetime(parse_quote!(#lifetime_param)));
+    generics
+        .params
+        .push(GenericParam::Type(parse_quote!(#visitor_param)));
+
+    let where_clause = generics.make_where_clause();
+    where_clause
+        .predicates
+        .push(parse_quote!(#visitor_param: #crate_path::ParVisitor));
+    if input.has_binders() {
+        where_clause
+            .predicates
+            .push(parse_quote!(#visitor_param: #crate_path::VisitBinder));
+    }
+    let type_binder = input.binder.is_some();
+    // The fields are shared between threads.
+    let mut need_visit_type = |f: &MyField| {
+        let field_ty = &f.ty;
+        where_clause
+            .predicates
+            .push(parse_quote!(#visitor_param: #visit_trait<#lifetime_param, #field_ty>));
+        where_clause
+            .predicates
+            .push(parse_quote!(#field_ty: ::std::marker::Sync));
+    };
+
+    let mut match_variant_par = |name: Path, fields: &'_ Fields<MyField>| {
+        let mut destructuring = TokenStream::new();
+        let mut tasks = TokenStream::new();
+        for (index, field) in fields.iter().enumerate() {
+            if field.skip.is_some() {
+                continue;
+            }
+            need_visit_type(field);
+            let field_ty = &field.ty;
+            let field_id: TokenStream = match &field.ident {
+                None => Index::from(index).into_token_stream(),
+                Some(name) => name.into_token_stream(),
+            };
+            let var: TokenStream = match &field.ident {
+                None => Ident::new(&format!("i{}", index), Span::call_site()).into_token_stream(),
+                Some(name) => name.into_token_stream(),
+            };
+            destructuring.extend(quote!( #field_id : #var, ));
+            let visit = under_binder(
+                type_binder || field.binder.is_some(),
+                &parse_quote!(v),
+                quote!( <#visitor_param as #visit_trait<#field_ty>>::visit(v, #var) ),
+            );
+            tasks.extend(quote!(
+                &|v: &mut #visitor_param| #visit,
+            ));
+        }
+        quote! {
+            #name { #destructuring .. } => #crate_path::drive_par_tasks(&[#tasks], visitor),
+        }
+    };
+
+    let arms = match &input.data {
+        _ if input.skip.is_some() => quote!(),
+        Data::Struct(fields) => match_variant_par(parse_quote!(Self), fields),
+        Data::Enum(variants) => variants
+            .iter()
+            .filter(|variant| variant.skip.is_none())
+            .map(|variant| {
+                let vname = &variant.ident;
+                match_variant_par(parse_quote!(Self::#vname), &variant.fields)
+            })
+            .collect(),
+    };
+
+    let attrs = &input.attr;
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #(#attrs)*
+        impl #impl_generics #crate_path::DrivePar<#lifetime_param, #visitor_param> for #impl_subject
+        #where_clause {
+            #[allow(non_shorthand_field_patterns, unreachable_patterns, unused_variables)]
+            fn drive_par_inner(&#lifetime_param self, visitor: &mut #visitor_param)
+                    -> #control_flow<#visitor_param::Break> {
+                match self {
+                    #arms
+                    _ => #control_flow::Continue(()),
+                }
+            }
+        }
+    })
+}
+
+/// Implement `Fold`, which takes the value apart, folds each field and puts it back together.
+pub fn impl_fold(input: DeriveInput) -> Result<TokenStream> {
+    let crate_path: Path = parse_quote! { ::derive_generic_visitor };
+    let control_flow: Path = parse_quote!(::std::ops::ControlFlow);
+    let visitor_trait: Path = parse_quote!( #crate_path::Visitor );
+    let visit_fold_trait: Path = parse_quote!( #crate_path::VisitFold );
+    let visitor_param: Ident = parse_quote!(V);
+
+    let input = MyTypeDecl::from_derive_input(&input)?;
+
+    let name = &input.ident;
+    if input.discriminant.is_some() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "`#[drive(discriminant)]` is not supported by `Fold`",
+        ));
+    }
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let impl_subject = quote! { #name #ty_generics };
+
+    let mut generics = input.generics.clone();
+    generics
+        .params
+        .push(GenericParam::Type(parse_quote!(#visitor_param)));
+
+    let where_clause = generics.make_where_clause();
+    where_clause
+        .predicates
+        .push(parse_quote!(#visitor_param: #visitor_trait));
+    if input.has_binders() {
+        where_clause
+            .predicates
+            .push(parse_quote!(#visitor_param: #crate_path::VisitBinder));
+    }
+    let type_binder = input.binder.is_some();
+    let mut need_visit_type = |f: &MyField| {
+        let field_ty = &f.ty;
+        where_clause
+            .predicates
+            .push(parse_quote!(#visitor_param: #visit_fold_trait<#field_ty>));
+    };
+
+    let arms = match input.data {
+        _ if input.skip.is_some() => quote!(),
+        Data::Struct(fields) => match_variant_fold(
+            parse_quote!(Self),
+            type_binder,
+            fields.iter(),
+            &mut need_visit_type,
+            &visitor_param,
+            &visit_fold_trait,
+        ),
+        Data::Enum(variants) => variants
+            .iter()
+            .filter(|variant| variant.skip.is_none())
+            .map(|variant| {
+                let vname = &variant.ident;
+                match_variant_fold(
+                    parse_quote!(Self::#vname),
+                    type_binder,
+                    variant.fields.iter(),
+                    &mut need_visit_type,
+                    &visitor_param,
+                    &visit_fold_trait,
+                )
+            })
+            .collect(),
+    };
+
+    let attrs = &input.attr;
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #(#attrs)*
+        impl #impl_generics #crate_path::Fold<#visitor_param> for #impl_subject
+        #where_clause {
+            #[inline]
+            #[allow(non_shorthand_field_patterns, unreachable_patterns)]
+            fn fold_inner(self, visitor: &mut #visitor_param)
+                    -> #control_flow<#visitor_param::Break, Self> {
+                #control_flow::Continue(match self {
+                    #arms
+                    this => this,
+                })
+            }
+        }
+    })
+}
+
+/// Generate a match arm that destructures the given variant and rebuilds it from its folded
+/// fields. Skipped fields are moved over unchanged. With `all_binders`, every field is under a
+/// binder.
+fn match_variant_fold<'a>(
+    name: Path,
+    all_binders: bool,
+    fields: impl Iterator<Item = &'a MyField>,
+    mut for_each_field: impl FnMut(&'a MyField),
+    visitor_param: &Ident,
+    visit_fold_trait: &Path,
+) -> TokenStream {
+    let mut destructuring = TokenStream::new();
+    let mut rebuilding = TokenStream::new();
+    for (index, field) in fields.enumerate() {
+        let field_id: TokenStream = match &field.ident {
+            None => Index::from(index).into_token_stream(),
+            Some(name) => name.into_token_stream(),
+        };
+        let var: TokenStream = match &field.ident {
+            None => Ident::new(&format!("i{}", index), Span::call_site()).into_token_stream(),
+            Some(name) => name.into_token_stream(),
+        };
+        destructuring.extend(quote!( #field_id : #var, ));
+        if field.skip.is_some() {
+            rebuilding.extend(quote!( #field_id : #var, ));
+        } else {
+            for_each_field(field);
+            let field_ty = &field.ty;
+            let visit = under_binder(
+                all_binders || field.binder.is_some(),
+                &parse_quote!(visitor),
+                quote!( <#visitor_param as #visit_fold_trait<#field_ty>>::visit(visitor, #var) ),
+            );
+            rebuilding.extend(quote!( #field_id : #visit?, ));
+        }
+    }
+    quote! {
+        #name { #destructuring } => #name { #rebuilding },
+    }
+}
+
+/// Generate `DriveAny::path_step`, which names the fields visited by `Drive`.
+fn path_step_method(input: &MyTypeDecl) -> TokenStream {
+    let crate_path: Path = parse_quote! { ::derive_generic_visitor };
+    let path_step: Path = parse_quote! { #crate_path::PathStep };
+    // The fields visited in a variant, in order. The discriminant, if any, comes first.
+    let variant_arm = |pat: TokenStream, fields: &Fields<MyField>| {
+        let offset = input.discriminant.is_some() as usize;
+        let steps = fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.skip.is_none())
+            .enumerate()
+            .map(|(visited_index, (index, field))| {
+                let visited_index = visited_index + offset;
+                let step = match &field.ident {
+                    Some(name) => {
+                        let name = name.to_string();
+                        quote!(#path_step::Field(#name))
+                    }
+                    None => quote!(#path_step::TupleField(#index)),
+                };
+                quote!(#visited_index => #step,)
+            });
+        quote! {
+            #pat => match index {
+                #(#steps)*
+                _ => #path_step::Index(index),
+            },
+        }
+    };
+    let arms = match &input.data {
+        _ if input.skip.is_some() => quote!(),
+        Data::Struct(fields) => variant_arm(quote!(Self { .. }), fields),
+        Data::Enum(variants) => variants
+            .iter()
+            .filter(|variant| variant.skip.is_none())
+            .map(|variant| {
+                let vname = &variant.ident;
+                variant_arm(quote!(Self::#vname { .. }), &variant.fields)
+            })
+            .collect(),
+    };
+    quote! {
+        #[allow(unreachable_patterns)]
+        fn path_step(&self, index: usize) -> #path_step {
+            match self {
+                #arms
+                _ => #path_step::Index(index),
+            }
+        }
+    }
+}
+
+/// Implement `DriveAny[Mut]` on top of the `Drive[Mut]` impl of the type.
+pub fn impl_drive_any(input: DeriveInput, mutable: bool) -> Result<TokenStream> {
+    let crate_path: Path = parse_quote! { ::derive_generic_visitor };
+    let control_flow: Path = parse_quote!(::std::ops::ControlFlow);
+
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    let type_params: Vec<Ident> = generics.type_params().map(|p| p.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+
+    let method = if mutable {
+        for param in &type_params {
+            where_clause
+                .predicates
+                .push(parse_quote!(#param: #crate_path::DriveAnyMut));
+        }
+        quote! {
+            #[inline]
+            fn drive_any_inner_mut(&mut self, v: &mut dyn #crate_path::AnyVisitorMut)
+                    -> #control_flow<()> {
+                #crate_path::DriveMut::drive_inner_mut(self, &mut #crate_path::AnyDriverMut::new(v))
+            }
+        }
+    } else {
+        for param in &type_params {
+            where_clause
+                .predicates
+                .push(parse_quote!(#param: #crate_path::DriveAny));
+        }
+        let path_step = path_step_method(&MyTypeDecl::from_derive_input(&input)?);
+        quote! {
+            #[inline]
+            fn drive_any_inner<'a>(&'a self, v: &mut dyn #crate_path::AnyVisitor<'a>)
+                    -> #control_flow<()> {
+                #crate_path::Drive::drive_inner(self, &mut #crate_path::AnyDriver::new(v))
+            }
+            #path_step
+        }
+    };
+    let drive_any_trait = if mutable {
+        quote!(#crate_path::DriveAnyMut)
+    } else {
+        quote!(#crate_path::DriveAny)
+    };
+
+    let attrs = MyTypeDecl::from_derive_input(&input)?.attr;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #(#attrs)*
+        impl #impl_generics #drive_any_trait for #name #ty_generics #where_clause {
+            #method
+        }
+    })
+}
+
+pub fn impl_drive_diff(input: DeriveInput) -> Result<TokenStream> {
+    let crate_path: Path = parse_quote! { ::derive_generic_visitor };
+    let control_flow: Path = parse_quote!(::std::ops::ControlFlow);
+
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    let type_params: Vec<Ident> = generics.type_params().map(|p| p.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for param in &type_params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#param: #crate_path::DriveDiff + ::std::fmt::Debug));
+    }
+
+    let attrs = MyTypeDecl::from_derive_input(&input)?.attr;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #(#attrs)*
+        impl #impl_generics #crate_path::DriveDiff for #name #ty_generics #where_clause {
+            #[inline]
+            fn drive_diff_inner<'a>(
+                &'a self,
+                other: &'a Self,
+                v: &mut #crate_path::DiffVisitor,
+            ) -> #control_flow<()> {
+                #crate_path::DriveTwo::drive_two_inner(self, other, v)
+            }
+        }
+    })
+}
+
+pub fn impl_drive_schema(input: DeriveInput) -> Result<TokenStream> {
+    let crate_path: Path = parse_quote! { ::derive_generic_visitor };
+    let input = MyTypeDecl::from_derive_input(&input)?;
+
+    let fields_schema = |fields: &Fields<MyField>| {
+        let fields = fields.iter().map(|field| {
+            let name = match &field.ident {
+                Some(name) => {
+                    let name = name.to_string();
+                    quote!(::std::option::Option::Some(#name))
+                }
+                None => quote!(::std::option::Option::None),
+            };
+            let ty = type_to_string(&field.ty);
+            let skip = field.skip.is_some();
+            quote!(#crate_path::FieldSchema { name: #name, ty: #ty, skip: #skip })
+        });
+        quote!(&[#(#fields),*])
+    };
+    let shape = match &input.data {
+        Data::Struct(fields) => {
+            let fields = fields_schema(fields);
+            quote!(#crate_path::Shape::Struct(#fields))
+        }
+        Data::Enum(variants) => {
+            let variants = variants.iter().map(|variant| {
+                let name = variant.ident.to_string();
+                let skip = variant.skip.is_some();
+                let fields = fields_schema(&variant.fields);
+                quote!(#crate_path::VariantSchema { name: #name, skip: #skip, fields: #fields })
+            });
+            quote!(#crate_path::Shape::Enum(&[#(#variants),*]))
+        }
+    };
+
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let skip = input.skip.is_some();
+    let attrs = &input.attr;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        #(#attrs)*
+        impl #impl_generics #crate_path::DriveSchema for #name #ty_generics #where_clause {
+            const SCHEMA: #crate_path::Schema = #crate_path::Schema {
+                name: #name_str,
+                skip: #skip,
+                shape: #shape,
+            };
+        }
+    })
+}
+
+/// Print a type roughly like rustfmt would.
+fn type_to_string(ty: &Type) -> String {
+    let mut s = ty.to_token_stream().to_string();
+    for (from, to) in [
+        (" <", "<"),
+        ("< ", "<"),
+        (" >", ">"),
+        (" ::", "::"),
+        (":: ", "::"),
+        (" ,", ","),
+        ("& ", "&"),
+        (" ;", ";"),
+        ("[ ", "["),
+        (" ]", "]"),
+        ("( ", "("),
+        (" )", ")"),
+    ] {
+        s = s.replace(from, to);
+    }
+    s
+}
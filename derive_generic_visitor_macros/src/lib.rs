@@ -6,6 +6,8 @@ pub(crate) use common::*;
 
 mod common;
 mod drive;
+mod node_kind;
+mod type_set;
 mod visit;
 mod visitable_group;
 
@@ -21,17 +23,17 @@ fn wrap_for_derive(
 
 #[proc_macro_derive(Visitor, attributes(visit))]
 pub fn derive_visitor(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    wrap_for_derive(input, |input| visit::impl_visitor(input))
+    wrap_for_derive(input, visit::impl_visitor)
 }
 
 #[proc_macro_derive(Visit, attributes(visit))]
 pub fn derive_visit(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    wrap_for_derive(input, |input| visit::impl_visit(input, false))
+    wrap_for_derive(input, |input| derive_with_sets("visit", input))
 }
 
 #[proc_macro_derive(VisitMut, attributes(visit))]
 pub fn derive_visit_mut(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    wrap_for_derive(input, |input| visit::impl_visit(input, true))
+    wrap_for_derive(input, |input| derive_with_sets("visit_mut", input))
 }
 
 #[proc_macro_derive(Drive, attributes(drive))]
@@ -46,12 +48,105 @@ pub fn derive_drive_mut(input: proc_macro::TokenStream) -> proc_macro::TokenStre
 
 #[proc_macro_derive(DriveTwo, attributes(drive))]
 pub fn derive_drive_two(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    wrap_for_derive(input, |input| drive::impl_drive_two(input))
+    wrap_for_derive(input, |input| drive::impl_drive_two(input, false))
+}
+
+#[proc_macro_derive(DriveZipMut, attributes(drive))]
+pub fn derive_drive_zip_mut(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    wrap_for_derive(input, |input| drive::impl_drive_two(input, true))
+}
+
+#[proc_macro_derive(DrivePar, attributes(drive))]
+pub fn derive_drive_par(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    wrap_for_derive(input, drive::impl_drive_par)
+}
+
+#[proc_macro_derive(Fold, attributes(drive))]
+pub fn derive_fold(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    wrap_for_derive(input, drive::impl_fold)
+}
+
+#[proc_macro_derive(DriveSchema, attributes(drive))]
+pub fn derive_drive_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    wrap_for_derive(input, drive::impl_drive_schema)
+}
+
+#[proc_macro_derive(DriveAny, attributes(drive))]
+pub fn derive_drive_any(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    wrap_for_derive(input, |input| drive::impl_drive_any(input, false))
+}
+
+#[proc_macro_derive(DriveAnyMut, attributes(drive))]
+pub fn derive_drive_any_mut(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    wrap_for_derive(input, |input| drive::impl_drive_any(input, true))
+}
+
+#[proc_macro_derive(DriveDiff, attributes(drive))]
+pub fn derive_drive_diff(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    wrap_for_derive(input, drive::impl_drive_diff)
+}
+
+#[proc_macro_derive(NodeKind)]
+pub fn derive_node_kind(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    wrap_for_derive(input, node_kind::impl_node_kind)
+}
+
+#[proc_macro_derive(VisitFold, attributes(visit))]
+pub fn derive_visit_fold(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    wrap_for_derive(input, |input| derive_with_sets("visit_fold", input))
 }
 
 #[proc_macro_derive(VisitTwo, attributes(visit_two))]
 pub fn derive_visit_two(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    wrap_for_derive(input, |input| visit::impl_visit_two(input))
+    wrap_for_derive(input, |input| derive_with_sets("visit_two", input))
+}
+
+/// Run one of the `Visit*` derives, identified by `kind`, after expanding the type sets it
+/// references.
+fn derive_with_sets(kind: &str, input: DeriveInput) -> Result<TokenStream> {
+    match kind {
+        "visit" => {
+            type_set::derive_with_sets(input, "visit", |i| visit::impl_visit(i, false), kind)
+        }
+        "visit_mut" => {
+            type_set::derive_with_sets(input, "visit", |i| visit::impl_visit(i, true), kind)
+        }
+        "visit_fold" => type_set::derive_with_sets(input, "visit", visit::impl_visit_fold, kind),
+        "visit_two" => type_set::derive_with_sets(input, "visit_two", visit::impl_visit_two, kind),
+        _ => unreachable!(),
+    }
+}
+
+fn visitable_group_with_sets(attrs: TokenStream, item: TokenStream) -> Result<TokenStream> {
+    type_set::group_with_sets(attrs, item, |attrs, item| {
+        let item: ItemTrait = parse2(item)?;
+        let attrs: visitable_group::Options = parse2(attrs)?;
+        visitable_group::impl_visitable_group(attrs, item)
+    })
+}
+
+/// Define named sets of types that can be used in the type lists of the `Visit[Mut]`/`VisitTwo`
+/// derives and of `visitable_group`, by writing `SetName!` in place of a type.
+///
+/// ```ignore
+/// visit_types! {
+///     pub set AstLeaves = (String, Span, u32);
+/// }
+/// ```
+#[proc_macro]
+pub fn visit_types(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    type_set::impl_visit_types(input.into())
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Implementation detail of `visit_types!`.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __expand_type_set(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    type_set::impl_expand_type_set(input.into(), derive_with_sets, visitable_group_with_sets)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
 }
 
 #[proc_macro_attribute]
@@ -59,9 +154,7 @@ pub fn visitable_group(
     attrs: proc_macro::TokenStream,
     item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let item = parse_macro_input!(item as ItemTrait);
-    let attrs = parse_macro_input!(attrs as visitable_group::Options);
-    visitable_group::impl_visitable_group(attrs, item)
+    visitable_group_with_sets(attrs.into(), item.into())
         .unwrap_or_else(|error| error.to_compile_error())
         .into()
 }
@@ -0,0 +1,53 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, LitStr, Path, Result};
+
+/// The bodies of the `kind` and `kind_index` methods: enums are named after their variant
+/// (`Expr::Add`) and indexed by variant order, other types are named after the type.
+fn kind_bodies(input: &DeriveInput) -> (TokenStream, TokenStream) {
+    let name = &input.ident;
+    match &input.data {
+        Data::Enum(data) if !data.variants.is_empty() => {
+            let (kinds, indices): (Vec<_>, Vec<_>) = data
+                .variants
+                .iter()
+                .enumerate()
+                .map(|(i, variant)| {
+                    let vname = &variant.ident;
+                    let kind = LitStr::new(&format!("{name}::{vname}"), vname.span());
+                    (
+                        quote!(Self::#vname { .. } => #kind,),
+                        quote!(Self::#vname { .. } => #i,),
+                    )
+                })
+                .unzip();
+            (
+                quote!(match self { #(#kinds)* }),
+                quote!(match self { #(#indices)* }),
+            )
+        }
+        _ => {
+            let kind = LitStr::new(&name.to_string(), name.span());
+            (quote!(#kind), quote!(0))
+        }
+    }
+}
+
+pub fn impl_node_kind(input: DeriveInput) -> Result<TokenStream> {
+    let crate_path: Path = syn::parse_quote! { ::derive_generic_visitor };
+    let name = &input.ident;
+    let (kind, index) = kind_bodies(&input);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #crate_path::NodeKind for #name #ty_generics #where_clause {
+            #[inline]
+            fn kind(&self) -> &'static str {
+                #kind
+            }
+            #[inline]
+            fn kind_index(&self) -> usize {
+                #index
+            }
+        }
+    })
+}
@@ -0,0 +1,266 @@
+//! Named sets of types, defined with `visit_types!` and referenced as `SetName!` in the type lists
+//! of `#[visit(..)]`, `#[visit_two(..)]` and `visitable_group(..)`.
+//!
+//! A proc macro can't look up the definition of a set, so each set is a `macro_rules!` macro that
+//! forwards its contents to a callback. When a macro finds a set reference in its arguments, it
+//! expands to a call to the set macro with `__expand_type_set` as callback, passing along its own
+//! input. `__expand_type_set` then substitutes the contents of the set for the reference and runs
+//! the original macro again, which handles any remaining references the same way.
+use proc_macro2::{Delimiter, Ident, Spacing, TokenStream, TokenTree};
+use quote::quote;
+use syn::{
+    parenthesized,
+    parse::{Parse, ParseStream},
+    Attribute, DeriveInput, Meta, Result, Token, Visibility,
+};
+
+mod kw {
+    syn::custom_keyword!(set);
+}
+
+/// `[attrs] [vis] set Name = (Ty1, Ty2, ..);`
+struct SetDef {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    tys: TokenStream,
+}
+
+impl Parse for SetDef {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        let _: kw::set = input.parse()?;
+        let name = input.parse()?;
+        let _: Token![=] = input.parse()?;
+        let content;
+        parenthesized!(content in input);
+        let tys = content.parse()?;
+        let _: Token![;] = input.parse()?;
+        Ok(SetDef {
+            attrs,
+            vis,
+            name,
+            tys,
+        })
+    }
+}
+
+struct SetDefs(Vec<SetDef>);
+
+impl Parse for SetDefs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut defs = vec![];
+        while !input.is_empty() {
+            defs.push(input.parse()?);
+        }
+        Ok(SetDefs(defs))
+    }
+}
+
+pub fn impl_visit_types(input: TokenStream) -> Result<TokenStream> {
+    let SetDefs(defs) = syn::parse2(input)?;
+    Ok(defs
+        .into_iter()
+        .map(|def| {
+            let SetDef {
+                attrs,
+                vis,
+                name,
+                tys,
+            } = def;
+            let export = match &vis {
+                Visibility::Public(_) => quote!(#[macro_export]),
+                Visibility::Restricted(_) => quote!(),
+                Visibility::Inherited => quote!(),
+            };
+            let reexport = match &vis {
+                Visibility::Restricted(_) => Some(quote!(#[allow(unused_imports)] #vis use #name;)),
+                _ => None,
+            };
+            quote!(
+                #(#attrs)*
+                #export
+                macro_rules! #name {
+                    (@cb [$($cb:tt)*] $($args:tt)*) => {
+                        $($cb)*! { $($args)* [#tys] }
+                    };
+                }
+                #reexport
+            )
+        })
+        .collect())
+}
+
+/// Find the first set reference `path::to::Set!` in `tokens`, and return the tokens of its path.
+fn find_set_ref(tokens: TokenStream) -> Option<TokenStream> {
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    for (i, tt) in tts.iter().enumerate() {
+        match tt {
+            TokenTree::Punct(p) if p.as_char() == '!' && path_start(&tts, i).is_some() => {
+                let start = path_start(&tts, i).unwrap();
+                return Some(tts[start..i].iter().cloned().collect());
+            }
+            TokenTree::Group(g) => {
+                if let Some(path) = find_set_ref(g.stream()) {
+                    return Some(path);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// If the token at `bang` is preceded by a path, return the index where the path starts.
+fn path_start(tts: &[TokenTree], bang: usize) -> Option<usize> {
+    let is_colon = |i: usize, spacing: Spacing| matches!(&tts[i], TokenTree::Punct(p) if p.as_char() == ':' && p.spacing() == spacing);
+    let mut start = bang.checked_sub(1)?;
+    if !matches!(tts[start], TokenTree::Ident(_)) {
+        return None;
+    }
+    // Walk back over `ident::` pairs.
+    while start >= 2 && is_colon(start - 1, Spacing::Alone) && is_colon(start - 2, Spacing::Joint) {
+        if start >= 3 && matches!(tts[start - 3], TokenTree::Ident(_)) {
+            start -= 3;
+        } else {
+            // Leading `::`.
+            start -= 2;
+            break;
+        }
+    }
+    Some(start)
+}
+
+/// Replace the first set reference in `tokens` (with an optional empty `()` after the `!`) with
+/// `tys`. Returns whether a reference was found.
+fn replace_set_ref(tokens: TokenStream, tys: &TokenStream) -> (TokenStream, bool) {
+    let tts: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut out = TokenStream::new();
+    let mut iter = tts.iter().enumerate();
+    while let Some((i, tt)) = iter.next() {
+        match tt {
+            TokenTree::Punct(p) if p.as_char() == '!' && path_start(&tts, i).is_some() => {
+                let start = path_start(&tts, i).unwrap();
+                // Drop the path we already emitted.
+                let kept: Vec<TokenTree> = out.into_iter().collect();
+                let kept_len = kept.len() - (i - start);
+                out = kept.into_iter().take(kept_len).collect();
+                out.extend(tys.clone());
+                let mut rest = iter.map(|(_, tt)| tt.clone()).peekable();
+                if let Some(TokenTree::Group(g)) = rest.peek() {
+                    if g.delimiter() == Delimiter::Parenthesis && g.stream().is_empty() {
+                        rest.next();
+                    }
+                }
+                out.extend(rest);
+                return (out, true);
+            }
+            TokenTree::Group(g) => {
+                let (stream, found) = replace_set_ref(g.stream(), tys);
+                let mut new_g = proc_macro2::Group::new(g.delimiter(), stream);
+                new_g.set_span(g.span());
+                out.extend([TokenTree::Group(new_g)]);
+                if found {
+                    out.extend(iter.map(|(_, tt)| tt.clone()));
+                    return (out, true);
+                }
+            }
+            _ => out.extend([tt.clone()]),
+        }
+    }
+    (out, false)
+}
+
+/// Call the set macro at `path` with `__expand_type_set` as callback.
+fn call_set(path: TokenStream, kind: &str, payload: TokenStream) -> TokenStream {
+    let kind = Ident::new(kind, proc_macro2::Span::call_site());
+    quote!(
+        #path! { @cb [::derive_generic_visitor::__expand_type_set] #kind { #payload } }
+    )
+}
+
+/// Run a derive that reads its type lists from the `attr_name` attributes, first expanding any
+/// type set they reference.
+pub fn derive_with_sets(
+    input: DeriveInput,
+    attr_name: &str,
+    handler: fn(DeriveInput) -> Result<TokenStream>,
+    kind: &str,
+) -> Result<TokenStream> {
+    for attr in &input.attrs {
+        if !attr.path().is_ident(attr_name) {
+            continue;
+        }
+        if let Meta::List(list) = &attr.meta {
+            if let Some(path) = find_set_ref(list.tokens.clone()) {
+                return Ok(call_set(path, kind, quote!(#input)));
+            }
+        }
+    }
+    handler(input)
+}
+
+/// Run `visitable_group`, first expanding any type set referenced in its arguments.
+pub fn group_with_sets(
+    args: TokenStream,
+    item: TokenStream,
+    handler: fn(TokenStream, TokenStream) -> Result<TokenStream>,
+) -> Result<TokenStream> {
+    match find_set_ref(args.clone()) {
+        Some(path) => Ok(call_set(path, "visitable_group", quote!((#args) #item))),
+        None => handler(args, item),
+    }
+}
+
+/// The input to `__expand_type_set`: `kind { payload } [tys]`.
+struct Callback {
+    kind: Ident,
+    payload: TokenStream,
+    tys: TokenStream,
+}
+
+impl Parse for Callback {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let kind = input.parse()?;
+        let payload;
+        syn::braced!(payload in input);
+        let tys;
+        syn::bracketed!(tys in input);
+        Ok(Callback {
+            kind,
+            payload: payload.parse()?,
+            tys: tys.parse()?,
+        })
+    }
+}
+
+/// Substitute the contents of a set for the first set reference in the payload, then run the
+/// macro identified by `kind` on the result.
+pub fn impl_expand_type_set(
+    input: TokenStream,
+    derive: fn(&str, DeriveInput) -> Result<TokenStream>,
+    group: fn(TokenStream, TokenStream) -> Result<TokenStream>,
+) -> Result<TokenStream> {
+    let Callback { kind, payload, tys } = syn::parse2(input)?;
+    if kind == "visitable_group" {
+        let mut iter = payload.into_iter();
+        let Some(TokenTree::Group(args)) = iter.next() else {
+            unreachable!()
+        };
+        let (args, _) = replace_set_ref(args.stream(), &tys);
+        group(args, iter.collect())
+    } else {
+        let mut input: DeriveInput = syn::parse2(payload)?;
+        for attr in &mut input.attrs {
+            if let Meta::List(list) = &mut attr.meta {
+                let (tokens, found) = replace_set_ref(list.tokens.clone(), &tys);
+                if found {
+                    list.tokens = tokens;
+                    break;
+                }
+            }
+        }
+        derive(&kind.to_string(), input)
+    }
+}
@@ -25,19 +25,22 @@ struct VisitEntry {
 }
 
 mod parse {
+    use proc_macro2::{Delimiter, TokenStream, TokenTree};
     use syn::parse::{Parse, ParseStream};
     use syn::punctuated::Punctuated;
     use syn::token::{self};
-    use syn::{parenthesized, Attribute, Result, Token};
+    use syn::{parenthesized, Attribute, Ident, Result, Token, Type};
 
     use super::{VisitEntry, VisitKind};
-    use crate::common::NamedGenericTy;
+    use crate::common::{unknown_keyword, ForwardedAttrs, NamedGenericTy};
 
     mod kw {
         syn::custom_keyword!(skip);
         syn::custom_keyword!(drive);
         syn::custom_keyword!(enter);
         syn::custom_keyword!(exit);
+        syn::custom_keyword!(attr);
+        syn::custom_keyword!(fallible);
     }
 
     #[allow(unused)]
@@ -50,16 +53,34 @@ mod parse {
     }
 
     #[allow(unused)]
-    struct VisitOption {
-        /// Optional because `visit(Ty)` is allowed and means the same as `visit(override(Ty))`.
-        kind_token: Option<(VisitKindToken, token::Paren)>,
-        tys: Punctuated<NamedGenericTy, Token![,]>,
+    enum VisitOption {
+        Tys {
+            /// Optional because `visit(Ty)` is allowed and means the same as
+            /// `visit(override(Ty))`.
+            kind_token: Option<(VisitKindToken, token::Paren)>,
+            tys: Punctuated<NamedGenericTy, Token![,]>,
+        },
+        /// `attr(...)`: attributes to put on the generated impls.
+        Attr(ForwardedAttrs),
+        /// `fallible(Error)`: the visitor breaks with `Error`, and its custom methods return
+        /// `Result`s.
+        Fallible(Type),
     }
 
     impl Parse for VisitOption {
         fn parse(input: ParseStream) -> Result<Self> {
             let lookahead = input.lookahead1();
-            let visit_kind_token = if lookahead.peek(Token![override]) {
+            let visit_kind_token = if lookahead.peek(kw::attr) {
+                let _: kw::attr = input.parse()?;
+                let content;
+                parenthesized!(content in input);
+                return Ok(VisitOption::Attr(content.parse()?));
+            } else if lookahead.peek(kw::fallible) {
+                let _: kw::fallible = input.parse()?;
+                let content;
+                parenthesized!(content in input);
+                return Ok(VisitOption::Fallible(content.parse()?));
+            } else if lookahead.peek(Token![override]) {
                 VisitKindToken::Override(input.parse()?)
             } else if lookahead.peek(kw::enter) {
                 VisitKindToken::Enter(input.parse()?)
@@ -69,9 +90,18 @@ mod parse {
                 VisitKindToken::Drive(input.parse()?)
             } else if lookahead.peek(kw::skip) {
                 VisitKindToken::Skip(input.parse()?)
+            } else if input.peek(Ident) && input.peek2(token::Paren) {
+                // Looks like `kind(Ty)` with a misspelt kind.
+                let ident: Ident = input.parse()?;
+                return Err(unknown_keyword(
+                    &ident,
+                    &[
+                        "override", "enter", "exit", "drive", "skip", "attr", "fallible",
+                    ],
+                ));
             } else {
-                return match Punctuated::parse_terminated(&input) {
-                    Ok(tys) => Ok(VisitOption {
+                return match Punctuated::parse_terminated(input) {
+                    Ok(tys) => Ok(VisitOption::Tys {
                         kind_token: None,
                         tys,
                     }),
@@ -79,7 +109,7 @@ mod parse {
                 };
             };
             let content;
-            Ok(VisitOption {
+            Ok(VisitOption::Tys {
                 kind_token: Some((visit_kind_token, parenthesized!(content in input))),
                 tys: Punctuated::parse_terminated(&content)?,
             })
@@ -98,16 +128,40 @@ mod parse {
         }
     }
 
-    pub fn parse_attrs(attrs: &[Attribute], attr_name: &str) -> Result<Vec<super::VisitEntry>> {
+    /// The contents of the `#[visit(..)]` attributes.
+    pub struct VisitAttrs {
+        /// The types to visit.
+        pub entries: Vec<VisitEntry>,
+        /// The attributes to put on the generated impls.
+        pub forwarded: Vec<ForwardedAttrs>,
+        /// The error type given by `fallible(..)`.
+        pub fallible: Option<Type>,
+    }
+
+    /// Parse the `#[visit(..)]` attributes.
+    pub fn parse_attrs(attrs: &[Attribute], attr_name: &str) -> Result<VisitAttrs> {
         let mut out = Vec::new();
+        let mut forwarded = Vec::new();
+        let mut fallible = None;
         for attr in attrs {
             if !attr.path().is_ident(attr_name) {
                 continue;
             }
             let visit_options: VisitOptions = attr.parse_args()?;
             for opt in visit_options.options {
-                for named_ty in opt.tys {
-                    let kind = match &opt.kind_token {
+                let (kind_token, tys) = match opt {
+                    VisitOption::Tys { kind_token, tys } => (kind_token, tys),
+                    VisitOption::Attr(attrs) => {
+                        forwarded.push(attrs);
+                        continue;
+                    }
+                    VisitOption::Fallible(ty) => {
+                        fallible = Some(ty);
+                        continue;
+                    }
+                };
+                for named_ty in tys {
+                    let kind = match &kind_token {
                         Some((tok, _)) => match tok {
                             VisitKindToken::Skip(..) => VisitKind::Skip,
                             VisitKindToken::Drive(..) => VisitKind::Drive,
@@ -127,7 +181,47 @@ mod parse {
                 }
             }
         }
-        Ok(out)
+        Ok(VisitAttrs {
+            entries: out,
+            forwarded,
+            fallible,
+        })
+    }
+
+    /// Parse only the `attr(..)` and `fallible(..)` options of the `#[visit(..)]` attributes.
+    /// Unlike `parse_attrs`, this doesn't look at the types, which may still contain unexpanded
+    /// type sets.
+    pub fn parse_visitor_attrs(
+        attrs: &[Attribute],
+        attr_name: &str,
+    ) -> Result<(Vec<ForwardedAttrs>, Option<Type>)> {
+        let mut forwarded = Vec::new();
+        let mut fallible = None;
+        for attr in attrs {
+            if !attr.path().is_ident(attr_name) {
+                continue;
+            }
+            let args: TokenStream = attr.parse_args()?;
+            let mut tokens = args.into_iter().peekable();
+            while let Some(tt) = tokens.next() {
+                let TokenTree::Ident(ident) = &tt else {
+                    continue;
+                };
+                if ident != "attr" && ident != "fallible" {
+                    continue;
+                }
+                if let Some(TokenTree::Group(group)) = tokens.peek() {
+                    if group.delimiter() == Delimiter::Parenthesis {
+                        if ident == "attr" {
+                            forwarded.push(syn::parse2(group.stream())?);
+                        } else {
+                            fallible = Some(syn::parse2(group.stream())?);
+                        }
+                    }
+                }
+            }
+        }
+        Ok((forwarded, fallible))
     }
 }
 
@@ -144,7 +238,11 @@ pub fn impl_visit(input: DeriveInput, mutable: bool) -> Result<TokenStream> {
         ..
     } = &names;
 
-    let visit_options: Vec<VisitEntry> = parse::parse_attrs(&input.attrs, "visit")?;
+    let parse::VisitAttrs {
+        entries: visit_options,
+        forwarded: attrs,
+        fallible,
+    } = parse::parse_attrs(&input.attrs, "visit")?;
 
     let name = input.ident;
     let (_, ty_generics, _) = input.generics.split_for_impl();
@@ -187,20 +285,21 @@ pub fn impl_visit(input: DeriveInput, mutable: bool) -> Result<TokenStream> {
                 Skip => quote!(),
                 Drive => drive_inner,
                 Enter(name) => {
-                    let method = Ident::new(&format!("enter_{name}"), Span::call_site());
-                    quote!( self.#method(x); #drive_inner )
+                    let call = call_method(&fallible, "enter", name, quote!(x));
+                    quote!( #call; #drive_inner )
                 }
                 Exit(name) => {
-                    let method = Ident::new(&format!("exit_{name}"), Span::call_site());
-                    quote!( #drive_inner self.#method(x); )
+                    let call = call_method(&fallible, "exit", name, quote!(x));
+                    quote!( #drive_inner #call; )
                 }
                 Override(name) => {
-                    let method = Ident::new(&format!("visit_{name}"), Span::call_site());
-                    quote!( self.#method(x)?; )
+                    let call = call_method(&fallible, "visit", name, quote!(x));
+                    quote!( #call; )
                 }
             };
             let (impl_generics, _, where_clause) = generics.split_for_impl();
             quote! {
+                #(#attrs)*
                 impl #impl_generics
                     #visit_trait<#lifetime_param, #ty>
                     for #impl_subject
@@ -227,7 +326,11 @@ pub fn impl_visit_two(input: DeriveInput) -> Result<TokenStream> {
     let control_flow: Path = parse_quote!(::std::ops::ControlFlow);
     let lifetime_param: syn::Lifetime = parse_quote!('s);
 
-    let visit_options: Vec<VisitEntry> = parse::parse_attrs(&input.attrs, "visit_two")?;
+    let parse::VisitAttrs {
+        entries: visit_options,
+        forwarded: attrs,
+        fallible,
+    } = parse::parse_attrs(&input.attrs, "visit_two")?;
 
     let name = input.ident;
     let (_, ty_generics, _) = input.generics.split_for_impl();
@@ -270,20 +373,21 @@ pub fn impl_visit_two(input: DeriveInput) -> Result<TokenStream> {
                 Skip => quote!(),
                 Drive => drive_two_inner,
                 Enter(name) => {
-                    let method = Ident::new(&format!("enter_{name}"), Span::call_site());
-                    quote!( self.#method(x, y); #drive_two_inner )
+                    let call = call_method(&fallible, "enter", name, quote!(x, y));
+                    quote!( #call; #drive_two_inner )
                 }
                 Exit(name) => {
-                    let method = Ident::new(&format!("exit_{name}"), Span::call_site());
-                    quote!( #drive_two_inner self.#method(x, y); )
+                    let call = call_method(&fallible, "exit", name, quote!(x, y));
+                    quote!( #drive_two_inner #call; )
                 }
                 Override(name) => {
-                    let method = Ident::new(&format!("visit_{name}"), Span::call_site());
-                    quote!( self.#method(x, y)?; )
+                    let call = call_method(&fallible, "visit", name, quote!(x, y));
+                    quote!( #call; )
                 }
             };
             let (impl_generics, _, where_clause) = generics.split_for_impl();
             quote! {
+                #(#attrs)*
                 impl #impl_generics
                     #visit_two_trait<#lifetime_param, #ty>
                     for #impl_subject
@@ -302,6 +406,109 @@ pub fn impl_visit_two(input: DeriveInput) -> Result<TokenStream> {
     Ok(visit_impls)
 }
 
+/// Implement `VisitFold`. Custom methods take the value and return the value to replace it with.
+pub fn impl_visit_fold(input: DeriveInput) -> Result<TokenStream> {
+    use VisitKind::*;
+    let crate_path: Path = parse_quote! { ::derive_generic_visitor };
+    let visit_fold_trait: Path = parse_quote!( #crate_path::VisitFold );
+    let fold_trait: Path = parse_quote!( #crate_path::Fold );
+    let control_flow: Path = parse_quote!(::std::ops::ControlFlow);
+
+    let parse::VisitAttrs {
+        entries: visit_options,
+        forwarded: attrs,
+        fallible,
+    } = parse::parse_attrs(&input.attrs, "visit")?;
+
+    let name = input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let impl_subject = quote! { #name #ty_generics };
+
+    let visit_impls: TokenStream = visit_options
+        .iter()
+        .map(|visit| {
+            let generics = {
+                let mut generics = input.generics.clone();
+                generics
+                    .params
+                    .extend(visit.generics.params.iter().cloned());
+                let where_clause = generics.make_where_clause();
+                where_clause.predicates.extend(
+                    visit
+                        .generics
+                        .where_clause
+                        .iter()
+                        .flat_map(|cl| &cl.predicates)
+                        .cloned(),
+                );
+                for param in visit.generics.type_params() {
+                    let param = &param.ident;
+                    where_clause
+                        .predicates
+                        .push(parse_quote!(Self: #visit_fold_trait<#param>));
+                }
+                generics
+            };
+
+            let ty = &visit.ty;
+            let fold_inner = quote!(
+                let x = <#ty as #fold_trait<Self>>::fold_inner(x, self)?;
+            );
+            let body = match &visit.kind {
+                Skip => quote!(),
+                Drive => fold_inner,
+                Enter(name) => {
+                    let call = call_method(&fallible, "enter", name, quote!(x));
+                    quote!( let x = #call; #fold_inner )
+                }
+                Exit(name) => {
+                    let call = call_method(&fallible, "exit", name, quote!(x));
+                    quote!( #fold_inner let x = #call; )
+                }
+                Override(name) => {
+                    let call = call_method(&fallible, "visit", name, quote!(x));
+                    quote!( let x = #call; )
+                }
+            };
+            let (impl_generics, _, where_clause) = generics.split_for_impl();
+            quote! {
+                #(#attrs)*
+                impl #impl_generics
+                    #visit_fold_trait<#ty>
+                    for #impl_subject
+                    #where_clause
+                {
+                    #[inline]
+                    fn visit(&mut self, x: #ty) -> #control_flow<Self::Break, #ty> {
+                        #body
+                        #control_flow::Continue(x)
+                    }
+                }
+            }
+        })
+        .collect();
+    Ok(visit_impls)
+}
+
+/// Call the custom method `{prefix}_{name}`. `visit_*` methods return a `ControlFlow`, and with
+/// `fallible(..)` all the methods return a `Result`; either way we propagate the error.
+fn call_method(
+    fallible: &Option<Type>,
+    prefix: &str,
+    name: &Ident,
+    args: TokenStream,
+) -> TokenStream {
+    let method = Ident::new(&format!("{prefix}_{name}"), Span::call_site());
+    let call = quote!(self.#method(#args));
+    if fallible.is_some() {
+        quote!(::derive_generic_visitor::IntoControlFlow::into_control_flow(#call)?)
+    } else if prefix == "visit" {
+        quote!(#call?)
+    } else {
+        call
+    }
+}
+
 /// Implement the `Visitor` trait for our type, which provides the `Break` assoc ty.
 pub fn impl_visitor(input: DeriveInput) -> Result<TokenStream> {
     let names = Names::new(false);
@@ -311,10 +518,16 @@ pub fn impl_visitor(input: DeriveInput) -> Result<TokenStream> {
     let (_, ty_generics, _) = input.generics.split_for_impl();
     let impl_subject = quote! { #name #ty_generics };
 
+    let (attrs, fallible) = parse::parse_visitor_attrs(&input.attrs, "visit")?;
+    let break_ty = match fallible {
+        Some(ty) => quote!(#ty),
+        None => quote!(::std::convert::Infallible),
+    };
     let (impl_generics, _, where_clause) = input.generics.split_for_impl();
     Ok(quote! {
+        #(#attrs)*
         impl #impl_generics #visitor_trait for #impl_subject #where_clause {
-            type Break = ::std::convert::Infallible;
+            type Break = #break_ty;
         }
     })
 }
@@ -1,8 +1,8 @@
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
-use syn::{parse_quote, Attribute, Ident, ItemImpl, ItemTrait, Result, Token};
+use syn::{parse_quote, Attribute, Ident, Item, ItemImpl, ItemTrait, Result, Token};
 
-use crate::{GenericTy, Names};
+use crate::{ForwardedAttrs, GenericTy, Names};
 
 enum TyVisitKind {
     Skip,
@@ -18,12 +18,28 @@ struct VisitorDef {
     faillible: bool,
     attrs: Vec<Attribute>,
     super_bounds: Vec<syn::TypeParamBound>,
+    /// The drive method and visitor trait of the base group that this visitor extends.
+    extends: Option<(Ident, syn::Path)>,
+    /// Whether the visitor methods return a `Self::Output` computed from the outputs of the
+    /// contents instead of `ControlFlow`.
+    synthesized: bool,
+    /// The type of the context passed by reference alongside each visited value, if any.
+    ctx: Option<syn::Type>,
 }
 
 #[derive(Default)]
 pub struct Options {
     visitors: Vec<VisitorDef>,
     tys: Vec<(GenericTy, TyVisitKind)>,
+    strict: bool,
+    /// The visitable trait of the base group, if this group extends another.
+    extends: Option<syn::Path>,
+    /// Attributes to put on the generated items.
+    attrs: Vec<ForwardedAttrs>,
+    /// Whether visitors number the overridden nodes they visit.
+    node_ids: bool,
+    /// Whether to add `for_each_$ty` and `map_each_$ty_mut` methods to the visitable trait.
+    closure_helpers: bool,
 }
 
 mod parse {
@@ -35,6 +51,7 @@ mod parse {
     };
 
     use crate::{
+        common::{unknown_keyword, ForwardedAttrs},
         visitable_group::{TyVisitKind, VisitorDef},
         NamedGenericTy,
     };
@@ -47,11 +64,21 @@ mod parse {
         syn::custom_keyword!(override_skip);
         syn::custom_keyword!(bounds);
         syn::custom_keyword!(two);
+        syn::custom_keyword!(strict);
+        syn::custom_keyword!(extends);
+        syn::custom_keyword!(attr);
+        syn::custom_keyword!(synthesized);
+        syn::custom_keyword!(node_ids);
+        syn::custom_keyword!(ctx);
+        syn::custom_keyword!(closure_helpers);
     }
 
     /// Optional settings that follow the main `visitor(method_name(&[mut|two] TraitName), ...)` args.
     enum VisitorOpt {
         Infallible(#[allow(unused)] kw::infallible),
+        Synthesized(kw::synthesized),
+        /// `ctx(Ty)`: the visitor methods take a `&Ty` before the visited value.
+        Ctx(kw::ctx, syn::Type),
         Bounds {
             #[allow(unused)]
             kw: kw::bounds,
@@ -59,6 +86,12 @@ mod parse {
             paren: token::Paren,
             bounds: Punctuated<syn::TypeParamBound, Token![+]>,
         },
+        /// `extends(base_method(&[mut|two] BaseVisitor))`, written like the base visitor's own
+        /// declaration.
+        Extends {
+            method_name: Ident,
+            trait_path: syn::Path,
+        },
     }
 
     impl Parse for VisitorOpt {
@@ -66,6 +99,13 @@ mod parse {
             let lookahead = input.lookahead1();
             if lookahead.peek(kw::infallible) {
                 Ok(VisitorOpt::Infallible(input.parse()?))
+            } else if lookahead.peek(kw::synthesized) {
+                Ok(VisitorOpt::Synthesized(input.parse()?))
+            } else if lookahead.peek(kw::ctx) {
+                let kw = input.parse()?;
+                let content;
+                parenthesized!(content in input);
+                Ok(VisitorOpt::Ctx(kw, content.parse()?))
             } else if lookahead.peek(kw::bounds) {
                 let content;
                 Ok(VisitorOpt::Bounds {
@@ -73,6 +113,28 @@ mod parse {
                     paren: parenthesized!(content in input),
                     bounds: Punctuated::parse_terminated(&content)?,
                 })
+            } else if lookahead.peek(kw::extends) {
+                let _: kw::extends = input.parse()?;
+                let content;
+                let content2;
+                parenthesized!(content in input);
+                let method_name = content.parse()?;
+                parenthesized!(content2 in content);
+                let _: Token![&] = content2.parse()?;
+                if content2.peek(kw::two) {
+                    let _: kw::two = content2.parse()?;
+                } else {
+                    let _: Option<Token![mut]> = content2.parse()?;
+                }
+                Ok(VisitorOpt::Extends {
+                    method_name,
+                    trait_path: content2.parse()?,
+                })
+            } else if let Ok(ident) = input.fork().parse::<Ident>() {
+                Err(unknown_keyword(
+                    &ident,
+                    &["infallible", "synthesized", "ctx", "bounds", "extends"],
+                ))
             } else {
                 Err(lookahead.error())
             }
@@ -115,6 +177,18 @@ mod parse {
             paren: token::Paren,
             tys: Punctuated<NamedGenericTy, Token![,]>,
         },
+        /// `strict` checks at compile time that the contents of the driven and overridden types
+        /// are all part of the group.
+        Strict(#[allow(unused)] kw::strict),
+        /// `extends(BaseVisitable)` makes this group an extension of another group: all the types
+        /// of the base group are visitable by this group's visitors.
+        Extends(syn::Path),
+        /// `attr(...)`: attributes to put on all the generated items.
+        Attr(ForwardedAttrs),
+        /// `node_ids` makes the visitors assign a `NodeId` to each overridden node they visit.
+        NodeIds(kw::node_ids),
+        /// `closure_helpers` adds methods that visit with a closure to the visitable trait.
+        ClosureHelpers(#[allow(unused)] kw::closure_helpers),
     }
 
     impl Parse for MacroArg {
@@ -146,6 +220,24 @@ mod parse {
                     paren: parenthesized!(content in input),
                     tys: Punctuated::parse_terminated(&content)?,
                 }
+            } else if lookahead.peek(kw::strict) {
+                MacroArg::Strict(input.parse()?)
+            } else if lookahead.peek(kw::node_ids) {
+                MacroArg::NodeIds(input.parse()?)
+            } else if lookahead.peek(kw::closure_helpers) {
+                MacroArg::ClosureHelpers(input.parse()?)
+            } else if lookahead.peek(kw::attr) {
+                let _: kw::attr = input.parse()?;
+                MacroArg::Attr({
+                    parenthesized!(content in input);
+                    content.parse()?
+                })
+            } else if lookahead.peek(kw::extends) {
+                let _: kw::extends = input.parse()?;
+                MacroArg::Extends({
+                    parenthesized!(content in input);
+                    content.parse()?
+                })
             } else if lookahead.peek(kw::visitor) {
                 let two;
                 MacroArg::SetVisitorTrait {
@@ -176,6 +268,22 @@ mod parse {
                         Punctuated::new()
                     },
                 }
+            } else if let Ok(ident) = input.fork().parse::<Ident>() {
+                return Err(unknown_keyword(
+                    &ident,
+                    &[
+                        "visitor",
+                        "drive",
+                        "skip",
+                        "override",
+                        "override_skip",
+                        "strict",
+                        "extends",
+                        "attr",
+                        "node_ids",
+                        "closure_helpers",
+                    ],
+                ));
             } else {
                 return Err(lookahead.error());
             })
@@ -188,6 +296,7 @@ mod parse {
             use VisitableTypeKind::*;
             let args: Punctuated<MacroArg, Token![,]> = Punctuated::parse_terminated(input)?;
             let mut options = super::Options::default();
+            let mut node_ids = None;
             for arg in args {
                 match arg {
                     SetVisitorTrait {
@@ -201,12 +310,41 @@ mod parse {
                     } => {
                         let mut faillible = true;
                         let mut super_bounds = vec![];
+                        let mut extends = None;
+                        let mut synthesized = None;
+                        let mut ctx = None;
                         for opt in opts {
                             match opt {
                                 VisitorOpt::Infallible(_) => faillible = false,
+                                VisitorOpt::Synthesized(kw) => synthesized = Some(kw),
+                                VisitorOpt::Ctx(kw, ty) => ctx = Some((kw, ty)),
                                 VisitorOpt::Bounds { bounds, .. } => {
                                     super_bounds.extend(bounds);
                                 }
+                                VisitorOpt::Extends {
+                                    method_name,
+                                    trait_path,
+                                } => extends = Some((method_name, trait_path)),
+                            }
+                        }
+                        if let Some(kw) = synthesized {
+                            if two.is_some() || extends.is_some() {
+                                return Err(syn::Error::new_spanned(
+                                    kw,
+                                    "synthesized visitors can't be lockstep visitors or extend \
+                                    another visitor",
+                                ));
+                            }
+                            // Synthesized visitors don't return `ControlFlow`.
+                            faillible = false;
+                        }
+                        if let Some((kw, _)) = &ctx {
+                            if two.is_some() || extends.is_some() || synthesized.is_some() {
+                                return Err(syn::Error::new_spanned(
+                                    kw,
+                                    "visitors with a context can't be lockstep, synthesized or \
+                                    extend another visitor",
+                                ));
                             }
                         }
                         options.visitors.push(VisitorDef {
@@ -217,6 +355,9 @@ mod parse {
                             faillible,
                             attrs,
                             super_bounds,
+                            extends,
+                            synthesized: synthesized.is_some(),
+                            ctx: ctx.map(|(_, ty)| ty),
                         });
                     }
                     SetVisitableTypes { kind, tys, .. } => {
@@ -236,8 +377,22 @@ mod parse {
                             options.tys.push((ty.ty, kind));
                         }
                     }
+                    Strict(_) => options.strict = true,
+                    Extends(path) => options.extends = Some(path),
+                    Attr(attrs) => options.attrs.push(attrs),
+                    NodeIds(kw) => node_ids = Some(kw),
+                    ClosureHelpers(_) => options.closure_helpers = true,
                 }
             }
+            if let Some(kw) = node_ids {
+                if options.extends.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        kw,
+                        "`node_ids` can't be used together with `extends`",
+                    ));
+                }
+                options.node_ids = true;
+            }
             Ok(options)
         }
     }
@@ -271,17 +426,45 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
             mutability,
             is_two,
             faillible,
+            synthesized,
+            ctx,
             ..
         } = vis_def;
-        let return_type = faillible.then_some(quote!(-> #control_flow<V::Break>));
+        let return_type = if *synthesized {
+            Some(quote!(-> V::Output))
+        } else {
+            faillible.then_some(quote!(-> #control_flow<V::Break>))
+        };
         let other_param = is_two.then(|| quote!(, other: &Self));
+        let ctx_param = ctx.as_ref().map(|ctx| quote!(, ctx: &#ctx));
         item.items.push(parse_quote!(
             /// Recursively visit this type with the provided visitor. This calls the visitor's `visit_$any`
             /// method if it exists, otherwise `visit_inner`.
-            fn #method_name<V: #vis_trait_name>(& #mutability self #other_param, v: &mut V) #return_type;
+            fn #method_name<V: #vis_trait_name>(& #mutability self #other_param #ctx_param, v: &mut V) #return_type;
         ));
     }
 
+    // Check that either all or none of the visitors extend a base visitor.
+    for (vis_def, _) in &visitor_traits {
+        match (&options.extends, &vis_def.extends) {
+            (Some(_), None) => {
+                return Err(syn::Error::new_spanned(
+                    &vis_def.vis_trait_name,
+                    "this group extends another group, so this visitor must declare which base \
+                    visitor it extends with `extends(base_method(&[mut] BaseVisitor))`",
+                ))
+            }
+            (None, Some((method, _))) => {
+                return Err(syn::Error::new_spanned(
+                    method,
+                    "this visitor extends a base visitor, so the group must declare which group \
+                    it extends with `extends(BaseVisitable)`",
+                ))
+            }
+            _ => {}
+        }
+    }
+
     // Implement the visitable trait for the listed types.
     let mut impls: Vec<ItemImpl> = options
         .tys
@@ -299,24 +482,49 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
                     mutability,
                     is_two,
                     faillible,
+                    synthesized,
+                    ctx,
                     ..
                 } = vis_def;
                 let other_param = is_two.then(|| quote!(, other: &Self));
                 let other_arg = is_two.then(|| quote!(, other));
-                let return_type = faillible.then_some(quote!(-> #control_flow<V::Break>));
+                let ctx_param = ctx.as_ref().map(|ctx| quote!(, ctx: &#ctx));
+                let ctx_arg = ctx.as_ref().map(|_| quote!(ctx,));
+                let return_type = if *synthesized {
+                    Some(quote!(-> V::Output))
+                } else {
+                    faillible.then_some(quote!(-> #control_flow<V::Break>))
+                };
                 let body = match kind {
-                    TyVisitKind::Skip if *faillible => quote!( #control_flow::Continue(()) ),
-                    TyVisitKind::Skip => quote!(),
-                    TyVisitKind::Drive => quote!(v.visit_inner(self #other_arg)),
+                    TyVisitKind::Skip if *synthesized => quote!(::std::default::Default::default()),
+                    TyVisitKind::Skip => {
+                        let ignore_ctx = ctx.as_ref().map(|_| quote!(let _ = ctx;));
+                        let result = faillible.then_some(quote!(#control_flow::Continue(())));
+                        quote!( #ignore_ctx #result )
+                    }
+                    TyVisitKind::Drive => {
+                        quote!(#vis_trait_name::visit_inner(v, #ctx_arg self #other_arg))
+                    }
                     TyVisitKind::Override { name, .. } => {
                         let method = Ident::new(&format!("visit_{name}"), Span::call_site());
-                        quote!( v.#method(self #other_arg) )
+                        if options.node_ids {
+                            quote!(
+                                #vis_trait_name::node_ids(v).__enter();
+                                let result = v.#method(#ctx_arg self #other_arg);
+                                #vis_trait_name::node_ids(v).__exit();
+                                result
+                            )
+                        } else {
+                            quote!( v.#method(#ctx_arg self #other_arg) )
+                        }
                     }
                 };
                 timpl.items.push(parse_quote!(
                     #[inline]
-                    fn #method_name<V: #vis_trait_name>(& #mutability self #other_param, v: &mut V)
-                        #return_type
+                    fn #method_name<V: #vis_trait_name>(
+                        & #mutability self #other_param #ctx_param,
+                        v: &mut V,
+                    ) #return_type
                     {
                         #body
                     }
@@ -326,6 +534,40 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
         })
         .collect();
 
+    // Make all the types of the base group visitable by delegating to the base group's methods.
+    // This works because our visitor traits have the base visitor traits as supertraits.
+    if let Some(base_trait) = &options.extends {
+        let mut timpl: ItemImpl = parse_quote! {
+            impl<T: ?Sized + #base_trait> #trait_name for T {}
+        };
+        for (vis_def, _) in &visitor_traits {
+            let VisitorDef {
+                vis_trait_name,
+                method_name,
+                mutability,
+                is_two,
+                faillible,
+                extends,
+                ..
+            } = vis_def;
+            let Some((base_method, _)) = extends else {
+                continue;
+            };
+            let other_param = is_two.then(|| quote!(, other: &Self));
+            let other_arg = is_two.then(|| quote!(other,));
+            let return_type = faillible.then_some(quote!(-> #control_flow<V::Break>));
+            timpl.items.push(parse_quote!(
+                #[inline]
+                fn #method_name<V: #vis_trait_name>(& #mutability self #other_param, v: &mut V)
+                    #return_type
+                {
+                    #base_trait::#base_method(self, #other_arg v)
+                }
+            ));
+        }
+        impls.push(timpl);
+    }
+
     // Define a wrapper type that implements `Visit[Mut]` to pass through the `Drive[Mut]` API.
     let wrapper_name = Ident::new(&format!("{trait_name}Wrapper"), Span::call_site());
     let infallible_wrapper_name =
@@ -347,25 +589,38 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
             })
         };
         let wrapper_struct = define_struct(&wrapper_name);
+        let wrapper_binder =
+            forward_visit_binder(quote!(<V>), quote!(#wrapper_name<V>), quote!(&mut self.0));
         let wrapper_visitor = quote!(
             #wrapper_struct
             impl<V: Visitor> Visitor for #wrapper_name<V> {
                 type Break = V::Break;
             }
+            #wrapper_binder
         );
         let infallible_wrapper_struct = define_struct(&infallible_wrapper_name);
-        let any_infallible_visitor = !visitor_traits.iter().all(|(v, _)| v.faillible);
+        let any_infallible_visitor = visitor_traits
+            .iter()
+            .any(|(v, _)| !v.faillible && !v.synthesized);
+        let infallible_wrapper_binder = forward_visit_binder(
+            quote!(<V>),
+            quote!(#infallible_wrapper_name<V>),
+            quote!(&mut self.0),
+        );
         let infallible_wrapper_visitor = any_infallible_visitor.then_some(quote!(
             #infallible_wrapper_struct
+            // Breaks when the visitor asks to stop, see `should_stop`.
             impl<V> Visitor for #infallible_wrapper_name<V> {
-                type Break = std::convert::Infallible;
+                type Break = ();
             }
+            #infallible_wrapper_binder
         ));
         quote!(
             #wrapper_visitor
             #infallible_wrapper_visitor
         )
     };
+    let mut synth_wrappers = vec![];
     for (vis_def, names) in &visitor_traits {
         let Names { visit_trait, .. } = &names;
         let VisitorDef {
@@ -373,8 +628,83 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
             mutability,
             is_two,
             faillible,
+            synthesized,
+            ctx,
             ..
         } = vis_def;
+        if let Some(ctx) = ctx {
+            // Visitors with a context get their own wrapper, which carries the context.
+            let wrapper_name = ctx_wrapper_name(vis_trait_name);
+            let break_ty = if *faillible {
+                quote!(V::Break)
+            } else {
+                quote!(::std::convert::Infallible)
+            };
+            let mut body = quote!(#vis_trait_name::visit(self.v, self.ctx, x));
+            if !faillible {
+                body = quote!(#control_flow::Continue(#body));
+            }
+            synth_wrappers.push(quote!(
+                /// Implementation detail: wrapper that implements `Visit[Mut]<T>` for
+                /// `T: #trait_name` by calling our trait's `visit` with the context. Used in the
+                /// implementation of `visit_inner`.
+                pub struct #wrapper_name<'w, V> {
+                    v: &'w mut V,
+                    ctx: &'w #ctx,
+                }
+                impl<V: #vis_trait_name> #the_visitor_trait for #wrapper_name<'_, V> {
+                    type Break = #break_ty;
+                }
+            ));
+            synth_wrappers.push(forward_visit_binder(
+                quote!(<V: #vis_trait_name>),
+                quote!(#wrapper_name<'_, V>),
+                quote!(&mut *self.v),
+            ));
+            impls.push(parse_quote!(
+                impl<'s, V: #vis_trait_name, T: #trait_name> #visit_trait<'s, T> for #wrapper_name<'_, V> {
+                    #[inline]
+                    fn visit(&mut self, x: &'s #mutability T) -> #control_flow<Self::Break> {
+                        #body
+                    }
+                }
+            ));
+            continue;
+        }
+        if *synthesized {
+            // Synthesized visitors get their own wrapper, which accumulates the outputs of the
+            // contents.
+            let wrapper_name = synth_wrapper_name(vis_trait_name);
+            synth_wrappers.push(quote!(
+                /// Implementation detail: wrapper that implements `Visit[Mut]<T>` for
+                /// `T: #trait_name` by calling our trait's `visit` and combining the outputs. Used
+                /// in the implementation of `visit_inner`.
+                pub struct #wrapper_name<'w, V: #vis_trait_name> {
+                    v: &'w mut V,
+                    acc: V::Output,
+                }
+                impl<V: #vis_trait_name> #the_visitor_trait for #wrapper_name<'_, V> {
+                    type Break = ::std::convert::Infallible;
+                }
+            ));
+            synth_wrappers.push(forward_visit_binder(
+                quote!(<V: #vis_trait_name>),
+                quote!(#wrapper_name<'_, V>),
+                quote!(&mut *self.v),
+            ));
+            impls.push(parse_quote!(
+                impl<'s, V: #vis_trait_name, T: #trait_name> #visit_trait<'s, T> for #wrapper_name<'_, V> {
+                    #[inline]
+                    fn visit(&mut self, x: &'s #mutability T) -> #control_flow<Self::Break> {
+                        let out = #vis_trait_name::visit(self.v, x);
+                        let acc = ::std::mem::take(&mut self.acc);
+                        self.acc = #vis_trait_name::combine(self.v, acc, out);
+                        #control_flow::Continue(())
+                    }
+                }
+            ));
+            continue;
+        }
         let wrapper_name = if *faillible {
             &wrapper_name
         } else {
@@ -383,9 +713,16 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
 
         let y_param = is_two.then(|| quote!(, y: &'s T));
         let y_arg = is_two.then(|| quote!(, y));
-        let mut body = quote!(self.0.visit(x #y_arg));
+        let mut body = quote!(#vis_trait_name::visit(&mut self.0, x #y_arg));
         if !faillible {
-            body = quote!(Continue(#body));
+            body = quote!(
+                if #vis_trait_name::should_stop(&self.0) {
+                    #control_flow::Break(())
+                } else {
+                    #body;
+                    #control_flow::Continue(())
+                }
+            );
         }
         impls.push(parse_quote!(
             impl<'s, V: #vis_trait_name, T: #trait_name> #visit_trait<'s, T> for #wrapper_name<V> {
@@ -414,7 +751,31 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
             faillible,
             attrs,
             super_bounds,
+            extends,
+            synthesized,
+            ctx,
         } = vis_def;
+        if *synthesized {
+            traits.push(synthesized_visitor_trait(
+                trait_name,
+                vis,
+                &options.tys,
+                vis_def,
+                names,
+            ));
+            continue;
+        }
+        if let Some(ctx) = ctx {
+            traits.push(ctx_visitor_trait(
+                trait_name,
+                vis,
+                &options.tys,
+                vis_def,
+                names,
+                ctx,
+            ));
+            continue;
+        }
         let return_type = faillible.then_some(quote!(-> #control_flow<Self::Break>));
         let return_type_val = if *faillible {
             quote!(-> #control_flow<Self::Break, Self>)
@@ -433,9 +794,8 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
             };
             let mut body = quote! {x.#drive_inner_method(#y_arg_t_comma #wrapper_name::wrap(self))};
             if !*faillible {
-                body = quote!(match #body {
-                    #control_flow::Continue(x) => x,
-                });
+                // The wrapper only breaks to stop early, which isn't an error here.
+                body = quote!(let _ = #body;);
             }
             quote! {
                 /// Visit the contents of `x`. This calls `self.visit()` on each field of `T`. This
@@ -459,6 +819,7 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
             faillible.then_some(quote!(Visitor))
         }
         .into_iter()
+        .chain(extends.iter().map(|(_, base)| quote!(#base)))
         .chain(super_bounds.iter().map(|b| quote!(#b)));
 
         // Generate `visit`, `visit_by_val`, and optionally `visit_by_val_infallible`.
@@ -476,9 +837,9 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
             }
         };
         let visit_by_val_body = if *faillible {
-            quote!(self.visit(x #y_arg_vis).map_continue(|()| self))
+            quote!(#vis_trait_name::visit(&mut self, x #y_arg_vis).map_continue(|()| self))
         } else {
-            quote!( self.visit(x); self )
+            quote!( #vis_trait_name::visit(&mut self, x); self )
         };
         let visit_by_val_method = quote! {
             /// Convenience alias for method chaining.
@@ -497,7 +858,7 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
                 where
                     Self: #the_visitor_trait<Break=::std::convert::Infallible> + Sized,
                 {
-                    match self.visit_by_val(x) {
+                    match #vis_trait_name::visit_by_val(self, x) {
                         #control_flow::Continue(x) => x,
                     }
                 }
@@ -506,13 +867,68 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
             None
         };
 
+        // Infallible visitors can stop early with `should_stop`, and get `try_visit[_inner]` to
+        // report why they stopped.
+        let try_visit = (!*faillible).then(|| {
+            quote! {
+                /// Whether to stop the traversal early. This is checked before visiting each of
+                /// the contents of a value: once it returns `true`, the rest of the contents are
+                /// skipped, so the traversal winds down without visiting anything else. The code
+                /// of overrides that runs after `visit_inner` returns still runs. The default
+                /// implementation never stops.
+                #[inline]
+                fn should_stop(&self) -> bool {
+                    false
+                }
+                /// Like `visit`, for early-exit queries. If the visitor stopped early (see
+                /// `should_stop`), returns `Break(b)` where `b` is taken out of the visitor by
+                /// `take_break`, e.g. `|v| v.found.take()`.
+                #[inline]
+                fn try_visit<T: #trait_name, B>(
+                    &mut self,
+                    x: & #mutability T #y_param_vis,
+                    take_break: impl FnOnce(&mut Self) -> Option<B>,
+                ) -> #control_flow<B> {
+                    if !#vis_trait_name::should_stop(self) {
+                        #vis_trait_name::visit(self, x #y_arg_vis);
+                    }
+                    match take_break(self) {
+                        Some(b) => #control_flow::Break(b),
+                        None => #control_flow::Continue(()),
+                    }
+                }
+                /// Like `visit_inner`, for early-exit queries. See `try_visit`.
+                #[inline]
+                fn try_visit_inner<T, B>(
+                    &mut self,
+                    x: & #mutability T #y_param_t,
+                    take_break: impl FnOnce(&mut Self) -> Option<B>,
+                ) -> #control_flow<B>
+                where
+                    T: #trait_name,
+                    T: for<'s> #drive_trait<'s, #infallible_wrapper_name<Self>>,
+                {
+                    if !#vis_trait_name::should_stop(self) {
+                        #vis_trait_name::visit_inner(self, x #y_arg_vis);
+                    }
+                    match take_break(self) {
+                        Some(b) => #control_flow::Break(b),
+                        None => #control_flow::Continue(()),
+                    }
+                }
+            }
+        });
+
         let mut visitor_trait: ItemTrait = parse_quote! {
             #(#attrs)*
+            // The signatures of the overrideable methods are dictated by the visited types.
+            #[allow(clippy::ptr_arg)]
             #vis trait #vis_trait_name: #(#visitor_constraints + )* Sized where  {
                 #visit_method
                 #visit_by_val_method
                 #visit_by_val_infallible
                 #visit_inner
+                #try_visit
             }
         };
 
@@ -534,7 +950,7 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
 
             let body = (!skip).then_some(quote! {
                 self.#enter_method(x #y_arg);
-                self.visit_inner(x #y_arg)#question_mark;
+                #vis_trait_name::visit_inner(self, x #y_arg)#question_mark;
                 self.#exit_method(x #y_arg);
             });
             visitor_trait.items.push(parse_quote!(
@@ -569,12 +985,571 @@ pub fn impl_visitable_group(options: Options, mut item: ItemTrait) -> Result<Tok
         }
         traits.push(visitor_trait);
     }
+    if options.node_ids {
+        for visitor_trait in &mut traits {
+            visitor_trait.items.push(parse_quote!(
+                /// The `NodeIds` that numbers the nodes visited by this visitor. Overridden types
+                /// get an id before `visit_$ty` is called; within it, `self.node_ids().current()`
+                /// is the id of `x`.
+                fn node_ids(&mut self) -> &mut ::derive_generic_visitor::NodeIds;
+            ));
+        }
+    }
+
+    let strict_checks = options
+        .strict
+        .then(|| strict_checks(trait_name, &options.tys, &visitor_traits));
+    if options.closure_helpers {
+        for mutable in [false, true] {
+            item.items.extend(closure_methods(
+                &options.tys,
+                &visitor_traits,
+                mutable,
+                options.node_ids,
+            ));
+        }
+    }
 
     traits.insert(0, item);
 
-    Ok(quote!(
+    let generated = quote!(
         #visitor_wrappers
+        #(#synth_wrappers)*
         #(#traits)*
         #(#impls)*
-    ))
+        #strict_checks
+    );
+    if options.attrs.is_empty() {
+        Ok(generated)
+    } else {
+        forward_attrs(&options.attrs, generated)
+    }
+}
+
+/// Implement `VisitBinder` for a visitor wrapper by forwarding to the wrapped visitor `inner`, so
+/// that types with `#[drive(binder)]` fields can be part of the group.
+fn forward_visit_binder(
+    generics: TokenStream,
+    wrapper: TokenStream,
+    inner: TokenStream,
+) -> TokenStream {
+    let visit_binder = quote!(::derive_generic_visitor::VisitBinder);
+    let generics: syn::Generics = parse_quote!(#generics);
+    let mut generics_with_bound = generics.clone();
+    generics_with_bound
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(V: #visit_binder));
+    let (impl_generics, _, where_clause) = generics_with_bound.split_for_impl();
+    quote!(
+        impl #impl_generics #visit_binder for #wrapper #where_clause {
+            #[inline]
+            fn binder_depth(&mut self) -> &mut usize {
+                #visit_binder::binder_depth(#inner)
+            }
+            #[inline]
+            fn enter_binder(&mut self) {
+                #visit_binder::enter_binder(#inner)
+            }
+            #[inline]
+            fn exit_binder(&mut self) {
+                #visit_binder::exit_binder(#inner)
+            }
+        }
+    )
+}
+
+/// Name of the wrapper used to implement `visit_inner` for a synthesized visitor.
+fn synth_wrapper_name(vis_trait_name: &Ident) -> Ident {
+    Ident::new(&format!("{vis_trait_name}Wrapper"), Span::call_site())
+}
+
+/// Generate the visitor trait of a `synthesized` visitor: its methods return `Self::Output`, and by
+/// default the output for a value is the combination of the outputs of its contents.
+fn synthesized_visitor_trait(
+    trait_name: &Ident,
+    vis: &syn::Visibility,
+    tys: &[(GenericTy, TyVisitKind)],
+    vis_def: &VisitorDef,
+    names: &Names,
+) -> ItemTrait {
+    let Names {
+        drive_trait,
+        drive_inner_method,
+        control_flow,
+        ..
+    } = names;
+    let VisitorDef {
+        vis_trait_name,
+        method_name,
+        mutability,
+        attrs,
+        super_bounds,
+        ..
+    } = vis_def;
+    let wrapper_name = synth_wrapper_name(vis_trait_name);
+    let super_bounds = super_bounds.iter().map(|b| quote!(#b + ));
+    let mut visitor_trait: ItemTrait = parse_quote! {
+        #(#attrs)*
+        // The signatures of the overrideable methods are dictated by the visited types.
+        #[allow(clippy::ptr_arg)]
+        #vis trait #vis_trait_name: #(#super_bounds)* Sized {
+            /// The value computed for each visited node.
+            type Output: ::std::default::Default;
+
+            /// Combine the outputs of two sibling values. The output of a value without contents
+            /// is `Output::default()`.
+            fn combine(&mut self, acc: Self::Output, x: Self::Output) -> Self::Output;
+
+            /// Visit a visitable type. This calls the appropriate method of this trait on `x`
+            /// (`visit_$ty` if it exists, `visit_inner` if not) and returns its output.
+            #[inline]
+            fn visit<T: #trait_name>(&mut self, x: & #mutability T) -> Self::Output {
+                x.#method_name(self)
+            }
+
+            /// Visit the contents of `x`. This calls `self.visit()` on each field of `T` and
+            /// combines the outputs with `combine`. This is available for any type whose contents
+            /// are all `#trait_name`.
+            #[inline]
+            fn visit_inner<T>(&mut self, x: & #mutability T) -> Self::Output
+            where
+                T: #trait_name,
+                T: for<'s, 'w> #drive_trait<'s, #wrapper_name<'w, Self>>,
+            {
+                let mut wrapper = #wrapper_name {
+                    v: self,
+                    acc: ::std::default::Default::default(),
+                };
+                match x.#drive_inner_method(&mut wrapper) {
+                    #control_flow::Continue(()) => {}
+                }
+                wrapper.acc
+            }
+        }
+    };
+
+    // Add the overrideable methods.
+    for (ty, kind) in tys {
+        let TyVisitKind::Override { name, skip } = kind else {
+            continue;
+        };
+        let visit_method_name = Ident::new(&format!("visit_{name}"), Span::call_site());
+        let enter_method = Ident::new(&format!("enter_{name}"), Span::call_site());
+        let exit_method = Ident::new(&format!("exit_{name}"), Span::call_site());
+        let (impl_generics, _, where_clause) = ty.generics.split_for_impl();
+        let ty = &ty.ty;
+        let body = if *skip {
+            quote!(::std::default::Default::default())
+        } else {
+            quote! {
+                self.#enter_method(x);
+                let out = #vis_trait_name::visit_inner(self, x);
+                self.#exit_method(x);
+                out
+            }
+        };
+        visitor_trait.items.push(parse_quote!(
+            /// Overrideable method called when visiting a `$ty`. When overriding this method,
+            /// call `self.visit_inner(x)` to get the combined output of the contents of `x`.
+            ///
+            /// The default implementation calls `enter_$ty` then `visit_inner` then `exit_$ty`.
+            #[inline]
+            fn #visit_method_name #impl_generics(&mut self, x: &#mutability #ty) -> Self::Output
+            #where_clause
+            {
+                #body
+            }
+        ));
+        if !skip {
+            visitor_trait.items.push(parse_quote!(
+                /// Called when starting to visit a `$ty` (unless `visit_$ty` is overriden).
+                #[inline]
+                fn #enter_method #impl_generics(&mut self, x: &#mutability #ty) #where_clause {}
+            ));
+            visitor_trait.items.push(parse_quote!(
+                /// Called when finished visiting a `$ty` (unless `visit_$ty` is overriden).
+                #[inline]
+                fn #exit_method #impl_generics(&mut self, x: &#mutability #ty) #where_clause {}
+            ));
+        }
+    }
+    visitor_trait
+}
+
+/// Name of the wrapper used to implement `visit_inner` for a visitor with a context.
+fn ctx_wrapper_name(vis_trait_name: &Ident) -> Ident {
+    Ident::new(&format!("{vis_trait_name}CtxWrapper"), Span::call_site())
+}
+
+/// Generate the visitor trait of a visitor with a `ctx(Ctx)`: its methods take a `&Ctx` before the
+/// visited value, and pass it on to the contents unless overridden.
+fn ctx_visitor_trait(
+    trait_name: &Ident,
+    vis: &syn::Visibility,
+    tys: &[(GenericTy, TyVisitKind)],
+    vis_def: &VisitorDef,
+    names: &Names,
+    ctx: &syn::Type,
+) -> ItemTrait {
+    let Names {
+        drive_trait,
+        drive_inner_method,
+        control_flow,
+        visitor_trait,
+        ..
+    } = names;
+    let VisitorDef {
+        vis_trait_name,
+        method_name,
+        mutability,
+        faillible,
+        attrs,
+        super_bounds,
+        ..
+    } = vis_def;
+    let wrapper_name = ctx_wrapper_name(vis_trait_name);
+    let return_type = faillible.then_some(quote!(-> #control_flow<Self::Break>));
+    let visitor_constraints = faillible
+        .then_some(quote!(#visitor_trait))
+        .into_iter()
+        .chain(super_bounds.iter().map(|b| quote!(#b)));
+    let mut drive = quote!(x.#drive_inner_method(&mut #wrapper_name { v: self, ctx }));
+    if !faillible {
+        drive = quote!(match #drive {
+            #control_flow::Continue(()) => {}
+        });
+    }
+    let mut visitor_trait: ItemTrait = parse_quote! {
+        #(#attrs)*
+        // The signatures of the overrideable methods are dictated by the visited types.
+        #[allow(clippy::ptr_arg)]
+        #vis trait #vis_trait_name: #(#visitor_constraints + )* Sized {
+            /// Visit a visitable type in the given context. This calls the appropriate method of
+            /// this trait on `x` (`visit_$ty` if it exists, `visit_inner` if not).
+            #[inline]
+            fn visit<T: #trait_name>(&mut self, ctx: &#ctx, x: & #mutability T) #return_type {
+                x.#method_name(ctx, self)
+            }
+
+            /// Visit the contents of `x` in the given context. This calls `self.visit(ctx, _)` on
+            /// each field of `T`. This is available for any type whose contents are all
+            /// `#trait_name`.
+            #[inline]
+            fn visit_inner<T>(&mut self, ctx: &#ctx, x: & #mutability T) #return_type
+            where
+                T: #trait_name,
+                T: for<'s, 'w> #drive_trait<'s, #wrapper_name<'w, Self>>,
+            {
+                #drive
+            }
+        }
+    };
+
+    // Add the overrideable methods.
+    for (ty, kind) in tys {
+        let TyVisitKind::Override { name, skip } = kind else {
+            continue;
+        };
+        let visit_method_name = Ident::new(&format!("visit_{name}"), Span::call_site());
+        let enter_method = Ident::new(&format!("enter_{name}"), Span::call_site());
+        let exit_method = Ident::new(&format!("exit_{name}"), Span::call_site());
+        let (impl_generics, _, where_clause) = ty.generics.split_for_impl();
+        let ty = &ty.ty;
+        let question_mark = faillible.then_some(quote!(?));
+        let return_value = faillible.then_some(quote!(#control_flow::Continue(())));
+        let body = if *skip {
+            quote!(let _ = (ctx, x);)
+        } else {
+            quote! {
+                self.#enter_method(ctx, x);
+                #vis_trait_name::visit_inner(self, ctx, x)#question_mark;
+                self.#exit_method(ctx, x);
+            }
+        };
+        visitor_trait.items.push(parse_quote!(
+            /// Overrideable method called when visiting a `$ty`. When overriding this method,
+            /// call `self.visit_inner(ctx, x)` to keep recursively visiting the type, possibly with
+            /// a different context, or don't call it if the contents of `x` should not be visited.
+            ///
+            /// The default implementation calls `enter_$ty` then `visit_inner` then `exit_$ty`.
+            #[inline]
+            fn #visit_method_name #impl_generics(&mut self, ctx: &#ctx, x: &#mutability #ty)
+                #return_type
+            #where_clause
+            {
+                #body
+                #return_value
+            }
+        ));
+        if !skip {
+            visitor_trait.items.push(parse_quote!(
+                /// Called when starting to visit a `$ty` (unless `visit_$ty` is overriden).
+                #[inline]
+                fn #enter_method #impl_generics(&mut self, ctx: &#ctx, x: &#mutability #ty)
+                    #where_clause {}
+            ));
+            visitor_trait.items.push(parse_quote!(
+                /// Called when finished visiting a `$ty` (unless `visit_$ty` is overriden).
+                #[inline]
+                fn #exit_method #impl_generics(&mut self, ctx: &#ctx, x: &#mutability #ty)
+                    #where_clause {}
+            ));
+        }
+    }
+    visitor_trait
+}
+
+/// Add the given attributes to each of the generated items.
+fn forward_attrs(attrs: &[ForwardedAttrs], generated: TokenStream) -> Result<TokenStream> {
+    let attrs: Vec<Attribute> = attrs
+        .iter()
+        .flat_map(|attrs| &attrs.0)
+        .map(|meta| parse_quote!(#[#meta]))
+        .collect();
+    let mut file: syn::File = syn::parse2(generated)?;
+    for item in &mut file.items {
+        let item_attrs = match item {
+            Item::Const(item) => &mut item.attrs,
+            Item::Fn(item) => &mut item.attrs,
+            Item::Impl(item) => &mut item.attrs,
+            Item::Macro(item) => &mut item.attrs,
+            Item::Struct(item) => &mut item.attrs,
+            Item::Trait(item) => &mut item.attrs,
+            Item::Use(item) => &mut item.attrs,
+            _ => continue,
+        };
+        item_attrs.splice(0..0, attrs.iter().cloned());
+    }
+    Ok(quote!(#file))
+}
+
+/// Generate compile-time assertions that every type we recurse into (i.e. `drive` and `override`
+/// types) only contains types that are part of the group. This uses a dummy visitor that can visit
+/// exactly the group types: a type whose `Drive` impl is not applicable to it has a field that
+/// is not part of the group.
+fn strict_checks(
+    trait_name: &Ident,
+    tys: &[(GenericTy, TyVisitKind)],
+    visitor_traits: &[(VisitorDef, Names)],
+) -> TokenStream {
+    let Names {
+        control_flow,
+        visitor_trait,
+        ..
+    } = Names::new(false);
+    let visit_impls = [Names::new(false), Names::new(true)]
+        .into_iter()
+        .map(|names| {
+            let Names {
+                visit_trait,
+                mut_modifier,
+                ..
+            } = names;
+            quote!(
+                impl<'s, T: ?Sized + #trait_name> #visit_trait<'s, T> for StrictCheck {
+                    fn visit(&mut self, _: &'s #mut_modifier T) -> #control_flow<()> {
+                        #control_flow::Continue(())
+                    }
+                }
+            )
+        });
+    let visit_two_trait = Names::new_two().visit_trait;
+
+    // Deduplicate the drive traits used by the visitors.
+    let mut drive_traits = vec![];
+    for (_, names) in visitor_traits {
+        let drive_trait = &names.drive_trait;
+        let drive_trait = quote!(#drive_trait).to_string();
+        if !drive_traits.iter().any(|(s, _)| *s == drive_trait) {
+            drive_traits.push((drive_trait, &names.drive_trait));
+        }
+    }
+    let checks = tys
+        .iter()
+        .filter(|(_, kind)| {
+            matches!(
+                kind,
+                TyVisitKind::Drive | TyVisitKind::Override { skip: false, .. }
+            )
+        })
+        .map(|(ty, _)| {
+            let (impl_generics, _, where_clause) = ty.generics.split_for_impl();
+            let ty = &ty.ty;
+            let asserts = drive_traits.iter().map(|(_, drive_trait)| {
+                quote!(
+                    fn assert<T: ?Sized + for<'s> #drive_trait<'s, StrictCheck>>() {}
+                    assert::<#ty>();
+                )
+            });
+            quote!(
+                #[allow(unused)]
+                fn check #impl_generics () #where_clause {
+                    #({ #asserts })*
+                }
+            )
+        });
+    quote!(
+        const _: () = {
+            struct StrictCheck;
+            impl #visitor_trait for StrictCheck {
+                type Break = ();
+            }
+            #(#visit_impls)*
+            impl<'s, T: ?Sized + #trait_name> #visit_two_trait<'s, T> for StrictCheck {
+                fn visit(&mut self, _: &'s T, _: &'s T) -> #control_flow<()> {
+                    #control_flow::Continue(())
+                }
+            }
+            #({ #checks })*
+        };
+    )
+}
+
+/// Generate the methods of the visitable trait added by `closure_helpers`, which call a closure on
+/// each `$ty` reachable from `self`, for non-generic `override` types. With `mutable == false`,
+/// these are `for_each_$ty` (and `try_for_each_$ty` for fallible visitors), which use the first
+/// immutable visitor trait and call the closure in pre-order. With `mutable == true`, these are
+/// `map_each_$ty_mut` (and `try_map_each_$ty_mut`), which use the first mutable visitor trait and
+/// call the closure in post-order, so that a rewrite sees already-rewritten contents. Visitor traits
+/// with extra bounds can't be implemented by a closure wrapper, so they're skipped.
+fn closure_methods(
+    tys: &[(GenericTy, TyVisitKind)],
+    visitor_traits: &[(VisitorDef, Names)],
+    mutable: bool,
+    node_ids: bool,
+) -> Vec<syn::TraitItem> {
+    let Some((vis_def, names)) = visitor_traits.iter().find(|(v, _)| {
+        v.mutability.is_some() == mutable
+            && !v.is_two
+            && !v.synthesized
+            && v.ctx.is_none()
+            && v.super_bounds.is_empty()
+    }) else {
+        return vec![];
+    };
+    let VisitorDef {
+        vis_trait_name,
+        faillible,
+        mutability,
+        extends,
+        ..
+    } = vis_def;
+    let base_trait: Vec<_> = extends.iter().map(|(_, base)| base).collect();
+    // With `node_ids`, the closure wrapper also carries the `NodeIds` required by the trait.
+    let node_ids_field = node_ids.then(|| quote!(::derive_generic_visitor::NodeIds));
+    let node_ids_method = node_ids.then(|| {
+        quote!(
+            fn node_ids(&mut self) -> &mut ::derive_generic_visitor::NodeIds {
+                &mut self.1
+            }
+        )
+    });
+    let node_ids_init = node_ids.then(|| quote!(::std::default::Default::default()));
+    let Names {
+        control_flow,
+        visitor_trait,
+        ..
+    } = names;
+    tys.iter()
+        .filter_map(|(ty, kind)| match kind {
+            TyVisitKind::Override { skip: false, name } if ty.generics.params.is_empty() => {
+                Some((&ty.ty, name))
+            }
+            _ => None,
+        })
+        .flat_map(|(ty, name)| {
+            let ty_str = quote!(#ty).to_string().replace(' ', "");
+            let (fn_name, doc) = if mutable {
+                (
+                    format!("map_each_{name}_mut"),
+                    format!(
+                        " Call `f` on each `{ty_str}` in `self`, in post-order (i.e. after its \
+                        contents)."
+                    ),
+                )
+            } else {
+                (
+                    format!("for_each_{name}"),
+                    format!(" Call `f` on each `{ty_str}` in `self`, in pre-order."),
+                )
+            };
+            let func = Ident::new(&fn_name, Span::call_site());
+            let try_func = Ident::new(&format!("try_{fn_name}"), Span::call_site());
+            let visit_method = Ident::new(&format!("visit_{name}"), Span::call_site());
+            let question_mark = faillible.then_some(quote!(?));
+            let visit_body = if mutable {
+                quote!(
+                    #vis_trait_name::visit_inner(self, x)#question_mark;
+                    (self.0)(x)
+                )
+            } else {
+                quote!(
+                    (self.0)(x)#question_mark;
+                    #vis_trait_name::visit_inner(self, x)
+                )
+            };
+            if *faillible {
+                vec![
+                    parse_quote!(
+                        #[doc = #doc]
+                        fn #func(&#mutability self, mut f: impl FnMut(&#mutability #ty))
+                        where
+                            Self: Sized,
+                        {
+                            let result = Self::#try_func::<::std::convert::Infallible>(self, |x| {
+                                f(x);
+                                #control_flow::Continue(())
+                            });
+                            match result {
+                                #control_flow::Continue(()) => {}
+                            }
+                        }
+                    ),
+                    parse_quote!(
+                        #[doc = #doc]
+                        /// Stops at the first `Break`.
+                        fn #try_func<B>(
+                            &#mutability self,
+                            f: impl FnMut(&#mutability #ty) -> #control_flow<B>,
+                        ) -> #control_flow<B>
+                        where
+                            Self: Sized,
+                        {
+                            struct Closure<F>(F, #node_ids_field);
+                            impl<B, F: FnMut(&#mutability #ty) -> #control_flow<B>> #visitor_trait for Closure<F> {
+                                type Break = B;
+                            }
+                            #(impl<B, F: FnMut(&#mutability #ty) -> #control_flow<B>> #base_trait for Closure<F> {})*
+                            impl<B, F: FnMut(&#mutability #ty) -> #control_flow<B>> #vis_trait_name for Closure<F> {
+                                fn #visit_method(&mut self, x: &#mutability #ty) -> #control_flow<B> {
+                                    #visit_body
+                                }
+                                #node_ids_method
+                            }
+                            #vis_trait_name::visit(&mut Closure(f, #node_ids_init), self)
+                        }
+                    ),
+                ]
+            } else {
+                vec![parse_quote!(
+                    #[doc = #doc]
+                    fn #func(&#mutability self, f: impl FnMut(&#mutability #ty))
+                    where
+                        Self: Sized,
+                    {
+                        struct Closure<F>(F, #node_ids_field);
+                        #(impl<F: FnMut(&#mutability #ty)> #base_trait for Closure<F> {})*
+                        impl<F: FnMut(&#mutability #ty)> #vis_trait_name for Closure<F> {
+                            fn #visit_method(&mut self, x: &#mutability #ty) {
+                                #visit_body
+                            }
+                            #node_ids_method
+                        }
+                        #vis_trait_name::visit(&mut Closure(f, #node_ids_init), self)
+                    }
+                )]
+            }
+        })
+        .collect()
 }